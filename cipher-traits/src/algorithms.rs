@@ -81,6 +81,40 @@ pub mod aead_xchacha20poly1305 {
     pub trait AeadXChaCha20Poly1305: Aead<KEY_LEN, NONCE_LEN, TAG_LEN> {}
 }
 
+/// Constants and trait for the AES-256-GCM AEAD
+pub mod aead_aes256gcm {
+    use crate::primitives::aead::*;
+
+    // See https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38d.pdf
+
+    /// The key length used in [`AeadAes256Gcm`].
+    pub const KEY_LEN: usize = 32;
+    /// The nonce length used in [`AeadAes256Gcm`].
+    pub const NONCE_LEN: usize = 12;
+    /// The tag length used in [`AeadAes256Gcm`].
+    pub const TAG_LEN: usize = 16;
+
+    /// An [`Aead`] that is AES-256-GCM.
+    pub trait AeadAes256Gcm: Aead<KEY_LEN, NONCE_LEN, TAG_LEN> {}
+}
+
+/// Constants and trait for the XAES-256-GCM AEAD (i.e. AES-256-GCM with extended nonce lengths)
+pub mod aead_xaes256gcm {
+    use crate::primitives::aead::*;
+
+    // See https://c2sp.org/XAES-256-GCM
+
+    /// The key length used in [`AeadXAes256Gcm`].
+    pub const KEY_LEN: usize = 32;
+    /// The nonce length used in [`AeadXAes256Gcm`].
+    pub const NONCE_LEN: usize = 24;
+    /// The tag length used in [`AeadXAes256Gcm`].
+    pub const TAG_LEN: usize = 16;
+
+    /// An [`Aead`] that is XAES-256-GCM.
+    pub trait AeadXAes256Gcm: Aead<KEY_LEN, NONCE_LEN, TAG_LEN> {}
+}
+
 /// Constants and trait for the Kyber512 KEM
 pub mod kem_kyber512 {
     use crate::primitives::kem::*;
@@ -104,6 +138,73 @@ pub mod kem_kyber512 {
     pub trait KemKyber512: Kem<SK_LEN, PK_LEN, CT_LEN, SHK_LEN> {}
 }
 
+/// Constants and trait for the ML-KEM-768 KEM
+pub mod kem_ml_kem_768 {
+    use crate::primitives::kem::*;
+
+    // page 39 of https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.203.pdf
+
+    /// The secret key length used in [`KemMlKem768`].
+    pub const SK_LEN: usize = 2400;
+
+    /// The public key length used in [`KemMlKem768`].
+    pub const PK_LEN: usize = 1184;
+
+    /// The ciphertext length used in [`KemMlKem768`].
+    pub const CT_LEN: usize = 1088;
+
+    /// The shared key length used in [`KemMlKem768`].
+    pub const SHK_LEN: usize = 32;
+
+    /// A [`Kem`] that is ML-KEM-768.
+    pub trait KemMlKem768: Kem<SK_LEN, PK_LEN, CT_LEN, SHK_LEN> {}
+}
+
+/// Constants and trait for the ML-KEM-1024 KEM
+pub mod kem_ml_kem_1024 {
+    use crate::primitives::kem::*;
+
+    // page 39 of https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.203.pdf
+
+    /// The secret key length used in [`KemMlKem1024`].
+    pub const SK_LEN: usize = 3168;
+
+    /// The public key length used in [`KemMlKem1024`].
+    pub const PK_LEN: usize = 1568;
+
+    /// The ciphertext length used in [`KemMlKem1024`].
+    pub const CT_LEN: usize = 1568;
+
+    /// The shared key length used in [`KemMlKem1024`].
+    pub const SHK_LEN: usize = 32;
+
+    /// A [`Kem`] that is ML-KEM-1024.
+    pub trait KemMlKem1024: Kem<SK_LEN, PK_LEN, CT_LEN, SHK_LEN> {}
+}
+
+/// Constants and trait for X25519 used as a KEM
+pub mod kem_x25519 {
+    use crate::primitives::kem::*;
+
+    // See https://datatracker.ietf.org/doc/html/rfc7748#section-6.1; the ciphertext is the
+    // ephemeral public key of the encapsulating party
+
+    /// The secret key length used in [`KemX25519`].
+    pub const SK_LEN: usize = 32;
+
+    /// The public key length used in [`KemX25519`].
+    pub const PK_LEN: usize = 32;
+
+    /// The ciphertext length used in [`KemX25519`].
+    pub const CT_LEN: usize = 32;
+
+    /// The shared key length used in [`KemX25519`].
+    pub const SHK_LEN: usize = 32;
+
+    /// A [`Kem`] that is an X25519 Diffie-Hellman key exchange.
+    pub trait KemX25519: Kem<SK_LEN, PK_LEN, CT_LEN, SHK_LEN> {}
+}
+
 /// Constants and trait for the Classic McEliece 460896 KEM
 pub mod kem_classic_mceliece460896 {
     use crate::primitives::kem::*;
@@ -129,8 +230,14 @@ pub mod kem_classic_mceliece460896 {
 pub use aead_chacha20poly1305::AeadChaCha20Poly1305;
 pub use aead_xchacha20poly1305::AeadXChaCha20Poly1305;
 
+pub use aead_aes256gcm::AeadAes256Gcm;
+pub use aead_xaes256gcm::AeadXAes256Gcm;
+
 pub use kem_classic_mceliece460896::KemClassicMceliece460896;
 pub use kem_kyber512::KemKyber512;
+pub use kem_ml_kem_1024::KemMlKem1024;
+pub use kem_ml_kem_768::KemMlKem768;
+pub use kem_x25519::KemX25519;
 
 pub use keyed_hash_blake2b::KeyedHashBlake2b;
 pub use keyed_hash_incorrect_hmac_blake2b::KeyedHashIncorrectHmacBlake2b;
@@ -38,6 +38,9 @@ mod kem {
         benches,
         bench_kyber512_libcrux,
         bench_kyber512_oqs,
+        bench_mlkem768_libcrux,
+        bench_mlkem1024_libcrux,
+        bench_x25519_dalek,
         bench_classicmceliece460896_oqs
     );
 
@@ -65,6 +68,33 @@ mod kem {
         template(c, "kyber512", "oqs", rosenpass_oqs::Kyber512);
     }
 
+    fn bench_mlkem768_libcrux(c: &mut Criterion) {
+        template(
+            c,
+            "mlkem768",
+            "libcrux",
+            rosenpass_ciphers::subtle::libcrux::ml_kem_768::MlKem768,
+        );
+    }
+
+    fn bench_x25519_dalek(c: &mut Criterion) {
+        template(
+            c,
+            "x25519",
+            "dalek",
+            rosenpass_ciphers::subtle::custom::x25519::X25519,
+        );
+    }
+
+    fn bench_mlkem1024_libcrux(c: &mut Criterion) {
+        template(
+            c,
+            "mlkem1024",
+            "libcrux",
+            rosenpass_ciphers::subtle::libcrux::ml_kem_1024::MlKem1024,
+        );
+    }
+
     use rosenpass_cipher_traits::primitives::Kem;
 
     fn template<
@@ -134,6 +164,8 @@ mod aead {
         bench_chachapoly_libcrux,
         bench_chachapoly_rustcrypto,
         bench_xchachapoly_rustcrypto,
+        bench_aes256gcm_rustcrypto,
+        bench_xaes256gcm_rustcrypto,
     );
 
     use criterion::Criterion;
@@ -159,6 +191,24 @@ mod aead {
         );
     }
 
+    fn bench_aes256gcm_rustcrypto(c: &mut Criterion) {
+        template(
+            c,
+            "aes256gcm",
+            "rustcrypto",
+            rosenpass_ciphers::subtle::rust_crypto::aes256gcm::Aes256Gcm,
+        );
+    }
+
+    fn bench_xaes256gcm_rustcrypto(c: &mut Criterion) {
+        template(
+            c,
+            "xaes256gcm",
+            "rustcrypto",
+            rosenpass_ciphers::subtle::rust_crypto::xaes256gcm::XAes256Gcm,
+        );
+    }
+
     fn bench_chachapoly_libcrux(c: &mut Criterion) {
         template(
             c,
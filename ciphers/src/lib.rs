@@ -7,6 +7,8 @@ pub mod subtle;
 pub const KEY_LEN: usize = 32;
 const_assert!(KEY_LEN == Aead::KEY_LEN);
 const_assert!(KEY_LEN == XAead::KEY_LEN);
+const_assert!(KEY_LEN == Aes256Gcm::KEY_LEN);
+const_assert!(KEY_LEN == XAes256Gcm::KEY_LEN);
 const_assert!(KEY_LEN == hash_domain::KEY_LEN);
 
 /// Keyed hashing
@@ -30,6 +32,17 @@ pub use crate::subtle::rust_crypto::chacha20poly1305_ietf::ChaCha20Poly1305 as A
 /// XChacha20poly1305 is used.
 pub use crate::subtle::rust_crypto::xchacha20poly1305_ietf::XChaCha20Poly1305 as XAead;
 
+/// AES-256-GCM, selectable per peer as a replacement for [Aead]
+///
+/// See [subtle::rust_crypto::aes256gcm::Aes256Gcm] for more details.
+pub use subtle::rust_crypto::aes256gcm::Aes256Gcm;
+
+/// XAES-256-GCM, the extended-nonce variant of [Aes256Gcm]; used in place of [XAead]
+/// together with [Aes256Gcm]
+///
+/// See [subtle::rust_crypto::xaes256gcm::XAes256Gcm] for more details.
+pub use subtle::rust_crypto::xaes256gcm::XAes256Gcm;
+
 /// Use Classic-McEcliece-460986 as the Static KEM.
 ///
 /// See [rosenpass_oqs::ClassicMceliece460896] for more details.
@@ -43,4 +56,19 @@ pub use rosenpass_oqs::Kyber512 as EphemeralKem;
 #[cfg(feature = "experiment_libcrux_kyber")]
 pub use subtle::libcrux::kyber512::Kyber512 as EphemeralKem;
 
+/// ML-KEM-768, selectable per peer as a replacement for [EphemeralKem]
+///
+/// See [subtle::libcrux::ml_kem_768::MlKem768] for more details.
+pub use subtle::libcrux::ml_kem_768::MlKem768;
+
+/// ML-KEM-1024, selectable per peer as a replacement for [EphemeralKem]
+///
+/// See [subtle::libcrux::ml_kem_1024::MlKem1024] for more details.
+pub use subtle::libcrux::ml_kem_1024::MlKem1024;
+
+/// X25519 used as a KEM; the classical component of hybrid handshakes
+///
+/// See [subtle::custom::x25519::X25519] for more details.
+pub use subtle::custom::x25519::X25519;
+
 pub mod hash_domain;
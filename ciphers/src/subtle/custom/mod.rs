@@ -1,3 +1,4 @@
 //! Own implementations of custom algorithms
 
 pub mod incorrect_hmac_blake2b;
+pub mod x25519;
@@ -0,0 +1,117 @@
+//! Implementation of the [`KemX25519`] trait based on the [`x25519_dalek`] crate.
+//!
+//! X25519 is a Diffie-Hellman key exchange, not a KEM; it is turned into one by using a fresh
+//! ephemeral key pair for every encapsulation. The ciphertext is the ephemeral public key and
+//! the shared key is the raw Diffie-Hellman output. Users must bind the public key and the
+//! ciphertext to the shared key; Rosenpass does so by mixing all three into the chaining key.
+
+use rand::RngCore;
+use x25519_dalek::{PublicKey, SharedSecret, StaticSecret};
+
+use rosenpass_cipher_traits::algorithms::KemX25519;
+use rosenpass_cipher_traits::primitives::{Kem, KemError};
+
+pub use rosenpass_cipher_traits::algorithms::kem_x25519::{CT_LEN, PK_LEN, SHK_LEN, SK_LEN};
+
+/// An implementation of X25519 as a KEM based on x25519-dalek
+pub struct X25519;
+
+/// Generate a fresh secret key
+fn random_secret() -> StaticSecret {
+    let mut sk = [0u8; SK_LEN];
+    rosenpass_secret_memory::rand::rng().fill_bytes(&mut sk);
+    StaticSecret::from(sk)
+}
+
+/// Copy the shared secret to `shk`, rejecting low order points
+fn export_shared_secret(shk: &mut [u8; SHK_LEN], dh: SharedSecret) -> Result<(), KemError> {
+    if !dh.was_contributory() {
+        return Err(KemError::InvalidArgument);
+    }
+    shk.copy_from_slice(dh.as_bytes());
+    Ok(())
+}
+
+impl Kem<SK_LEN, PK_LEN, CT_LEN, SHK_LEN> for X25519 {
+    fn keygen(&self, sk: &mut [u8; SK_LEN], pk: &mut [u8; PK_LEN]) -> Result<(), KemError> {
+        let secret = random_secret();
+        sk.copy_from_slice(secret.as_bytes());
+        pk.copy_from_slice(PublicKey::from(&secret).as_bytes());
+        Ok(())
+    }
+
+    fn encaps(
+        &self,
+        shk: &mut [u8; SHK_LEN],
+        ct: &mut [u8; CT_LEN],
+        pk: &[u8; PK_LEN],
+    ) -> Result<(), KemError> {
+        let secret = random_secret();
+        ct.copy_from_slice(PublicKey::from(&secret).as_bytes());
+        export_shared_secret(shk, secret.diffie_hellman(&PublicKey::from(*pk)))
+    }
+
+    fn decaps(
+        &self,
+        shk: &mut [u8; SHK_LEN],
+        sk: &[u8; SK_LEN],
+        ct: &[u8; CT_LEN],
+    ) -> Result<(), KemError> {
+        let secret = StaticSecret::from(*sk);
+        export_shared_secret(shk, secret.diffie_hellman(&PublicKey::from(*ct)))
+    }
+}
+
+impl Default for X25519 {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl KemX25519 for X25519 {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encaps_decaps_roundtrip() {
+        let (mut sk, mut pk) = ([0; SK_LEN], [0; PK_LEN]);
+        let mut ct = [0; CT_LEN];
+        let (mut shk_enc, mut shk_dec) = ([0; SHK_LEN], [0; SHK_LEN]);
+
+        for _ in 0..100 {
+            X25519.keygen(&mut sk, &mut pk).unwrap();
+            X25519.encaps(&mut shk_enc, &mut ct, &pk).unwrap();
+            X25519.decaps(&mut shk_dec, &sk, &ct).unwrap();
+            assert_eq!(shk_enc, shk_dec);
+        }
+    }
+
+    #[test]
+    fn rfc7748_test_vector() {
+        // https://datatracker.ietf.org/doc/html/rfc7748#section-6.1
+        let alice_sk =
+            hex_literal::hex!("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob_pk =
+            hex_literal::hex!("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f");
+        let shared =
+            hex_literal::hex!("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+
+        let mut shk = [0; SHK_LEN];
+        X25519.decaps(&mut shk, &alice_sk, &bob_pk).unwrap();
+        assert_eq!(shk, shared);
+    }
+
+    #[test]
+    fn rejects_low_order_points() {
+        let (mut sk, mut pk) = ([0; SK_LEN], [0; PK_LEN]);
+        X25519.keygen(&mut sk, &mut pk).unwrap();
+
+        let mut shk = [0; SHK_LEN];
+        assert!(X25519.decaps(&mut shk, &sk, &[0; CT_LEN]).is_err());
+        assert!(X25519
+            .encaps(&mut shk, &mut [0; CT_LEN], &[0; PK_LEN])
+            .is_err());
+    }
+}
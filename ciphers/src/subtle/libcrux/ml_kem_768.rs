@@ -0,0 +1,110 @@
+//! Implementation of the [`KemMlKem768`] trait based on the [`libcrux_ml_kem`] crate.
+
+use libcrux_ml_kem::mlkem768;
+use rand::RngCore;
+
+use rosenpass_cipher_traits::algorithms::KemMlKem768;
+use rosenpass_cipher_traits::primitives::{Kem, KemError};
+
+pub use rosenpass_cipher_traits::algorithms::kem_ml_kem_768::{CT_LEN, PK_LEN, SHK_LEN, SK_LEN};
+
+/// An implementation of the ML-KEM-768 KEM based on libcrux
+pub struct MlKem768;
+
+impl Kem<SK_LEN, PK_LEN, CT_LEN, SHK_LEN> for MlKem768 {
+    fn keygen(&self, sk: &mut [u8; SK_LEN], pk: &mut [u8; PK_LEN]) -> Result<(), KemError> {
+        let mut randomness = [0u8; libcrux_ml_kem::KEY_GENERATION_SEED_SIZE];
+        rosenpass_secret_memory::rand::rng().fill_bytes(&mut randomness);
+
+        let key_pair = mlkem768::generate_key_pair(randomness);
+
+        let new_sk: &[u8; SK_LEN] = key_pair.sk();
+        let new_pk: &[u8; PK_LEN] = key_pair.pk();
+
+        sk.clone_from_slice(new_sk);
+        pk.clone_from_slice(new_pk);
+
+        Ok(())
+    }
+
+    fn encaps(
+        &self,
+        shk: &mut [u8; SHK_LEN],
+        ct: &mut [u8; CT_LEN],
+        pk: &[u8; PK_LEN],
+    ) -> Result<(), KemError> {
+        // Reject public keys with coefficients that are not reduced modulo q (FIPS 203, §7.2)
+        let pk = pk.into();
+        if !mlkem768::validate_public_key(&pk) {
+            return Err(KemError::InvalidArgument);
+        }
+
+        let mut randomness = [0u8; libcrux_ml_kem::SHARED_SECRET_SIZE];
+        rosenpass_secret_memory::rand::rng().fill_bytes(&mut randomness);
+
+        let (new_ct, new_shk) = mlkem768::encapsulate(&pk, randomness);
+        let new_ct: &[u8; CT_LEN] = new_ct.as_slice();
+
+        shk.clone_from_slice(&new_shk);
+        ct.clone_from_slice(new_ct);
+
+        Ok(())
+    }
+
+    fn decaps(
+        &self,
+        shk: &mut [u8; SHK_LEN],
+        sk: &[u8; SK_LEN],
+        ct: &[u8; CT_LEN],
+    ) -> Result<(), KemError> {
+        let new_shk: [u8; SHK_LEN] = mlkem768::decapsulate(&sk.into(), &ct.into());
+        shk.clone_from(&new_shk);
+        Ok(())
+    }
+}
+
+impl Default for MlKem768 {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl KemMlKem768 for MlKem768 {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encaps_decaps_roundtrip() {
+        let (mut sk, mut pk) = ([0; SK_LEN], [0; PK_LEN]);
+        let mut ct = [0; CT_LEN];
+        let (mut shk_enc, mut shk_dec) = ([0; SHK_LEN], [0; SHK_LEN]);
+
+        for _ in 0..100 {
+            MlKem768.keygen(&mut sk, &mut pk).unwrap();
+            MlKem768.encaps(&mut shk_enc, &mut ct, &pk).unwrap();
+            MlKem768.decaps(&mut shk_dec, &sk, &ct).unwrap();
+            assert_eq!(shk_enc, shk_dec);
+
+            // ML-KEM uses implicit rejection; a modified ciphertext yields an unrelated key
+            ct[0] ^= 1;
+            MlKem768.decaps(&mut shk_dec, &sk, &ct).unwrap();
+            assert_ne!(shk_enc, shk_dec);
+        }
+    }
+
+    #[test]
+    fn encaps_rejects_unreduced_public_key() {
+        let (mut sk, mut pk) = ([0; SK_LEN], [0; PK_LEN]);
+        let (mut shk, mut ct) = ([0; SHK_LEN], [0; CT_LEN]);
+        MlKem768.keygen(&mut sk, &mut pk).unwrap();
+
+        // Every coefficient of the first polynomial becomes 4095, which exceeds q = 3329
+        pk[..384].fill(0xFF);
+        assert!(matches!(
+            MlKem768.encaps(&mut shk, &mut ct, &pk),
+            Err(KemError::InvalidArgument)
+        ));
+    }
+}
@@ -12,3 +12,6 @@ pub mod chacha20poly1305_ietf;
 
 #[cfg(feature = "experiment_libcrux_define_kyber")]
 pub mod kyber512;
+
+pub mod ml_kem_1024;
+pub mod ml_kem_768;
@@ -0,0 +1,125 @@
+use rosenpass_to::ops::copy_slice;
+use rosenpass_to::To;
+
+use rosenpass_cipher_traits::algorithms::AeadAes256Gcm;
+use rosenpass_cipher_traits::primitives::{Aead, AeadError};
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::Aes256Gcm as AeadImpl;
+use aes_gcm::{AeadInPlace, KeyInit};
+
+pub use rosenpass_cipher_traits::algorithms::aead_aes256gcm::{KEY_LEN, NONCE_LEN, TAG_LEN};
+
+/// Implements the [`Aead`] and [`AeadAes256Gcm`] traits backed by the RustCrypto
+/// implementation.
+pub struct Aes256Gcm;
+
+impl Aead<KEY_LEN, NONCE_LEN, TAG_LEN> for Aes256Gcm {
+    fn encrypt(
+        &self,
+        ciphertext: &mut [u8],
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        ad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<(), AeadError> {
+        // The comparison looks complicated, but we need to do it this way to prevent
+        // over/underflows.
+        if ciphertext.len() < TAG_LEN || ciphertext.len() - TAG_LEN < plaintext.len() {
+            return Err(AeadError::InvalidLengths);
+        }
+
+        let nonce = GenericArray::from_slice(nonce);
+        let (ct, mac) = ciphertext.split_at_mut(ciphertext.len() - TAG_LEN);
+        copy_slice(plaintext).to(ct);
+
+        // This only fails if the length is wrong, which really shouldn't happen and would
+        // constitute an internal error.
+        let encrypter = AeadImpl::new_from_slice(key).map_err(|_| AeadError::InternalError)?;
+
+        let mac_value = encrypter
+            .encrypt_in_place_detached(nonce, ad, ct)
+            .map_err(|_| AeadError::InternalError)?;
+        copy_slice(&mac_value[..]).to(mac);
+
+        Ok(())
+    }
+
+    fn decrypt(
+        &self,
+        plaintext: &mut [u8],
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        ad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<(), AeadError> {
+        // The comparison looks complicated, but we need to do it this way to prevent
+        // over/underflows.
+        if ciphertext.len() < TAG_LEN || ciphertext.len() - TAG_LEN < plaintext.len() {
+            return Err(AeadError::InvalidLengths);
+        }
+
+        let nonce = GenericArray::from_slice(nonce);
+        let (ct, mac) = ciphertext.split_at(ciphertext.len() - TAG_LEN);
+        let tag = GenericArray::from_slice(mac);
+        copy_slice(ct).to(plaintext);
+
+        // This only fails if the length is wrong, which really shouldn't happen and would
+        // constitute an internal error.
+        let decrypter = AeadImpl::new_from_slice(key).map_err(|_| AeadError::InternalError)?;
+
+        decrypter
+            .decrypt_in_place_detached(nonce, ad, plaintext, tag)
+            .map_err(|_| AeadError::DecryptError)?;
+
+        Ok(())
+    }
+}
+
+impl AeadAes256Gcm for Aes256Gcm {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn gcm_spec_test_case_16() {
+        // Test Case 16 of the GCM specification
+        // https://csrc.nist.rip/groups/ST/toolkit/BCM/documents/proposedmodes/gcm/gcm-spec.pdf
+        let key = hex!("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308");
+        let nonce = hex!("cafebabefacedbaddecaf888");
+        let ad = hex!("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let plaintext = hex!(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72"
+            "1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39"
+        );
+        let ciphertext = hex!(
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa"
+            "8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662"
+            "76fc6ece0f4e1768cddf8853bb2d551b"
+        );
+
+        let mut ct = [0u8; 60 + TAG_LEN];
+        Aes256Gcm
+            .encrypt(&mut ct, &key, &nonce, &ad, &plaintext)
+            .unwrap();
+        assert_eq!(ct, ciphertext);
+
+        let mut pt = [0u8; 60];
+        Aes256Gcm
+            .decrypt(&mut pt, &key, &nonce, &ad, &ciphertext)
+            .unwrap();
+        assert_eq!(pt, plaintext);
+
+        // Any modification must be detected
+        let mut tampered = ciphertext;
+        tampered[0] ^= 1;
+        assert!(Aes256Gcm
+            .decrypt(&mut pt, &key, &nonce, &ad, &tampered)
+            .is_err());
+        assert!(Aes256Gcm
+            .decrypt(&mut pt, &key, &nonce, &[], &ciphertext)
+            .is_err());
+    }
+}
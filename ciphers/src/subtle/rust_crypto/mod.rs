@@ -5,3 +5,6 @@ pub mod keyed_shake256;
 
 pub mod chacha20poly1305_ietf;
 pub mod xchacha20poly1305_ietf;
+
+pub mod aes256gcm;
+pub mod xaes256gcm;
@@ -0,0 +1,147 @@
+//! Implementation of XAES-256-GCM as specified in <https://c2sp.org/XAES-256-GCM>.
+//!
+//! XAES-256-GCM extends the nonce of AES-256-GCM to 24 bytes by deriving a fresh AES-256-GCM key
+//! from the key and the first half of the nonce for every message, so nonces can be chosen at
+//! random. Only the AES block cipher is used, which makes this construction suitable where
+//! XChaCha20Poly1305 is not an option.
+
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes256;
+use zeroize::Zeroizing;
+
+use rosenpass_cipher_traits::algorithms::AeadXAes256Gcm;
+use rosenpass_cipher_traits::primitives::{Aead, AeadError};
+
+use super::aes256gcm::Aes256Gcm;
+
+pub use rosenpass_cipher_traits::algorithms::aead_xaes256gcm::{KEY_LEN, NONCE_LEN, TAG_LEN};
+
+/// Implements the [`Aead`] and [`AeadXAes256Gcm`] traits on top of [Aes256Gcm] and the
+/// RustCrypto AES implementation.
+pub struct XAes256Gcm;
+
+/// Derive the AES-256-GCM key for the given nonce
+///
+/// This is the CMAC-based key derivation from the specification; returns the derived key and
+/// the nonce to be used with it.
+fn derive_key(
+    key: &[u8; KEY_LEN],
+    nonce: &[u8; NONCE_LEN],
+) -> (Zeroizing<[u8; KEY_LEN]>, [u8; NONCE_LEN - 12]) {
+    let aes = Aes256::new(GenericArray::from_slice(key));
+
+    // L = AES-256ₖ(0¹²⁸); K1 = L << 1, reduced modulo the CMAC polynomial
+    let mut l = Zeroizing::new([0u8; 16]);
+    aes.encrypt_block(GenericArray::from_mut_slice(&mut *l));
+    let l = Zeroizing::new(u128::from_be_bytes(*l));
+    let k1 = Zeroizing::new(((*l << 1) ^ ((*l >> 127) * 0x87)).to_be_bytes());
+
+    // Kₓ = AES-256ₖ(M1 ⊕ K1) || AES-256ₖ(M2 ⊕ K1) with Mᵢ = 0x00 || i || "X" || 0x00 || N[:12]
+    let mut derived = Zeroizing::new([0u8; KEY_LEN]);
+    for (i, block) in derived.chunks_exact_mut(16).enumerate() {
+        block[..4].copy_from_slice(&[0x00, i as u8 + 1, b'X', 0x00]);
+        block[4..].copy_from_slice(&nonce[..12]);
+        for (b, k) in block.iter_mut().zip(k1.iter()) {
+            *b ^= k;
+        }
+        aes.encrypt_block(GenericArray::from_mut_slice(block));
+    }
+
+    let mut n = [0u8; NONCE_LEN - 12];
+    n.copy_from_slice(&nonce[12..]);
+    (derived, n)
+}
+
+impl Aead<KEY_LEN, NONCE_LEN, TAG_LEN> for XAes256Gcm {
+    fn encrypt(
+        &self,
+        ciphertext: &mut [u8],
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        ad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<(), AeadError> {
+        let (key, nonce) = derive_key(key, nonce);
+        Aes256Gcm.encrypt(ciphertext, &key, &nonce, ad, plaintext)
+    }
+
+    fn decrypt(
+        &self,
+        plaintext: &mut [u8],
+        key: &[u8; KEY_LEN],
+        nonce: &[u8; NONCE_LEN],
+        ad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<(), AeadError> {
+        let (key, nonce) = derive_key(key, nonce);
+        Aes256Gcm.decrypt(plaintext, &key, &nonce, ad, ciphertext)
+    }
+}
+
+impl AeadXAes256Gcm for XAes256Gcm {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+    use rosenpass_cipher_traits::primitives::AeadWithNonceInCiphertext;
+
+    /// The test vectors from the specification
+    #[test]
+    fn c2sp_test_vectors() {
+        let nonce = b"ABCDEFGHIJKLMNOPQRSTUVWX";
+        let plaintext = b"XAES-256-GCM";
+        let vectors: [(_, &[u8], _); 2] = [
+            (
+                [0x01; KEY_LEN],
+                b"",
+                hex!("ce546ef63c9cc60765923609b33a9a1974e96e52daf2fcf7075e2271"),
+            ),
+            (
+                [0x03; KEY_LEN],
+                b"c2sp.org/XAES-256-GCM",
+                hex!("986ec1832593df5443a179437fd083bf3fdb41abd740a21f71eb769d"),
+            ),
+        ];
+
+        for (key, ad, ciphertext) in vectors {
+            let mut ct = [0u8; 12 + TAG_LEN];
+            XAes256Gcm
+                .encrypt(&mut ct, &key, nonce, ad, plaintext)
+                .unwrap();
+            assert_eq!(ct, ciphertext);
+
+            let mut pt = [0u8; 12];
+            XAes256Gcm
+                .decrypt(&mut pt, &key, nonce, ad, &ciphertext)
+                .unwrap();
+            assert_eq!(&pt, plaintext);
+        }
+    }
+
+    #[test]
+    fn nonce_in_ciphertext_roundtrip() {
+        let key = [0x42; KEY_LEN];
+        let nonce = [0x17; NONCE_LEN];
+        let plaintext = b"post-quantum cryptography is very important";
+
+        let mut ct = [0u8; NONCE_LEN + 43 + TAG_LEN];
+        XAes256Gcm
+            .encrypt_with_nonce_in_ctxt(&mut ct, &key, &nonce, b"ad", plaintext)
+            .unwrap();
+        assert_eq!(ct[..NONCE_LEN], nonce);
+
+        let mut pt = [0u8; 43];
+        XAes256Gcm
+            .decrypt_with_nonce_in_ctxt(&mut pt, &key, b"ad", &ct)
+            .unwrap();
+        assert_eq!(&pt, plaintext);
+
+        // The first half of the nonce is used for the key derivation
+        ct[0] ^= 1;
+        assert!(XAes256Gcm
+            .decrypt_with_nonce_in_ctxt(&mut pt, &key, b"ad", &ct)
+            .is_err());
+    }
+}
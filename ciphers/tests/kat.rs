@@ -0,0 +1,402 @@
+//! Known-answer tests for the primitives in this crate
+//!
+//! The test vectors live in `tests/kat/`; see the readme there for their format and origin.
+//! Every set of vectors is run against each backend implementing the respective algorithm, so
+//! swapping one backend for another can not silently change the results.
+
+use std::fmt::Debug;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use rosenpass_cipher_traits::primitives::{Aead, Kem, KeyedHash, KeyedHashInstance};
+
+/// Contents of a test vector file
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TestFile<Group> {
+    algorithm: String,
+    test_groups: Vec<Group>,
+}
+
+/// Whether a test vector describes a valid input
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Expect {
+    Valid,
+    Invalid,
+}
+
+/// Group of Wycheproof style AEAD tests
+#[derive(Deserialize)]
+struct AeadGroup {
+    tests: Vec<AeadTest>,
+}
+
+/// Wycheproof style AEAD test
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AeadTest {
+    tc_id: u32,
+    comment: String,
+    #[serde(with = "hex::serde")]
+    key: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    iv: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    aad: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    msg: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    ct: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    tag: Vec<u8>,
+    result: Expect,
+}
+
+/// Group of Wycheproof style MAC tests; used for the keyed hashes
+#[derive(Deserialize)]
+struct MacGroup {
+    tests: Vec<MacTest>,
+}
+
+/// Wycheproof style MAC test
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MacTest {
+    tc_id: u32,
+    comment: String,
+    #[serde(with = "hex::serde")]
+    key: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    msg: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    tag: Vec<u8>,
+    result: Expect,
+}
+
+/// Group of ACVP style KEM decapsulation tests sharing a key pair
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KemGroup {
+    tg_id: u32,
+    #[serde(with = "hex::serde")]
+    ek: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    dk: Vec<u8>,
+    tests: Vec<KemTest>,
+}
+
+/// ACVP style KEM decapsulation test
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KemTest {
+    tc_id: u32,
+    comment: String,
+    #[serde(with = "hex::serde")]
+    c: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    k: Vec<u8>,
+}
+
+/// Load the test vector file `name` from `tests/kat/`
+fn load<Group: DeserializeOwned>(name: &str) -> TestFile<Group> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/kat")
+        .join(name);
+    let file = std::fs::File::open(&path)
+        .unwrap_or_else(|e| panic!("Could not open {}: {e}", path.display()));
+    serde_json::from_reader(file)
+        .unwrap_or_else(|e| panic!("Could not parse {}: {e}", path.display()))
+}
+
+/// Convert a field of a test vector into an array
+fn arr<const N: usize>(v: &[u8]) -> &[u8; N] {
+    v.try_into()
+        .unwrap_or_else(|_| panic!("Expected {N} bytes in test vector, got {}", v.len()))
+}
+
+/// Run the AEAD test vectors from `file` against `aead`
+fn check_aead<const NONCE_LEN: usize, A: Aead<32, NONCE_LEN, 16>>(
+    file: &str,
+    backend: &str,
+    aead: A,
+) {
+    let vectors = load::<AeadGroup>(file);
+    let tests = vectors.test_groups.iter().flat_map(|g| g.tests.iter());
+    for t in tests {
+        let ctx = format!(
+            "{} test {} ({}) with {backend}",
+            vectors.algorithm, t.tc_id, t.comment
+        );
+        let (key, nonce) = (arr(&t.key), arr(&t.iv));
+        let ct = [t.ct.as_slice(), t.tag.as_slice()].concat();
+        let mut pt = vec![0u8; t.ct.len()];
+        let decrypted = aead.decrypt(&mut pt, key, nonce, &t.aad, &ct);
+
+        match t.result {
+            Expect::Valid => {
+                decrypted.unwrap_or_else(|e| panic!("{ctx}: decryption failed: {e}"));
+                assert_eq!(pt, t.msg, "{ctx}: wrong plaintext");
+
+                let mut out = vec![0u8; ct.len()];
+                aead.encrypt(&mut out, key, nonce, &t.aad, &t.msg)
+                    .unwrap_or_else(|e| panic!("{ctx}: encryption failed: {e}"));
+                assert_eq!(out, ct, "{ctx}: wrong ciphertext");
+            }
+            Expect::Invalid => assert!(decrypted.is_err(), "{ctx}: accepted invalid input"),
+        }
+    }
+}
+
+/// Run the keyed hash test vectors from `file` against `H`
+fn check_keyed_hash<H: KeyedHash<32, 32>>(file: &str, backend: &str)
+where
+    H::Error: Debug,
+{
+    check_keyed_hash_fn(file, backend, |key, data, out| {
+        H::keyed_hash(key, data, out).unwrap()
+    });
+}
+
+/// Run the keyed hash test vectors from `file` against `hash`
+fn check_keyed_hash_instance(file: &str, backend: &str, hash: rosenpass_ciphers::KeyedHash) {
+    check_keyed_hash_fn(file, backend, |key, data, out| {
+        hash.keyed_hash(key, data, out).unwrap()
+    });
+}
+
+/// Run the keyed hash test vectors from `file` against the hash function `hash`
+fn check_keyed_hash_fn(file: &str, backend: &str, hash: impl Fn(&[u8; 32], &[u8], &mut [u8; 32])) {
+    let vectors = load::<MacGroup>(file);
+    let tests = vectors.test_groups.iter().flat_map(|g| g.tests.iter());
+    for t in tests {
+        let ctx = format!(
+            "{} test {} ({}) with {backend}",
+            vectors.algorithm, t.tc_id, t.comment
+        );
+        let mut out = [0u8; 32];
+        hash(arr(&t.key), &t.msg, &mut out);
+        let matches = out.as_slice() == t.tag;
+        assert_eq!(matches, t.result == Expect::Valid, "{ctx}: wrong result");
+    }
+}
+
+/// Run the KEM test vectors from `file` against `kem`
+///
+/// Besides the decapsulation tests from the file, this encapsulates to each public key from the
+/// file and makes sure that `kem` can decapsulate the result.
+fn check_kem<const SK: usize, const PK: usize, const CT: usize, const SHK: usize>(
+    file: &str,
+    backend: &str,
+    kem: impl Kem<SK, PK, CT, SHK>,
+) {
+    check_kem_interop(file, backend, &kem, backend, &kem);
+}
+
+/// Like [check_kem], but encapsulates with `enc` and decapsulates with `dec`
+///
+/// This makes sure that two backends of the same KEM can interoperate.
+fn check_kem_interop<const SK: usize, const PK: usize, const CT: usize, const SHK: usize>(
+    file: &str,
+    enc_backend: &str,
+    enc: &impl Kem<SK, PK, CT, SHK>,
+    dec_backend: &str,
+    dec: &impl Kem<SK, PK, CT, SHK>,
+) {
+    let vectors = load::<KemGroup>(file);
+    for g in vectors.test_groups.iter() {
+        let (sk, pk) = (arr::<SK>(&g.dk), arr::<PK>(&g.ek));
+        let mut shk = [0u8; SHK];
+
+        for t in g.tests.iter() {
+            let ctx = format!(
+                "{} test {} ({}) with {dec_backend}",
+                vectors.algorithm, t.tc_id, t.comment
+            );
+            dec.decaps(&mut shk, sk, arr(&t.c))
+                .unwrap_or_else(|e| panic!("{ctx}: decapsulation failed: {e}"));
+            assert_eq!(shk.as_slice(), t.k, "{ctx}: wrong shared key");
+        }
+
+        let ctx = format!(
+            "{} group {}, encapsulating with {enc_backend} and decapsulating with {dec_backend}",
+            vectors.algorithm, g.tg_id
+        );
+        let (mut ct, mut shk_enc) = ([0u8; CT], [0u8; SHK]);
+        enc.encaps(&mut shk_enc, &mut ct, pk)
+            .unwrap_or_else(|e| panic!("{ctx}: encapsulation failed: {e}"));
+        dec.decaps(&mut shk, sk, &ct)
+            .unwrap_or_else(|e| panic!("{ctx}: decapsulation failed: {e}"));
+        assert_eq!(shk, shk_enc, "{ctx}: shared keys differ");
+    }
+}
+
+mod aead {
+    use super::check_aead;
+    use rosenpass_ciphers::subtle::rust_crypto;
+
+    #[test]
+    fn chacha20poly1305_rustcrypto() {
+        check_aead(
+            "aead_chacha20poly1305.json",
+            "rustcrypto",
+            rust_crypto::chacha20poly1305_ietf::ChaCha20Poly1305,
+        );
+    }
+
+    #[cfg(feature = "experiment_libcrux_define_chachapoly")]
+    #[test]
+    fn chacha20poly1305_libcrux() {
+        check_aead(
+            "aead_chacha20poly1305.json",
+            "libcrux",
+            rosenpass_ciphers::subtle::libcrux::chacha20poly1305_ietf::ChaCha20Poly1305,
+        );
+    }
+
+    #[test]
+    fn xchacha20poly1305_rustcrypto() {
+        check_aead(
+            "aead_xchacha20poly1305.json",
+            "rustcrypto",
+            rust_crypto::xchacha20poly1305_ietf::XChaCha20Poly1305,
+        );
+    }
+
+    #[test]
+    fn aes256gcm_rustcrypto() {
+        check_aead(
+            "aead_aes256gcm.json",
+            "rustcrypto",
+            rust_crypto::aes256gcm::Aes256Gcm,
+        );
+    }
+
+    #[test]
+    fn xaes256gcm_rustcrypto() {
+        check_aead(
+            "aead_xaes256gcm.json",
+            "rustcrypto",
+            rust_crypto::xaes256gcm::XAes256Gcm,
+        );
+    }
+
+    /// The AEADs used by the protocol
+    #[test]
+    fn selected() {
+        check_aead(
+            "aead_chacha20poly1305.json",
+            "Aead",
+            rosenpass_ciphers::Aead,
+        );
+        check_aead(
+            "aead_xchacha20poly1305.json",
+            "XAead",
+            rosenpass_ciphers::XAead,
+        );
+    }
+}
+
+mod keyed_hash {
+    use super::{check_keyed_hash, check_keyed_hash_instance};
+    use rosenpass_ciphers::subtle::{custom, rust_crypto};
+    use rosenpass_ciphers::KeyedHash;
+
+    #[test]
+    fn blake2b_rustcrypto() {
+        check_keyed_hash::<rust_crypto::blake2b::Blake2b>("keyed_hash_blake2b.json", "rustcrypto");
+    }
+
+    #[cfg(feature = "experiment_libcrux_define_blake2")]
+    #[test]
+    fn blake2b_libcrux() {
+        check_keyed_hash::<rosenpass_ciphers::subtle::libcrux::blake2b::Blake2b>(
+            "keyed_hash_blake2b.json",
+            "libcrux",
+        );
+    }
+
+    #[test]
+    fn incorrect_hmac_blake2b() {
+        check_keyed_hash::<custom::incorrect_hmac_blake2b::IncorrectHmacBlake2bCore>(
+            "keyed_hash_incorrect_hmac_blake2b.json",
+            "custom",
+        );
+        check_keyed_hash_instance(
+            "keyed_hash_incorrect_hmac_blake2b.json",
+            "KeyedHash",
+            KeyedHash::incorrect_hmac_blake2b(),
+        );
+    }
+
+    #[test]
+    fn shake256_rustcrypto() {
+        check_keyed_hash::<rust_crypto::keyed_shake256::SHAKE256Core<32, 32>>(
+            "keyed_hash_shake256.json",
+            "rustcrypto",
+        );
+        check_keyed_hash_instance(
+            "keyed_hash_shake256.json",
+            "KeyedHash",
+            KeyedHash::keyed_shake256(),
+        );
+    }
+}
+
+mod kem {
+    use super::check_kem;
+    use rosenpass_ciphers::subtle::{custom, libcrux};
+
+    #[test]
+    fn kyber512_oqs() {
+        check_kem("kem_kyber512.json", "oqs", rosenpass_oqs::Kyber512);
+    }
+
+    #[cfg(feature = "experiment_libcrux_define_kyber")]
+    #[test]
+    fn kyber512_libcrux() {
+        use super::check_kem_interop;
+        use libcrux::kyber512::Kyber512;
+
+        check_kem("kem_kyber512.json", "libcrux", Kyber512);
+
+        let (oqs, libcrux) = (&rosenpass_oqs::Kyber512, &Kyber512);
+        check_kem_interop("kem_kyber512.json", "oqs", oqs, "libcrux", libcrux);
+        check_kem_interop("kem_kyber512.json", "libcrux", libcrux, "oqs", oqs);
+    }
+
+    #[test]
+    fn ml_kem_768_libcrux() {
+        check_kem(
+            "kem_ml_kem_768.json",
+            "libcrux",
+            libcrux::ml_kem_768::MlKem768,
+        );
+    }
+
+    #[test]
+    fn ml_kem_1024_libcrux() {
+        check_kem(
+            "kem_ml_kem_1024.json",
+            "libcrux",
+            libcrux::ml_kem_1024::MlKem1024,
+        );
+    }
+
+    #[test]
+    fn x25519_custom() {
+        check_kem("kem_x25519.json", "custom", custom::x25519::X25519);
+    }
+
+    /// The ephemeral KEM used by the protocol by default
+    #[test]
+    fn selected() {
+        check_kem(
+            "kem_kyber512.json",
+            "EphemeralKem",
+            rosenpass_ciphers::EphemeralKem,
+        );
+    }
+}
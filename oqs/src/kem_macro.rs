@@ -57,6 +57,8 @@ macro_rules! oqs_kem {
             /// allow bigger buffers.
             impl Kem<SK_LEN, PK_LEN, CT_LEN, SHK_LEN> for [< $name:camel >] {
                 fn keygen(&self, sk: &mut [u8; SK_LEN], pk: &mut [u8; PK_LEN]) -> Result<(), KemError> {
+                    #[cfg(feature = "internal_deterministic")]
+                    crate::rand::init();
                     unsafe {
                         oqs_call!(
                             ::oqs_sys::kem::[< OQS_KEM _ $name:snake _ keypair >],
@@ -69,6 +71,8 @@ macro_rules! oqs_kem {
                 }
 
                     fn encaps(&self, shk: &mut [u8; SHK_LEN], ct: &mut [u8; CT_LEN], pk: &[u8; PK_LEN]) -> Result<(), KemError> {
+                    #[cfg(feature = "internal_deterministic")]
+                    crate::rand::init();
                     unsafe {
                         oqs_call!(
                             ::oqs_sys::kem::[< OQS_KEM _ $name:snake _ encaps >],
@@ -20,6 +20,9 @@ macro_rules! oqs_call {
     ($name:ident) => { oqs_call!($name, ) };
 }
 
+#[cfg(feature = "internal_deterministic")]
+mod rand;
+
 #[macro_use]
 mod kem_macro;
 oqs_kem!(kyber_512, rosenpass_cipher_traits::algorithms::KemKyber512);
@@ -0,0 +1,38 @@
+//! Routes the randomness used by liboqs through [rosenpass_secret_memory::rand]
+//!
+//! Only compiled with the `internal_deterministic` feature; otherwise, liboqs keeps its own
+//! source of randomness.
+
+use std::sync::Once;
+
+/// Randomness callback handed to liboqs
+///
+/// # Safety
+///
+/// `buf` must point to a writable buffer of at least `len` bytes.
+unsafe extern "C" fn randombytes(buf: *mut u8, len: usize) {
+    if len == 0 {
+        return;
+    }
+
+    let buf = std::slice::from_raw_parts_mut(buf, len);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        rosenpass_secret_memory::rand::fill_bytes_os_or_injected(buf)
+    }));
+
+    // Unwinding into liboqs is not an option and the caller can not be told about errors
+    if res.is_err() {
+        std::process::abort();
+    }
+}
+
+/// Make liboqs draw its randomness through [rosenpass_secret_memory::rand]
+///
+/// liboqs keeps using the randomness of the operating system; this only makes sure that
+/// generators injected using [rosenpass_secret_memory::rand::with_rng] apply to liboqs as well.
+pub(crate) fn init() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| unsafe {
+        oqs_sys::rand::OQS_randombytes_custom_algorithm(Some(randombytes));
+    });
+}
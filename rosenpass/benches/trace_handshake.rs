@@ -31,6 +31,7 @@ fn handle(
     let HandleMsgResult {
         exchanged_with: xch,
         resp,
+        ..
     } = rx.handle_msg(&msgb[..msgl], &mut **resb)?;
 
     assert!(matches!(xch, None | Some(PeerPtr(0))));
@@ -1,24 +1,38 @@
 // Note: This is business logic; tested through the integration tests in
 // rosenpass/tests/
 
-use std::{borrow::BorrowMut, collections::VecDeque, os::fd::OwnedFd};
+use std::{borrow::BorrowMut, collections::VecDeque, ops::Deref, os::fd::OwnedFd};
 
-use anyhow::Context;
+use anyhow::{bail, ensure, Context};
+use rosenpass_secret_memory::Public;
 use rosenpass_to::{ops::copy_slice, To};
 use rosenpass_util::{
-    fd::FdIo,
-    functional::{run, ApplyExt},
-    io::ReadExt,
-    mem::DiscardResultExt,
-    mio::UnixStreamExt,
+    fd::FdIo, functional::run, io::ReadExt, mem::DiscardResultExt, mio::UnixStreamExt,
     result::OkExt,
 };
 use rosenpass_wireguard_broker::brokers::mio_client::MioBrokerClient;
+use rosenpass_wireguard_broker::brokers::native_unix::NativeUnixBrokerConfigBaseBuilder;
 
 use crate::{
-    api::{add_listen_socket_response_status, add_psk_broker_response_status},
-    app_server::AppServer,
-    protocol::BuildCryptoServer,
+    api::{
+        add_listen_socket_response_status, add_peer_request_protocol_version,
+        add_peer_response_status, add_psk_broker_response_status, event_type,
+        get_peer_status_response_status, get_stats_response_status, list_peers_response_status,
+        peer_status_handshake_state, peer_status_key_output, peer_status_session_role,
+        promote_key_response_status, remove_peer_response_status, subscribe_events_response_status,
+        trigger_handshake_response_status, EventPayload, LIST_PEERS_MAX_PEERS,
+    },
+    app_server::{
+        AppPeerPtr, AppServer, AppServerEvent, BrokerPeer, BrokerStorePtr, Endpoint,
+        KeyOutputReason,
+    },
+    config::ProtocolVersion,
+    hash_domains,
+    protocol::basic_types::{SPk, SymKey},
+    protocol::osk_domain_separator::OskDomainSeparator,
+    protocol::{
+        timing::Timing, BuildCryptoServer, HandshakeRole, HandshakeStateMachine, IdentityPtr,
+    },
 };
 
 use super::{supply_keypair_response_status, Server as ApiServer};
@@ -31,14 +45,51 @@ use super::{supply_keypair_response_status, Server as ApiServer};
 /// [ApiHandlerContext] is what actually contains the API handler functions.
 #[derive(Debug)]
 pub struct ApiHandler {
-    _dummy: (),
+    /// Events waiting to be sent to the client; only set once the client subscribed to events
+    /// through [ApiServer::subscribe_events]
+    event_queue: Option<VecDeque<EventPayload>>,
 }
 
+/// Maximum number of events waiting to be sent to a client subscribed through
+/// [ApiServer::subscribe_events]
+pub const MAX_QUEUED_EVENTS: usize = 1024;
+
 impl ApiHandler {
     /// Construct an [Self]
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self { _dummy: () }
+        Self { event_queue: None }
+    }
+
+    /// Whether the client subscribed to events through [ApiServer::subscribe_events]
+    pub fn is_subscribed(&self) -> bool {
+        self.event_queue.is_some()
+    }
+
+    /// Stop sending events to the client
+    pub fn unsubscribe(&mut self) {
+        self.event_queue = None;
+    }
+
+    /// Queue an event for sending to a subscribed client
+    ///
+    /// Fails if [MAX_QUEUED_EVENTS] events are queued already; does nothing if the client did
+    /// not subscribe to events.
+    pub fn push_event(&mut self, ev: EventPayload) -> anyhow::Result<()> {
+        let Some(queue) = self.event_queue.as_mut() else {
+            return Ok(());
+        };
+        ensure!(
+            queue.len() < MAX_QUEUED_EVENTS,
+            "Too many events queued for API client"
+        );
+        queue.push_back(ev);
+        Ok(())
+    }
+
+    /// Retrieve the next event to send to a subscribed client
+    pub fn pop_event(&mut self) -> Option<EventPayload> {
+        self.event_queue.as_mut()?.pop_front()
     }
 }
 
@@ -121,6 +172,88 @@ impl<T, E: Into<anyhow::Error>> SupplyKeypairErrorExt<T> for Result<T, E> {
     }
 }
 
+/// Decode a zero-padded UTF-8 string field from an API message.
+///
+/// Returns `None` if the field consists entirely of zero bytes.
+fn zero_padded_str(field: &[u8]) -> anyhow::Result<Option<&str>> {
+    let len = field.iter().position(|&c| c == 0).unwrap_or(field.len());
+    ensure!(
+        field[len..].iter().all(|&c| c == 0),
+        "String field contains data after the terminating zero byte"
+    );
+    match len {
+        0 => Ok(None),
+        _ => Ok(Some(std::str::from_utf8(&field[..len])?)),
+    }
+}
+
+/// Convert a point in time relative to the server's time base to the milliseconds used in
+/// API messages; points in time before the time base are clamped to zero.
+fn timing_to_api_ms(t: Timing) -> u64 {
+    (t * 1000.0) as u64
+}
+
+/// Encode an endpoint as the zero-padded UTF-8 string used in API messages
+///
+/// Overly long host names are truncated; the field is purely informational.
+fn endpoint_to_api(ep: &Endpoint) -> [u8; 256] {
+    let ep = ep.to_string();
+    let mut field = [0u8; 256];
+    let len = ep.len().min(field.len());
+    copy_slice(&ep.as_bytes()[..len]).to(&mut field[..len]);
+    field
+}
+
+/// Translate an [AppServerEvent] into the [EventPayload] sent to API clients subscribed through
+/// [ApiServer::subscribe_events]
+pub(crate) fn event_payload(srv: &AppServer, ev: &AppServerEvent) -> anyhow::Result<EventPayload> {
+    use zerocopy::FromZeroes;
+    use AppServerEvent as E;
+
+    let (typ, peer, endpoint) = match *ev {
+        E::KeyOutput(peer, KeyOutputReason::Exchanged) => {
+            (event_type::KEY_EXCHANGED, Some(peer), None)
+        }
+        E::KeyOutput(peer, KeyOutputReason::Stale) => (event_type::KEY_STALE, Some(peer), None),
+        E::HandshakeStarted(peer) => (event_type::HANDSHAKE_STARTED, Some(peer), None),
+        E::HandshakeRetransmitted(peer) => (event_type::HANDSHAKE_RETRANSMITTED, Some(peer), None),
+        E::HandshakeFailed(ep) => (event_type::HANDSHAKE_FAILED, None, Some(ep)),
+        E::CookieReplySent(ep) => (event_type::COOKIE_REPLY_SENT, None, Some(ep)),
+        E::PeerEndpointChanged(peer) => (event_type::PEER_ENDPOINT_CHANGED, Some(peer), None),
+        E::UnderLoadEntered => (event_type::UNDER_LOAD_ENTERED, None, None),
+        E::UnderLoadLeft => (event_type::UNDER_LOAD_LEFT, None, None),
+    };
+
+    let mut payload = EventPayload::new_zeroed();
+    payload.event_type = typ;
+    if let Ok(crypto) = srv.crypto_server() {
+        payload.time_ms = timing_to_api_ms(crypto.timebase.now());
+    }
+
+    // Events relating to a peer carry the endpoint of the peer
+    let endpoint = match peer {
+        Some(peer) => {
+            payload.peer_id = peer.lower().get(srv.crypto_server()?).pidt()?.value;
+            peer.get_app(srv).endpoint()
+        }
+        None => endpoint,
+    };
+    if let Some(ep) = endpoint {
+        payload.endpoint = endpoint_to_api(ep);
+    }
+
+    Ok(payload)
+}
+
+/// Pointer to the most recently registered WireGuard PSK broker
+///
+/// See the comment in [ApiServer::add_psk_broker] on why only the most recent broker is used.
+fn latest_broker_ptr(srv: &AppServer) -> Option<BrokerStorePtr> {
+    use zerocopy::AsBytes;
+    let idx = srv.brokers.store.len().checked_sub(1)? as u64;
+    Some(BrokerStorePtr(Public::from_slice(idx.as_bytes())))
+}
+
 impl<T> ApiServer for T
 where
     T: ?Sized + ApiHandlerContext,
@@ -312,19 +445,9 @@ where
         // just add event and capability support to the API and use the API to deliver OSK events.
         //
         // For now, we just replace the latest broker.
-        let erase_ptr = {
-            use crate::app_server::BrokerStorePtr;
-            //
-            use rosenpass_secret_memory::Public;
-            use zerocopy::AsBytes;
-            (self.app_server().brokers.store.len() - 1)
-                .apply(|x| x as u64)
-                .apply(|x| Public::from_slice(x.as_bytes()))
-                .apply(BrokerStorePtr)
-        };
-
         let register_result = run(|| {
             let srv = self.app_server_mut();
+            let erase_ptr = latest_broker_ptr(srv).context("No PSK broker registered")?;
             srv.unregister_broker(erase_ptr)?;
             srv.register_broker(client)
         });
@@ -338,4 +461,444 @@ where
         res.payload.status = add_psk_broker_response_status::OK;
         Ok(())
     }
+
+    fn add_peer(
+        &mut self,
+        req: &super::boilerplate::AddPeerRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::boilerplate::AddPeerResponse,
+    ) -> anyhow::Result<()> {
+        let payload = &req.payload;
+
+        // Parse the request
+        let params = run(|| -> anyhow::Result<_> {
+            let mut pk_io = FdIo(
+                req_fds
+                    .front()
+                    .context("First file descriptor, public key, missing.")?,
+            );
+            let mut pk = SPk::zero();
+            pk_io.read_exact_til_end(pk.borrow_mut())?;
+
+            let psk = match req_fds.get(1) {
+                None => None,
+                Some(fd) => {
+                    let mut psk = SymKey::zero();
+                    FdIo(fd).read_exact_til_end(psk.secret_mut())?;
+                    Some(psk)
+                }
+            };
+
+            let protocol_version = match payload.protocol_version {
+                add_peer_request_protocol_version::V02 => ProtocolVersion::V02,
+                add_peer_request_protocol_version::V03 => ProtocolVersion::V03,
+                v => bail!("Invalid protocol version {v}"),
+            };
+
+            let endpoint = zero_padded_str(&payload.endpoint)?;
+            if let Some(endpoint) = endpoint {
+                let port = endpoint.rsplit_once(':').map(|(_, port)| port);
+                ensure!(
+                    port.is_some_and(|p| p.parse::<u16>().is_ok()),
+                    "Endpoint {endpoint:?} must be of the form host:port"
+                );
+            }
+
+            let osk_organization = zero_padded_str(&payload.osk_organization)?;
+            let osk_label = zero_padded_str(&payload.osk_label)?;
+            let osk_domain_separator = match (osk_organization, osk_label) {
+                (None, None) => OskDomainSeparator::for_wireguard_psk(),
+                (Some(org), Some(label)) => {
+                    OskDomainSeparator::custom_utf8_single_label(org, label)
+                }
+                _ => bail!("OSK organization and label must be specified together"),
+            };
+
+            let wg_device = zero_padded_str(&payload.wg_device)?;
+            let wg_peer = payload.wg_peer;
+            let wg = match (wg_device, wg_peer != [0u8; 32]) {
+                (None, false) => None,
+                (Some(device), true) => Some((device.to_owned(), wg_peer)),
+                _ => bail!("WireGuard device and peer must be specified together"),
+            };
+
+            Ok((
+                pk,
+                psk,
+                protocol_version,
+                endpoint.map(str::to_owned),
+                osk_domain_separator,
+                wg,
+            ))
+        });
+
+        let (pk, psk, protocol_version, endpoint, osk_domain_separator, wg) = match params {
+            Ok(params) => params,
+            Err(e) => {
+                log::debug!(
+                    "Request found to be invalid while processing AddPeer API request: {e:?}"
+                );
+                res.payload.status = add_peer_response_status::INVALID_REQUEST;
+                return Ok(());
+            }
+        };
+
+        let keyed_hash = crate::protocol::ProtocolVersion::from(protocol_version).keyed_hash();
+        let peer_id = hash_domains::peerid(keyed_hash)?
+            .mix(pk.deref())?
+            .into_value();
+
+        // Refuse duplicate peers; if the crypto server has not been constructed yet,
+        // this is only detected once the keypair is supplied
+        let duplicate = self
+            .app_server()
+            .crypto_server()
+            .ok()
+            .and_then(|srv| srv.find_peer(Public::new(peer_id)))
+            .is_some();
+        if duplicate {
+            log::debug!("AddPeer API request for a peer that is registered already");
+            res.payload.status = add_peer_response_status::PEER_ALREADY_REGISTERED;
+            return Ok(());
+        }
+
+        // Register the peer
+        let register_result = run(|| -> anyhow::Result<AppPeerPtr> {
+            let srv = self.app_server_mut();
+
+            let broker_peer = match wg {
+                None => None,
+                Some((device, wg_peer)) => {
+                    let peer_cfg = NativeUnixBrokerConfigBaseBuilder::default()
+                        .peer_id(Public::new(wg_peer))
+                        .interface(device)
+                        .extra_params_ser(&Vec::new())?
+                        .build()
+                        .map_err(|e| anyhow::Error::msg(format!("{e:?}")))?;
+                    let broker = latest_broker_ptr(srv).context("No PSK broker registered")?;
+                    Some(BrokerPeer::new(broker, Box::new(peer_cfg)))
+                }
+            };
+
+            srv.add_peer(
+                psk,
+                pk,
+                None,
+                broker_peer,
+                endpoint,
+                protocol_version,
+                osk_domain_separator,
+            )
+        });
+
+        if let Err(e) = register_result {
+            log::warn!("Internal error while processing AddPeer API request: {e:?}");
+            res.payload.status = add_peer_response_status::INTERNAL_ERROR;
+            return Ok(());
+        }
+
+        res.payload.status = add_peer_response_status::OK;
+        res.payload.peer_id = peer_id;
+        Ok(())
+    }
+
+    fn remove_peer(
+        &mut self,
+        req: &super::boilerplate::RemovePeerRequest,
+        _req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::boilerplate::RemovePeerResponse,
+    ) -> anyhow::Result<()> {
+        let peer_id = Public::new(req.payload.peer_id);
+
+        // Look up the peer
+        let peer = match self.app_server().crypto_server() {
+            Ok(srv) => srv.find_peer(peer_id),
+            Err(e) => {
+                log::debug!("Could not process RemovePeer API request without server keys: {e:?}");
+                res.payload.status = remove_peer_response_status::INVALID_REQUEST;
+                return Ok(());
+            }
+        };
+
+        let Some(peer) = peer else {
+            log::debug!("RemovePeer API request for unknown peer {peer_id:?}");
+            res.payload.status = remove_peer_response_status::NO_SUCH_PEER;
+            return Ok(());
+        };
+
+        // Remove the peer
+        if let Err(e) = self.app_server_mut().remove_peer(AppPeerPtr::lift(peer)) {
+            log::warn!("Internal error while processing RemovePeer API request: {e:?}");
+            res.payload.status = remove_peer_response_status::INTERNAL_ERROR;
+            return Ok(());
+        }
+
+        res.payload.status = remove_peer_response_status::OK;
+        Ok(())
+    }
+
+    fn list_peers(
+        &mut self,
+        req: &super::boilerplate::ListPeersRequest,
+        _req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::boilerplate::ListPeersResponse,
+    ) -> anyhow::Result<()> {
+        let srv = match self.app_server().crypto_server() {
+            Ok(srv) => srv,
+            Err(e) => {
+                log::debug!("Could not process ListPeers API request without server keys: {e:?}");
+                res.payload.status = list_peers_response_status::INVALID_REQUEST;
+                return Ok(());
+            }
+        };
+
+        let offset = usize::try_from(req.payload.offset).unwrap_or(usize::MAX);
+        let peer_ids = run(|| -> anyhow::Result<Vec<[u8; 32]>> {
+            srv.peer_ptrs()
+                .skip(offset)
+                .take(LIST_PEERS_MAX_PEERS)
+                .map(|peer| Ok(peer.get(srv).pidt()?.value))
+                .collect()
+        });
+
+        let peer_ids = match peer_ids {
+            Ok(peer_ids) => peer_ids,
+            Err(e) => {
+                log::warn!("Internal error while processing ListPeers API request: {e:?}");
+                res.payload.status = list_peers_response_status::INTERNAL_ERROR;
+                return Ok(());
+            }
+        };
+
+        let mut ids = [[0u8; 32]; LIST_PEERS_MAX_PEERS];
+        for (dst, src) in ids.iter_mut().zip(peer_ids.iter()) {
+            *dst = *src;
+        }
+
+        res.payload.status = list_peers_response_status::OK;
+        res.payload.total = srv.peer_ptrs().count() as u64;
+        res.payload.count = peer_ids.len() as u64;
+        res.payload.peer_ids = ids;
+        Ok(())
+    }
+
+    fn get_peer_status(
+        &mut self,
+        req: &super::boilerplate::GetPeerStatusRequest,
+        _req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::boilerplate::GetPeerStatusResponse,
+    ) -> anyhow::Result<()> {
+        let peer_id = Public::new(req.payload.peer_id);
+
+        // Look up the peer
+        let srv = match self.app_server().crypto_server() {
+            Ok(srv) => srv,
+            Err(e) => {
+                log::debug!(
+                    "Could not process GetPeerStatus API request without server keys: {e:?}"
+                );
+                res.payload.status = get_peer_status_response_status::INVALID_REQUEST;
+                return Ok(());
+            }
+        };
+
+        let Some(peer) = srv.find_peer(peer_id) else {
+            log::debug!("GetPeerStatus API request for unknown peer {peer_id:?}");
+            res.payload.status = get_peer_status_response_status::NO_SUCH_PEER;
+            return Ok(());
+        };
+
+        let cp = peer.get(srv);
+        let ap = AppPeerPtr::lift(peer).get_app(self.app_server());
+        let payload = &mut res.payload;
+
+        payload.peer_id = peer_id.value;
+        payload.now_ms = timing_to_api_ms(srv.timebase.now());
+
+        if let Some(ses) = cp.session.as_ref() {
+            payload.session_role = match ses.handshake_role {
+                HandshakeRole::Initiator => peer_status_session_role::INITIATOR,
+                HandshakeRole::Responder => peer_status_session_role::RESPONDER,
+            };
+            payload.session_created_at_ms = timing_to_api_ms(ses.created_at);
+        }
+
+        if let Some(hs) = cp.handshake.as_ref() {
+            payload.handshake_state = match hs.next {
+                HandshakeStateMachine::RespHello => {
+                    peer_status_handshake_state::AWAITING_RESP_HELLO
+                }
+                HandshakeStateMachine::RespConf => peer_status_handshake_state::AWAITING_RESP_CONF,
+            };
+            payload.handshake_created_at_ms = timing_to_api_ms(hs.created_at);
+            payload.retransmissions = hs.tx_count as u64;
+        }
+
+        if let Some((why, at)) = ap.last_key_output {
+            payload.last_key_output = match why {
+                KeyOutputReason::Exchanged => peer_status_key_output::EXCHANGED,
+                KeyOutputReason::Stale => peer_status_key_output::STALE,
+            };
+            payload.last_key_output_at_ms = timing_to_api_ms(at);
+        }
+
+        if let Some(ep) = ap.endpoint() {
+            payload.endpoint = endpoint_to_api(ep);
+        }
+
+        payload.status = get_peer_status_response_status::OK;
+        Ok(())
+    }
+
+    fn trigger_handshake(
+        &mut self,
+        req: &super::boilerplate::TriggerHandshakeRequest,
+        _req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::boilerplate::TriggerHandshakeResponse,
+    ) -> anyhow::Result<()> {
+        let peer_id = Public::new(req.payload.peer_id);
+
+        // Look up the peer
+        let peer = match self.app_server().crypto_server() {
+            Ok(srv) => srv.find_peer(peer_id),
+            Err(e) => {
+                log::debug!(
+                    "Could not process TriggerHandshake API request without server keys: {e:?}"
+                );
+                res.payload.status = trigger_handshake_response_status::INVALID_REQUEST;
+                return Ok(());
+            }
+        };
+
+        let Some(peer) = peer else {
+            log::debug!("TriggerHandshake API request for unknown peer {peer_id:?}");
+            res.payload.status = trigger_handshake_response_status::NO_SUCH_PEER;
+            return Ok(());
+        };
+
+        // The app server silently drops initiations for peers without endpoint
+        if AppPeerPtr::lift(peer)
+            .get_app(self.app_server())
+            .endpoint()
+            .is_none()
+        {
+            log::debug!("TriggerHandshake API request for peer {peer_id:?} without endpoint");
+            res.payload.status = trigger_handshake_response_status::NO_ENDPOINT;
+            return Ok(());
+        }
+
+        // Mark the peer; the next poll of the crypto server produces the initiation
+        let forced = run(|| -> anyhow::Result<()> {
+            self.app_server_mut()
+                .crypto_server_mut()?
+                .force_initiation(peer)
+        });
+        if let Err(e) = forced {
+            log::warn!("Internal error while processing TriggerHandshake API request: {e:?}");
+            res.payload.status = trigger_handshake_response_status::INTERNAL_ERROR;
+            return Ok(());
+        }
+
+        res.payload.status = trigger_handshake_response_status::OK;
+        Ok(())
+    }
+
+    fn get_stats(
+        &mut self,
+        _req: &super::boilerplate::GetStatsRequest,
+        _req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::boilerplate::GetStatsResponse,
+    ) -> anyhow::Result<()> {
+        let srv = self.app_server();
+        let m = &srv.metrics;
+        let payload = &mut res.payload;
+        payload.peers = srv.metrics_peers();
+        payload.under_load = srv.metrics_under_load();
+        payload.received_init_hello = m.received_init_hello;
+        payload.received_resp_hello = m.received_resp_hello;
+        payload.received_init_conf = m.received_init_conf;
+        payload.received_empty_data = m.received_empty_data;
+        payload.received_cookie_reply = m.received_cookie_reply;
+        payload.received_unknown = m.received_unknown;
+        payload.decode_errors = m.decode_errors;
+        payload.handshake_failures = m.handshake_failures;
+        payload.handshakes_started = m.handshakes_started;
+        payload.retransmissions = m.retransmissions;
+        payload.cookie_replies_sent = m.cookie_replies_sent;
+        payload.keys_exchanged = m.keys_exchanged;
+        payload.keys_stale = m.keys_stale;
+        payload.under_load_entered = m.under_load_entered;
+        payload.under_load_left = m.under_load_left;
+        payload.broker_errors = m.broker_errors;
+        payload.rate_limited = m.rate_limited;
+        payload.kem_queue_overflows = m.kem_queue_overflows;
+        payload.status = get_stats_response_status::OK;
+        Ok(())
+    }
+
+    fn promote_key(
+        &mut self,
+        req: &super::boilerplate::PromoteKeyRequest,
+        _req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::boilerplate::PromoteKeyResponse,
+    ) -> anyhow::Result<()> {
+        let peer_id = Public::new(req.payload.peer_id);
+
+        // Look up the identity or peer; all zeros refers to the main keypair
+        let (identity, peer) = match self.app_server().crypto_server() {
+            Ok(_) if req.payload.peer_id == [0u8; 32] => (Some(IdentityPtr(0)), None),
+            Ok(srv) => (srv.find_identity(peer_id), srv.find_peer(peer_id)),
+            Err(e) => {
+                log::debug!("Could not process PromoteKey API request without server keys: {e:?}");
+                res.payload.status = promote_key_response_status::INVALID_REQUEST;
+                return Ok(());
+            }
+        };
+
+        let srv = self.app_server().crypto_server()?;
+        let has_next_key = match (identity, peer) {
+            (Some(identity), _) => identity.get(srv).has_next_key(),
+            (None, Some(peer)) => peer.get(srv).has_next_key(),
+            (None, None) => {
+                log::debug!("PromoteKey API request for unknown peer {peer_id:?}");
+                res.payload.status = promote_key_response_status::NO_SUCH_PEER;
+                return Ok(());
+            }
+        };
+        if !has_next_key {
+            log::debug!("PromoteKey API request for {peer_id:?} without next key");
+            res.payload.status = promote_key_response_status::NO_NEXT_KEY;
+            return Ok(());
+        }
+
+        let promoted = match (identity, peer) {
+            (Some(identity), _) => self.app_server_mut().promote_identity_key(identity),
+            (None, Some(peer)) => self
+                .app_server_mut()
+                .promote_peer_key(AppPeerPtr::lift(peer)),
+            (None, None) => unreachable!(),
+        };
+        if let Err(e) = promoted {
+            log::warn!("Internal error while processing PromoteKey API request: {e:?}");
+            res.payload.status = promote_key_response_status::INTERNAL_ERROR;
+            return Ok(());
+        }
+
+        res.payload.status = promote_key_response_status::OK;
+        Ok(())
+    }
+
+    fn subscribe_events(
+        &mut self,
+        _req: &super::boilerplate::SubscribeEventsRequest,
+        _req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::boilerplate::SubscribeEventsResponse,
+    ) -> anyhow::Result<()> {
+        let handler = self.api_handler_mut();
+        if !handler.is_subscribed() {
+            handler.event_queue = Some(VecDeque::new());
+        }
+        res.payload.status = subscribe_events_response_status::OK;
+        Ok(())
+    }
 }
@@ -257,6 +257,356 @@ pub trait ByteSliceRefExt: ByteSlice {
     ) -> anyhow::Result<Ref<Self, super::AddPskBrokerResponse>> {
         self.zk_parse_suffix()
     }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn add_peer_request(self) -> anyhow::Result<Ref<Self, super::AddPeerRequest>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn add_peer_request_from_prefix(self) -> anyhow::Result<Ref<Self, super::AddPeerRequest>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn add_peer_request_from_suffix(self) -> anyhow::Result<Ref<Self, super::AddPeerRequest>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn add_peer_response_maker(self) -> RefMaker<Self, super::AddPeerResponse> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn add_peer_response(self) -> anyhow::Result<Ref<Self, super::AddPeerResponse>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn add_peer_response_from_prefix(self) -> anyhow::Result<Ref<Self, super::AddPeerResponse>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn add_peer_response_from_suffix(self) -> anyhow::Result<Ref<Self, super::AddPeerResponse>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn remove_peer_request(self) -> anyhow::Result<Ref<Self, super::RemovePeerRequest>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn remove_peer_request_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::RemovePeerRequest>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn remove_peer_request_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::RemovePeerRequest>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn remove_peer_response_maker(self) -> RefMaker<Self, super::RemovePeerResponse> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn remove_peer_response(self) -> anyhow::Result<Ref<Self, super::RemovePeerResponse>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn remove_peer_response_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::RemovePeerResponse>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn remove_peer_response_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::RemovePeerResponse>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn list_peers_request(self) -> anyhow::Result<Ref<Self, super::ListPeersRequest>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn list_peers_request_from_prefix(self) -> anyhow::Result<Ref<Self, super::ListPeersRequest>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn list_peers_request_from_suffix(self) -> anyhow::Result<Ref<Self, super::ListPeersRequest>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn list_peers_response_maker(self) -> RefMaker<Self, super::ListPeersResponse> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn list_peers_response(self) -> anyhow::Result<Ref<Self, super::ListPeersResponse>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn list_peers_response_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::ListPeersResponse>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn list_peers_response_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::ListPeersResponse>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn get_peer_status_request(self) -> anyhow::Result<Ref<Self, super::GetPeerStatusRequest>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn get_peer_status_request_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::GetPeerStatusRequest>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn get_peer_status_request_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::GetPeerStatusRequest>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn get_peer_status_response_maker(self) -> RefMaker<Self, super::GetPeerStatusResponse> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn get_peer_status_response(self) -> anyhow::Result<Ref<Self, super::GetPeerStatusResponse>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn get_peer_status_response_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::GetPeerStatusResponse>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn get_peer_status_response_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::GetPeerStatusResponse>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn trigger_handshake_request(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::TriggerHandshakeRequest>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn trigger_handshake_request_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::TriggerHandshakeRequest>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn trigger_handshake_request_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::TriggerHandshakeRequest>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn trigger_handshake_response_maker(self) -> RefMaker<Self, super::TriggerHandshakeResponse> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn trigger_handshake_response(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::TriggerHandshakeResponse>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn trigger_handshake_response_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::TriggerHandshakeResponse>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn trigger_handshake_response_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::TriggerHandshakeResponse>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn get_stats_request(self) -> anyhow::Result<Ref<Self, super::GetStatsRequest>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn get_stats_request_from_prefix(self) -> anyhow::Result<Ref<Self, super::GetStatsRequest>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn get_stats_request_from_suffix(self) -> anyhow::Result<Ref<Self, super::GetStatsRequest>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn get_stats_response_maker(self) -> RefMaker<Self, super::GetStatsResponse> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn get_stats_response(self) -> anyhow::Result<Ref<Self, super::GetStatsResponse>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn get_stats_response_from_prefix(self) -> anyhow::Result<Ref<Self, super::GetStatsResponse>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn get_stats_response_from_suffix(self) -> anyhow::Result<Ref<Self, super::GetStatsResponse>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn promote_key_request(self) -> anyhow::Result<Ref<Self, super::PromoteKeyRequest>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn promote_key_request_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::PromoteKeyRequest>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn promote_key_request_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::PromoteKeyRequest>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn promote_key_response_maker(self) -> RefMaker<Self, super::PromoteKeyResponse> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn promote_key_response(self) -> anyhow::Result<Ref<Self, super::PromoteKeyResponse>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn promote_key_response_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::PromoteKeyResponse>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn promote_key_response_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::PromoteKeyResponse>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn subscribe_events_request(self) -> anyhow::Result<Ref<Self, super::SubscribeEventsRequest>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn subscribe_events_request_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::SubscribeEventsRequest>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn subscribe_events_request_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::SubscribeEventsRequest>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn subscribe_events_response_maker(self) -> RefMaker<Self, super::SubscribeEventsResponse> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn subscribe_events_response(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::SubscribeEventsResponse>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn subscribe_events_response_from_prefix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::SubscribeEventsResponse>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn subscribe_events_response_from_suffix(
+        self,
+    ) -> anyhow::Result<Ref<Self, super::SubscribeEventsResponse>> {
+        self.zk_parse_suffix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_ref_maker].
+    fn event_maker(self) -> RefMaker<Self, super::Event> {
+        self.zk_ref_maker()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse].
+    fn event(self) -> anyhow::Result<Ref<Self, super::Event>> {
+        self.zk_parse()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_prefix].
+    fn event_from_prefix(self) -> anyhow::Result<Ref<Self, super::Event>> {
+        self.zk_parse_prefix()
+    }
+
+    /// Shorthand for the typed use of [ZerocopySliceExt::zk_parse_suffix].
+    fn event_from_suffix(self) -> anyhow::Result<Ref<Self, super::Event>> {
+        self.zk_parse_suffix()
+    }
 }
 
 impl<B: ByteSlice> ByteSliceRefExt for B {}
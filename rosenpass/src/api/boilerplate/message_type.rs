@@ -35,6 +35,66 @@ const ADD_PSK_BROKER_REQUEST: RawMsgType =
 const ADD_PSK_BROKER_RESPONSE: RawMsgType =
     RawMsgType::from_le_bytes(hex!("bd25 e418 ffb0 6930    248b 217e 2fae e353"));
 
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Add Peer Request
+const ADD_PEER_REQUEST: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("69ac 7483 ce72 b30b    cf7f 37ad 40da a4a0"));
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Add Peer Response
+const ADD_PEER_RESPONSE: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("ff13 d033 8575 b2eb    3615 d0ad 81c7 4b34"));
+
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Remove Peer Request
+const REMOVE_PEER_REQUEST: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("d33c 778c 7434 40ab    d3cb 115c 5fd5 a18c"));
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Remove Peer Response
+const REMOVE_PEER_RESPONSE: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("c45e 842e 9c1f 377c    3bb3 3f0a d037 c867"));
+
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> List Peers Request
+const LIST_PEERS_REQUEST: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("97b8 a3ca 8d8c 924f    02c8 b967 1bfc 3775"));
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> List Peers Response
+const LIST_PEERS_RESPONSE: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("16d6 30ea 2182 edcd    3158 461c 928b 886c"));
+
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Get Peer Status Request
+const GET_PEER_STATUS_REQUEST: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("7048 4da2 8178 2207    46ea 0db7 ee93 1272"));
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Get Peer Status Response
+const GET_PEER_STATUS_RESPONSE: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("de5e da3b 7a58 ef8f    6a5e 604b b859 6e7f"));
+
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Trigger Handshake Request
+const TRIGGER_HANDSHAKE_REQUEST: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("162d 26e9 9e2b ae01    e313 ab61 ad23 6296"));
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Trigger Handshake Response
+const TRIGGER_HANDSHAKE_RESPONSE: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("c66f 2dc1 706f 479a    ba1a 8172 0a23 bd1a"));
+
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Get Stats Request
+const GET_STATS_REQUEST: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("881a 1e00 646f 94ba    d780 b61d f440 6a5a"));
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Get Stats Response
+const GET_STATS_RESPONSE: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("f270 87ba c386 ea16    0785 e547 203f d45b"));
+
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Promote Key Request
+const PROMOTE_KEY_REQUEST: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("484a b838 951e e281    df6a f556 1c43 00b8"));
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Promote Key Response
+const PROMOTE_KEY_RESPONSE: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("92d9 a438 63bd c96a    f6fc 3eb6 dd59 8ed3"));
+
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Subscribe Events Request
+const SUBSCRIBE_EVENTS_REQUEST: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("661a d283 1dfd c064    ea12 9f13 f9e4 247b"));
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Subscribe Events Response
+const SUBSCRIBE_EVENTS_RESPONSE: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("6a95 4367 0f7b 81e3    b66c dde0 d9a6 2cd8"));
+
+// hash domain hash of: Rosenpass IPC API -> Rosenpass Protocol Server -> Event
+const EVENT: RawMsgType =
+    RawMsgType::from_le_bytes(hex!("ac3b 62e8 7ff3 a28b    8a8b 5832 6124 4702"));
+
 /// Message properties global to the message type
 pub trait MessageAttributes {
     /// Get the size of the message
@@ -50,6 +110,14 @@ pub enum RequestMsgType {
     SupplyKeypair,
     AddListenSocket,
     AddPskBroker,
+    AddPeer,
+    RemovePeer,
+    ListPeers,
+    GetPeerStatus,
+    TriggerHandshake,
+    GetStats,
+    PromoteKey,
+    SubscribeEvents,
 }
 
 /// API response messages types as an enum
@@ -59,6 +127,15 @@ pub enum ResponseMsgType {
     SupplyKeypair,
     AddListenSocket,
     AddPskBroker,
+    AddPeer,
+    RemovePeer,
+    ListPeers,
+    GetPeerStatus,
+    TriggerHandshake,
+    GetStats,
+    PromoteKey,
+    SubscribeEvents,
+    Event,
 }
 
 impl MessageAttributes for RequestMsgType {
@@ -68,6 +145,14 @@ impl MessageAttributes for RequestMsgType {
             Self::SupplyKeypair => std::mem::size_of::<super::SupplyKeypairRequest>(),
             Self::AddListenSocket => std::mem::size_of::<super::AddListenSocketRequest>(),
             Self::AddPskBroker => std::mem::size_of::<super::AddPskBrokerRequest>(),
+            Self::AddPeer => std::mem::size_of::<super::AddPeerRequest>(),
+            Self::RemovePeer => std::mem::size_of::<super::RemovePeerRequest>(),
+            Self::ListPeers => std::mem::size_of::<super::ListPeersRequest>(),
+            Self::GetPeerStatus => std::mem::size_of::<super::GetPeerStatusRequest>(),
+            Self::TriggerHandshake => std::mem::size_of::<super::TriggerHandshakeRequest>(),
+            Self::GetStats => std::mem::size_of::<super::GetStatsRequest>(),
+            Self::PromoteKey => std::mem::size_of::<super::PromoteKeyRequest>(),
+            Self::SubscribeEvents => std::mem::size_of::<super::SubscribeEventsRequest>(),
         }
     }
 }
@@ -79,6 +164,15 @@ impl MessageAttributes for ResponseMsgType {
             Self::SupplyKeypair => std::mem::size_of::<super::SupplyKeypairResponse>(),
             Self::AddListenSocket => std::mem::size_of::<super::AddListenSocketResponse>(),
             Self::AddPskBroker => std::mem::size_of::<super::AddPskBrokerResponse>(),
+            Self::AddPeer => std::mem::size_of::<super::AddPeerResponse>(),
+            Self::RemovePeer => std::mem::size_of::<super::RemovePeerResponse>(),
+            Self::ListPeers => std::mem::size_of::<super::ListPeersResponse>(),
+            Self::GetPeerStatus => std::mem::size_of::<super::GetPeerStatusResponse>(),
+            Self::TriggerHandshake => std::mem::size_of::<super::TriggerHandshakeResponse>(),
+            Self::GetStats => std::mem::size_of::<super::GetStatsResponse>(),
+            Self::PromoteKey => std::mem::size_of::<super::PromoteKeyResponse>(),
+            Self::SubscribeEvents => std::mem::size_of::<super::SubscribeEventsResponse>(),
+            Self::Event => std::mem::size_of::<super::Event>(),
         }
     }
 }
@@ -93,6 +187,14 @@ impl TryFrom<RawMsgType> for RequestMsgType {
             self::SUPPLY_KEYPAIR_REQUEST => E::SupplyKeypair,
             self::ADD_LISTEN_SOCKET_REQUEST => E::AddListenSocket,
             self::ADD_PSK_BROKER_REQUEST => E::AddPskBroker,
+            self::ADD_PEER_REQUEST => E::AddPeer,
+            self::REMOVE_PEER_REQUEST => E::RemovePeer,
+            self::LIST_PEERS_REQUEST => E::ListPeers,
+            self::GET_PEER_STATUS_REQUEST => E::GetPeerStatus,
+            self::TRIGGER_HANDSHAKE_REQUEST => E::TriggerHandshake,
+            self::GET_STATS_REQUEST => E::GetStats,
+            self::PROMOTE_KEY_REQUEST => E::PromoteKey,
+            self::SUBSCRIBE_EVENTS_REQUEST => E::SubscribeEvents,
             _ => return Err(InvalidApiMessageType(value)),
         })
     }
@@ -106,6 +208,14 @@ impl From<RequestMsgType> for RawMsgType {
             E::SupplyKeypair => self::SUPPLY_KEYPAIR_REQUEST,
             E::AddListenSocket => self::ADD_LISTEN_SOCKET_REQUEST,
             E::AddPskBroker => self::ADD_PSK_BROKER_REQUEST,
+            E::AddPeer => self::ADD_PEER_REQUEST,
+            E::RemovePeer => self::REMOVE_PEER_REQUEST,
+            E::ListPeers => self::LIST_PEERS_REQUEST,
+            E::GetPeerStatus => self::GET_PEER_STATUS_REQUEST,
+            E::TriggerHandshake => self::TRIGGER_HANDSHAKE_REQUEST,
+            E::GetStats => self::GET_STATS_REQUEST,
+            E::PromoteKey => self::PROMOTE_KEY_REQUEST,
+            E::SubscribeEvents => self::SUBSCRIBE_EVENTS_REQUEST,
         }
     }
 }
@@ -120,6 +230,15 @@ impl TryFrom<RawMsgType> for ResponseMsgType {
             self::SUPPLY_KEYPAIR_RESPONSE => E::SupplyKeypair,
             self::ADD_LISTEN_SOCKET_RESPONSE => E::AddListenSocket,
             self::ADD_PSK_BROKER_RESPONSE => E::AddPskBroker,
+            self::ADD_PEER_RESPONSE => E::AddPeer,
+            self::REMOVE_PEER_RESPONSE => E::RemovePeer,
+            self::LIST_PEERS_RESPONSE => E::ListPeers,
+            self::GET_PEER_STATUS_RESPONSE => E::GetPeerStatus,
+            self::TRIGGER_HANDSHAKE_RESPONSE => E::TriggerHandshake,
+            self::GET_STATS_RESPONSE => E::GetStats,
+            self::PROMOTE_KEY_RESPONSE => E::PromoteKey,
+            self::SUBSCRIBE_EVENTS_RESPONSE => E::SubscribeEvents,
+            self::EVENT => E::Event,
             _ => return Err(InvalidApiMessageType(value)),
         })
     }
@@ -133,6 +252,15 @@ impl From<ResponseMsgType> for RawMsgType {
             E::SupplyKeypair => self::SUPPLY_KEYPAIR_RESPONSE,
             E::AddListenSocket => self::ADD_LISTEN_SOCKET_RESPONSE,
             E::AddPskBroker => self::ADD_PSK_BROKER_RESPONSE,
+            E::AddPeer => self::ADD_PEER_RESPONSE,
+            E::RemovePeer => self::REMOVE_PEER_RESPONSE,
+            E::ListPeers => self::LIST_PEERS_RESPONSE,
+            E::GetPeerStatus => self::GET_PEER_STATUS_RESPONSE,
+            E::TriggerHandshake => self::TRIGGER_HANDSHAKE_RESPONSE,
+            E::GetStats => self::GET_STATS_RESPONSE,
+            E::PromoteKey => self::PROMOTE_KEY_RESPONSE,
+            E::SubscribeEvents => self::SUBSCRIBE_EVENTS_RESPONSE,
+            E::Event => self::EVENT,
         }
     }
 }
@@ -396,3 +396,991 @@ impl Message for AddPskBrokerResponse {
         self.msg_type = Self::MESSAGE_TYPE.into();
     }
 }
+
+#[allow(missing_docs)]
+pub mod add_peer_request_protocol_version {
+    #[allow(missing_docs)]
+    pub const V02: u128 = 0;
+    #[allow(missing_docs)]
+    pub const V03: u128 = 1;
+}
+
+/// Payload of [AddPeerRequest]
+///
+/// All string fields are UTF-8 encoded and padded with zero bytes; a field
+/// consisting entirely of zero bytes is treated as not being set.
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct AddPeerRequestPayload {
+    /// Protocol version used with this peer; see [add_peer_request_protocol_version]
+    pub protocol_version: u128,
+    /// Hostname and port to connect to (e.g. `rosenpass.eu:1427`); optional
+    ///
+    /// The hostname is resolved in the background; see [crate::endpoint_resolver].
+    pub endpoint: [u8; 256],
+    /// Organization part of a custom OSK domain separator; optional
+    ///
+    /// Must be set if and only if [Self::osk_label] is set. If neither is set, the
+    /// WireGuard PSK domain separator is used.
+    pub osk_organization: [u8; 256],
+    /// Label part of a custom OSK domain separator; optional
+    pub osk_label: [u8; 256],
+    /// Name of the WireGuard interface to supply with pre-shared keys; optional
+    ///
+    /// Must be set if and only if [Self::wg_peer] is set.
+    pub wg_device: [u8; 16],
+    /// WireGuard public key of the peer (raw bytes, not base64)
+    pub wg_peer: [u8; 32],
+}
+
+#[allow(missing_docs)]
+pub type AddPeerRequest = RequestEnvelope<AddPeerRequestPayload>;
+
+impl Default for AddPeerRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AddPeerRequest {
+    /// Construct a request for a peer without endpoint, using protocol version
+    /// [add_peer_request_protocol_version::V02], the WireGuard PSK domain separator and no
+    /// WireGuard configuration
+    pub fn new() -> Self {
+        Self::from_payload(AddPeerRequestPayload::new_zeroed())
+    }
+}
+
+impl Message for AddPeerRequest {
+    type Payload = AddPeerRequestPayload;
+    type MessageClass = RequestMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = RequestMsgType::AddPeer;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+pub mod add_peer_response_status {
+    #[allow(missing_docs)]
+    pub const OK: u128 = 0;
+    #[allow(missing_docs)]
+    pub const INVALID_REQUEST: u128 = 1;
+    #[allow(missing_docs)]
+    pub const INTERNAL_ERROR: u128 = 2;
+    #[allow(missing_docs)]
+    pub const PEER_ALREADY_REGISTERED: u128 = 3;
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct AddPeerResponsePayload {
+    #[allow(missing_docs)]
+    pub status: u128,
+    /// Rosenpass peer id of the newly added peer; used to identify the peer in
+    /// [RemovePeerRequest]. Zero unless the request was successful.
+    pub peer_id: [u8; 32],
+}
+
+#[allow(missing_docs)]
+pub type AddPeerResponse = ResponseEnvelope<AddPeerResponsePayload>;
+
+impl AddPeerResponse {
+    #[allow(missing_docs)]
+    pub fn new(status: u128, peer_id: [u8; 32]) -> Self {
+        Self::from_payload(AddPeerResponsePayload { status, peer_id })
+    }
+}
+
+impl Message for AddPeerResponse {
+    type Payload = AddPeerResponsePayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::AddPeer;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct RemovePeerRequestPayload {
+    /// Rosenpass peer id of the peer to remove, as returned in [AddPeerResponse]
+    pub peer_id: [u8; 32],
+}
+
+#[allow(missing_docs)]
+pub type RemovePeerRequest = RequestEnvelope<RemovePeerRequestPayload>;
+
+impl RemovePeerRequest {
+    #[allow(missing_docs)]
+    pub fn new(peer_id: [u8; 32]) -> Self {
+        Self::from_payload(RemovePeerRequestPayload { peer_id })
+    }
+}
+
+impl Message for RemovePeerRequest {
+    type Payload = RemovePeerRequestPayload;
+    type MessageClass = RequestMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = RequestMsgType::RemovePeer;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+pub mod remove_peer_response_status {
+    #[allow(missing_docs)]
+    pub const OK: u128 = 0;
+    #[allow(missing_docs)]
+    pub const INVALID_REQUEST: u128 = 1;
+    #[allow(missing_docs)]
+    pub const INTERNAL_ERROR: u128 = 2;
+    #[allow(missing_docs)]
+    pub const NO_SUCH_PEER: u128 = 3;
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct RemovePeerResponsePayload {
+    #[allow(missing_docs)]
+    pub status: u128,
+}
+
+#[allow(missing_docs)]
+pub type RemovePeerResponse = ResponseEnvelope<RemovePeerResponsePayload>;
+
+impl RemovePeerResponse {
+    #[allow(missing_docs)]
+    pub fn new(status: u128) -> Self {
+        Self::from_payload(RemovePeerResponsePayload { status })
+    }
+}
+
+impl Message for RemovePeerResponse {
+    type Payload = RemovePeerResponsePayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::RemovePeer;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+/// Maximum number of peer ids returned in a single [ListPeersResponse]
+pub const LIST_PEERS_MAX_PEERS: usize = 64;
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct ListPeersRequestPayload {
+    /// Number of peers to skip; used to retrieve more than [LIST_PEERS_MAX_PEERS] peers
+    /// through multiple requests
+    pub offset: u64,
+}
+
+#[allow(missing_docs)]
+pub type ListPeersRequest = RequestEnvelope<ListPeersRequestPayload>;
+
+impl ListPeersRequest {
+    #[allow(missing_docs)]
+    pub fn new(offset: u64) -> Self {
+        Self::from_payload(ListPeersRequestPayload { offset })
+    }
+}
+
+impl Message for ListPeersRequest {
+    type Payload = ListPeersRequestPayload;
+    type MessageClass = RequestMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = RequestMsgType::ListPeers;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+pub mod list_peers_response_status {
+    #[allow(missing_docs)]
+    pub const OK: u128 = 0;
+    #[allow(missing_docs)]
+    pub const INVALID_REQUEST: u128 = 1;
+    #[allow(missing_docs)]
+    pub const INTERNAL_ERROR: u128 = 2;
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct ListPeersResponsePayload {
+    #[allow(missing_docs)]
+    pub status: u128,
+    /// Total number of peers registered with the server
+    pub total: u64,
+    /// Number of valid entries in [Self::peer_ids]
+    pub count: u64,
+    /// Rosenpass peer ids, as returned in [AddPeerResponse]; only the first [Self::count]
+    /// entries are set
+    pub peer_ids: [[u8; 32]; LIST_PEERS_MAX_PEERS],
+}
+
+impl ListPeersResponsePayload {
+    /// The peer ids actually contained in this response
+    pub fn peer_ids(&self) -> &[[u8; 32]] {
+        let count = (self.count as usize).min(LIST_PEERS_MAX_PEERS);
+        &self.peer_ids[..count]
+    }
+}
+
+#[allow(missing_docs)]
+pub type ListPeersResponse = ResponseEnvelope<ListPeersResponsePayload>;
+
+impl ListPeersResponse {
+    #[allow(missing_docs)]
+    pub fn new(status: u128) -> Self {
+        Self::from_payload(ListPeersResponsePayload {
+            status,
+            total: 0,
+            count: 0,
+            peer_ids: [[0u8; 32]; LIST_PEERS_MAX_PEERS],
+        })
+    }
+}
+
+impl Message for ListPeersResponse {
+    type Payload = ListPeersResponsePayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::ListPeers;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct GetPeerStatusRequestPayload {
+    /// Rosenpass peer id of the peer to inspect, as returned in [AddPeerResponse] or
+    /// [ListPeersResponse]
+    pub peer_id: [u8; 32],
+}
+
+#[allow(missing_docs)]
+pub type GetPeerStatusRequest = RequestEnvelope<GetPeerStatusRequestPayload>;
+
+impl GetPeerStatusRequest {
+    #[allow(missing_docs)]
+    pub fn new(peer_id: [u8; 32]) -> Self {
+        Self::from_payload(GetPeerStatusRequestPayload { peer_id })
+    }
+}
+
+impl Message for GetPeerStatusRequest {
+    type Payload = GetPeerStatusRequestPayload;
+    type MessageClass = RequestMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = RequestMsgType::GetPeerStatus;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+pub mod get_peer_status_response_status {
+    #[allow(missing_docs)]
+    pub const OK: u128 = 0;
+    #[allow(missing_docs)]
+    pub const INVALID_REQUEST: u128 = 1;
+    #[allow(missing_docs)]
+    pub const INTERNAL_ERROR: u128 = 2;
+    #[allow(missing_docs)]
+    pub const NO_SUCH_PEER: u128 = 3;
+}
+
+/// Values for [GetPeerStatusResponsePayload::session_role]
+pub mod peer_status_session_role {
+    /// There is no established session with the peer
+    pub const NONE: u128 = 0;
+    /// We were the initiator of the handshake that established the session
+    pub const INITIATOR: u128 = 1;
+    /// We were the responder in the handshake that established the session
+    pub const RESPONDER: u128 = 2;
+}
+
+/// Values for [GetPeerStatusResponsePayload::handshake_state]
+pub mod peer_status_handshake_state {
+    /// We are not currently initiating a handshake with the peer
+    pub const NONE: u128 = 0;
+    /// We sent an InitHello message and are waiting for RespHello
+    pub const AWAITING_RESP_HELLO: u128 = 1;
+    /// We sent an InitConf message and are waiting for the responder's confirmation
+    pub const AWAITING_RESP_CONF: u128 = 2;
+}
+
+/// Values for [GetPeerStatusResponsePayload::last_key_output]
+pub mod peer_status_key_output {
+    /// No key was output for this peer yet
+    pub const NONE: u128 = 0;
+    /// The last key output was a freshly exchanged key
+    pub const EXCHANGED: u128 = 1;
+    /// The last key output erased a stale key
+    pub const STALE: u128 = 2;
+}
+
+/// Status of a single peer.
+///
+/// All points in time are given in milliseconds relative to the server's time base, i.e. the
+/// point in time at which the crypto server was constructed. Use [Self::now_ms] to turn them
+/// into ages.
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct GetPeerStatusResponsePayload {
+    #[allow(missing_docs)]
+    pub status: u128,
+    /// Rosenpass peer id of the peer
+    pub peer_id: [u8; 32],
+    /// The server's current time
+    pub now_ms: u64,
+    /// Our role in the handshake that established the current session; see
+    /// [peer_status_session_role]
+    pub session_role: u128,
+    /// When the current session was established; zero if there is no session
+    pub session_created_at_ms: u64,
+    /// State of the handshake we are currently initiating; see [peer_status_handshake_state]
+    pub handshake_state: u128,
+    /// When the ongoing handshake was started; zero if there is no handshake
+    pub handshake_created_at_ms: u64,
+    /// Number of times the last message of the ongoing handshake was retransmitted
+    pub retransmissions: u64,
+    /// Reason for the last key output; see [peer_status_key_output]
+    pub last_key_output: u128,
+    /// When the last key was output; zero if no key was output yet
+    pub last_key_output_at_ms: u64,
+    /// The endpoint currently used to reach the peer, as a zero-padded UTF-8 string.
+    /// Entirely zero if the endpoint is not known.
+    pub endpoint: [u8; 256],
+}
+
+#[allow(missing_docs)]
+pub type GetPeerStatusResponse = ResponseEnvelope<GetPeerStatusResponsePayload>;
+
+impl GetPeerStatusResponse {
+    #[allow(missing_docs)]
+    pub fn new(status: u128) -> Self {
+        let mut payload = GetPeerStatusResponsePayload::new_zeroed();
+        payload.status = status;
+        Self::from_payload(payload)
+    }
+}
+
+impl Message for GetPeerStatusResponse {
+    type Payload = GetPeerStatusResponsePayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::GetPeerStatus;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct TriggerHandshakeRequestPayload {
+    /// Rosenpass peer id of the peer to start a handshake with, as returned in
+    /// [AddPeerResponse] or [ListPeersResponse]
+    pub peer_id: [u8; 32],
+}
+
+#[allow(missing_docs)]
+pub type TriggerHandshakeRequest = RequestEnvelope<TriggerHandshakeRequestPayload>;
+
+impl TriggerHandshakeRequest {
+    #[allow(missing_docs)]
+    pub fn new(peer_id: [u8; 32]) -> Self {
+        Self::from_payload(TriggerHandshakeRequestPayload { peer_id })
+    }
+}
+
+impl Message for TriggerHandshakeRequest {
+    type Payload = TriggerHandshakeRequestPayload;
+    type MessageClass = RequestMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = RequestMsgType::TriggerHandshake;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+pub mod trigger_handshake_response_status {
+    #[allow(missing_docs)]
+    pub const OK: u128 = 0;
+    #[allow(missing_docs)]
+    pub const INVALID_REQUEST: u128 = 1;
+    #[allow(missing_docs)]
+    pub const INTERNAL_ERROR: u128 = 2;
+    #[allow(missing_docs)]
+    pub const NO_SUCH_PEER: u128 = 3;
+    /// The peer exists, but no network endpoint is known for it, so no InitHello
+    /// message can be sent
+    pub const NO_ENDPOINT: u128 = 4;
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct TriggerHandshakeResponsePayload {
+    #[allow(missing_docs)]
+    pub status: u128,
+}
+
+#[allow(missing_docs)]
+pub type TriggerHandshakeResponse = ResponseEnvelope<TriggerHandshakeResponsePayload>;
+
+impl TriggerHandshakeResponse {
+    #[allow(missing_docs)]
+    pub fn new(status: u128) -> Self {
+        Self::from_payload(TriggerHandshakeResponsePayload { status })
+    }
+}
+
+impl Message for TriggerHandshakeResponse {
+    type Payload = TriggerHandshakeResponsePayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::TriggerHandshake;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct GetStatsRequestPayload {}
+
+#[allow(missing_docs)]
+pub type GetStatsRequest = RequestEnvelope<GetStatsRequestPayload>;
+
+impl Default for GetStatsRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GetStatsRequest {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        Self::from_payload(GetStatsRequestPayload {})
+    }
+}
+
+impl Message for GetStatsRequest {
+    type Payload = GetStatsRequestPayload;
+    type MessageClass = RequestMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = RequestMsgType::GetStats;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+pub mod get_stats_response_status {
+    #[allow(missing_docs)]
+    pub const OK: u128 = 0;
+    #[allow(missing_docs)]
+    pub const INVALID_REQUEST: u128 = 1;
+    #[allow(missing_docs)]
+    pub const INTERNAL_ERROR: u128 = 2;
+}
+
+/// Counters describing the operation of the server; see [crate::metrics::Metrics] for a
+/// description of the individual counters
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct GetStatsResponsePayload {
+    #[allow(missing_docs)]
+    pub status: u128,
+    /// Number of peers configured
+    pub peers: u64,
+    /// One if the server currently operates under load, zero otherwise
+    pub under_load: u64,
+    pub received_init_hello: u64,
+    pub received_resp_hello: u64,
+    pub received_init_conf: u64,
+    pub received_empty_data: u64,
+    pub received_cookie_reply: u64,
+    pub received_unknown: u64,
+    pub decode_errors: u64,
+    pub handshake_failures: u64,
+    pub handshakes_started: u64,
+    pub retransmissions: u64,
+    pub cookie_replies_sent: u64,
+    pub keys_exchanged: u64,
+    pub keys_stale: u64,
+    pub under_load_entered: u64,
+    pub under_load_left: u64,
+    pub broker_errors: u64,
+    pub rate_limited: u64,
+    pub kem_queue_overflows: u64,
+}
+
+#[allow(missing_docs)]
+pub type GetStatsResponse = ResponseEnvelope<GetStatsResponsePayload>;
+
+impl GetStatsResponse {
+    #[allow(missing_docs)]
+    pub fn new(status: u128) -> Self {
+        let mut payload = GetStatsResponsePayload::new_zeroed();
+        payload.status = status;
+        Self::from_payload(payload)
+    }
+}
+
+impl Message for GetStatsResponse {
+    type Payload = GetStatsResponsePayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::GetStats;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct PromoteKeyRequestPayload {
+    /// Rosenpass peer id of the peer whose next public key to promote, as returned in
+    /// [AddPeerResponse] or [ListPeersResponse]
+    ///
+    /// May also be the peer id of one of the identities of the server, i.e. the peer id other
+    /// servers know it by, to promote the next keypair of that identity. All zeros refers to the
+    /// main keypair of the server.
+    pub peer_id: [u8; 32],
+}
+
+#[allow(missing_docs)]
+pub type PromoteKeyRequest = RequestEnvelope<PromoteKeyRequestPayload>;
+
+impl PromoteKeyRequest {
+    #[allow(missing_docs)]
+    pub fn new(peer_id: [u8; 32]) -> Self {
+        Self::from_payload(PromoteKeyRequestPayload { peer_id })
+    }
+}
+
+impl Message for PromoteKeyRequest {
+    type Payload = PromoteKeyRequestPayload;
+    type MessageClass = RequestMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = RequestMsgType::PromoteKey;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+pub mod promote_key_response_status {
+    #[allow(missing_docs)]
+    pub const OK: u128 = 0;
+    #[allow(missing_docs)]
+    pub const INVALID_REQUEST: u128 = 1;
+    #[allow(missing_docs)]
+    pub const INTERNAL_ERROR: u128 = 2;
+    /// There is neither a peer nor an identity with the given peer id
+    pub const NO_SUCH_PEER: u128 = 3;
+    /// The peer or identity exists, but no next key was configured for it
+    pub const NO_NEXT_KEY: u128 = 4;
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct PromoteKeyResponsePayload {
+    #[allow(missing_docs)]
+    pub status: u128,
+}
+
+#[allow(missing_docs)]
+pub type PromoteKeyResponse = ResponseEnvelope<PromoteKeyResponsePayload>;
+
+impl PromoteKeyResponse {
+    #[allow(missing_docs)]
+    pub fn new(status: u128) -> Self {
+        Self::from_payload(PromoteKeyResponsePayload { status })
+    }
+}
+
+impl Message for PromoteKeyResponse {
+    type Payload = PromoteKeyResponsePayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::PromoteKey;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct SubscribeEventsRequestPayload {}
+
+#[allow(missing_docs)]
+pub type SubscribeEventsRequest = RequestEnvelope<SubscribeEventsRequestPayload>;
+
+impl Default for SubscribeEventsRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SubscribeEventsRequest {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        Self::from_payload(SubscribeEventsRequestPayload {})
+    }
+}
+
+impl Message for SubscribeEventsRequest {
+    type Payload = SubscribeEventsRequestPayload;
+    type MessageClass = RequestMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = RequestMsgType::SubscribeEvents;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+#[allow(missing_docs)]
+pub mod subscribe_events_response_status {
+    #[allow(missing_docs)]
+    pub const OK: u128 = 0;
+    #[allow(missing_docs)]
+    pub const INVALID_REQUEST: u128 = 1;
+    #[allow(missing_docs)]
+    pub const INTERNAL_ERROR: u128 = 2;
+}
+
+#[allow(missing_docs)]
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct SubscribeEventsResponsePayload {
+    #[allow(missing_docs)]
+    pub status: u128,
+}
+
+#[allow(missing_docs)]
+pub type SubscribeEventsResponse = ResponseEnvelope<SubscribeEventsResponsePayload>;
+
+impl SubscribeEventsResponse {
+    #[allow(missing_docs)]
+    pub fn new(status: u128) -> Self {
+        Self::from_payload(SubscribeEventsResponsePayload { status })
+    }
+}
+
+impl Message for SubscribeEventsResponse {
+    type Payload = SubscribeEventsResponsePayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::SubscribeEvents;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
+
+/// Values for [EventPayload::event_type]
+pub mod event_type {
+    /// A new key was exchanged with the peer
+    pub const KEY_EXCHANGED: u128 = 1;
+    /// No key could be exchanged with the peer in time; the previous key was erased
+    pub const KEY_STALE: u128 = 2;
+    /// We sent an InitHello message to the peer, starting a new handshake as initiator
+    pub const HANDSHAKE_STARTED: u128 = 3;
+    /// Processing a handshake message received from [EventPayload::endpoint] failed; the peer
+    /// is generally not known in this case
+    pub const HANDSHAKE_FAILED: u128 = 4;
+    /// We retransmitted the last handshake message sent to the peer
+    pub const HANDSHAKE_RETRANSMITTED: u128 = 5;
+    /// We answered a message from [EventPayload::endpoint] with a cookie reply, because the
+    /// server is under load
+    pub const COOKIE_REPLY_SENT: u128 = 6;
+    /// The endpoint used to reach the peer changed; the new endpoint is given in
+    /// [EventPayload::endpoint]
+    pub const PEER_ENDPOINT_CHANGED: u128 = 7;
+    /// The server detected that it is under load and enabled its DoS mitigation
+    pub const UNDER_LOAD_ENTERED: u128 = 8;
+    /// The server is no longer under load
+    pub const UNDER_LOAD_LEFT: u128 = 9;
+}
+
+/// An event pushed to API clients after they sent a [SubscribeEventsRequest]
+///
+/// Fields not applicable to a particular event are zero.
+#[repr(packed)]
+#[derive(Debug, Copy, Clone, Hash, AsBytes, FromBytes, FromZeroes, PartialEq, Eq)]
+pub struct EventPayload {
+    /// What happened; see [event_type]
+    pub event_type: u128,
+    /// Rosenpass peer id of the peer the event relates to
+    pub peer_id: [u8; 32],
+    /// When the event happened, in milliseconds relative to the server's time base (see
+    /// [GetPeerStatusResponsePayload])
+    pub time_ms: u64,
+    /// Network endpoint the event relates to, as a zero-padded UTF-8 string
+    pub endpoint: [u8; 256],
+}
+
+#[allow(missing_docs)]
+pub type Event = ResponseEnvelope<EventPayload>;
+
+impl Event {
+    #[allow(missing_docs)]
+    pub fn new(event_type: u128) -> Self {
+        let mut payload = EventPayload::new_zeroed();
+        payload.event_type = event_type;
+        Self::from_payload(payload)
+    }
+}
+
+impl Message for Event {
+    type Payload = EventPayload;
+    type MessageClass = ResponseMsgType;
+    const MESSAGE_TYPE: Self::MessageClass = ResponseMsgType::Event;
+
+    fn from_payload(payload: Self::Payload) -> Self {
+        Self {
+            msg_type: Self::MESSAGE_TYPE.into(),
+            payload,
+        }
+    }
+
+    fn setup<B: ByteSliceMut>(buf: B) -> anyhow::Result<Ref<B, Self>> {
+        let mut r: Ref<B, Self> = buf.zk_zeroized()?;
+        r.init();
+        Ok(r)
+    }
+
+    fn init(&mut self) {
+        self.msg_type = Self::MESSAGE_TYPE.into();
+    }
+}
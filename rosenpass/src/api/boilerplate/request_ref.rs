@@ -67,6 +67,14 @@ impl<B: ByteSlice> RequestRef<B> {
             Self::SupplyKeypair(_) => RequestMsgType::SupplyKeypair,
             Self::AddListenSocket(_) => RequestMsgType::AddListenSocket,
             Self::AddPskBroker(_) => RequestMsgType::AddPskBroker,
+            Self::AddPeer(_) => RequestMsgType::AddPeer,
+            Self::RemovePeer(_) => RequestMsgType::RemovePeer,
+            Self::ListPeers(_) => RequestMsgType::ListPeers,
+            Self::GetPeerStatus(_) => RequestMsgType::GetPeerStatus,
+            Self::TriggerHandshake(_) => RequestMsgType::TriggerHandshake,
+            Self::GetStats(_) => RequestMsgType::GetStats,
+            Self::PromoteKey(_) => RequestMsgType::PromoteKey,
+            Self::SubscribeEvents(_) => RequestMsgType::SubscribeEvents,
         }
     }
 }
@@ -95,6 +103,54 @@ impl<B> From<Ref<B, super::AddPskBrokerRequest>> for RequestRef<B> {
     }
 }
 
+impl<B> From<Ref<B, super::AddPeerRequest>> for RequestRef<B> {
+    fn from(v: Ref<B, super::AddPeerRequest>) -> Self {
+        Self::AddPeer(v)
+    }
+}
+
+impl<B> From<Ref<B, super::RemovePeerRequest>> for RequestRef<B> {
+    fn from(v: Ref<B, super::RemovePeerRequest>) -> Self {
+        Self::RemovePeer(v)
+    }
+}
+
+impl<B> From<Ref<B, super::ListPeersRequest>> for RequestRef<B> {
+    fn from(v: Ref<B, super::ListPeersRequest>) -> Self {
+        Self::ListPeers(v)
+    }
+}
+
+impl<B> From<Ref<B, super::GetPeerStatusRequest>> for RequestRef<B> {
+    fn from(v: Ref<B, super::GetPeerStatusRequest>) -> Self {
+        Self::GetPeerStatus(v)
+    }
+}
+
+impl<B> From<Ref<B, super::TriggerHandshakeRequest>> for RequestRef<B> {
+    fn from(v: Ref<B, super::TriggerHandshakeRequest>) -> Self {
+        Self::TriggerHandshake(v)
+    }
+}
+
+impl<B> From<Ref<B, super::GetStatsRequest>> for RequestRef<B> {
+    fn from(v: Ref<B, super::GetStatsRequest>) -> Self {
+        Self::GetStats(v)
+    }
+}
+
+impl<B> From<Ref<B, super::PromoteKeyRequest>> for RequestRef<B> {
+    fn from(v: Ref<B, super::PromoteKeyRequest>) -> Self {
+        Self::PromoteKey(v)
+    }
+}
+
+impl<B> From<Ref<B, super::SubscribeEventsRequest>> for RequestRef<B> {
+    fn from(v: Ref<B, super::SubscribeEventsRequest>) -> Self {
+        Self::SubscribeEvents(v)
+    }
+}
+
 impl<B: ByteSlice> RequestRefMaker<B> {
     fn new(buf: B) -> anyhow::Result<Self> {
         let msg_type = buf.deref().request_msg_type_from_prefix()?;
@@ -117,6 +173,20 @@ impl<B: ByteSlice> RequestRefMaker<B> {
             RequestMsgType::AddPskBroker => {
                 RequestRef::AddPskBroker(self.buf.add_psk_broker_request()?)
             }
+            RequestMsgType::AddPeer => RequestRef::AddPeer(self.buf.add_peer_request()?),
+            RequestMsgType::RemovePeer => RequestRef::RemovePeer(self.buf.remove_peer_request()?),
+            RequestMsgType::ListPeers => RequestRef::ListPeers(self.buf.list_peers_request()?),
+            RequestMsgType::GetPeerStatus => {
+                RequestRef::GetPeerStatus(self.buf.get_peer_status_request()?)
+            }
+            RequestMsgType::TriggerHandshake => {
+                RequestRef::TriggerHandshake(self.buf.trigger_handshake_request()?)
+            }
+            RequestMsgType::GetStats => RequestRef::GetStats(self.buf.get_stats_request()?),
+            RequestMsgType::PromoteKey => RequestRef::PromoteKey(self.buf.promote_key_request()?),
+            RequestMsgType::SubscribeEvents => {
+                RequestRef::SubscribeEvents(self.buf.subscribe_events_request()?)
+            }
         })
     }
 
@@ -155,6 +225,14 @@ pub enum RequestRef<B> {
     SupplyKeypair(Ref<B, super::SupplyKeypairRequest>),
     AddListenSocket(Ref<B, super::AddListenSocketRequest>),
     AddPskBroker(Ref<B, super::AddPskBrokerRequest>),
+    AddPeer(Ref<B, super::AddPeerRequest>),
+    RemovePeer(Ref<B, super::RemovePeerRequest>),
+    ListPeers(Ref<B, super::ListPeersRequest>),
+    GetPeerStatus(Ref<B, super::GetPeerStatusRequest>),
+    TriggerHandshake(Ref<B, super::TriggerHandshakeRequest>),
+    GetStats(Ref<B, super::GetStatsRequest>),
+    PromoteKey(Ref<B, super::PromoteKeyRequest>),
+    SubscribeEvents(Ref<B, super::SubscribeEventsRequest>),
 }
 
 impl<B> RequestRef<B>
@@ -172,6 +250,14 @@ where
             Self::SupplyKeypair(r) => r.bytes(),
             Self::AddListenSocket(r) => r.bytes(),
             Self::AddPskBroker(r) => r.bytes(),
+            Self::AddPeer(r) => r.bytes(),
+            Self::RemovePeer(r) => r.bytes(),
+            Self::ListPeers(r) => r.bytes(),
+            Self::GetPeerStatus(r) => r.bytes(),
+            Self::TriggerHandshake(r) => r.bytes(),
+            Self::GetStats(r) => r.bytes(),
+            Self::PromoteKey(r) => r.bytes(),
+            Self::SubscribeEvents(r) => r.bytes(),
         }
     }
 }
@@ -187,6 +273,14 @@ where
             Self::SupplyKeypair(r) => r.bytes_mut(),
             Self::AddListenSocket(r) => r.bytes_mut(),
             Self::AddPskBroker(r) => r.bytes_mut(),
+            Self::AddPeer(r) => r.bytes_mut(),
+            Self::RemovePeer(r) => r.bytes_mut(),
+            Self::ListPeers(r) => r.bytes_mut(),
+            Self::GetPeerStatus(r) => r.bytes_mut(),
+            Self::TriggerHandshake(r) => r.bytes_mut(),
+            Self::GetStats(r) => r.bytes_mut(),
+            Self::PromoteKey(r) => r.bytes_mut(),
+            Self::SubscribeEvents(r) => r.bytes_mut(),
         }
     }
 }
@@ -73,6 +73,70 @@ impl ResponseMsg for super::AddPskBrokerResponse {
     type RequestMsg = super::AddPskBrokerRequest;
 }
 
+impl RequestMsg for super::AddPeerRequest {
+    type ResponseMsg = super::AddPeerResponse;
+}
+
+impl ResponseMsg for super::AddPeerResponse {
+    type RequestMsg = super::AddPeerRequest;
+}
+
+impl RequestMsg for super::RemovePeerRequest {
+    type ResponseMsg = super::RemovePeerResponse;
+}
+
+impl ResponseMsg for super::RemovePeerResponse {
+    type RequestMsg = super::RemovePeerRequest;
+}
+
+impl RequestMsg for super::ListPeersRequest {
+    type ResponseMsg = super::ListPeersResponse;
+}
+
+impl ResponseMsg for super::ListPeersResponse {
+    type RequestMsg = super::ListPeersRequest;
+}
+
+impl RequestMsg for super::GetPeerStatusRequest {
+    type ResponseMsg = super::GetPeerStatusResponse;
+}
+
+impl ResponseMsg for super::GetPeerStatusResponse {
+    type RequestMsg = super::GetPeerStatusRequest;
+}
+
+impl RequestMsg for super::TriggerHandshakeRequest {
+    type ResponseMsg = super::TriggerHandshakeResponse;
+}
+
+impl ResponseMsg for super::TriggerHandshakeResponse {
+    type RequestMsg = super::TriggerHandshakeRequest;
+}
+
+impl RequestMsg for super::GetStatsRequest {
+    type ResponseMsg = super::GetStatsResponse;
+}
+
+impl ResponseMsg for super::GetStatsResponse {
+    type RequestMsg = super::GetStatsRequest;
+}
+
+impl RequestMsg for super::PromoteKeyRequest {
+    type ResponseMsg = super::PromoteKeyResponse;
+}
+
+impl ResponseMsg for super::PromoteKeyResponse {
+    type RequestMsg = super::PromoteKeyRequest;
+}
+
+impl RequestMsg for super::SubscribeEventsRequest {
+    type ResponseMsg = super::SubscribeEventsResponse;
+}
+
+impl ResponseMsg for super::SubscribeEventsResponse {
+    type RequestMsg = super::SubscribeEventsRequest;
+}
+
 /// Request and response for the [crate::api::RequestMsgType::Ping] message type
 pub type PingPair<B1, B2> = (Ref<B1, PingRequest>, Ref<B2, PingResponse>);
 /// Request and response for the [crate::api::RequestMsgType::SupplyKeypair] message type
@@ -90,6 +154,46 @@ pub type AddPskBrokerPair<B1, B2> = (
     Ref<B1, super::AddPskBrokerRequest>,
     Ref<B2, super::AddPskBrokerResponse>,
 );
+/// Request and response for the [crate::api::RequestMsgType::AddPeer] message type
+pub type AddPeerPair<B1, B2> = (
+    Ref<B1, super::AddPeerRequest>,
+    Ref<B2, super::AddPeerResponse>,
+);
+/// Request and response for the [crate::api::RequestMsgType::RemovePeer] message type
+pub type RemovePeerPair<B1, B2> = (
+    Ref<B1, super::RemovePeerRequest>,
+    Ref<B2, super::RemovePeerResponse>,
+);
+/// Request and response for the [crate::api::RequestMsgType::ListPeers] message type
+pub type ListPeersPair<B1, B2> = (
+    Ref<B1, super::ListPeersRequest>,
+    Ref<B2, super::ListPeersResponse>,
+);
+/// Request and response for the [crate::api::RequestMsgType::GetPeerStatus] message type
+pub type GetPeerStatusPair<B1, B2> = (
+    Ref<B1, super::GetPeerStatusRequest>,
+    Ref<B2, super::GetPeerStatusResponse>,
+);
+/// Request and response for the [crate::api::RequestMsgType::TriggerHandshake] message type
+pub type TriggerHandshakePair<B1, B2> = (
+    Ref<B1, super::TriggerHandshakeRequest>,
+    Ref<B2, super::TriggerHandshakeResponse>,
+);
+/// Request and response for the [crate::api::RequestMsgType::GetStats] message type
+pub type GetStatsPair<B1, B2> = (
+    Ref<B1, super::GetStatsRequest>,
+    Ref<B2, super::GetStatsResponse>,
+);
+/// Request and response for the [crate::api::RequestMsgType::PromoteKey] message type
+pub type PromoteKeyPair<B1, B2> = (
+    Ref<B1, super::PromoteKeyRequest>,
+    Ref<B2, super::PromoteKeyResponse>,
+);
+/// Request and response for the [crate::api::RequestMsgType::SubscribeEvents] message type
+pub type SubscribeEventsPair<B1, B2> = (
+    Ref<B1, super::SubscribeEventsRequest>,
+    Ref<B2, super::SubscribeEventsResponse>,
+);
 
 /// A pair of references to messages; request and response each.
 pub enum RequestResponsePair<B1, B2> {
@@ -97,6 +201,14 @@ pub enum RequestResponsePair<B1, B2> {
     SupplyKeypair(SupplyKeypairPair<B1, B2>),
     AddListenSocket(AddListenSocketPair<B1, B2>),
     AddPskBroker(AddPskBrokerPair<B1, B2>),
+    AddPeer(AddPeerPair<B1, B2>),
+    RemovePeer(RemovePeerPair<B1, B2>),
+    ListPeers(ListPeersPair<B1, B2>),
+    GetPeerStatus(GetPeerStatusPair<B1, B2>),
+    TriggerHandshake(TriggerHandshakePair<B1, B2>),
+    GetStats(GetStatsPair<B1, B2>),
+    PromoteKey(PromoteKeyPair<B1, B2>),
+    SubscribeEvents(SubscribeEventsPair<B1, B2>),
 }
 
 impl<B1, B2> From<PingPair<B1, B2>> for RequestResponsePair<B1, B2> {
@@ -123,6 +235,54 @@ impl<B1, B2> From<AddPskBrokerPair<B1, B2>> for RequestResponsePair<B1, B2> {
     }
 }
 
+impl<B1, B2> From<AddPeerPair<B1, B2>> for RequestResponsePair<B1, B2> {
+    fn from(v: AddPeerPair<B1, B2>) -> Self {
+        RequestResponsePair::AddPeer(v)
+    }
+}
+
+impl<B1, B2> From<RemovePeerPair<B1, B2>> for RequestResponsePair<B1, B2> {
+    fn from(v: RemovePeerPair<B1, B2>) -> Self {
+        RequestResponsePair::RemovePeer(v)
+    }
+}
+
+impl<B1, B2> From<ListPeersPair<B1, B2>> for RequestResponsePair<B1, B2> {
+    fn from(v: ListPeersPair<B1, B2>) -> Self {
+        RequestResponsePair::ListPeers(v)
+    }
+}
+
+impl<B1, B2> From<GetPeerStatusPair<B1, B2>> for RequestResponsePair<B1, B2> {
+    fn from(v: GetPeerStatusPair<B1, B2>) -> Self {
+        RequestResponsePair::GetPeerStatus(v)
+    }
+}
+
+impl<B1, B2> From<TriggerHandshakePair<B1, B2>> for RequestResponsePair<B1, B2> {
+    fn from(v: TriggerHandshakePair<B1, B2>) -> Self {
+        RequestResponsePair::TriggerHandshake(v)
+    }
+}
+
+impl<B1, B2> From<GetStatsPair<B1, B2>> for RequestResponsePair<B1, B2> {
+    fn from(v: GetStatsPair<B1, B2>) -> Self {
+        RequestResponsePair::GetStats(v)
+    }
+}
+
+impl<B1, B2> From<PromoteKeyPair<B1, B2>> for RequestResponsePair<B1, B2> {
+    fn from(v: PromoteKeyPair<B1, B2>) -> Self {
+        RequestResponsePair::PromoteKey(v)
+    }
+}
+
+impl<B1, B2> From<SubscribeEventsPair<B1, B2>> for RequestResponsePair<B1, B2> {
+    fn from(v: SubscribeEventsPair<B1, B2>) -> Self {
+        RequestResponsePair::SubscribeEvents(v)
+    }
+}
+
 impl<B1, B2> RequestResponsePair<B1, B2>
 where
     B1: ByteSlice,
@@ -151,6 +311,46 @@ where
                 let res = ResponseRef::AddPskBroker(res.emancipate());
                 (req, res)
             }
+            Self::AddPeer((req, res)) => {
+                let req = RequestRef::AddPeer(req.emancipate());
+                let res = ResponseRef::AddPeer(res.emancipate());
+                (req, res)
+            }
+            Self::RemovePeer((req, res)) => {
+                let req = RequestRef::RemovePeer(req.emancipate());
+                let res = ResponseRef::RemovePeer(res.emancipate());
+                (req, res)
+            }
+            Self::ListPeers((req, res)) => {
+                let req = RequestRef::ListPeers(req.emancipate());
+                let res = ResponseRef::ListPeers(res.emancipate());
+                (req, res)
+            }
+            Self::GetPeerStatus((req, res)) => {
+                let req = RequestRef::GetPeerStatus(req.emancipate());
+                let res = ResponseRef::GetPeerStatus(res.emancipate());
+                (req, res)
+            }
+            Self::TriggerHandshake((req, res)) => {
+                let req = RequestRef::TriggerHandshake(req.emancipate());
+                let res = ResponseRef::TriggerHandshake(res.emancipate());
+                (req, res)
+            }
+            Self::GetStats((req, res)) => {
+                let req = RequestRef::GetStats(req.emancipate());
+                let res = ResponseRef::GetStats(res.emancipate());
+                (req, res)
+            }
+            Self::PromoteKey((req, res)) => {
+                let req = RequestRef::PromoteKey(req.emancipate());
+                let res = ResponseRef::PromoteKey(res.emancipate());
+                (req, res)
+            }
+            Self::SubscribeEvents((req, res)) => {
+                let req = RequestRef::SubscribeEvents(req.emancipate());
+                let res = ResponseRef::SubscribeEvents(res.emancipate());
+                (req, res)
+            }
         }
     }
 
@@ -193,6 +393,46 @@ where
                 let res = ResponseRef::AddPskBroker(res.emancipate_mut());
                 (req, res)
             }
+            Self::AddPeer((req, res)) => {
+                let req = RequestRef::AddPeer(req.emancipate_mut());
+                let res = ResponseRef::AddPeer(res.emancipate_mut());
+                (req, res)
+            }
+            Self::RemovePeer((req, res)) => {
+                let req = RequestRef::RemovePeer(req.emancipate_mut());
+                let res = ResponseRef::RemovePeer(res.emancipate_mut());
+                (req, res)
+            }
+            Self::ListPeers((req, res)) => {
+                let req = RequestRef::ListPeers(req.emancipate_mut());
+                let res = ResponseRef::ListPeers(res.emancipate_mut());
+                (req, res)
+            }
+            Self::GetPeerStatus((req, res)) => {
+                let req = RequestRef::GetPeerStatus(req.emancipate_mut());
+                let res = ResponseRef::GetPeerStatus(res.emancipate_mut());
+                (req, res)
+            }
+            Self::TriggerHandshake((req, res)) => {
+                let req = RequestRef::TriggerHandshake(req.emancipate_mut());
+                let res = ResponseRef::TriggerHandshake(res.emancipate_mut());
+                (req, res)
+            }
+            Self::GetStats((req, res)) => {
+                let req = RequestRef::GetStats(req.emancipate_mut());
+                let res = ResponseRef::GetStats(res.emancipate_mut());
+                (req, res)
+            }
+            Self::PromoteKey((req, res)) => {
+                let req = RequestRef::PromoteKey(req.emancipate_mut());
+                let res = ResponseRef::PromoteKey(res.emancipate_mut());
+                (req, res)
+            }
+            Self::SubscribeEvents((req, res)) => {
+                let req = RequestRef::SubscribeEvents(req.emancipate_mut());
+                let res = ResponseRef::SubscribeEvents(res.emancipate_mut());
+                (req, res)
+            }
         }
     }
 
@@ -71,6 +71,15 @@ impl<B: ByteSlice> ResponseRef<B> {
             Self::SupplyKeypair(_) => ResponseMsgType::SupplyKeypair,
             Self::AddListenSocket(_) => ResponseMsgType::AddListenSocket,
             Self::AddPskBroker(_) => ResponseMsgType::AddPskBroker,
+            Self::AddPeer(_) => ResponseMsgType::AddPeer,
+            Self::RemovePeer(_) => ResponseMsgType::RemovePeer,
+            Self::ListPeers(_) => ResponseMsgType::ListPeers,
+            Self::GetPeerStatus(_) => ResponseMsgType::GetPeerStatus,
+            Self::TriggerHandshake(_) => ResponseMsgType::TriggerHandshake,
+            Self::GetStats(_) => ResponseMsgType::GetStats,
+            Self::PromoteKey(_) => ResponseMsgType::PromoteKey,
+            Self::SubscribeEvents(_) => ResponseMsgType::SubscribeEvents,
+            Self::Event(_) => ResponseMsgType::Event,
         }
     }
 }
@@ -99,6 +108,60 @@ impl<B> From<Ref<B, super::AddPskBrokerResponse>> for ResponseRef<B> {
     }
 }
 
+impl<B> From<Ref<B, super::AddPeerResponse>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::AddPeerResponse>) -> Self {
+        Self::AddPeer(v)
+    }
+}
+
+impl<B> From<Ref<B, super::RemovePeerResponse>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::RemovePeerResponse>) -> Self {
+        Self::RemovePeer(v)
+    }
+}
+
+impl<B> From<Ref<B, super::ListPeersResponse>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::ListPeersResponse>) -> Self {
+        Self::ListPeers(v)
+    }
+}
+
+impl<B> From<Ref<B, super::GetPeerStatusResponse>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::GetPeerStatusResponse>) -> Self {
+        Self::GetPeerStatus(v)
+    }
+}
+
+impl<B> From<Ref<B, super::TriggerHandshakeResponse>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::TriggerHandshakeResponse>) -> Self {
+        Self::TriggerHandshake(v)
+    }
+}
+
+impl<B> From<Ref<B, super::GetStatsResponse>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::GetStatsResponse>) -> Self {
+        Self::GetStats(v)
+    }
+}
+
+impl<B> From<Ref<B, super::PromoteKeyResponse>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::PromoteKeyResponse>) -> Self {
+        Self::PromoteKey(v)
+    }
+}
+
+impl<B> From<Ref<B, super::SubscribeEventsResponse>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::SubscribeEventsResponse>) -> Self {
+        Self::SubscribeEvents(v)
+    }
+}
+
+impl<B> From<Ref<B, super::Event>> for ResponseRef<B> {
+    fn from(v: Ref<B, super::Event>) -> Self {
+        Self::Event(v)
+    }
+}
+
 impl<B: ByteSlice> ResponseRefMaker<B> {
     fn new(buf: B) -> anyhow::Result<Self> {
         let msg_type = buf.deref().response_msg_type_from_prefix()?;
@@ -121,6 +184,25 @@ impl<B: ByteSlice> ResponseRefMaker<B> {
             ResponseMsgType::AddPskBroker => {
                 ResponseRef::AddPskBroker(self.buf.add_psk_broker_response()?)
             }
+            ResponseMsgType::AddPeer => ResponseRef::AddPeer(self.buf.add_peer_response()?),
+            ResponseMsgType::RemovePeer => {
+                ResponseRef::RemovePeer(self.buf.remove_peer_response()?)
+            }
+            ResponseMsgType::ListPeers => ResponseRef::ListPeers(self.buf.list_peers_response()?),
+            ResponseMsgType::GetPeerStatus => {
+                ResponseRef::GetPeerStatus(self.buf.get_peer_status_response()?)
+            }
+            ResponseMsgType::TriggerHandshake => {
+                ResponseRef::TriggerHandshake(self.buf.trigger_handshake_response()?)
+            }
+            ResponseMsgType::GetStats => ResponseRef::GetStats(self.buf.get_stats_response()?),
+            ResponseMsgType::PromoteKey => {
+                ResponseRef::PromoteKey(self.buf.promote_key_response()?)
+            }
+            ResponseMsgType::SubscribeEvents => {
+                ResponseRef::SubscribeEvents(self.buf.subscribe_events_response()?)
+            }
+            ResponseMsgType::Event => ResponseRef::Event(self.buf.event()?),
         })
     }
 
@@ -159,6 +241,15 @@ pub enum ResponseRef<B> {
     SupplyKeypair(Ref<B, super::SupplyKeypairResponse>),
     AddListenSocket(Ref<B, super::AddListenSocketResponse>),
     AddPskBroker(Ref<B, super::AddPskBrokerResponse>),
+    AddPeer(Ref<B, super::AddPeerResponse>),
+    RemovePeer(Ref<B, super::RemovePeerResponse>),
+    ListPeers(Ref<B, super::ListPeersResponse>),
+    GetPeerStatus(Ref<B, super::GetPeerStatusResponse>),
+    TriggerHandshake(Ref<B, super::TriggerHandshakeResponse>),
+    GetStats(Ref<B, super::GetStatsResponse>),
+    PromoteKey(Ref<B, super::PromoteKeyResponse>),
+    SubscribeEvents(Ref<B, super::SubscribeEventsResponse>),
+    Event(Ref<B, super::Event>),
 }
 
 impl<B> ResponseRef<B>
@@ -176,6 +267,15 @@ where
             Self::SupplyKeypair(r) => r.bytes(),
             Self::AddListenSocket(r) => r.bytes(),
             Self::AddPskBroker(r) => r.bytes(),
+            Self::AddPeer(r) => r.bytes(),
+            Self::RemovePeer(r) => r.bytes(),
+            Self::ListPeers(r) => r.bytes(),
+            Self::GetPeerStatus(r) => r.bytes(),
+            Self::TriggerHandshake(r) => r.bytes(),
+            Self::GetStats(r) => r.bytes(),
+            Self::PromoteKey(r) => r.bytes(),
+            Self::SubscribeEvents(r) => r.bytes(),
+            Self::Event(r) => r.bytes(),
         }
     }
 }
@@ -191,6 +291,15 @@ where
             Self::SupplyKeypair(r) => r.bytes_mut(),
             Self::AddListenSocket(r) => r.bytes_mut(),
             Self::AddPskBroker(r) => r.bytes_mut(),
+            Self::AddPeer(r) => r.bytes_mut(),
+            Self::RemovePeer(r) => r.bytes_mut(),
+            Self::ListPeers(r) => r.bytes_mut(),
+            Self::GetPeerStatus(r) => r.bytes_mut(),
+            Self::TriggerHandshake(r) => r.bytes_mut(),
+            Self::GetStats(r) => r.bytes_mut(),
+            Self::PromoteKey(r) => r.bytes_mut(),
+            Self::SubscribeEvents(r) => r.bytes_mut(),
+            Self::Event(r) => r.bytes_mut(),
         }
     }
 }
@@ -141,6 +141,310 @@ pub trait Server {
         res: &mut super::AddPskBrokerResponse,
     ) -> anyhow::Result<()>;
 
+    /// Register a new Rosenpass peer via the API
+    ///
+    /// This implements the handler for the [crate::api::RequestMsgType::AddPeer] API message.
+    ///
+    /// # File descriptors
+    ///
+    /// 1. The peer's public key (size must match exactly); the file descriptor must be backed by
+    ///    either of
+    ///     - file-system file
+    ///     - [memfd](https://man.archlinux.org/man/memfd.2.en)
+    ///     - [memfd_secret](https://man.archlinux.org/man/memfd.2.en)
+    /// 2. Optional: The pre-shared key (raw bytes, size must match exactly); same requirements as
+    ///    for the public key
+    ///
+    /// # API Return Status
+    ///
+    /// 1. [crate::api::add_peer_response_status::OK] - Indicates success; the response contains
+    ///    the peer id of the new peer
+    /// 2. [crate::api::add_peer_response_status::INVALID_REQUEST] – Malformed request; could be:
+    ///     - Missing file descriptor for the public key
+    ///     - File descriptors contain data of invalid length
+    ///     - Invalid protocol version, endpoint, OSK domain separator, or WireGuard configuration
+    /// 3. [crate::api::add_peer_response_status::INTERNAL_ERROR] – Some other, non-fatal error
+    ///    occured. Check the logs on log
+    /// 4. [crate::api::add_peer_response_status::PEER_ALREADY_REGISTERED] – A peer with the
+    ///    same public key and protocol version is registered already
+    ///
+    /// # Description
+    ///
+    /// This endpoint is the API equivalent of a `[[peers]]` section in the configuration file.
+    /// Key exchanges with the new peer start right away if the server keypair is already known
+    /// and an endpoint was given.
+    ///
+    /// If a WireGuard device and peer are given, exchanged keys are supplied to WireGuard through
+    /// the most recently registered PSK broker (see [Self::add_psk_broker]). Writing keys to an
+    /// output file is not supported through the API.
+    ///
+    /// # Examples
+    ///
+    /// See the example of how to use the API in [crate::api].
+    fn add_peer(
+        &mut self,
+        req: &super::AddPeerRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::AddPeerResponse,
+    ) -> anyhow::Result<()>;
+
+    /// Remove a Rosenpass peer via the API
+    ///
+    /// This implements the handler for the [crate::api::RequestMsgType::RemovePeer] API message.
+    ///
+    /// # File descriptors
+    ///
+    /// None
+    ///
+    /// # API Return Status
+    ///
+    /// 1. [crate::api::remove_peer_response_status::OK] - Indicates success
+    /// 2. [crate::api::remove_peer_response_status::INVALID_REQUEST] – The server keypair was
+    ///    not supplied yet, so peers can not be looked up by their peer id
+    /// 3. [crate::api::remove_peer_response_status::INTERNAL_ERROR] – Some other, non-fatal error
+    ///    occured. Check the logs on log
+    /// 4. [crate::api::remove_peer_response_status::NO_SUCH_PEER] – There is no peer with the
+    ///    given peer id
+    ///
+    /// # Description
+    ///
+    /// Removes the peer along with all of its session state (see
+    /// [crate::app_server::AppServer::remove_peer]). If a key was exchanged with the peer and is
+    /// still live, it is erased first.
+    ///
+    /// # Examples
+    ///
+    /// See the example of how to use the API in [crate::api].
+    fn remove_peer(
+        &mut self,
+        req: &super::RemovePeerRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::RemovePeerResponse,
+    ) -> anyhow::Result<()>;
+
+    /// List the Rosenpass peers registered with the server
+    ///
+    /// This implements the handler for the [crate::api::RequestMsgType::ListPeers] API message.
+    ///
+    /// # File descriptors
+    ///
+    /// None
+    ///
+    /// # API Return Status
+    ///
+    /// 1. [crate::api::list_peers_response_status::OK] - Indicates success
+    /// 2. [crate::api::list_peers_response_status::INVALID_REQUEST] – The server keypair was
+    ///    not supplied yet, so peer ids can not be calculated
+    /// 3. [crate::api::list_peers_response_status::INTERNAL_ERROR] – Some other, non-fatal error
+    ///    occured. Check the logs on log
+    ///
+    /// # Description
+    ///
+    /// Returns the peer ids of at most [crate::api::LIST_PEERS_MAX_PEERS] peers, skipping the
+    /// first `offset` peers, along with the total number of peers. Peers from the configuration
+    /// file and peers added through [Self::add_peer] are listed alike, in the order they were
+    /// added.
+    ///
+    /// Details about each peer can be retrieved through [Self::get_peer_status].
+    ///
+    /// # Examples
+    ///
+    /// See the example of how to use the API in [crate::api].
+    fn list_peers(
+        &mut self,
+        req: &super::ListPeersRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::ListPeersResponse,
+    ) -> anyhow::Result<()>;
+
+    /// Retrieve the status of a single Rosenpass peer
+    ///
+    /// This implements the handler for the [crate::api::RequestMsgType::GetPeerStatus] API
+    /// message.
+    ///
+    /// # File descriptors
+    ///
+    /// None
+    ///
+    /// # API Return Status
+    ///
+    /// 1. [crate::api::get_peer_status_response_status::OK] - Indicates success
+    /// 2. [crate::api::get_peer_status_response_status::INVALID_REQUEST] – The server keypair
+    ///    was not supplied yet, so peers can not be looked up by their peer id
+    /// 3. [crate::api::get_peer_status_response_status::INTERNAL_ERROR] – Some other, non-fatal
+    ///    error occured. Check the logs on log
+    /// 4. [crate::api::get_peer_status_response_status::NO_SUCH_PEER] – There is no peer with
+    ///    the given peer id
+    ///
+    /// # Description
+    ///
+    /// Reports the state of the current session and of any handshake we are initiating with
+    /// the peer, the endpoint used to reach the peer, and the reason for the last key output
+    /// (see [crate::app_server::KeyOutputReason]). See
+    /// [crate::api::GetPeerStatusResponsePayload] for details.
+    ///
+    /// # Examples
+    ///
+    /// See the example of how to use the API in [crate::api].
+    fn get_peer_status(
+        &mut self,
+        req: &super::GetPeerStatusRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::GetPeerStatusResponse,
+    ) -> anyhow::Result<()>;
+
+    /// Start a new handshake with a Rosenpass peer right away
+    ///
+    /// This implements the handler for the [crate::api::RequestMsgType::TriggerHandshake] API
+    /// message.
+    ///
+    /// # File descriptors
+    ///
+    /// None
+    ///
+    /// # API Return Status
+    ///
+    /// 1. [crate::api::trigger_handshake_response_status::OK] - Indicates success
+    /// 2. [crate::api::trigger_handshake_response_status::INVALID_REQUEST] – The server keypair
+    ///    was not supplied yet, so peers can not be looked up by their peer id
+    /// 3. [crate::api::trigger_handshake_response_status::INTERNAL_ERROR] – Some other, non-fatal
+    ///    error occured. Check the logs on log
+    /// 4. [crate::api::trigger_handshake_response_status::NO_SUCH_PEER] – There is no peer with
+    ///    the given peer id
+    /// 5. [crate::api::trigger_handshake_response_status::NO_ENDPOINT] – No network endpoint is
+    ///    known for the peer, so no handshake can be started
+    ///
+    /// # Description
+    ///
+    /// Marks the peer for immediate initiation (see
+    /// [crate::protocol::CryptoServer::force_initiation]), so the server sends an InitHello
+    /// message to the peer without waiting for the current session to become stale. This can be
+    /// used to force a rekey. Any handshake we are currently initiating with the peer is
+    /// abandoned.
+    ///
+    /// The response is sent before the handshake takes place. To find out whether the handshake
+    /// completed, subscribe to events through [Self::subscribe_events] and wait for a
+    /// [crate::api::event_type::KEY_EXCHANGED] event for the peer.
+    ///
+    /// # Examples
+    ///
+    /// See the example of how to use the API in [crate::api].
+    fn trigger_handshake(
+        &mut self,
+        req: &super::TriggerHandshakeRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::TriggerHandshakeResponse,
+    ) -> anyhow::Result<()>;
+
+    /// Read the counters describing the operation of the server
+    ///
+    /// This implements the handler for the [crate::api::RequestMsgType::GetStats] API
+    /// message.
+    ///
+    /// # File descriptors
+    ///
+    /// None
+    ///
+    /// # API Return Status
+    ///
+    /// 1. [crate::api::get_stats_response_status::OK] - Indicates success
+    /// 2. [crate::api::get_stats_response_status::INVALID_REQUEST] – Currently unused
+    /// 3. [crate::api::get_stats_response_status::INTERNAL_ERROR] – Some other, non-fatal
+    ///    error occured. Check the logs on log
+    ///
+    /// # Description
+    ///
+    /// Returns the same counters that are exported in the Prometheus text format by the
+    /// listeners configured in [crate::metrics::MetricsConfig]; see [crate::metrics::Metrics].
+    /// All counters start at zero when the server starts.
+    ///
+    /// # Examples
+    ///
+    /// See the example of how to use the API in [crate::api].
+    fn get_stats(
+        &mut self,
+        req: &super::GetStatsRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::GetStatsResponse,
+    ) -> anyhow::Result<()>;
+
+    /// Promote the next static key of a peer or of the server during a key rotation
+    ///
+    /// This implements the handler for the [crate::api::RequestMsgType::PromoteKey] API
+    /// message.
+    ///
+    /// # File descriptors
+    ///
+    /// None
+    ///
+    /// # API Return Status
+    ///
+    /// 1. [crate::api::promote_key_response_status::OK] - Indicates success
+    /// 2. [crate::api::promote_key_response_status::INVALID_REQUEST] – The server keypair
+    ///    was not supplied yet, so there are no keys to promote
+    /// 3. [crate::api::promote_key_response_status::INTERNAL_ERROR] – Some other, non-fatal
+    ///    error occured. Check the logs on log
+    /// 4. [crate::api::promote_key_response_status::NO_SUCH_PEER] – There is neither a peer nor
+    ///    an identity with the given peer id
+    /// 5. [crate::api::promote_key_response_status::NO_NEXT_KEY] – No next key was configured
+    ///    for the peer or identity
+    ///
+    /// # Description
+    ///
+    /// Makes the next key the active one, see [crate::protocol::key_rotation]. For a peer, this
+    /// is the `next_public_key` from the configuration file; for the server, this is its
+    /// `next_keypair`. The previous key keeps being accepted for incoming handshakes for
+    /// [crate::app_server::AppServer::key_rotation_overlap] seconds. New handshakes with the
+    /// affected peers are started right away; established sessions are preserved.
+    ///
+    /// The configuration file is not modified.
+    ///
+    /// # Examples
+    ///
+    /// See the example of how to use the API in [crate::api].
+    fn promote_key(
+        &mut self,
+        req: &super::PromoteKeyRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::PromoteKeyResponse,
+    ) -> anyhow::Result<()>;
+
+    /// Subscribe to events from the Rosenpass server
+    ///
+    /// This implements the handler for the [crate::api::RequestMsgType::SubscribeEvents] API
+    /// message.
+    ///
+    /// # File descriptors
+    ///
+    /// None
+    ///
+    /// # API Return Status
+    ///
+    /// 1. [crate::api::subscribe_events_response_status::OK] - Indicates success
+    /// 2. [crate::api::subscribe_events_response_status::INVALID_REQUEST] – Currently unused
+    /// 3. [crate::api::subscribe_events_response_status::INTERNAL_ERROR] – Some other, non-fatal
+    ///    error occured. Check the logs on log
+    ///
+    /// # Description
+    ///
+    /// After a successful response, the connection turns into a push stream: The server sends
+    /// an [crate::api::Event] message – length-prefixed like any other response – for each
+    /// event listed in [crate::api::event_type] and stops processing further requests on this
+    /// connection. Use a separate connection for other requests.
+    ///
+    /// Clients that do not read their events fast enough are disconnected once
+    /// [crate::api::MAX_QUEUED_EVENTS] events are waiting to be sent.
+    ///
+    /// # Examples
+    ///
+    /// See the example of how to use the API in [crate::api].
+    fn subscribe_events(
+        &mut self,
+        req: &super::SubscribeEventsRequest,
+        req_fds: &mut VecDeque<OwnedFd>,
+        res: &mut super::SubscribeEventsResponse,
+    ) -> anyhow::Result<()>;
+
     /// Similar to [Self::handle_message], but takes a [RequestResponsePair]
     /// instead of taking to separate byte buffers.
     ///
@@ -164,6 +468,20 @@ pub trait Server {
                 self.add_listen_socket(req, req_fds, res)
             }
             RequestResponsePair::AddPskBroker((req, res)) => self.add_psk_broker(req, req_fds, res),
+            RequestResponsePair::AddPeer((req, res)) => self.add_peer(req, req_fds, res),
+            RequestResponsePair::RemovePeer((req, res)) => self.remove_peer(req, req_fds, res),
+            RequestResponsePair::ListPeers((req, res)) => self.list_peers(req, req_fds, res),
+            RequestResponsePair::GetPeerStatus((req, res)) => {
+                self.get_peer_status(req, req_fds, res)
+            }
+            RequestResponsePair::TriggerHandshake((req, res)) => {
+                self.trigger_handshake(req, req_fds, res)
+            }
+            RequestResponsePair::GetStats((req, res)) => self.get_stats(req, req_fds, res),
+            RequestResponsePair::PromoteKey((req, res)) => self.promote_key(req, req_fds, res),
+            RequestResponsePair::SubscribeEvents((req, res)) => {
+                self.subscribe_events(req, req_fds, res)
+            }
         }
     }
 
@@ -208,6 +526,46 @@ pub trait Server {
                 res.init();
                 RequestResponsePair::AddPskBroker((req, res))
             }
+            RequestRef::AddPeer(req) => {
+                let mut res = res.add_peer_response_from_prefix()?;
+                res.init();
+                RequestResponsePair::AddPeer((req, res))
+            }
+            RequestRef::RemovePeer(req) => {
+                let mut res = res.remove_peer_response_from_prefix()?;
+                res.init();
+                RequestResponsePair::RemovePeer((req, res))
+            }
+            RequestRef::ListPeers(req) => {
+                let mut res = res.list_peers_response_from_prefix()?;
+                res.init();
+                RequestResponsePair::ListPeers((req, res))
+            }
+            RequestRef::GetPeerStatus(req) => {
+                let mut res = res.get_peer_status_response_from_prefix()?;
+                res.init();
+                RequestResponsePair::GetPeerStatus((req, res))
+            }
+            RequestRef::TriggerHandshake(req) => {
+                let mut res = res.trigger_handshake_response_from_prefix()?;
+                res.init();
+                RequestResponsePair::TriggerHandshake((req, res))
+            }
+            RequestRef::GetStats(req) => {
+                let mut res = res.get_stats_response_from_prefix()?;
+                res.init();
+                RequestResponsePair::GetStats((req, res))
+            }
+            RequestRef::PromoteKey(req) => {
+                let mut res = res.promote_key_response_from_prefix()?;
+                res.init();
+                RequestResponsePair::PromoteKey((req, res))
+            }
+            RequestRef::SubscribeEvents(req) => {
+                let mut res = res.subscribe_events_response_from_prefix()?;
+                res.init();
+                RequestResponsePair::SubscribeEvents((req, res))
+            }
         };
         self.dispatch(&mut pair, req_fds)?;
 
@@ -0,0 +1,87 @@
+//! A minimal, blocking client for the Rosenpass API
+//!
+//! This is used by the `rosenpass` subcommands that talk to a running Rosenpass instance
+//! through its API socket.
+
+use std::{os::unix::net::UnixStream, path::Path, time::Duration};
+
+use anyhow::{ensure, Context};
+use rosenpass_util::{
+    length_prefix_encoding::{decoder::LengthPrefixDecoder, encoder::LengthPrefixEncoder},
+    zerocopy::ZerocopySliceExt,
+};
+use zerocopy::{AsBytes, FromBytes};
+
+use super::{
+    ByteSliceRefExt, Event, EventPayload, Message, RequestMsg, ResponseMsgType, MAX_RESPONSE_LEN,
+};
+
+/// A blocking connection to the API socket of a running Rosenpass instance
+///
+/// # Examples
+///
+/// ```no_run
+/// use rosenpass::api::{client::ApiClient, PingRequest};
+///
+/// let api = ApiClient::connect("/run/rosenpass/api.sock")?;
+/// let res = api.request(&PingRequest::new([0u8; 256]))?;
+/// assert_eq!(res.payload.echo, [0u8; 256]);
+///
+/// Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ApiClient {
+    /// The connection to the API socket
+    stream: UnixStream,
+}
+
+impl ApiClient {
+    /// Connect to the API socket at the given path
+    pub fn connect<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let stream = UnixStream::connect(path)
+            .with_context(|| format!("Could not connect to the Rosenpass API at {path:?}"))?;
+        Ok(Self { stream })
+    }
+
+    /// Limit how long [Self::request] and [Self::next_event] wait for the server; `None`
+    /// waits indefinitely
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> anyhow::Result<()> {
+        self.stream.set_read_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Send a request and wait for the matching response
+    pub fn request<Req>(&self, req: &Req) -> anyhow::Result<Req::ResponseMsg>
+    where
+        Req: RequestMsg + AsBytes,
+        Req::ResponseMsg: Message<MessageClass = ResponseMsgType> + FromBytes + Copy,
+    {
+        LengthPrefixEncoder::from_message(req.as_bytes()).write_all_to_stdio(&self.stream)?;
+        self.read_message::<Req::ResponseMsg>()
+    }
+
+    /// Wait for the next event on a connection subscribed through
+    /// [crate::api::SubscribeEventsRequest]
+    pub fn next_event(&self) -> anyhow::Result<EventPayload> {
+        Ok(self.read_message::<Event>()?.payload)
+    }
+
+    /// Read a single message of the given type from the connection
+    fn read_message<Res>(&self) -> anyhow::Result<Res>
+    where
+        Res: Message<MessageClass = ResponseMsgType> + FromBytes + Copy,
+    {
+        let mut decoder = LengthPrefixDecoder::new([0u8; MAX_RESPONSE_LEN]);
+        let buf = decoder.read_all_from_stdio(&self.stream)?;
+
+        let typ = (&*buf).response_msg_type_from_prefix()?;
+        ensure!(
+            typ == Res::MESSAGE_TYPE,
+            "Received unexpected {typ:?} message from the Rosenpass API (expected {:?})",
+            Res::MESSAGE_TYPE
+        );
+
+        Ok(*buf.zk_parse_prefix::<Res>()?)
+    }
+}
@@ -4,6 +4,7 @@ use std::os::fd::OwnedFd;
 
 use mio::net::UnixStream;
 use rosenpass_secret_memory::Secret;
+use rosenpass_to::{ops::copy_slice, To};
 use rosenpass_util::mio::ReadWithFileDescriptors;
 use rosenpass_util::{
     io::{IoResultKindHintExt, TryIoResultKindHintExt},
@@ -13,9 +14,10 @@ use rosenpass_util::{
     },
     mio::interest::RW as MIO_RW,
 };
+use zerocopy::AsBytes;
 use zeroize::Zeroize;
 
-use crate::api::MAX_REQUEST_FDS;
+use crate::api::{Event, EventPayload, Message, MAX_REQUEST_FDS};
 use crate::{api::Server, app_server::AppServer};
 
 use super::super::{ApiHandler, ApiHandlerContext};
@@ -114,6 +116,25 @@ impl MioConnection {
     pub fn mio_token(&self) -> mio::Token {
         self.mio_token
     }
+
+    /// Whether the client subscribed to events; see [ApiHandler::is_subscribed]
+    pub fn is_subscribed(&self) -> bool {
+        self.api_handler.is_subscribed()
+    }
+
+    /// Queue an event for sending to a subscribed client.
+    ///
+    /// Clients that fall too far behind are disconnected; see [ApiHandler::push_event].
+    pub fn push_event(&mut self, ev: EventPayload) {
+        if let Err(e) = self.api_handler.push_event(ev) {
+            log::warn!(
+                "API client is not reading its events; disconnecting the client.\n\
+                    Error: {e:?}"
+            );
+            self.api_handler.unsubscribe();
+            self.invalid_read = true; // closed later by mio_manager
+        }
+    }
 }
 
 /// We require references to both [MioConnection] and to the [AppServer] that contains it.
@@ -141,6 +162,12 @@ pub trait MioConnectionContext {
         // All of these functions return an error, None ("operation incomplete")
         // or some ("operation complete, keep processing")
         short!(self.flush_write_buffer()?); // Flush last message
+
+        // Subscribed clients just receive events
+        if self.mio_connection().is_subscribed() {
+            return self.send_events();
+        }
+
         short!(self.recv()?); // Receive new message
         short!(self.handle_incoming_message()?); // Process new message with API
         short!(self.flush_write_buffer()?); // Begin flushing response
@@ -172,6 +199,24 @@ pub trait MioConnectionContext {
         })
     }
 
+    /// Called by [Self::poll] to send queued events to a client that subscribed to events
+    fn send_events(&mut self) -> anyhow::Result<()> {
+        loop {
+            let Some(ev) = self.mio_connection_mut().api_handler.pop_event() else {
+                return Ok(());
+            };
+
+            let ev = Event::from_payload(ev);
+            let write_buf = self.write_buf_mut();
+            copy_slice(ev.as_bytes()).to(&mut write_buf.buffer_bytes_mut()[..ev.as_bytes().len()]);
+            write_buf.restart_write_with_new_message(ev.as_bytes().len())?;
+
+            if self.flush_write_buffer()?.is_none() {
+                return Ok(()); // Would block; continue once the socket becomes writable
+            }
+        }
+    }
+
     /// Called by [Self::poll] to write data in the send buffer to the unix stream
     fn flush_write_buffer(&mut self) -> anyhow::Result<Option<()>> {
         if self.write_buf_mut().exhausted() {
@@ -205,6 +250,15 @@ pub trait MioConnectionContext {
                 Ok(_) => continue, /* Ret { bytes_written > 0, done = false } acc. to previous cases*/
                 Err((_e, K::Interrupted)) => continue,
 
+                // Subscribers end the event stream by closing the connection
+                Err((e, _ek)) if conn.api_handler.is_subscribed() => {
+                    log::debug!("Event subscriber disconnected from the API: {e:?}");
+                    conn.api_handler.unsubscribe();
+                    conn.invalid_read = true; // closed later by mio_manager
+                    write_buf.zeroize();
+                    break Ok(None);
+                }
+
                 // Other errors
                 Err((e, _ek)) => Err(e)?,
             }
@@ -6,6 +6,7 @@ use rosenpass_util::{
     functional::ApplyExt, io::nonblocking_handle_io_errors, mio::interest::RW as MIO_RW,
 };
 
+use crate::api::EventPayload;
 use crate::app_server::{AppServer, AppServerIoSource};
 
 use super::{MioConnection, MioConnectionContext};
@@ -88,21 +89,48 @@ pub trait MioManagerContext {
         let mio_token = connection.mio_token();
         let conns: &mut Vec<Option<MioConnection>> =
             self.mio_manager_mut().connections.borrow_mut();
+        // Reuse the slot of a closed connection if possible; the indices of other connections
+        // must stay stable since they are referenced by their io sources
         let idx = conns
             .iter_mut()
             .enumerate()
-            .find(|(_, slot)| slot.is_some())
+            .find(|(_, slot)| slot.is_none())
             .map(|(idx, _)| idx)
             .unwrap_or(conns.len());
-        conns.insert(idx, Some(connection));
+        match conns.get_mut(idx) {
+            Some(slot) => *slot = Some(connection),
+            None => conns.push(Some(connection)),
+        }
         let io_source = idx
-            .apply(MioManagerIoSource::Listener)
+            .apply(MioManagerIoSource::Connection)
             .apply(AppServerIoSource::MioManager);
         self.app_server_mut()
             .register_io_source(mio_token, io_source);
         Ok(())
     }
 
+    /// Whether any API client subscribed to events through
+    /// [crate::api::Server::subscribe_events]
+    fn has_event_subscribers(&self) -> bool {
+        self.mio_manager()
+            .connections
+            .iter()
+            .flatten()
+            .any(|conn| conn.is_subscribed())
+    }
+
+    /// Queue an event for all clients that subscribed to events and start sending it right away
+    fn broadcast_event(&mut self, ev: &EventPayload) -> anyhow::Result<()> {
+        for idx in 0..self.mio_manager().connections.len() {
+            match self.mio_manager_mut().connections[idx].as_mut() {
+                Some(conn) if conn.is_subscribed() => conn.push_event(*ev),
+                _ => continue,
+            }
+            self.poll_particular_connection(idx)?;
+        }
+        Ok(())
+    }
+
     /// Poll a particular [MioManagerIoSource] in this [MioManager]
     fn poll_particular(&mut self, io_source: MioManagerIoSource) -> anyhow::Result<()> {
         use MioManagerIoSource as S;
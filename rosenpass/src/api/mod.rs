@@ -13,5 +13,6 @@ pub use api_handler::*;
 pub use boilerplate::*;
 
 pub mod cli;
+pub mod client;
 pub mod config;
 pub mod mio;
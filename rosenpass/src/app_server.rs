@@ -2,12 +2,15 @@
 //! the actual cryptographic code lives in the [crate::protocol] module
 
 use std::collections::{HashMap, VecDeque};
+use std::fs::{File, Permissions};
 use std::io::{stdout, ErrorKind, Write};
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
-use std::time::{Duration, Instant};
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use std::{cell::Cell, fmt::Debug, io, path::PathBuf, slice};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use derive_builder::Builder;
 use log::{error, info, warn};
 use mio::{Interest, Token};
@@ -17,18 +20,31 @@ use rosenpass_util::attempt;
 use rosenpass_util::functional::{run, ApplyExt};
 use rosenpass_util::io::{IoResultKindHintExt, SubstituteForIoErrorKindExt};
 use rosenpass_util::{
-    b64::B64Display, build::ConstructionSite, file::StoreValueB64, option::SomeExt, result::OkExt,
+    b64::B64Display,
+    build::ConstructionSite,
+    file::{fwrite_atomic, StoreValueB64Writer, Visibility},
+    option::SomeExt,
+    result::OkExt,
 };
 
 use rosenpass_secret_memory::{Public, Secret};
 use rosenpass_wireguard_broker::{WireguardBrokerCfg, WireguardBrokerMio, WG_KEY_LEN};
 
 use crate::config::{ProtocolVersion, Verbosity};
+use crate::endpoint_resolver::EndpointResolver;
+use crate::kem_workers::KemWorkers;
+use crate::key_out_exec::{KeyOutCommands, KeyOutExec};
+use crate::load_detection::LoadDetection;
+use crate::rate_limit::RateLimiter;
 
 use crate::protocol::basic_types::{MsgBuf, SPk, SSk, SymKey};
+use crate::protocol::constants::KEY_ROTATION_OVERLAP;
+use crate::protocol::decapsulation::Decapsulation;
 use crate::protocol::osk_domain_separator::OskDomainSeparator;
 use crate::protocol::timing::Timing;
-use crate::protocol::{BuildCryptoServer, CryptoServer, HostIdentification, PeerPtr};
+use crate::protocol::{
+    BuildCryptoServer, CipherSuite, CryptoServer, HostIdentification, IdentityPtr, PeerPtr,
+};
 
 /// The maximum size of a base64 encoded symmetric key (estimate)
 pub const MAX_B64_KEY_SIZE: usize = 32 * 5 / 3;
@@ -42,12 +58,6 @@ const IPV4_ANY_ADDR: Ipv4Addr = Ipv4Addr::new(0, 0, 0, 0);
 /// when listening
 const IPV6_ANY_ADDR: Ipv6Addr = Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0);
 
-/// Ratio of blocking epoll(7) polls to non-blocking polls at which the rosenpass server
-/// assumes it is under load (i.e. a DOS attack may be happening)
-const UNDER_LOAD_RATIO: f64 = 0.5;
-/// Period at which the DOS detection code updates whether there is an "under load" status
-const DURATION_UPDATE_UNDER_LOAD_STATUS: Duration = Duration::from_millis(500);
-
 pub const BROKER_ID_BYTES: usize = 8;
 
 /// IPv4 address that tells the network layer to listen on any interface
@@ -181,15 +191,81 @@ pub struct AppPeer {
     /// packets to, trying to exchange a key.
     ///
     /// Note that the remote peer may connect with another address. See [Self::current_endpoint].
+    ///
+    /// If the address was given as a hostname, this is updated whenever the hostname resolves to
+    /// other addresses; see [crate::endpoint_resolver].
     pub initial_endpoint: Option<Endpoint>,
     /// The network address currently used for a particular peer.
     ///
     /// This is not necessarily the address that was configured at program start (see [Self::initial_endpoint]),
     /// because the remote peer can initiate handshakes from an arbitrary network address.
     ///
-    /// If another peer successfully connects to this one from any address, then this field will
-    /// be updated to reflect which address this was.
+    /// Whenever a message authenticated as coming from the remote peer arrives (see
+    /// [crate::protocol::HandleMsgResult::authenticated_by]), this field is updated to the address
+    /// the message came from, unless [Self::pin_endpoint] is set. This allows the remote peer to
+    /// roam between networks.
     pub current_endpoint: Option<Endpoint>,
+    /// If set, [Self::current_endpoint] is not updated from the addresses of incoming messages;
+    /// the peer is always contacted at [Self::initial_endpoint]
+    pub pin_endpoint: bool,
+    /// The reason for and time of the last call to [AppServer::output_key] for this peer.
+    ///
+    /// The time is relative to the [rosenpass_util::time::Timebase] of the crypto server.
+    pub last_key_output: Option<(KeyOutputReason, Timing)>,
+    /// If set, then [AppServer::output_key] runs the command configured here for each key;
+    /// see [crate::key_out_exec]
+    pub key_out_exec: Option<KeyOutExec>,
+    /// Owner and group applied to [Self::outfile]
+    pub outfile_ownership: OutfileOwnership,
+}
+
+/// Owner and group of the key files written by [AppServer::output_key]
+///
+/// Ids set to `None` are left at the default, i.e. the user and group of the Rosenpass process.
+/// Changing the owner usually requires Rosenpass to run as root.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OutfileOwnership {
+    /// Numeric id of the user owning the key file
+    pub owner: Option<u32>,
+    /// Numeric id of the group owning the key file
+    ///
+    /// If set, members of the group may read the key file; otherwise, only the owner may.
+    pub group: Option<u32>,
+}
+
+impl OutfileOwnership {
+    /// Apply the ownership and the matching permissions to a newly written key file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    /// use rosenpass::app_server::OutfileOwnership;
+    /// use rosenpass_util::file::{fwrite_atomic, Visibility};
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let path = dir.path().join("osk");
+    ///
+    /// // Handing the file to our own group is always permitted
+    /// let group = std::fs::metadata(dir.path())?.gid();
+    /// let ownership = OutfileOwnership { owner: None, group: Some(group) };
+    /// fwrite_atomic(&path, Visibility::Secret, |f| ownership.apply(f))?;
+    /// assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o640);
+    ///
+    /// Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn apply(&self, file: &File) -> anyhow::Result<()> {
+        if *self == Self::default() {
+            return Ok(());
+        }
+
+        std::os::unix::fs::fchown(file, self.owner, self.group)
+            .context("Could not change the ownership of the key file")?;
+        if self.group.is_some() {
+            file.set_permissions(Permissions::from_mode(0o640))?;
+        }
+        Ok(())
+    }
 }
 
 impl AppPeer {
@@ -208,6 +284,10 @@ impl AppPeer {
     ///   broker_peer: None,
     ///   initial_endpoint: Some(Endpoint::discovery_from_hostname("0.0.0.0:0".to_string())?),
     ///   current_endpoint: Some(Endpoint::discovery_from_hostname("0.0.0.0:1".to_string())?),
+    ///   pin_endpoint: false,
+    ///   last_key_output: None,
+    ///   key_out_exec: None,
+    ///   outfile_ownership: Default::default(),
     /// };
     ///
     /// fn same(a: Option<&Endpoint>, b: Option<&Endpoint>) -> bool {
@@ -290,6 +370,15 @@ pub enum AppServerIoSource {
     /// see [AppServer::api_manager]
     #[cfg(feature = "experiment_api")]
     MioManager(crate::api::mio::MioManagerIoSource),
+    /// IO source refers to the SIGHUP notification pipe in [AppServer::config_reload]
+    ReloadSignal,
+    /// IO source refers to the SIGUSR2 notification pipe in [AppServer::upgrade]
+    UpgradeSignal,
+    /// IO source refers to a listener or connection of the metrics exporter;
+    /// see [AppServer::metrics_server]
+    Metrics(crate::metrics::MetricsIoSource),
+    /// IO source refers to [AppServer::waker]
+    Waker,
 }
 
 /// Number of epoll(7) events Rosenpass can receive at a time
@@ -314,7 +403,10 @@ pub struct AppServer {
     /// is wrapped in a ConstructionSite
     pub crypto_site: ConstructionSite<BuildCryptoServer, CryptoServer>,
     /// The UDP sockets used to send and receive protocol messages
-    pub sockets: Vec<mio::net::UdpSocket>,
+    ///
+    /// Sockets removed through [Self::unregister_listen_socket] leave a tombstone (`None`)
+    /// behind, so all other [SocketPtr] values remain valid.
+    pub sockets: Vec<Option<mio::net::UdpSocket>>,
     /// Buffer for [mio] (epoll(7), async IO handling) IO events
     pub events: mio::Events,
     /// Supplemental buffer for [mio] events. See the inline documentation of [AppServer::try_recv]
@@ -337,15 +429,29 @@ pub struct AppServer {
     pub brokers: BrokerStore,
     /// This is our view of the peers; generally every peer in here is associated with one peer in
     /// CryptoServer
-    pub peers: Vec<AppPeer>,
+    ///
+    /// Just like [CryptoServer::peers], removed peers leave a tombstone (`None`) behind;
+    /// see [Self::remove_peer].
+    pub peers: Vec<Option<AppPeer>>,
     /// If set to [Verbosity::Verbose], then some extra information will be printed
     /// at the info log level
     pub verbosity: Verbosity,
+    /// Format of the key output events written to stdout; see [LogFormat]
+    pub log_format: LogFormat,
     /// Used by [AppServer::try_recv] to ensure that all packages have been read
     /// from the UDP sockets
     pub all_sockets_drained: bool,
     /// Whether network message handling determined that a Denial of Service attack is happening
     pub under_load: DoSOperation,
+    /// State of the detection of whether the server is under load; see
+    /// [crate::load_detection]
+    pub load_detection: LoadDetection,
+    /// Per-source limit of the InitHello messages processed; see [crate::rate_limit]
+    pub rate_limiter: RateLimiter,
+    /// Worker threads decapsulating handshake messages; see [crate::kem_workers]
+    pub kem_workers: Option<KemWorkers>,
+    /// Wakes up the event loop from other threads; see [Self::waker]
+    pub waker: Option<Arc<mio::Waker>>,
     /// State kept by the [AppServer::try_recv] for polling
     pub blocking_polls_count: usize,
     /// State kept by the [AppServer::try_recv] for polling
@@ -372,6 +478,25 @@ pub struct AppServer {
     /// feature that can be used to embed Rosenpass in external applications
     /// via communication by unix socket
     pub api_manager: crate::api::mio::MioManager,
+    /// State needed to re-read the configuration file upon SIGHUP;
+    /// see [Self::enable_config_reload]
+    pub config_reload: Option<crate::config_reload::ConfigReload>,
+    /// Counters describing the operation of the server; see [crate::metrics]
+    pub metrics: crate::metrics::Metrics,
+    /// Listeners and connections serving [Self::metrics] over HTTP
+    pub metrics_server: crate::metrics::MetricsServer,
+    /// Commands started through [AppPeer::key_out_exec] that have not finished yet
+    pub key_out_commands: KeyOutCommands,
+    /// Lookups keeping the hostname endpoints of the peers up to date; see
+    /// [crate::endpoint_resolver]
+    pub endpoint_resolver: EndpointResolver,
+    /// File storing the biscuit keys and cookie secrets; see [crate::state_file]
+    pub state_file: Option<crate::state_file::StateFile>,
+    /// State needed to replace the process upon SIGUSR2; see [Self::enable_upgrade]
+    pub upgrade: Option<crate::upgrade::Upgrade>,
+    /// Seconds during which the previous static key is still accepted after a promotion; see
+    /// [Self::promote_identity_key] and [Self::promote_peer_key]
+    pub key_rotation_overlap: Timing,
 }
 
 /// A socket pointer is an index assigned to a socket;
@@ -385,20 +510,37 @@ pub struct SocketPtr(pub usize);
 
 impl SocketPtr {
     /// Retrieve the concrete udp socket associated with the pointer
+    ///
+    /// # Panic & Safety
+    ///
+    /// Panics if the socket was removed using [AppServer::unregister_listen_socket].
     pub fn get<'a>(&self, srv: &'a AppServer) -> &'a mio::net::UdpSocket {
-        &srv.sockets[self.0]
+        srv.sockets[self.0]
+            .as_ref()
+            .expect("Socket referenced by SocketPtr was removed")
     }
 
     /// Retrieve the concrete udp socket associated with the pointer, mutably
+    ///
+    /// # Panic & Safety
+    ///
+    /// Panics if the socket was removed using [AppServer::unregister_listen_socket].
     pub fn get_mut<'a>(&self, srv: &'a mut AppServer) -> &'a mut mio::net::UdpSocket {
-        &mut srv.sockets[self.0]
+        srv.sockets[self.0]
+            .as_mut()
+            .expect("Socket referenced by SocketPtr was removed")
     }
 
     /// Send a UDP packet to another address.
     ///
-    /// Merely forwards to [mio::net::UdpSocket::send_to]
+    /// Merely forwards to [mio::net::UdpSocket::send_to]; returns an error if the socket
+    /// was removed in the meantime.
     pub fn send_to(&self, srv: &AppServer, buf: &[u8], addr: SocketAddr) -> anyhow::Result<()> {
-        self.get(srv).send_to(buf, addr)?;
+        srv.sockets
+            .get(self.0)
+            .and_then(Option::as_ref)
+            .context("Socket referenced by SocketPtr was removed")?
+            .send_to(buf, addr)?;
         Ok(())
     }
 }
@@ -425,13 +567,21 @@ impl AppPeerPtr {
     }
 
     /// Retrieve the [AppPeer] pointed to by [Self]
+    ///
+    /// Panics if the peer was removed using [AppServer::remove_peer]
     pub fn get_app<'a>(&self, srv: &'a AppServer) -> &'a AppPeer {
-        &srv.peers[self.0]
+        srv.peers[self.0]
+            .as_ref()
+            .expect("Peer referenced by AppPeerPtr was removed")
     }
 
     /// Retrieve the [AppPeer] pointed to by [Self], mutably
+    ///
+    /// Panics if the peer was removed using [AppServer::remove_peer]
     pub fn get_app_mut<'a>(&self, srv: &'a mut AppServer) -> &'a mut AppPeer {
-        &mut srv.peers[self.0]
+        srv.peers[self.0]
+            .as_mut()
+            .expect("Peer referenced by AppPeerPtr was removed")
     }
 
     /// Use the associated WireGuard PSK broker via [BrokerStorePtr]
@@ -440,11 +590,17 @@ impl AppPeerPtr {
     /// If no PSK broker is set and [AppPeer::outfile] is none, then
     /// this prints a warning
     pub fn set_psk(&self, server: &mut AppServer, psk: &Secret<WG_KEY_LEN>) -> anyhow::Result<()> {
-        if let Some(broker) = server.peers[self.0].broker_peer.as_ref() {
+        let peer = server.peers[self.0]
+            .as_ref()
+            .context("Peer referenced by AppPeerPtr was removed")?;
+        if let Some(broker) = peer.broker_peer.as_ref() {
             let config = broker.peer_cfg.create_config(psk);
             let broker = server.brokers.store.get_mut(&broker.ptr().0).unwrap();
-            broker.set_psk(config)?;
-        } else if server.peers[self.0].outfile.is_none() {
+            if let Err(e) = broker.set_psk(config) {
+                server.metrics.record_broker_error();
+                return Err(e);
+            }
+        } else if peer.outfile.is_none() {
             log::warn!("No broker peer found for peer {}", self.0);
         }
         Ok(())
@@ -465,12 +621,48 @@ pub enum AppPollResult {
     SendRetransmission(AppPeerPtr),
     /// Received a network message.
     ///
-    /// This is the only case without a correspondence in [crate::protocol::PollResult]
+    /// This is one of the two cases without a correspondence in [crate::protocol::PollResult]
     ReceivedMessage(usize, Endpoint),
+    /// A network message was decapsulated by the KEM workers and can now be processed.
+    ///
+    /// See [crate::kem_workers].
+    Decapsulated(crate::kem_workers::Decapsulated),
+    /// Re-read the configuration file; the process received SIGHUP.
+    ///
+    /// See [AppServer::reload_config].
+    ReloadConfig,
+    /// Replace the process, handing over the sessions; the process received SIGUSR2.
+    ///
+    /// See [AppServer::upgrade].
+    Upgrade,
+}
+
+/// Noteworthy occurrences in the [AppServer], reported to API clients that subscribed to events.
+///
+/// See [AppServer::emit_event].
+#[derive(Debug, Clone, Copy)]
+pub enum AppServerEvent<'a> {
+    /// A key was output for the peer; see [AppServer::output_key]
+    KeyOutput(AppPeerPtr, KeyOutputReason),
+    /// We started a new handshake with the peer as initiator
+    HandshakeStarted(AppPeerPtr),
+    /// We retransmitted the last handshake message sent to the peer
+    HandshakeRetransmitted(AppPeerPtr),
+    /// Processing a handshake message received from the given endpoint failed
+    HandshakeFailed(&'a Endpoint),
+    /// We answered a message from the given endpoint with a cookie reply because we are under
+    /// load
+    CookieReplySent(&'a Endpoint),
+    /// The endpoint used to reach the peer changed; see [AppPeer::endpoint]
+    PeerEndpointChanged(AppPeerPtr),
+    /// The server entered [DoSOperation::UnderLoad]
+    UnderLoadEntered,
+    /// The server returned to [DoSOperation::Normal]
+    UnderLoadLeft,
 }
 
 /// The reason why we are outputting a key
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyOutputReason {
     /// The reason is that a new key for the given peer was successfully exchanged
     Exchanged,
@@ -482,6 +674,23 @@ pub enum KeyOutputReason {
     Stale,
 }
 
+/// Format of the log output and of the key output events written to stdout by
+/// [AppServer::output_key]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Free-form log messages; `output-key` lines are only written for peers with a
+    /// [AppPeer::outfile]
+    #[default]
+    Text,
+    /// One JSON object per line, for consumption by log processing pipelines
+    ///
+    /// Log messages carry the fields `timestamp`, `level`, `target`, and `message`. Each key
+    /// output is reported on stdout as an object with the fields `event` (always `output-key`),
+    /// `peer` (the base64 encoded peer id), `reason` (`exchanged` or `stale`), `key_file` (the
+    /// [AppPeer::outfile] or `null`), and `timestamp` (RFC 3339).
+    Json,
+}
+
 /// Represents a communication partner rosenpass may be sending packets to
 ///
 /// Generally at the start of Rosenpass either no address or a Hostname is known;
@@ -567,6 +776,11 @@ impl SocketBoundEndpoint {
         }
     }
 
+    /// The network address of the endpoint
+    pub fn addr(&self) -> &SocketAddr {
+        &self.addr
+    }
+
     /// Computes [HostIdentification::encode] for [Self]. Value cached in [Self::bytes].
     fn to_bytes(
         socket: &SocketPtr,
@@ -619,6 +833,40 @@ impl Endpoint {
         Ok(Endpoint::Discovery(host))
     }
 
+    /// Given a hostname, start peer discovery without waiting for the hostname to be resolved
+    ///
+    /// The endpoint has no addresses until [crate::endpoint_resolver] resolved the hostname in
+    /// the background; messages sent to it before are dropped. Literal addresses are used right
+    /// away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::SocketAddr;
+    /// use rosenpass::app_server::Endpoint;
+    ///
+    /// let host = |name: &str| match Endpoint::discovery_from_unresolved_hostname(name.into()) {
+    ///     Endpoint::Discovery(host) => host,
+    ///     Endpoint::SocketBoundAddress(_) => unreachable!(),
+    /// };
+    ///
+    /// // No lookup takes place
+    /// let unresolved = host("peer.invalid:9999");
+    /// assert!(unresolved.addresses().is_empty());
+    /// assert_eq!(unresolved.hostname(), Some("peer.invalid:9999"));
+    ///
+    /// let literal = host("[::1]:9999");
+    /// assert_eq!(literal.addresses(), &["[::1]:9999".parse::<SocketAddr>()?]);
+    /// assert_eq!(literal.hostname(), None);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn discovery_from_unresolved_hostname(hostname: String) -> Self {
+        match hostname.parse::<SocketAddr>() {
+            Ok(addr) => Self::discovery_from_addresses(vec![addr]),
+            Err(_) => Endpoint::Discovery(HostPathDiscoveryEndpoint::resolved(hostname, vec![])),
+        }
+    }
+
     // Restart discovery; joining two sources of (potential) addresses
     //
     // This is used when the connection to an endpoint is lost in order
@@ -657,7 +905,7 @@ impl Endpoint {
     /// List of addresses this endpoint may be associated with.
     ///
     /// During peer discovery, this can be multiple addresses.
-    fn addresses(&self) -> &[SocketAddr] {
+    pub(crate) fn addresses(&self) -> &[SocketAddr] {
         use Endpoint::*;
         match self {
             SocketBoundAddress(host) => slice::from_ref(&host.addr),
@@ -703,6 +951,9 @@ pub struct HostPathDiscoveryEndpoint {
     scouting_state: Cell<(usize, usize)>,
     /// List of addresses fir oeer discovery
     addresses: Vec<SocketAddr>,
+    /// The hostname [Self::addresses] were resolved from, if it needs to be resolved again
+    /// from time to time; see [crate::endpoint_resolver]
+    hostname: Option<String>,
 }
 
 impl std::fmt::Display for HostPathDiscoveryEndpoint {
@@ -718,15 +969,26 @@ impl HostPathDiscoveryEndpoint {
         Self {
             addresses,
             scouting_state,
+            hostname: None,
         }
     }
 
     /// Initiate a peer discovery process through hostname lookup
+    ///
+    /// This blocks until the hostname is resolved.
     pub fn lookup(hostname: String) -> anyhow::Result<Self> {
-        Ok(Self {
-            addresses: ToSocketAddrs::to_socket_addrs(&hostname)?.collect(),
-            scouting_state: Cell::new((0, 0)),
-        })
+        let addresses = ToSocketAddrs::to_socket_addrs(&hostname)?.collect();
+        Ok(Self::resolved(hostname, addresses))
+    }
+
+    /// Initiate a peer discovery process through the addresses the hostname was resolved to
+    pub fn resolved(hostname: String, addresses: Vec<SocketAddr>) -> Self {
+        // Literal addresses never change
+        let hostname = hostname.parse::<SocketAddr>().is_err().then_some(hostname);
+        Self {
+            hostname,
+            ..Self::from_addresses(addresses)
+        }
     }
 
     /// List of address candidates for the peer
@@ -734,6 +996,11 @@ impl HostPathDiscoveryEndpoint {
         &self.addresses
     }
 
+    /// The hostname the addresses were resolved from, unless it is a literal address
+    pub fn hostname(&self) -> Option<&str> {
+        self.hostname.as_deref()
+    }
+
     /// Calculates and stores the next value for [Self::scouting_state]
     /// given the address and socket we just sent a scouting [crate::msgs::InitHello] message
     /// to
@@ -748,6 +1015,11 @@ impl HostPathDiscoveryEndpoint {
     ///
     /// Will round-robin-try different socket-ip-combinations on each call.
     pub fn send_scouting(&self, srv: &AppServer, buf: &[u8]) -> anyhow::Result<()> {
+        // The hostname was not resolved yet; see [crate::endpoint_resolver]
+        if self.addresses.is_empty() {
+            return Ok(());
+        }
+
         let (addr_off, sock_off) = self.scouting_state.get();
 
         let mut addrs = (self.addresses)
@@ -761,7 +1033,8 @@ impl HostPathDiscoveryEndpoint {
             .enumerate()
             .cycle()
             .skip(sock_off)
-            .take(srv.sockets.len());
+            .take(srv.sockets.len())
+            .filter_map(|(sock_no, sock)| Some((sock_no, sock.as_ref()?)));
 
         for (addr_no, addr) in addrs.by_ref() {
             for (sock_no, sock) in sockets.by_ref() {
@@ -900,7 +1173,8 @@ impl AppServer {
             crypto_site,
             peers: Vec::new(),
             verbosity,
-            sockets,
+            log_format: LogFormat::Text,
+            sockets: sockets.into_iter().map(Some).collect(),
             events,
             short_poll_queue: Default::default(),
             performed_long_poll: false,
@@ -910,6 +1184,10 @@ impl AppServer {
             brokers: BrokerStore::default(),
             all_sockets_drained: false,
             under_load: DoSOperation::Normal,
+            load_detection: Default::default(),
+            rate_limiter: Default::default(),
+            kem_workers: None,
+            waker: None,
             blocking_polls_count: 0,
             non_blocking_polls_count: 0,
             unpolled_count: 0,
@@ -917,6 +1195,14 @@ impl AppServer {
             test_helpers,
             #[cfg(feature = "experiment_api")]
             api_manager: crate::api::mio::MioManager::default(),
+            config_reload: None,
+            metrics: Default::default(),
+            metrics_server: Default::default(),
+            key_out_commands: Default::default(),
+            endpoint_resolver: Default::default(),
+            state_file: None,
+            upgrade: None,
+            key_rotation_overlap: KEY_ROTATION_OVERLAP,
         })
     }
 
@@ -951,14 +1237,54 @@ impl AppServer {
     }
 
     /// Used by [Self::new] to register a new udp listen source
-    pub fn register_listen_socket(&mut self, mut sock: mio::net::UdpSocket) -> anyhow::Result<()> {
+    pub fn register_listen_socket(
+        &mut self,
+        mut sock: mio::net::UdpSocket,
+    ) -> anyhow::Result<SocketPtr> {
         let mio_token = self.mio_token_dispenser.dispense();
         self.mio_poll
             .registry()
             .register(&mut sock, mio_token, mio::Interest::READABLE)?;
-        let io_source = self.sockets.len().apply(AppServerIoSource::Socket);
-        self.sockets.push(sock);
-        self.register_io_source(mio_token, io_source);
+        let sock_no = self.sockets.len();
+        self.sockets.push(Some(sock));
+        self.register_io_source(mio_token, AppServerIoSource::Socket(sock_no));
+        Ok(SocketPtr(sock_no))
+    }
+
+    /// Close a udp listen socket registered with [Self::register_listen_socket] or [Self::new]
+    ///
+    /// Peers whose current endpoint was bound to the socket fall back to host-path discovery
+    /// (see [Endpoint::discovery_from_multiple_sources]).
+    pub fn unregister_listen_socket(&mut self, sock: SocketPtr) -> anyhow::Result<()> {
+        let mut socket = self
+            .sockets
+            .get_mut(sock.0)
+            .and_then(Option::take)
+            .context("Socket not found")?;
+        self.mio_poll.registry().deregister(&mut socket)?;
+
+        let token = self
+            .io_source_index
+            .iter()
+            .find(|(_, src)| **src == AppServerIoSource::Socket(sock.0))
+            .map(|(token, _)| *token);
+        if let Some(token) = token {
+            self.unregister_io_source(token);
+        }
+
+        for peer in self.peers.iter_mut().flatten() {
+            let bound_to_sock = matches!(
+                &peer.current_endpoint,
+                Some(Endpoint::SocketBoundAddress(ep)) if ep.socket.0 == sock.0
+            );
+            if bound_to_sock {
+                peer.current_endpoint = Endpoint::discovery_from_multiple_sources(
+                    peer.current_endpoint.as_ref(),
+                    peer.initial_endpoint.as_ref(),
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -968,6 +1294,21 @@ impl AppServer {
         assert!(prev.is_none());
     }
 
+    /// The waker used by other threads to wake up the event loop, created on first use
+    ///
+    /// [mio] supports a single waker per poll, so the [crate::kem_workers] and the
+    /// [crate::endpoint_resolver] share this one; [Self::poll] checks both after waking up.
+    pub fn waker(&mut self) -> io::Result<Arc<mio::Waker>> {
+        if let Some(waker) = &self.waker {
+            return Ok(waker.clone());
+        }
+        let token = self.mio_token_dispenser.dispense();
+        let waker = Arc::new(mio::Waker::new(self.mio_poll.registry(), token)?);
+        self.register_io_source(token, AppServerIoSource::Waker);
+        self.waker = Some(waker.clone());
+        Ok(waker)
+    }
+
     /// Unregister an IO source registered with [Self::register_io_source]
     pub fn unregister_io_source(&mut self, token: mio::Token) {
         let value = self.io_source_index.remove(&token);
@@ -1009,6 +1350,35 @@ impl AppServer {
         Ok(())
     }
 
+    /// Add another static keypair to the crypto server; see [CryptoServer::add_identity]
+    ///
+    /// Requires the crypto server to be constructed, i.e. the primary keypair to be known.
+    pub fn add_identity(&mut self, sk: SSk, pk: SPk) -> anyhow::Result<IdentityPtr> {
+        self.crypto_server_mut()
+            .context("Additional identities require the server keypair to be supplied first")?
+            .add_identity(sk, pk)
+    }
+
+    /// Make the next keypair of the given identity the active one, keeping the previous keypair
+    /// valid for [Self::key_rotation_overlap]; see [CryptoServer::promote_identity_key]
+    pub fn promote_identity_key(&mut self, identity: IdentityPtr) -> anyhow::Result<()> {
+        let overlap = self.key_rotation_overlap;
+        self.crypto_server_mut()?
+            .promote_identity_key(identity, overlap)?;
+        info!("Promoted the next key of identity {}", identity.0);
+        Ok(())
+    }
+
+    /// Make the next public key of the given peer the active one, keeping the previous public
+    /// key valid for [Self::key_rotation_overlap]; see [CryptoServer::promote_peer_key]
+    pub fn promote_peer_key(&mut self, peer: AppPeerPtr) -> anyhow::Result<()> {
+        let overlap = self.key_rotation_overlap;
+        self.crypto_server_mut()?
+            .promote_peer_key(peer.lower(), overlap)?;
+        info!("Promoted the next key of peer {}", peer.0);
+        Ok(())
+    }
+
     /// Register a new protocol peer
     ///
     /// # Examples
@@ -1024,31 +1394,126 @@ impl AppServer {
         hostname: Option<String>,
         protocol_version: ProtocolVersion,
         osk_domain_separator: OskDomainSeparator,
+    ) -> anyhow::Result<AppPeerPtr> {
+        self.add_peer_for_identity(
+            IdentityPtr(0),
+            psk,
+            pk,
+            outfile,
+            broker_peer,
+            hostname,
+            protocol_version,
+            osk_domain_separator,
+        )
+    }
+
+    /// Register a new protocol peer talking to the given identity; see [Self::add_identity]
+    /// and [CryptoServer::add_peer_for_identity]
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_peer_for_identity(
+        &mut self,
+        identity: IdentityPtr,
+        psk: Option<SymKey>,
+        pk: SPk,
+        outfile: Option<PathBuf>,
+        broker_peer: Option<BrokerPeer>,
+        hostname: Option<String>,
+        protocol_version: ProtocolVersion,
+        osk_domain_separator: OskDomainSeparator,
     ) -> anyhow::Result<AppPeerPtr> {
         let PeerPtr(pn) = match &mut self.crypto_site {
             ConstructionSite::Void => bail!("Crypto server construction site is void"),
             ConstructionSite::Builder(builder) => {
+                ensure!(
+                    identity == IdentityPtr(0),
+                    "Additional identities require the server keypair to be supplied first"
+                );
                 builder.add_peer(psk, pk, protocol_version, osk_domain_separator)
             }
-            ConstructionSite::Product(srv) => {
-                srv.add_peer(psk, pk, protocol_version.into(), osk_domain_separator)?
-            }
+            ConstructionSite::Product(srv) => srv.add_peer_for_identity(
+                identity,
+                psk,
+                pk,
+                protocol_version.into(),
+                osk_domain_separator,
+            )?,
         };
         assert!(pn == self.peers.len());
 
-        let initial_endpoint = hostname
-            .map(Endpoint::discovery_from_hostname)
-            .transpose()?;
+        // Resolved in the background; see [crate::endpoint_resolver]
+        let initial_endpoint = hostname.map(Endpoint::discovery_from_unresolved_hostname);
         let current_endpoint = None;
-        self.peers.push(AppPeer {
+        self.peers.push(Some(AppPeer {
             outfile,
             broker_peer,
             initial_endpoint,
             current_endpoint,
-        });
+            pin_endpoint: false,
+            last_key_output: None,
+            key_out_exec: None,
+            outfile_ownership: Default::default(),
+        }));
+        self.endpoint_resolver.peers_changed();
         Ok(AppPeerPtr(pn))
     }
 
+    /// Choose the cipher suite used for the key exchange with the given peer; see
+    /// [CryptoServer::set_peer_cipher_suite]
+    pub fn set_peer_cipher_suite(
+        &mut self,
+        peer: AppPeerPtr,
+        suite: CipherSuite,
+    ) -> anyhow::Result<()> {
+        match &mut self.crypto_site {
+            ConstructionSite::Void => bail!("Crypto server construction site is void"),
+            ConstructionSite::Builder(builder) => {
+                let params = (builder.peers.get_mut(peer.0))
+                    .with_context(|| format!("No such peer {}", peer.0))?;
+                ensure!(
+                    crate::protocol::ProtocolVersion::from(params.protocol_version)
+                        == suite.protocol_version,
+                    "Cannot change the protocol version of peer {}; the peer has to be re-added instead.",
+                    peer.0
+                );
+                params.ephemeral_kem = suite.ephemeral_kem;
+                params.aead = suite.aead;
+                params.hybrid_x25519 = suite.hybrid_x25519;
+            }
+            ConstructionSite::Product(srv) => srv.set_peer_cipher_suite(peer.lower(), suite)?,
+        }
+        Ok(())
+    }
+
+    /// Remove a protocol peer previously registered with [Self::add_peer]
+    ///
+    /// If there is a live session with the peer, the key exchanged in it is erased first,
+    /// just as if the session had expired (see [KeyOutputReason::Stale]). Afterwards, the peer is
+    /// removed from the crypto server using [CryptoServer::remove_peer] and its outfile,
+    /// broker peer and endpoints are dropped. The WireGuard broker itself may be shared with other
+    /// peers, so it stays registered.
+    ///
+    /// All other [AppPeerPtr] values remain valid.
+    pub fn remove_peer(&mut self, peer: AppPeerPtr) -> anyhow::Result<()> {
+        if !self.contains_peer(peer) {
+            bail!("Cannot remove peer {}; no such peer registered", peer.0);
+        }
+
+        let has_session = peer.lower().session().get(self.crypto_server()?).is_some();
+        if has_session {
+            self.output_key(peer, KeyOutputReason::Stale, &SymKey::random())?;
+        }
+
+        self.crypto_server_mut()?.remove_peer(peer.lower())?;
+        self.peers[peer.0] = None;
+        self.endpoint_resolver.peers_changed();
+        Ok(())
+    }
+
+    /// Check whether the given peer is registered and was not removed
+    pub fn contains_peer(&self, peer: AppPeerPtr) -> bool {
+        matches!(self.peers.get(peer.0), Some(Some(_)))
+    }
+
     /// Main IO handler; this generally does not terminate
     ///
     /// # Examples
@@ -1131,7 +1596,6 @@ impl AppServer {
         }
 
         loop {
-            use crate::protocol::HandleMsgResult;
             use AppPollResult::*;
             use KeyOutputReason::*;
 
@@ -1159,14 +1623,24 @@ impl AppServer {
             #[allow(clippy::redundant_closure_call)]
             match (have_crypto, poll_result) {
                 (CryptoSrv::Missing, SendInitiation(_)) => {}
-                (CryptoSrv::Avail, SendInitiation(peer)) => tx_maybe_with!(peer, || self
-                    .crypto_server_mut()?
-                    .initiate_handshake(peer.lower(), &mut *tx))?,
+                (CryptoSrv::Avail, SendInitiation(peer)) => {
+                    tx_maybe_with!(peer, || self
+                        .crypto_server_mut()?
+                        .initiate_handshake(peer.lower(), &mut *tx))?;
+                    if peer.get_app(self).endpoint().is_some() {
+                        self.emit_event(AppServerEvent::HandshakeStarted(peer));
+                    }
+                }
 
                 (CryptoSrv::Missing, SendRetransmission(_)) => {}
-                (CryptoSrv::Avail, SendRetransmission(peer)) => tx_maybe_with!(peer, || self
-                    .crypto_server_mut()?
-                    .retransmit_handshake(peer.lower(), &mut *tx))?,
+                (CryptoSrv::Avail, SendRetransmission(peer)) => {
+                    tx_maybe_with!(peer, || self
+                        .crypto_server_mut()?
+                        .retransmit_handshake(peer.lower(), &mut *tx))?;
+                    if peer.get_app(self).endpoint().is_some() {
+                        self.emit_event(AppServerEvent::HandshakeRetransmitted(peer));
+                    }
+                }
 
                 (CryptoSrv::Missing, DeleteKey(_)) => {}
                 (CryptoSrv::Avail, DeleteKey(peer)) => {
@@ -1176,59 +1650,128 @@ impl AppServer {
                     // starting from the last used address but including all the initially
                     // specified addresses
                     // TODO: We could do this preemptively, before any connection loss actually occurs.
-                    let p = peer.get_app_mut(self);
-                    p.current_endpoint = Endpoint::discovery_from_multiple_sources(
+                    let p = peer.get_app(self);
+                    let endpoint = Endpoint::discovery_from_multiple_sources(
                         p.current_endpoint.as_ref(),
                         p.initial_endpoint.as_ref(),
                     );
+                    self.set_current_endpoint(peer, endpoint);
                 }
 
-                (CryptoSrv::Missing, ReceivedMessage(_, _)) => {}
-                (CryptoSrv::Avail, ReceivedMessage(len, endpoint)) => {
-                    let msg_result = match self.under_load {
-                        DoSOperation::UnderLoad => {
-                            self.handle_msg_under_load(&endpoint, &rx[..len], &mut *tx)
-                        }
-                        DoSOperation::Normal => {
-                            self.crypto_server_mut()?.handle_msg(&rx[..len], &mut *tx)
-                        }
-                    };
-                    match msg_result {
-                        Err(ref e) => {
-                            self.verbose().then(|| {
-                                info!(
-                                    "error processing incoming message from {}: {:?} {}",
-                                    endpoint,
-                                    e,
-                                    e.backtrace()
-                                );
-                            });
-                        }
+                (_, ReloadConfig) => {
+                    // An invalid configuration file must not bring down the server
+                    if let Err(e) = self.reload_config() {
+                        error!("Refusing to apply reloaded configuration: {e:?}");
+                    }
+                }
 
-                        Ok(HandleMsgResult {
-                            resp,
-                            exchanged_with,
-                            ..
-                        }) => {
-                            if let Some(len) = resp {
-                                endpoint.send(self, &tx[0..len])?;
-                            }
-
-                            if let Some(p) = exchanged_with {
-                                let ap = AppPeerPtr::lift(p);
-                                ap.get_app_mut(self).current_endpoint = Some(endpoint);
-
-                                // TODO: Maybe we should rather call the key "rosenpass output"?
-                                let osk = &self.crypto_server_mut()?.osk(p)?;
-                                self.output_key(ap, Exchanged, osk)?;
-                            }
+                (_, Upgrade) => {
+                    // Only returns on failure; the running process then just carries on
+                    if let Err(e) = self.upgrade() {
+                        error!("Could not replace the process: {e:?}");
+                    }
+                }
+
+                (CryptoSrv::Missing, ReceivedMessage(len, _)) => {
+                    self.metrics.record_received(&rx[..len]);
+                }
+                (CryptoSrv::Avail, ReceivedMessage(len, endpoint)) => {
+                    self.metrics.record_received(&rx[..len]);
+                    self.load_detection.record_message(&endpoint);
+                    let msg = &rx[..len];
+                    if self.requires_cookie(&endpoint) {
+                        if !self.rate_limit_admits(&endpoint, msg)? {
+                            self.metrics.record_rate_limited();
+                            continue;
                         }
+                        self.handle_received_message(msg, &mut *tx, endpoint, true, None)?;
+                    } else if let Some(endpoint) = self.submit_decapsulation(msg, endpoint) {
+                        self.handle_received_message(msg, &mut *tx, endpoint, false, None)?;
                     }
                 }
+
+                (CryptoSrv::Missing, Decapsulated(_)) => {}
+                (CryptoSrv::Avail, Decapsulated(msg)) => {
+                    // Only messages that did not need a cookie were handed to the workers
+                    let (endpoint, decapsulation) = (msg.endpoint, msg.decapsulation);
+                    self.handle_received_message(
+                        &msg.msg,
+                        &mut *tx,
+                        endpoint,
+                        false,
+                        decapsulation,
+                    )?;
+                }
             };
         }
     }
 
+    /// Helper for [Self::event_loop_without_error_handling] to process a network message,
+    /// possibly using a decapsulation computed by the KEM workers
+    ///
+    /// `requires_cookie` is the result of [Self::requires_cookie] at the time the message was
+    /// received.
+    fn handle_received_message(
+        &mut self,
+        msg: &[u8],
+        tx: &mut [u8],
+        endpoint: Endpoint,
+        requires_cookie: bool,
+        decapsulation: Option<Decapsulation>,
+    ) -> Result<()> {
+        use crate::protocol::HandleMsgResult;
+        use KeyOutputReason::*;
+
+        let started = Instant::now();
+        let msg_result = match requires_cookie {
+            true => self.handle_msg_under_load(&endpoint, msg, &mut *tx, decapsulation),
+            false => self
+                .crypto_server_mut()?
+                .with_decapsulation(decapsulation, |srv| srv.handle_msg(msg, &mut *tx)),
+        };
+        self.load_detection.record_busy(started.elapsed());
+        match msg_result {
+            Err(ref e) => {
+                self.verbose().then(|| {
+                    info!(
+                        "error processing incoming message from {}: {:?} {}",
+                        endpoint,
+                        e,
+                        e.backtrace()
+                    );
+                });
+                self.metrics.record_message_error(e);
+                self.emit_event(AppServerEvent::HandshakeFailed(&endpoint));
+            }
+
+            Ok(HandleMsgResult {
+                resp,
+                exchanged_with,
+                authenticated_by,
+            }) => {
+                if let Some(len) = resp {
+                    endpoint.send(self, &tx[0..len])?;
+                    if tx[0] == crate::msgs::MsgType::CookieReply as u8 {
+                        self.emit_event(AppServerEvent::CookieReplySent(&endpoint));
+                    }
+                }
+
+                if let Some(p) = authenticated_by {
+                    self.roam(AppPeerPtr::lift(p), endpoint);
+                }
+
+                if let Some(p) = exchanged_with {
+                    let ap = AppPeerPtr::lift(p);
+
+                    // TODO: Maybe we should rather call the key "rosenpass output"?
+                    let osk = &self.crypto_server_mut()?.osk(p)?;
+                    self.output_key(ap, Exchanged, osk)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Helper for [Self::event_loop_without_error_handling] to handle network messages
     /// under DoS condition
     fn handle_msg_under_load(
@@ -1236,17 +1779,96 @@ impl AppServer {
         endpoint: &Endpoint,
         rx: &[u8],
         tx: &mut [u8],
+        decapsulation: Option<Decapsulation>,
     ) -> Result<crate::protocol::HandleMsgResult> {
         match endpoint {
             Endpoint::SocketBoundAddress(socket) => self
                 .crypto_server_mut()?
-                .handle_msg_under_load(rx, &mut *tx, socket),
+                .with_decapsulation(decapsulation, |srv| {
+                    srv.handle_msg_under_load(rx, &mut *tx, socket)
+                }),
             Endpoint::Discovery(_) => {
                 anyhow::bail!("Host-path discovery is not supported under load")
             }
         }
     }
 
+    /// Helper for [Self::event_loop_without_error_handling] to apply the [Self::rate_limiter] to
+    /// a message that requires a cookie
+    ///
+    /// Only InitHello messages with a valid cookie are charged: these are the ones that get
+    /// decapsulated, and the cookie proves that the source address was not spoofed.
+    fn rate_limit_admits(&mut self, endpoint: &Endpoint, msg: &[u8]) -> Result<bool> {
+        let Endpoint::SocketBoundAddress(socket) = endpoint else {
+            return Ok(true);
+        };
+        if !self.crypto_server_mut()?.has_valid_cookie(msg, socket) {
+            return Ok(true);
+        }
+        Ok(self.rate_limiter.admit(endpoint, msg, Instant::now()))
+    }
+
+    /// Contact the peer at the address an authenticated message from the peer was received from
+    /// from now on, unless its endpoint is pinned; see [AppPeer::current_endpoint]
+    fn roam(&mut self, peer: AppPeerPtr, endpoint: Endpoint) {
+        if !peer.get_app(self).pin_endpoint {
+            self.set_current_endpoint(peer, Some(endpoint));
+        }
+    }
+
+    /// Update [AppPeer::current_endpoint]; see [Self::change_endpoint]
+    fn set_current_endpoint(&mut self, peer: AppPeerPtr, endpoint: Option<Endpoint>) {
+        self.change_endpoint(peer, |ap| ap.current_endpoint = endpoint);
+    }
+
+    /// Modify the endpoints of the peer through `f`, logging the change and reporting it to API
+    /// clients subscribed to events if [AppPeer::endpoint] actually changed
+    pub(crate) fn change_endpoint(&mut self, peer: AppPeerPtr, f: impl FnOnce(&mut AppPeer)) {
+        let fmt = |ep: Option<&Endpoint>| ep.map(|ep| ep.to_string());
+        let before = fmt(peer.get_app(self).endpoint());
+        f(peer.get_app_mut(self));
+        let after = fmt(peer.get_app(self).endpoint());
+        if after == before {
+            return;
+        }
+
+        if self.verbose() {
+            let none = || "none".to_string();
+            info!(
+                "Endpoint of peer {} changed from {} to {}",
+                peer.0,
+                before.unwrap_or_else(none),
+                after.unwrap_or_else(none)
+            );
+        }
+        self.emit_event(AppServerEvent::PeerEndpointChanged(peer));
+    }
+
+    /// Count an event in [Self::metrics] and report it to API clients that subscribed to events
+    /// through [crate::api::Server::subscribe_events]
+    ///
+    /// Errors are logged instead of being returned; failing to report an event must not
+    /// disturb the key exchange. Without the `experiment_api` feature, events are only counted.
+    pub fn emit_event(&mut self, ev: AppServerEvent<'_>) {
+        self.metrics.record_event(&ev);
+
+        #[cfg(feature = "experiment_api")]
+        {
+            use crate::api::mio::MioManagerContext;
+            if !MioManagerFocus(self).has_event_subscribers() {
+                return;
+            }
+            let res = crate::api::event_payload(self, &ev)
+                .and_then(|payload| MioManagerFocus(self).broadcast_event(&payload));
+            if let Err(e) = res {
+                warn!("Could not report event {ev:?} to API clients: {e:?}");
+            }
+        }
+
+        #[cfg(not(feature = "experiment_api"))]
+        let _ = ev;
+    }
+
     /// Used as a helper by [Self::event_loop_without_error_handling] when
     /// a new output key has been exchanged
     pub fn output_key(
@@ -1256,6 +1878,9 @@ impl AppServer {
         key: &SymKey,
     ) -> anyhow::Result<()> {
         let peerid = peer.lower().get(self.crypto_server()?).pidt()?;
+        let now = self.crypto_server()?.timebase.now();
+        peer.get_app_mut(self).last_key_output = Some((why, now));
+        self.emit_event(AppServerEvent::KeyOutput(peer, why));
 
         if self.verbose() {
             let msg = match why {
@@ -1266,6 +1891,10 @@ impl AppServer {
         }
 
         let ap = peer.get_app(self);
+        let why = match why {
+            KeyOutputReason::Exchanged => "exchanged",
+            KeyOutputReason::Stale => "stale",
+        };
 
         if let Some(of) = ap.outfile.as_ref() {
             // This might leave some fragments of the secret on the stack;
@@ -1275,24 +1904,43 @@ impl AppServer {
             // data will linger in the linux page cache anyways with the current
             // implementation, going to great length to erase the secret here is
             // not worth it right now.
-            key.store_b64::<MAX_B64_KEY_SIZE, _>(of)?;
-            let why = match why {
-                KeyOutputReason::Exchanged => "exchanged",
-                KeyOutputReason::Stale => "stale",
-            };
+            //
+            // The file is replaced atomically, so programs polling it never read a partial key.
+            fwrite_atomic(of, Visibility::Secret, |f| {
+                ap.outfile_ownership.apply(f)?;
+                key.store_b64_writer::<MAX_B64_KEY_SIZE, _>(f)
+            })?;
+        }
 
+        // In JSON mode, keys passed only to WireGuard are reported too
+        let peerid = peerid.fmt_b64::<MAX_B64_PEER_ID_SIZE>();
+        let line = match (self.log_format, ap.outfile.as_ref()) {
+            (LogFormat::Json, outfile) => serde_json::json!({
+                "event": "output-key",
+                "peer": peerid.to_string(),
+                "reason": why,
+                "key_file": outfile.map(|of| of.to_string_lossy()),
+                "timestamp": humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            })
+            .to_string()
+            .some(),
+            (LogFormat::Text, Some(of)) => {
+                format!("output-key peer {peerid} key-file {of:?} {why}").some()
+            }
+            (LogFormat::Text, None) => None,
+        };
+
+        if let Some(line) = line {
             // this is intentionally writing to stdout instead of stderr, because
             // it is meant to allow external detection of a successful key-exchange
             let stdout = stdout();
             let mut stdout = stdout.lock();
-            writeln!(
-                stdout,
-                "output-key peer {} key-file {of:?} {why}",
-                peerid.fmt_b64::<MAX_B64_PEER_ID_SIZE>()
-            )?;
+            writeln!(stdout, "{line}")?;
             stdout.flush()?;
         }
 
+        self.run_key_out_exec(peer, &peerid.to_string(), why, key);
+
         peer.set_psk(self, key)?;
 
         Ok(())
@@ -1306,6 +1954,23 @@ impl AppServer {
         use crate::protocol::PollResult as C;
         use AppPollResult as A;
         let res = loop {
+            // Configuration reload was requested via SIGHUP
+            let reload_requested =
+                (self.config_reload.as_ref()).is_some_and(|reload| reload.signal.take_requested());
+            if reload_requested {
+                break A::ReloadConfig;
+            }
+
+            // Replacing the process was requested via SIGUSR2
+            let upgrade_requested =
+                (self.upgrade.as_ref()).is_some_and(|upgrade| upgrade.signal.take_requested());
+            if upgrade_requested {
+                break A::Upgrade;
+            }
+
+            // Load the state file or store rotated keys
+            self.poll_state_file();
+
             // Call CryptoServer's poll (if available)
             let crypto_poll = self
                 .crypto_site
@@ -1324,6 +1989,17 @@ impl AppServer {
                 None => crate::protocol::timing::UNENDING, // Crypto server is uninitialized, do IO
             };
 
+            // Reap key_out_exec commands; wake up regularly while they are running
+            let io_poll_timeout = self.poll_key_out_commands(io_poll_timeout);
+
+            // Resolve hostname endpoints again once they are due
+            let io_poll_timeout = self.poll_endpoint_resolver(io_poll_timeout);
+
+            // Process messages decapsulated by the KEM workers
+            if let Some(res) = self.poll_kem_workers() {
+                break res;
+            }
+
             // Perform IO (look for a message)
             if let Some((len, addr)) = self.try_recv(rx_buf, io_poll_timeout)? {
                 break A::ReceivedMessage(len, addr);
@@ -1398,38 +2074,7 @@ impl AppServer {
             Ok(())
         })?;
 
-        if let Some(AppServerTest {
-            enable_dos_permanently: true,
-            ..
-        }) = self.test_helpers
-        {
-            self.under_load = DoSOperation::UnderLoad;
-        } else {
-            //Reset blocking poll count if waiting for more than BLOCKING_POLL_COUNT_DURATION
-            if self.last_update_time.elapsed() > DURATION_UPDATE_UNDER_LOAD_STATUS {
-                self.last_update_time = Instant::now();
-                let total_polls = self.blocking_polls_count + self.non_blocking_polls_count;
-
-                let load_ratio = if total_polls > 0 {
-                    self.non_blocking_polls_count as f64 / total_polls as f64
-                } else if self.unpolled_count > 0 {
-                    //There are no polls, so we are under load
-                    1.0
-                } else {
-                    0.0
-                };
-
-                if load_ratio > UNDER_LOAD_RATIO {
-                    self.under_load = DoSOperation::UnderLoad;
-                } else {
-                    self.under_load = DoSOperation::Normal;
-                }
-
-                self.blocking_polls_count = 0;
-                self.non_blocking_polls_count = 0;
-                self.unpolled_count = 0;
-            }
-        }
+        self.update_under_load();
 
         // Focused polling – i.e. actually using mio::Token – is experimental for now.
         // The reason for this is that we need to figure out how to integrate load detection
@@ -1468,6 +2113,12 @@ impl AppServer {
         // drain all sockets
         let mut would_block_count = 0;
         for sock_no in 0..self.sockets.len() {
+            if self.sockets[sock_no].is_none() {
+                // Socket was removed; nothing to drain
+                would_block_count += 1;
+                continue;
+            }
+
             match self
                 .try_recv_from_listen_socket(buf, sock_no)
                 .io_err_kind_hint()
@@ -1502,6 +2153,9 @@ impl AppServer {
             MioManagerFocus(self).poll()?;
         }
 
+        // Metrics exporter poll
+        self.poll_metrics()?;
+
         self.performed_long_poll = true;
 
         Ok(None)
@@ -1509,7 +2163,19 @@ impl AppServer {
 
     /// Internal helper for [Self::try_recv]
     fn perform_mio_poll_and_register_events(&mut self, timeout: Duration) -> io::Result<()> {
-        self.mio_poll.poll(&mut self.events, Some(timeout))?;
+        match self.mio_poll.poll(&mut self.events, Some(timeout)) {
+            // SIGHUP and SIGUSR2 interrupt the poll; they are picked up by [Self::poll]
+            Err(e)
+                if e.kind() == ErrorKind::Interrupted
+                    && ((self.config_reload.as_ref())
+                        .is_some_and(|reload| reload.signal.is_requested())
+                        || (self.upgrade.as_ref())
+                            .is_some_and(|upgrade| upgrade.signal.is_requested())) =>
+            {
+                return Ok(())
+            }
+            res => res?,
+        };
         // Fill the short poll buffer with the acquired events
         self.events
             .iter()
@@ -1563,6 +2229,27 @@ impl AppServer {
                     .poll_particular(mmio_src)
                     .map(|_| None)
             }
+
+            AppServerIoSource::ReloadSignal => {
+                if let Some(reload) = self.config_reload.as_mut() {
+                    reload.signal.drain()?;
+                }
+                Ok(None)
+            }
+
+            AppServerIoSource::UpgradeSignal => {
+                if let Some(upgrade) = self.upgrade.as_mut() {
+                    upgrade.signal.drain()?;
+                }
+                Ok(None)
+            }
+
+            AppServerIoSource::Metrics(metrics_src) => {
+                self.poll_metrics_source(metrics_src).map(|_| None)
+            }
+
+            // Finished decapsulations and lookups are picked up by [Self::poll]
+            AppServerIoSource::Waker => Ok(None),
         }
     }
 
@@ -1573,8 +2260,12 @@ impl AppServer {
         idx: usize,
     ) -> io::Result<Option<(usize, Endpoint)>> {
         use std::io::ErrorKind as K;
+        let Some(sock) = self.sockets[idx].as_ref() else {
+            // Stale event for a socket that was removed
+            return Ok(None);
+        };
         let (n, addr) = loop {
-            match self.sockets[idx].recv_from(buf).io_err_kind_hint() {
+            match sock.recv_from(buf).io_err_kind_hint() {
                 Ok(v) => break v,
                 Err((_, K::Interrupted)) => continue,
                 Err((e, _)) => return Err(e)?,
@@ -0,0 +1,389 @@
+//! Generates test vectors for complete Rosenpass handshakes
+//!
+//! The handshakes are performed between two [CryptoServer]s in deterministic mode (see
+//! [CryptoServer::new_deterministic]), so running this program always produces the same output.
+//! The output is checked in as `tests/vectors/handshake.json` and verified by the
+//! `gen-handshake-vectors` test; see `tests/vectors/readme.md` for the format.
+
+use std::fmt::Display;
+use std::ops::DerefMut;
+
+use anyhow::{ensure, Context, Result};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::Serialize;
+
+use rosenpass::msgs::MsgType;
+use rosenpass::protocol::basic_types::{MsgBuf, SPk, SSk, SymKey};
+use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
+use rosenpass::protocol::{
+    CryptoServer, HandleMsgResult, HostIdentification, PeerPtr, ProtocolVersion,
+};
+use rosenpass_cipher_traits::primitives::Kem;
+use rosenpass_ciphers::StaticKem;
+use rosenpass_secret_memory::rand::{with_rng, InjectedRng};
+use rosenpass_util::time::{with_clock, ManualClock};
+
+/// Seed for the static keys and the pre-shared key
+const KEY_SEED: [u8; 32] = [0x00; 32];
+/// Seed for the randomness of the initiator
+const INITIATOR_SEED: [u8; 32] = [0x01; 32];
+/// Seed for the randomness of the responder
+const RESPONDER_SEED: [u8; 32] = [0x02; 32];
+
+/// Host identification of the initiator as seen by the responder: 192.0.2.1, port 9999
+const HOST_ID: [u8; 6] = [192, 0, 2, 1, 0x27, 0x0f];
+
+/// Description of the random number generator, for the output
+const RNG_DESCRIPTION: &str = "ChaCha20Rng from rand_chacha 0.3 (ChaCha20 keystream with the \
+    seed as key and a zero nonce); the static keys and the psk are drawn from a generator seeded \
+    with keySeed, all randomness used by the initiator and responder from generators seeded with \
+    initiatorSeed and responderSeed";
+
+/// All test vectors
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Vectors {
+    /// What this file is
+    description: &'static str,
+    /// How the random values were generated
+    rng: &'static str,
+    /// Seed for the static keys and the psk
+    key_seed: String,
+    /// Static keys and psk shared by all transcripts
+    keys: Keys,
+    /// Host identification of the initiator, used for the cookie mechanism
+    host_identification: String,
+    /// One transcript per protocol version
+    transcripts: Vec<Transcript>,
+}
+
+/// Static keys and pre-shared key
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Keys {
+    /// Static secret key of the initiator
+    sski: String,
+    /// Static public key of the initiator
+    spki: String,
+    /// Static secret key of the responder
+    sskr: String,
+    /// Static public key of the responder
+    spkr: String,
+    /// Pre-shared key
+    psk: String,
+}
+
+/// A complete handshake
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Transcript {
+    /// The protocol version used by both parties
+    protocol_version: String,
+    /// Seed for the randomness of the initiator
+    initiator_seed: String,
+    /// Seed for the randomness of the responder
+    responder_seed: String,
+    /// Every message sent, in order
+    messages: Vec<Message>,
+    /// Ephemeral secret key generated by the initiator
+    eski: String,
+    /// Biscuit key of the responder
+    biscuit_key: String,
+    /// Cookie secret of the responder
+    cookie_secret: String,
+    /// Chaining keys at different points of the handshake
+    chaining_keys: ChainingKeys,
+    /// Output shared key, using the default domain separator
+    osk: String,
+}
+
+/// A message exchanged during the handshake
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Message {
+    /// Type of the message
+    msg_type: String,
+    /// Either `initiator` or `responder`
+    sender: &'static str,
+    /// The message, including the envelope
+    data: String,
+}
+
+/// Chaining keys at different points of the handshake
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChainingKeys {
+    /// Chaining key of the initiator after producing the [MsgType::InitHello]
+    initiator_init_hello: String,
+    /// Chaining key of the initiator's session after producing the [MsgType::InitConf]
+    initiator_session: String,
+    /// Chaining key of the responder's session after processing the [MsgType::InitConf]
+    responder_session: String,
+}
+
+/// Host identification for [CryptoServer::handle_msg_under_load]
+struct HostId;
+
+impl HostIdentification for HostId {
+    fn encode(&self) -> &[u8] {
+        &HOST_ID
+    }
+}
+
+impl Display for HostId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", HOST_ID)
+    }
+}
+
+/// Static keypairs of initiator and responder, and the pre-shared key
+struct KeyMaterial {
+    /// Static keypair of the initiator
+    initiator: (SSk, SPk),
+    /// Static keypair of the responder
+    responder: (SSk, SPk),
+    /// Pre-shared key
+    psk: SymKey,
+}
+
+impl KeyMaterial {
+    /// Generate the key material from [KEY_SEED]
+    fn generate() -> Result<Self> {
+        let keypair = || -> Result<(SSk, SPk)> {
+            let (mut sk, mut pk) = (SSk::zero(), SPk::zero());
+            StaticKem.keygen(sk.secret_mut(), pk.deref_mut())?;
+            Ok((sk, pk))
+        };
+
+        let mut rng = Some(InjectedRng::new(ChaCha20Rng::from_seed(KEY_SEED)));
+        with_rng(&mut rng, || {
+            Ok(Self {
+                initiator: keypair()?,
+                responder: keypair()?,
+                psk: SymKey::random(),
+            })
+        })
+    }
+
+    /// Output representation
+    fn keys(&self) -> Keys {
+        Keys {
+            sski: hex::encode(self.initiator.0.secret()),
+            spki: hex::encode(self.initiator.1.value),
+            sskr: hex::encode(self.responder.0.secret()),
+            spkr: hex::encode(self.responder.1.value),
+            psk: hex::encode(self.psk.secret()),
+        }
+    }
+}
+
+/// Create a deterministic server with the `own` keypair and `peer` as its only peer
+fn make_server(
+    own: &(SSk, SPk),
+    peer: &SPk,
+    psk: &SymKey,
+    protocol_version: ProtocolVersion,
+    seed: [u8; 32],
+) -> Result<CryptoServer> {
+    let mut srv = CryptoServer::new_deterministic(
+        own.0.clone(),
+        own.1.clone(),
+        InjectedRng::new(ChaCha20Rng::from_seed(seed)),
+    );
+    srv.add_peer(
+        Some(psk.clone()),
+        peer.clone(),
+        protocol_version,
+        OskDomainSeparator::default(),
+    )?;
+    Ok(srv)
+}
+
+/// Record the message of length `len` in `buf`, checking its type
+fn record(
+    messages: &mut Vec<Message>,
+    sender: &'static str,
+    expected: MsgType,
+    buf: &MsgBuf,
+    len: usize,
+) -> Result<()> {
+    let msg_type = MsgType::try_from(buf[0])?;
+    ensure!(
+        msg_type == expected,
+        "Expected {expected:?} from {sender}, got {msg_type:?}"
+    );
+    messages.push(Message {
+        msg_type: format!("{msg_type:?}"),
+        sender,
+        data: hex::encode(&buf[..len]),
+    });
+    Ok(())
+}
+
+/// The chaining key of `ck` as hex
+fn ck_hex(ck: &rosenpass_ciphers::hash_domain::SecretHashDomainNamespace) -> String {
+    hex::encode(ck.clone().danger_into_secret().secret())
+}
+
+/// Perform a complete handshake with the given protocol version
+///
+/// The responder is under load, so the handshake starts with a [MsgType::CookieReply].
+fn transcript(keys: &KeyMaterial, protocol_version: ProtocolVersion) -> Result<Transcript> {
+    let peer = PeerPtr(0);
+    let (mut ini, mut res) = (
+        make_server(
+            &keys.initiator,
+            &keys.responder.1,
+            &keys.psk,
+            protocol_version.clone(),
+            INITIATOR_SEED,
+        )?,
+        make_server(
+            &keys.responder,
+            &keys.initiator.1,
+            &keys.psk,
+            protocol_version.clone(),
+            RESPONDER_SEED,
+        )?,
+    );
+
+    let (mut ini_buf, mut res_buf) = (MsgBuf::zero(), MsgBuf::zero());
+    let mut messages = Vec::new();
+    let no_response = "Expected a response";
+
+    // InitHello, answered with a CookieReply
+    let len = ini.initiate_handshake(peer, &mut *ini_buf)?;
+    record(
+        &mut messages,
+        "initiator",
+        MsgType::InitHello,
+        &ini_buf,
+        len,
+    )?;
+    let hs = peer.hs().get(&ini).as_ref().context("No handshake")?;
+    let eski = hex::encode(hs.eski.secret());
+    let ck_init_hello = ck_hex(&hs.core.ck);
+
+    let HandleMsgResult { resp, .. } =
+        res.handle_msg_under_load(&ini_buf[..len], &mut *res_buf, &HostId)?;
+    let len = resp.context(no_response)?;
+    record(
+        &mut messages,
+        "responder",
+        MsgType::CookieReply,
+        &res_buf,
+        len,
+    )?;
+
+    // InitHello retransmitted with the cookie, answered with a RespHello
+    ini.handle_msg(&res_buf[..len], &mut *ini_buf)?;
+    let len = ini.retransmit_handshake(peer, &mut *ini_buf)?;
+    record(
+        &mut messages,
+        "initiator",
+        MsgType::InitHello,
+        &ini_buf,
+        len,
+    )?;
+
+    let HandleMsgResult { resp, .. } =
+        res.handle_msg_under_load(&ini_buf[..len], &mut *res_buf, &HostId)?;
+    let len = resp.context(no_response)?;
+    record(
+        &mut messages,
+        "responder",
+        MsgType::RespHello,
+        &res_buf,
+        len,
+    )?;
+
+    // InitConf, answered with an EmptyData
+    let len = ini.handle_msg(&res_buf[..len], &mut *ini_buf)?.resp;
+    let len = len.context(no_response)?;
+    record(&mut messages, "initiator", MsgType::InitConf, &ini_buf, len)?;
+
+    let len = res.handle_msg(&ini_buf[..len], &mut *res_buf)?.resp;
+    let len = len.context(no_response)?;
+    record(
+        &mut messages,
+        "responder",
+        MsgType::EmptyData,
+        &res_buf,
+        len,
+    )?;
+    ini.handle_msg(&res_buf[..len], &mut *ini_buf)?;
+
+    let osk = ini.osk(peer)?;
+    ensure!(
+        osk.secret() == res.osk(peer)?.secret(),
+        "Initiator and responder disagree on the shared key"
+    );
+
+    let session = |srv: &CryptoServer| -> Result<String> {
+        let session = peer.session().get(srv).as_ref().context("No session")?;
+        Ok(ck_hex(&session.ck))
+    };
+
+    // The keys most recently created by the responder
+    let biscuit_key = res
+        .biscuit_keys
+        .iter()
+        .max_by(|a, b| a.created_at.total_cmp(&b.created_at));
+    let cookie_secret = res
+        .cookie_secrets
+        .iter()
+        .max_by(|a, b| a.created_at.total_cmp(&b.created_at));
+
+    Ok(Transcript {
+        protocol_version: format!("{protocol_version:?}"),
+        initiator_seed: hex::encode(INITIATOR_SEED),
+        responder_seed: hex::encode(RESPONDER_SEED),
+        messages,
+        eski,
+        biscuit_key: hex::encode(biscuit_key.context("No biscuit key")?.value.secret()),
+        cookie_secret: hex::encode(cookie_secret.context("No cookie secret")?.value.secret()),
+        chaining_keys: ChainingKeys {
+            initiator_init_hello: ck_init_hello,
+            initiator_session: session(&ini)?,
+            responder_session: session(&res)?,
+        },
+        osk: hex::encode(osk.secret()),
+    })
+}
+
+/// Generate all test vectors
+fn generate() -> Result<Vectors> {
+    let keys = KeyMaterial::generate()?;
+    // Time stands still, so no key rotation or retransmission timer interferes
+    let clock = ManualClock::default();
+    let transcripts = with_clock(&clock, || {
+        [ProtocolVersion::V02, ProtocolVersion::V03]
+            .into_iter()
+            .map(|protocol_version| transcript(&keys, protocol_version))
+            .collect::<Result<Vec<_>>>()
+    })?;
+
+    Ok(Vectors {
+        description: "Deterministic transcripts of complete Rosenpass handshakes",
+        rng: RNG_DESCRIPTION,
+        key_seed: hex::encode(KEY_SEED),
+        keys: keys.keys(),
+        host_identification: hex::encode(HOST_ID),
+        transcripts,
+    })
+}
+
+fn main() -> Result<()> {
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+
+    // The Classic McEliece operations need a lot of stack space
+    let vectors = std::thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(generate)?
+        .join()
+        .map_err(|_| anyhow::anyhow!("Generating the test vectors panicked"))??;
+
+    println!("{}", serde_json::to_string_pretty(&vectors)?);
+    Ok(())
+}
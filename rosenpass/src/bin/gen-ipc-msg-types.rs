@@ -88,6 +88,23 @@ fn main() -> Result<()> {
                 Tree::Leaf("Add Listen Socket Response".to_owned()),
                 Tree::Leaf("Add Psk Broker Request".to_owned()),
                 Tree::Leaf("Add Psk Broker Response".to_owned()),
+                Tree::Leaf("Add Peer Request".to_owned()),
+                Tree::Leaf("Add Peer Response".to_owned()),
+                Tree::Leaf("Remove Peer Request".to_owned()),
+                Tree::Leaf("Remove Peer Response".to_owned()),
+                Tree::Leaf("List Peers Request".to_owned()),
+                Tree::Leaf("List Peers Response".to_owned()),
+                Tree::Leaf("Get Peer Status Request".to_owned()),
+                Tree::Leaf("Get Peer Status Response".to_owned()),
+                Tree::Leaf("Trigger Handshake Request".to_owned()),
+                Tree::Leaf("Trigger Handshake Response".to_owned()),
+                Tree::Leaf("Get Stats Request".to_owned()),
+                Tree::Leaf("Get Stats Response".to_owned()),
+                Tree::Leaf("Promote Key Request".to_owned()),
+                Tree::Leaf("Promote Key Response".to_owned()),
+                Tree::Leaf("Subscribe Events Request".to_owned()),
+                Tree::Leaf("Subscribe Events Response".to_owned()),
+                Tree::Leaf("Event".to_owned()),
             ],
         )],
     );
@@ -8,32 +8,37 @@ use clap::{Parser, Subcommand};
 use rosenpass_cipher_traits::primitives::Kem;
 use rosenpass_ciphers::StaticKem;
 use rosenpass_secret_memory::file::StoreSecret;
-use rosenpass_util::file::{LoadValue, LoadValueB64, StoreValue};
-use rosenpass_wireguard_broker::brokers::native_unix::{
-    NativeUnixBroker, NativeUnixBrokerConfigBaseBuilder, NativeUnixBrokerConfigBaseBuilderError,
-};
+use rosenpass_util::file::{LoadValue, StoreValue};
+use rosenpass_wireguard_broker::brokers::native_unix::NativeUnixBroker;
+use std::net::SocketAddr;
 use std::ops::DerefMut;
 use std::path::PathBuf;
 
+use crate::app_server::AppServer;
 use crate::app_server::AppServerTest;
-use crate::app_server::{AppServer, BrokerPeer};
-use crate::protocol::basic_types::{SPk, SSk, SymKey};
+use crate::app_server::LogFormat;
+use crate::protocol::basic_types::{SPk, SSk};
 
 use super::config;
 
 #[cfg(feature = "experiment_api")]
 use {
+    crate::api::client::ApiClient,
+    crate::app_server::MAX_B64_PEER_ID_SIZE,
     command_fds::{CommandFdExt, FdMapping},
     log::{error, info},
     mio::net::UnixStream,
+    rosenpass_util::b64::{b64_decode, B64Display},
     rosenpass_util::fd::claim_fd,
     rosenpass_wireguard_broker::brokers::mio_client::MioBrokerClient,
     rosenpass_wireguard_broker::WireguardBrokerMio,
     rustix::net::{socketpair, AddressFamily, SocketFlags, SocketType},
     std::os::fd::AsRawFd,
     std::os::unix::net,
+    std::path::Path,
     std::process::Command,
     std::thread,
+    std::time::{Duration, Instant},
 };
 
 /// How to reach a WireGuard PSK Broker
@@ -70,6 +75,10 @@ pub struct CliArgs {
     #[arg(short, long, group = "log-level")]
     quiet: bool,
 
+    /// Format of the log output and of the key output events written to stdout
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t)]
+    log_format: LogFormat,
+
     #[command(flatten)]
     #[cfg(feature = "experiment_api")]
     api: crate::api::cli::ApiCli,
@@ -95,6 +104,32 @@ pub struct CliArgs {
     #[arg(short, long, group = "psk-broker-specs")]
     psk_broker_spawn: bool,
 
+    /// Serve metrics in the Prometheus text format over HTTP on this TCP address,
+    /// e.g. `127.0.0.1:9477`
+    #[arg(long, value_name = "ADDR")]
+    metrics_listen: Vec<SocketAddr>,
+
+    /// Serve metrics in the Prometheus text format over HTTP on a unix socket created at this
+    /// path
+    #[arg(long, value_name = "PATH")]
+    metrics_listen_path: Vec<PathBuf>,
+
+    /// Keep the biscuit keys and cookie secrets in this file, so restarts do not disrupt
+    /// handshakes in flight
+    #[arg(long, value_name = "PATH")]
+    state_file: Option<PathBuf>,
+
+    /// Replace the process upon SIGUSR2, handing over the sessions; see [crate::upgrade]
+    #[arg(long)]
+    upgrade_on_sigusr2: bool,
+
+    /// Restore the sessions from a snapshot in this file descriptor
+    ///
+    /// This is passed by a running Rosenpass process replacing itself upon SIGUSR2; see
+    /// [crate::upgrade]
+    #[arg(long, value_name = "FD")]
+    restore_snapshot_fd: Option<i32>,
+
     /// The subcommand to be invoked
     #[command(subcommand)]
     pub command: Option<CliCommand>,
@@ -118,9 +153,17 @@ impl CliArgs {
     ///
     /// Generally the flow of control here is that all the command line parameters
     /// are merged into the configuration file to avoid much code duplication.
-    pub fn apply_to_config(&self, _cfg: &mut config::Rosenpass) -> anyhow::Result<()> {
+    pub fn apply_to_config(&self, cfg: &mut config::Rosenpass) -> anyhow::Result<()> {
         #[cfg(feature = "experiment_api")]
-        self.api.apply_to_config(_cfg)?;
+        self.api.apply_to_config(cfg)?;
+        cfg.metrics.listen.extend_from_slice(&self.metrics_listen);
+        cfg.metrics
+            .listen_path
+            .extend_from_slice(&self.metrics_listen_path);
+        if let Some(path) = &self.state_file {
+            cfg.state_file = Some(path.clone());
+        }
+        cfg.upgrade_on_sigusr2 |= self.upgrade_on_sigusr2;
         Ok(())
     }
 
@@ -143,6 +186,11 @@ impl CliArgs {
         None
     }
 
+    /// Returns the format of the log output set by CLI args
+    pub fn get_log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
     /// Return the WireGuard PSK broker interface configured.
     ///
     /// Returns `None` if the `experiment_api` feature is disabled.
@@ -178,6 +226,10 @@ pub enum CliCommand {
     /// the specified peers. If a peer's endpoint is specified, this Rosenpass
     /// instance will try to initiate a key exchange with the peer; otherwise,
     /// only initiation attempts from other peers will be responded to.
+    ///
+    /// Upon receiving SIGHUP, the configuration file is read again and changes
+    /// to the peers, listen addresses, and verbosity are applied without
+    /// interrupting the sessions of unchanged peers.
     ExchangeConfig { config_file: PathBuf },
 
     /// Start Rosenpass key exchanges based on command line arguments
@@ -254,6 +306,50 @@ pub enum CliCommand {
     /// Defined secret & public keys are checked for existence and validity.
     Validate { config_files: Vec<PathBuf> },
 
+    /// Start a new handshake with a peer of a running Rosenpass instance
+    ///
+    /// Connects to the API socket of the running instance and asks it to
+    /// initiate a key exchange with the given peer right away, even if the
+    /// current key is not due for renewal yet. Then waits until the key
+    /// exchange completed.
+    #[cfg(feature = "experiment_api")]
+    TriggerHandshake {
+        /// Path of the API socket of the running Rosenpass instance
+        #[clap(long, value_name = "PATH")]
+        api: PathBuf,
+
+        /// Base64 encoded peer id of the peer, as printed upon key exchange
+        peer_id: String,
+
+        /// How many seconds to wait for the key exchange to complete
+        #[clap(long, default_value_t = 10.0)]
+        timeout: f64,
+
+        /// Return right after the handshake was started, without waiting for
+        /// the key exchange to complete
+        #[clap(long)]
+        no_wait: bool,
+    },
+
+    /// Promote the next static key during a key rotation
+    ///
+    /// Connects to the API socket of the running instance and makes the
+    /// configured `next_keypair` of the server, or the `next_public_key` of
+    /// the given peer, the active key. The previous key keeps being accepted
+    /// for `key_rotation_overlap_secs`. The configuration file is not changed.
+    #[cfg(feature = "experiment_api")]
+    PromoteKey {
+        /// Path of the API socket of the running Rosenpass instance
+        #[clap(long, value_name = "PATH")]
+        api: PathBuf,
+
+        /// Base64 encoded peer id of the peer whose next public key to promote;
+        /// the peer id of one of the server's identities promotes its next
+        /// keypair. Promotes the next keypair of the server if not given.
+        #[clap(long, value_name = "PEER_ID")]
+        peer: Option<String>,
+    },
+
     /// DEPRECATED - use the gen-keys command instead
     #[allow(rustdoc::broken_intra_doc_links)]
     #[allow(rustdoc::invalid_html_tags)]
@@ -383,7 +479,13 @@ impl CliArgs {
                 self.apply_to_config(&mut config)?;
                 config.check_usefullness()?;
 
-                Self::event_loop(config, broker_interface, test_helpers)?;
+                Self::event_loop(
+                    config,
+                    broker_interface,
+                    test_helpers,
+                    self.log_format,
+                    self.restore_snapshot_fd,
+                )?;
             }
 
             Some(Exchange {
@@ -404,7 +506,13 @@ impl CliArgs {
                 self.apply_to_config(&mut config)?;
                 config.check_usefullness()?;
 
-                Self::event_loop(config, broker_interface, test_helpers)?;
+                Self::event_loop(
+                    config,
+                    broker_interface,
+                    test_helpers,
+                    self.log_format,
+                    self.restore_snapshot_fd,
+                )?;
             }
 
             Some(Validate { config_files }) => {
@@ -422,6 +530,21 @@ impl CliArgs {
                 }
             }
 
+            #[cfg(feature = "experiment_api")]
+            Some(TriggerHandshake {
+                api,
+                peer_id,
+                timeout,
+                no_wait,
+            }) => {
+                Self::trigger_handshake(api, peer_id, *timeout, *no_wait)?;
+            }
+
+            #[cfg(feature = "experiment_api")]
+            Some(PromoteKey { api, peer }) => {
+                Self::promote_key(api, peer.as_deref())?;
+            }
+
             &None => {} // calp print help if no command is given
         }
 
@@ -433,9 +556,9 @@ impl CliArgs {
         config: config::Rosenpass,
         broker_interface: Option<BrokerInterface>,
         test_helpers: Option<AppServerTest>,
+        log_format: LogFormat,
+        restore_snapshot_fd: Option<i32>,
     ) -> anyhow::Result<()> {
-        const MAX_PSK_SIZE: usize = 1000;
-
         // load own keys
         let keypair = config
             .keypair
@@ -454,50 +577,170 @@ impl CliArgs {
             config.verbosity,
             test_helpers,
         )?);
+        srv.log_format = log_format;
 
         config.apply_to_app_server(&mut srv)?;
 
         let broker = Self::create_broker(broker_interface)?;
         let broker_store_ptr = srv.register_broker(broker)?;
 
-        fn cfg_err_map(e: NativeUnixBrokerConfigBaseBuilderError) -> anyhow::Error {
-            anyhow::Error::msg(format!("NativeUnixBrokerConfigBaseBuilderError: {:?}", e))
+        let peers = (config.peers.iter())
+            .map(|cfg_peer| {
+                let identity = config.identity_ptr(cfg_peer)?;
+                cfg_peer.add_to_app_server(&mut srv, &broker_store_ptr, identity)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // Continue the sessions of the process we replace; without them, the peers
+        // just perform new handshakes
+        if let Some(fd) = restore_snapshot_fd {
+            if let Err(e) = srv.restore_snapshot_fd(fd) {
+                log::error!("Could not restore the sessions of the previous process: {e:?}");
+            }
         }
 
-        for cfg_peer in config.peers {
-            let broker_peer = if let Some(wg) = &cfg_peer.wg {
-                let peer_cfg = NativeUnixBrokerConfigBaseBuilder::default()
-                    .peer_id_b64(&wg.peer)?
-                    .interface(wg.device.clone())
-                    .extra_params_ser(&wg.extra_params)?
-                    .build()
-                    .map_err(cfg_err_map)?;
-
-                let broker_peer = BrokerPeer::new(broker_store_ptr.clone(), Box::new(peer_cfg));
-
-                Some(broker_peer)
-            } else {
-                None
-            };
-
-            srv.add_peer(
-                // psk, pk, outfile, outwg, tx_addr
-                cfg_peer
-                    .pre_shared_key
-                    .map(SymKey::load_b64::<MAX_PSK_SIZE, _>)
-                    .transpose()?,
-                SPk::load(&cfg_peer.public_key)?,
-                cfg_peer.key_out,
-                broker_peer,
-                cfg_peer.endpoint.clone(),
-                cfg_peer.protocol_version.into(),
-                cfg_peer.osk_domain_separator.try_into()?,
-            )?;
+        // Without a configuration file, there is nothing to reload
+        if !config.config_file_path.as_os_str().is_empty() {
+            srv.enable_config_reload(config, peers, broker_store_ptr)?;
         }
 
         srv.event_loop()
     }
 
+    /// Used by [Self::run] to start a handshake with a peer through the API
+    /// of a running Rosenpass instance and to wait for the key exchange to
+    /// complete
+    #[cfg(feature = "experiment_api")]
+    fn trigger_handshake(
+        api_path: &Path,
+        peer_id: &str,
+        timeout: f64,
+        no_wait: bool,
+    ) -> anyhow::Result<()> {
+        use crate::api::{
+            event_type, subscribe_events_response_status, trigger_handshake_response_status,
+            SubscribeEventsRequest, TriggerHandshakeRequest,
+        };
+
+        let id = Self::parse_peer_id(peer_id)?;
+
+        let timeout = Duration::try_from_secs_f64(timeout)
+            .with_context(|| format!("Invalid timeout {timeout}"))?;
+        let deadline = Instant::now() + timeout;
+
+        // Subscribe before triggering the handshake so we can not miss the key exchange
+        let events = match no_wait {
+            true => None,
+            false => {
+                let events = ApiClient::connect(api_path)?;
+                events.set_timeout(Some(timeout))?;
+                let status = events
+                    .request(&SubscribeEventsRequest::new())?
+                    .payload
+                    .status;
+                ensure!(
+                    status == subscribe_events_response_status::OK,
+                    "Could not subscribe to events from the Rosenpass API (status {status})"
+                );
+                Some(events)
+            }
+        };
+
+        let api = ApiClient::connect(api_path)?;
+        api.set_timeout(Some(timeout))?;
+        match api
+            .request(&TriggerHandshakeRequest::new(id))?
+            .payload
+            .status
+        {
+            trigger_handshake_response_status::OK => {}
+            trigger_handshake_response_status::INVALID_REQUEST => {
+                bail!("The Rosenpass instance has no keypair yet")
+            }
+            trigger_handshake_response_status::NO_SUCH_PEER => {
+                bail!("The Rosenpass instance has no peer {peer_id}")
+            }
+            trigger_handshake_response_status::NO_ENDPOINT => {
+                bail!("No endpoint is known for peer {peer_id}; can not start a handshake")
+            }
+            status => bail!("Could not start the handshake with peer {peer_id} (status {status})"),
+        }
+
+        let Some(events) = events else {
+            println!("Started handshake with peer {peer_id}");
+            return Ok(());
+        };
+
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            ensure!(
+                !left.is_zero(),
+                "Key exchange with peer {peer_id} did not complete within {timeout:?}"
+            );
+            events.set_timeout(Some(left))?;
+
+            let ev = events.next_event().with_context(|| {
+                format!("Key exchange with peer {peer_id} did not complete within {timeout:?}")
+            })?;
+            let (typ, ev_peer) = (ev.event_type, ev.peer_id);
+            if typ == event_type::KEY_EXCHANGED && ev_peer == id {
+                println!("Exchanged key with peer {peer_id}");
+                return Ok(());
+            }
+        }
+    }
+
+    /// Used by [Self::run] to promote the next static key of the server or of
+    /// a peer through the API of a running Rosenpass instance
+    #[cfg(feature = "experiment_api")]
+    fn promote_key(api_path: &Path, peer_id: Option<&str>) -> anyhow::Result<()> {
+        use crate::api::{promote_key_response_status, PromoteKeyRequest};
+
+        let id = peer_id.map(Self::parse_peer_id).transpose()?;
+        let target = match peer_id {
+            Some(peer_id) => format!("peer {peer_id}"),
+            None => "the server".to_owned(),
+        };
+
+        let api = ApiClient::connect(api_path)?;
+        api.set_timeout(Some(Duration::from_secs(10)))?;
+        match api
+            .request(&PromoteKeyRequest::new(id.unwrap_or([0u8; 32])))?
+            .payload
+            .status
+        {
+            promote_key_response_status::OK => {}
+            promote_key_response_status::INVALID_REQUEST => {
+                bail!("The Rosenpass instance has no keypair yet")
+            }
+            promote_key_response_status::NO_SUCH_PEER => {
+                bail!("The Rosenpass instance has no {target}")
+            }
+            promote_key_response_status::NO_NEXT_KEY => {
+                bail!("No next key is configured for {target}")
+            }
+            status => bail!("Could not promote the next key of {target} (status {status})"),
+        }
+
+        println!("Promoted the next key of {target}");
+        Ok(())
+    }
+
+    /// Decode a base64 encoded peer id as printed upon key exchange
+    #[cfg(feature = "experiment_api")]
+    fn parse_peer_id(peer_id: &str) -> anyhow::Result<[u8; 32]> {
+        let mut id = [0u8; 32];
+        b64_decode(peer_id.as_bytes(), &mut id)
+            .and_then(|()| {
+                // b64_decode accepts inputs that are too short
+                let reencoded = id.fmt_b64::<MAX_B64_PEER_ID_SIZE>().to_string();
+                ensure!(reencoded == peer_id, "Peer ids are 32 bytes long");
+                Ok(())
+            })
+            .with_context(|| format!("Invalid peer id {peer_id:?}"))?;
+        Ok(id)
+    }
+
     /// Create the WireGuard PSK broker to be used by
     /// [crate::app_server::AppServer].
     ///
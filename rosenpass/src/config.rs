@@ -9,18 +9,29 @@
 
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use std::{collections::HashSet, fs, io::Write};
 
-use anyhow::{bail, ensure};
+use anyhow::{bail, ensure, Context};
 
 use serde::{Deserialize, Serialize};
 
-use rosenpass_util::file::{fopen_w, LoadValue, Visibility};
+use rosenpass_util::file::{fopen_w, LoadValue, LoadValueB64, Visibility};
+use rosenpass_wireguard_broker::brokers::native_unix::{
+    NativeUnixBrokerConfigBaseBuilder, NativeUnixBrokerConfigBaseBuilderError,
+};
 
-use crate::protocol::basic_types::{SPk, SSk};
+use crate::protocol::basic_types::{SPk, SSk, SymKey};
 use crate::protocol::osk_domain_separator::OskDomainSeparator;
+use crate::protocol::IdentityPtr;
 
-use crate::app_server::AppServer;
+use crate::app_server::{
+    AppPeer, AppPeerPtr, AppServer, BrokerPeer, BrokerStorePtr, OutfileOwnership,
+};
+use crate::key_out_exec::KeyOutExec;
+
+/// Maximum size of a base64 encoded pre-shared key file
+const MAX_PSK_SIZE: usize = 1000;
 
 #[cfg(feature = "experiment_api")]
 fn empty_api_config() -> crate::api::config::ApiConfig {
@@ -42,11 +53,75 @@ pub struct Rosenpass {
     #[serde(flatten)]
     pub keypair: Option<Keypair>,
 
+    /// Keypair replacing [Self::keypair] during a static key rotation
+    ///
+    /// Handshakes addressed to either keypair are accepted; the next keypair is used for
+    /// outgoing handshakes once it is promoted through the API (`rosenpass promote-key`).
+    /// See [crate::protocol::key_rotation].
+    ///
+    /// ```toml
+    /// [next_keypair]
+    /// public_key = "/path/to/next-public-key"
+    /// secret_key = "/path/to/next-secret-key"
+    /// ```
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_keypair: Option<Keypair>,
+
+    /// Seconds during which the previous static key is still accepted after promoting the next
+    /// one; defaults to [crate::protocol::constants::KEY_ROTATION_OVERLAP]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_rotation_overlap_secs: Option<u64>,
+
+    /// Seconds after which peer endpoints given as hostnames are resolved again; defaults to
+    /// [crate::endpoint_resolver::DEFAULT_REFRESH_INTERVAL]
+    ///
+    /// Zero disables resolving hostnames again. See [crate::endpoint_resolver].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_refresh_secs: Option<u64>,
+
     /// Location of the API listen sockets
     #[cfg(feature = "experiment_api")]
     #[serde(default = "empty_api_config")]
     pub api: crate::api::config::ApiConfig,
 
+    /// Where to export metrics in the Prometheus text format; see [crate::metrics]
+    #[serde(
+        default,
+        skip_serializing_if = "crate::metrics::MetricsConfig::is_empty"
+    )]
+    pub metrics: crate::metrics::MetricsConfig,
+
+    /// Thresholds deciding when the server is under load and asks for cookies; see
+    /// [crate::load_detection]
+    #[serde(
+        default,
+        skip_serializing_if = "crate::load_detection::UnderLoadConfig::is_default"
+    )]
+    pub under_load: crate::load_detection::UnderLoadConfig,
+
+    /// Per-source limit of the InitHello messages processed; see [crate::rate_limit]
+    #[serde(
+        default,
+        skip_serializing_if = "crate::rate_limit::RateLimitConfig::is_default"
+    )]
+    pub rate_limit: crate::rate_limit::RateLimitConfig,
+
+    /// Worker threads decapsulating handshake messages; see [crate::kem_workers]
+    #[serde(
+        default,
+        skip_serializing_if = "crate::kem_workers::KemWorkersConfig::is_default"
+    )]
+    pub kem_workers: crate::kem_workers::KemWorkersConfig,
+
+    /// File keeping the biscuit keys and cookie secrets across restarts; see
+    /// [crate::state_file]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_file: Option<PathBuf>,
+
+    /// Replace the process upon SIGUSR2, handing over the sessions; see [crate::upgrade]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub upgrade_on_sigusr2: bool,
+
     /// list of [`SocketAddr`] to listen on
     ///
     /// Examples:
@@ -62,6 +137,21 @@ pub struct Rosenpass {
     #[serde(default)]
     pub verbosity: Verbosity,
 
+    /// Additional keypairs the server answers to, so a single listen socket can serve
+    /// several isolated tenants
+    ///
+    /// Peers select the identity they talk to through [RosenpassPeer::identity]; peers
+    /// without an identity talk to [Self::keypair]. See [crate::protocol::Identity].
+    ///
+    /// ```toml
+    /// [[identities]]
+    /// name = "tenant-a"
+    /// public_key = "/path/to/tenant-a.pk"
+    /// secret_key = "/path/to/tenant-a.sk"
+    /// ```
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identities: Vec<Identity>,
+
     /// list of peers
     ///
     /// See the [`RosenpassPeer`] type for more information and examples.
@@ -98,6 +188,24 @@ impl Keypair {
     }
 }
 
+/// An additional keypair of the server; see [Rosenpass::identities]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Identity {
+    /// Name used to refer to this identity in [RosenpassPeer::identity]
+    pub name: String,
+
+    /// path to the public key file
+    pub public_key: PathBuf,
+
+    /// path to the secret key file
+    pub secret_key: PathBuf,
+
+    /// Keypair replacing this one during a static key rotation; see [Rosenpass::next_keypair]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_keypair: Option<Keypair>,
+}
+
 /// Level of verbosity for [crate::app_server::AppServer]
 ///
 /// The value of the field [crate::app_server::AppServer::verbosity]. See the field documentation
@@ -120,6 +228,27 @@ pub enum ProtocolVersion {
     V03,
 }
 
+/// The KEM used for the ephemeral key exchange with a peer; see
+/// [crate::protocol::EphemeralKemChoice]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Copy, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub enum EphemeralKemChoice {
+    #[default]
+    Kyber512,
+    MlKem768,
+    MlKem1024,
+}
+
+/// The AEAD used for encrypted fields in the exchange with a peer; see
+/// [crate::protocol::AeadChoice]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Copy, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub enum AeadChoice {
+    #[default]
+    ChaCha20Poly1305,
+    Aes256Gcm,
+}
+
 /// Configuration data for a single Rosenpass peer
 #[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -127,6 +256,14 @@ pub struct RosenpassPeer {
     /// path to the public key of the peer
     pub public_key: PathBuf,
 
+    /// path to the public key replacing [Self::public_key] during a static key rotation
+    ///
+    /// Handshakes from the peer under either key are accepted; the next public key is used for
+    /// outgoing handshakes once it is promoted through the API (`rosenpass promote-key --peer`).
+    /// See [crate::protocol::key_rotation].
+    #[serde(default)]
+    pub next_public_key: Option<PathBuf>,
+
     /// The hostname and port to connect to
     ///
     /// Can be a
@@ -134,8 +271,18 @@ pub struct RosenpassPeer {
     /// - hostname and port, e.g. `localhost:8876` or `rosenpass.eu:1427`
     /// - IPv4 address and port, e.g. `1.2.3.4:7764`
     /// - IPv6 address and port, e.g. `[fe80::24]:7890`
+    ///
+    /// Once the peer authenticates a message sent from another address, that address is used
+    /// instead, unless [Self::pin_endpoint] is set.
     pub endpoint: Option<String>,
 
+    /// Always contact the peer at [Self::endpoint], even if it sends messages from another
+    /// address
+    ///
+    /// By default, the peer may roam between networks. Requires [Self::endpoint] to be set.
+    #[serde(default)]
+    pub pin_endpoint: bool,
+
     /// path to the pre-shared key shared with the peer
     ///
     /// NOTE: this item can be skipped in the config if you do not use a pre-shared key with the peer
@@ -147,6 +294,31 @@ pub struct RosenpassPeer {
     #[serde(default)]
     pub key_out: Option<PathBuf>,
 
+    /// Numeric id of the user owning the [Self::key_out] file
+    ///
+    /// The file is owned by the user running Rosenpass if this is not set.
+    #[serde(default)]
+    pub key_out_owner: Option<u32>,
+
+    /// Numeric id of the group owning the [Self::key_out] file
+    ///
+    /// If set, the members of the group may read the file; this allows handing the keys to an
+    /// unprivileged consumer. Otherwise, only the owner may read the file.
+    #[serde(default)]
+    pub key_out_group: Option<u32>,
+
+    /// If this field is set, Rosenpass runs the given command for each exchanged key, passing
+    /// the key through standard input or a memfd; see [crate::key_out_exec]
+    ///
+    /// ```toml
+    /// [[peers]]
+    /// public_key = "my_public_key"
+    /// ...
+    /// key_out_exec = { command = ["/usr/local/bin/install-key"], timeout_secs = 5 }
+    /// ```
+    #[serde(default)]
+    pub key_out_exec: Option<KeyOutExec>,
+
     /// Information for supplying exchanged keys directly to WireGuard
     #[serde(flatten)]
     pub wg: Option<WireGuard>,
@@ -155,9 +327,36 @@ pub struct RosenpassPeer {
     /// The protocol version to use for the exchange
     pub protocol_version: ProtocolVersion,
 
+    /// The KEM used for the ephemeral key exchange; Kyber512 if not set
+    ///
+    /// Must match the configuration of the peer. ML-KEM-768 and ML-KEM-1024 provide NIST
+    /// security categories 3 and 5 respectively, at the cost of larger handshake messages.
+    #[serde(default)]
+    pub ephemeral_kem: Option<EphemeralKemChoice>,
+
+    /// The AEAD used for encrypted fields; ChaCha20Poly1305 if not set
+    ///
+    /// Must match the configuration of the peer.
+    #[serde(default)]
+    pub aead: Option<AeadChoice>,
+
+    /// Whether to complement the ephemeral KEM with an X25519 key exchange; off if not set
+    ///
+    /// This gives the exchanged keys hybrid (classical and post-quantum) security when they are
+    /// not handed to WireGuard, e.g. with [Self::key_out]. Must match the configuration of the
+    /// peer.
+    #[serde(default)]
+    pub hybrid_x25519: bool,
+
     /// Allows using a custom domain separator
     #[serde(flatten)]
     pub osk_domain_separator: RosenpassPeerOskDomainSeparator,
+
+    /// Name of the identity from [Rosenpass::identities] this peer talks to
+    ///
+    /// The peer talks to the main keypair of the server if this is not set.
+    #[serde(default)]
+    pub identity: Option<String>,
 }
 
 /// Configuration for [crate::protocol::osk_domain_separator::OskDomainSeparator]
@@ -212,6 +411,85 @@ impl RosenpassPeerOskDomainSeparator {
     }
 }
 
+impl RosenpassPeer {
+    /// The [crate::protocol::CipherSuite] configured for this peer
+    pub fn cipher_suite(&self) -> crate::protocol::CipherSuite {
+        crate::protocol::CipherSuite {
+            protocol_version: self.protocol_version.into(),
+            ephemeral_kem: self.ephemeral_kem.unwrap_or_default().into(),
+            aead: self.aead.unwrap_or_default().into(),
+            hybrid_x25519: self.hybrid_x25519,
+        }
+    }
+
+    /// Load the pre-shared key referenced by [Self::pre_shared_key], if any
+    pub fn load_psk(&self) -> anyhow::Result<Option<SymKey>> {
+        self.pre_shared_key
+            .as_ref()
+            .map(SymKey::load_b64::<MAX_PSK_SIZE, _>)
+            .transpose()
+    }
+
+    /// Construct the [BrokerPeer] supplying keys exchanged with this peer to WireGuard
+    /// through the given broker, if [Self::wg] is set
+    pub fn broker_peer(&self, broker: &BrokerStorePtr) -> anyhow::Result<Option<BrokerPeer>> {
+        fn cfg_err_map(e: NativeUnixBrokerConfigBaseBuilderError) -> anyhow::Error {
+            anyhow::Error::msg(format!("NativeUnixBrokerConfigBaseBuilderError: {:?}", e))
+        }
+
+        let Some(wg) = &self.wg else {
+            return Ok(None);
+        };
+
+        let peer_cfg = NativeUnixBrokerConfigBaseBuilder::default()
+            .peer_id_b64(&wg.peer)?
+            .interface(wg.device.clone())
+            .extra_params_ser(&wg.extra_params)?
+            .build()
+            .map_err(cfg_err_map)?;
+
+        Ok(Some(BrokerPeer::new(broker.clone(), Box::new(peer_cfg))))
+    }
+
+    /// Register this peer with the given [AppServer] for the given identity (see
+    /// [Rosenpass::identity_ptr]), supplying WireGuard through the given broker
+    pub fn add_to_app_server(
+        &self,
+        srv: &mut AppServer,
+        broker: &BrokerStorePtr,
+        identity: IdentityPtr,
+    ) -> anyhow::Result<AppPeerPtr> {
+        let peer = srv.add_peer_for_identity(
+            identity,
+            self.load_psk()?,
+            SPk::load(&self.public_key)?,
+            self.key_out.clone(),
+            self.broker_peer(broker)?,
+            self.endpoint.clone(),
+            self.protocol_version,
+            self.osk_domain_separator.clone().try_into()?,
+        )?;
+        self.apply_to_app_peer(peer.get_app_mut(srv));
+        srv.set_peer_cipher_suite(peer, self.cipher_suite())?;
+        if let Some(next) = &self.next_public_key {
+            srv.crypto_server_mut()?
+                .set_next_peer_key(peer.lower(), Some(SPk::load(next)?))?;
+        }
+        Ok(peer)
+    }
+
+    /// Apply the key output and endpoint settings not covered by [AppServer::add_peer] to a
+    /// registered peer
+    pub fn apply_to_app_peer(&self, ap: &mut AppPeer) {
+        ap.pin_endpoint = self.pin_endpoint;
+        ap.key_out_exec.clone_from(&self.key_out_exec);
+        ap.outfile_ownership = OutfileOwnership {
+            owner: self.key_out_owner,
+            group: self.key_out_group,
+        };
+    }
+}
+
 impl TryFrom<RosenpassPeerOskDomainSeparator> for OskDomainSeparator {
     type Error = anyhow::Error;
 
@@ -274,12 +552,27 @@ impl Rosenpass {
 
         // resolve `~` (see https://github.com/rosenpass/rosenpass/issues/237)
         use util::resolve_path_with_tilde;
-        if let Some(ref mut keypair) = config.keypair {
+        for keypair in config
+            .keypair
+            .iter_mut()
+            .chain(config.next_keypair.iter_mut())
+        {
             resolve_path_with_tilde(&mut keypair.public_key);
             resolve_path_with_tilde(&mut keypair.secret_key);
         }
+        for identity in config.identities.iter_mut() {
+            resolve_path_with_tilde(&mut identity.public_key);
+            resolve_path_with_tilde(&mut identity.secret_key);
+            if let Some(ref mut next) = identity.next_keypair {
+                resolve_path_with_tilde(&mut next.public_key);
+                resolve_path_with_tilde(&mut next.secret_key);
+            }
+        }
         for peer in config.peers.iter_mut() {
             resolve_path_with_tilde(&mut peer.public_key);
+            if let Some(ref mut next) = &mut peer.next_public_key {
+                resolve_path_with_tilde(next);
+            }
             if let Some(ref mut psk) = &mut peer.pre_shared_key {
                 resolve_path_with_tilde(psk);
             }
@@ -324,12 +617,62 @@ impl Rosenpass {
     }
 
     /// Apply the configuration in this object to the given [crate::app_server::AppServer]
-    pub fn apply_to_app_server(&self, _srv: &mut AppServer) -> anyhow::Result<()> {
+    ///
+    /// This adds the [Self::identities], so it must be called before adding the peers.
+    pub fn apply_to_app_server(&self, srv: &mut AppServer) -> anyhow::Result<()> {
+        for (no, identity) in self.identities.iter().enumerate() {
+            let sk = SSk::load(&identity.secret_key)?;
+            let pk = SPk::load(&identity.public_key)?;
+            let ptr = srv.add_identity(sk, pk)?;
+            assert!(ptr == IdentityPtr(no + 1));
+        }
+        let next_keypairs = std::iter::once(&self.next_keypair).chain(
+            self.identities
+                .iter()
+                .map(|identity| &identity.next_keypair),
+        );
+        for (no, next) in next_keypairs.enumerate() {
+            if let Some(next) = next {
+                let next = (SSk::load(&next.secret_key)?, SPk::load(&next.public_key)?);
+                srv.crypto_server_mut()?
+                    .set_next_identity_key(IdentityPtr(no), Some(next))?;
+            }
+        }
+        if let Some(secs) = self.key_rotation_overlap_secs {
+            srv.key_rotation_overlap = secs as f64;
+        }
+        if let Some(secs) = self.endpoint_refresh_secs {
+            srv.endpoint_resolver.refresh_interval = Duration::from_secs(secs);
+        }
+        srv.load_detection.config = self.under_load.clone();
+        srv.rate_limiter.set_config(self.rate_limit.clone());
+        srv.enable_kem_workers(&self.kem_workers)?;
         #[cfg(feature = "experiment_api")]
-        self.api.apply_to_app_server(_srv)?;
+        self.api.apply_to_app_server(srv)?;
+        self.metrics.apply_to_app_server(srv)?;
+        if let Some(path) = &self.state_file {
+            srv.enable_state_file(path.clone());
+        }
+        if self.upgrade_on_sigusr2 {
+            srv.enable_upgrade()?;
+        }
         Ok(())
     }
 
+    /// The identity of the server the given peer talks to
+    ///
+    /// Identities are added in the order of [Self::identities] by [Self::apply_to_app_server],
+    /// following the main keypair.
+    pub fn identity_ptr(&self, peer: &RosenpassPeer) -> anyhow::Result<IdentityPtr> {
+        let Some(name) = &peer.identity else {
+            return Ok(IdentityPtr(0));
+        };
+        let no = (self.identities.iter())
+            .position(|identity| &identity.name == name)
+            .with_context(|| format!("No such identity {name:?}"))?;
+        Ok(IdentityPtr(no + 1))
+    }
+
     /// Check that the configuration is sound, ensuring
     /// for instance that the referenced files exist
     ///
@@ -369,7 +712,70 @@ impl Rosenpass {
             );
         }
 
+        if let Some(ref next) = self.next_keypair {
+            ensure!(
+                self.keypair.is_some(),
+                "next_keypair requires the server keypair to be configured"
+            );
+            ensure!(
+                SPk::load(&next.public_key).is_ok(),
+                "could not load next public-key file {:?}",
+                next.public_key
+            );
+            ensure!(
+                SSk::load(&next.secret_key).is_ok(),
+                "could not load next secret-key file {:?}",
+                next.secret_key
+            );
+        }
+
+        self.under_load.validate()?;
+        self.rate_limit.validate()?;
+        self.kem_workers.validate()?;
+
+        let mut identity_names = HashSet::new();
+        for identity in self.identities.iter() {
+            let name = &identity.name;
+            ensure!(
+                self.keypair.is_some(),
+                "identity {name:?} requires the server keypair to be configured"
+            );
+            ensure!(
+                identity_names.insert(name),
+                "identity {name:?} is defined twice"
+            );
+            ensure!(
+                SPk::load(&identity.public_key).is_ok(),
+                "could not load public-key file {:?} of identity {name:?}",
+                identity.public_key
+            );
+            ensure!(
+                SSk::load(&identity.secret_key).is_ok(),
+                "could not load secret-key file {:?} of identity {name:?}",
+                identity.secret_key
+            );
+            if let Some(ref next) = identity.next_keypair {
+                ensure!(
+                    SPk::load(&next.public_key).is_ok(),
+                    "could not load next public-key file {:?} of identity {name:?}",
+                    next.public_key
+                );
+                ensure!(
+                    SSk::load(&next.secret_key).is_ok(),
+                    "could not load next secret-key file {:?} of identity {name:?}",
+                    next.secret_key
+                );
+            }
+        }
+
         for (i, peer) in self.peers.iter().enumerate() {
+            if let Some(name) = &peer.identity {
+                ensure!(
+                    identity_names.contains(name),
+                    "peer {i} refers to identity {name:?}, which is not defined"
+                );
+            }
+
             // check peer's public-key file exists
             ensure!(
                 peer.public_key.is_file(),
@@ -384,32 +790,58 @@ impl Rosenpass {
                 peer.public_key
             );
 
-            // check endpoint is usable
+            if let Some(next) = &peer.next_public_key {
+                ensure!(
+                    self.keypair.is_some(),
+                    "peer {i} next_public_key requires the server keypair to be configured"
+                );
+                ensure!(
+                    SPk::load(next).is_ok(),
+                    "peer {i} next public-key file {:?} is invalid",
+                    next
+                );
+            }
+
+            // check endpoint is usable; hostnames are resolved later, see
+            // [crate::endpoint_resolver]
             if let Some(addr) = peer.endpoint.as_ref() {
+                let host_port = addr
+                    .rsplit_once(':')
+                    .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
                 ensure!(
-                    addr.to_socket_addrs().is_ok(),
-                    "peer {i} endpoint {} can not be parsed to a socket address",
+                    host_port,
+                    "peer {i} endpoint {} must be of the form host:port",
                     addr
                 );
             }
+            ensure!(
+                !peer.pin_endpoint || peer.endpoint.is_some(),
+                "peer {i} has `pin_endpoint` set but no `endpoint` to pin"
+            );
 
-            // check if `key_out` or `device` and `peer` are defined
-            if peer.key_out.is_none() {
+            // check if `key_out`, `key_out_exec` or `device` and `peer` are defined
+            if peer.key_out.is_none() && peer.key_out_exec.is_none() {
                 if let Some(wg) = &peer.wg {
                     if wg.device.is_empty() || wg.peer.is_empty() {
                         ensure!(
                             false,
-                            "peer {i} has neither `key_out` nor valid wireguard config defined"
+                            "peer {i} has neither `key_out`, `key_out_exec` nor valid wireguard config defined"
                         );
                     }
                 } else {
                     ensure!(
                         false,
-                        "peer {i} has neither `key_out` nor valid wireguard config defined"
+                        "peer {i} has neither `key_out`, `key_out_exec` nor valid wireguard config defined"
                     );
                 }
             }
 
+            if let Some(exec) = &peer.key_out_exec {
+                if let Err(e) = exec.validate() {
+                    bail!("Invalid key_out_exec configuration for peer {i}: {e}");
+                }
+            }
+
             if let Err(e) = peer.osk_domain_separator.validate() {
                 bail!("Invalid OSK domain separation configuration for peer {i}: {e}");
             }
@@ -483,10 +915,20 @@ impl Rosenpass {
     pub fn new(keypair: Option<Keypair>) -> Self {
         Self {
             keypair,
+            next_keypair: None,
+            key_rotation_overlap_secs: None,
+            endpoint_refresh_secs: None,
             listen: vec![],
             #[cfg(feature = "experiment_api")]
             api: crate::api::config::ApiConfig::default(),
+            metrics: Default::default(),
+            under_load: Default::default(),
+            rate_limit: Default::default(),
+            kem_workers: Default::default(),
+            state_file: None,
+            upgrade_on_sigusr2: false,
             verbosity: Verbosity::Quiet,
+            identities: Vec::new(),
             peers: vec![],
             config_file_path: PathBuf::new(),
         }
@@ -729,15 +1171,81 @@ secret_key = "/path/to/rp-secret-key"
 listen = []
 verbosity = "Verbose"
 
+# Keep the biscuit keys across restarts, so handshakes in flight are not disrupted
+# state_file = "/var/lib/rosenpass/state"
+
+# Replace the process by the Rosenpass binary on disk upon SIGUSR2, keeping the sessions
+# upgrade_on_sigusr2 = true
+
+# How long the previous key keeps working after `rosenpass promote-key`
+# key_rotation_overlap_secs = 86400
+
+# Resolve peer endpoints given as hostnames again every five minutes (default); 0 disables this
+# endpoint_refresh_secs = 300
+
+# When to ask for cookies to fend off denial of service attacks (defaults shown)
+# [under_load]
+# update_interval_ms = 500
+# enter_poll_percent = 50
+# leave_poll_percent = 30
+# enter_busy_percent = 80
+# leave_busy_percent = 50
+# calm_periods = 2
+# Exempt sources sending at most this many messages per second; 0 asks all sources for cookies
+# source_rate = 0
+# At most this many messages per second are processed without a cookie, over all sources
+# uncookied_rate = 100
+
+# How many InitHello messages with a valid cookie a single source may make the server process
+# while under load (defaults shown); sources are aggregated by address prefix, and
+# init_hello_rate = 0 disables the limit
+# [rate_limit]
+# init_hello_rate = 10
+# init_hello_burst = 20
+# ipv4_prefix_len = 32
+# ipv6_prefix_len = 64
+# max_sources = 4096
+
+# Decapsulate handshake messages on worker threads; threads defaults to the number of CPUs, but at
+# most 4, and 0 processes all messages on the event loop
+# [kem_workers]
+# threads = 4
+# queue_len = 64
+
+# Serve metrics in the Prometheus text format over HTTP
+# [metrics]
+# listen = ["127.0.0.1:9477"]
+# listen_path = ["/run/rosenpass/metrics.sock"]
+
+# Serve further tenants with their own keypairs on the same sockets; peers
+# select one through `identity = "tenant-a"`
+# [[identities]]
+# name = "tenant-a"
+# public_key = "/path/to/tenant-a-public-key"
+# secret_key = "/path/to/tenant-a-secret-key"
+
+# Accept handshakes under a second keypair until it is promoted through
+# `rosenpass promote-key`; also available for identities
+# [next_keypair]
+# public_key = "/path/to/rp-next-public-key"
+# secret_key = "/path/to/rp-next-secret-key"
+
 [[peers]]
 # Commented out fields are optional
 public_key = "/path/to/rp-peer-public-key"
+# next_public_key = "/path/to/rp-peer-next-public-key" # accepted during key rotation
 endpoint = "127.0.0.1:9998"
+# pin_endpoint = true # do not follow the peer when it sends from another address
 # pre_shared_key = "/path/to/preshared-key"
+# ephemeral_kem = "MlKem768" # Kyber512 (default), MlKem768 or MlKem1024; must match the peer
+# aead = "Aes256Gcm" # ChaCha20Poly1305 (default) or Aes256Gcm; must match the peer
+# hybrid_x25519 = true # add an X25519 exchange for standalone use; must match the peer
 
 # Choose to store the key in a file via `key_out` or pass it to WireGuard by
 # defining `device` and `peer`. You may choose to do both.
 key_out = "/path/to/rp-key-out.txt" # path to store the key
+# key_out_group = 1000 # group allowed to read the key file
+# key_out_exec = { command = ["/path/to/hook"] } # command receiving the key on stdin
 # device = "wg0" # WireGuard interface
 #peer = "RULdRAtUw7SFfVfGD..." # WireGuard public key
 # extra_params = [] # passed to WireGuard `wg set`
@@ -858,6 +1366,339 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_metrics_config() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str(
+            r#"
+            listen = []
+            peers = []
+
+            [metrics]
+            listen = ["127.0.0.1:9477"]
+            listen_path = ["/run/rosenpass/metrics.sock"]
+        "#,
+        )?;
+        assert_eq!(
+            config.metrics.listen,
+            vec!["127.0.0.1:9477".parse::<SocketAddr>()?]
+        );
+        assert_eq!(
+            config.metrics.listen_path,
+            vec![PathBuf::from("/run/rosenpass/metrics.sock")]
+        );
+
+        // Unlike an empty metrics configuration, this one is stored
+        assert!(toml_ser(&config)?.contains_key("metrics"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_identities_config() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str(
+            r#"
+            listen = []
+
+            [[identities]]
+            name = "tenant-a"
+            public_key = "/tenant-a/pk"
+            secret_key = "/tenant-a/sk"
+
+            [[peers]]
+            public_key = "/peer-a/pk"
+            identity = "tenant-a"
+
+            [[peers]]
+            public_key = "/peer-b/pk"
+        "#,
+        )?;
+        assert_eq!(config.identities.len(), 1);
+        assert_eq!(config.identity_ptr(&config.peers[0])?, IdentityPtr(1));
+        assert_eq!(config.identity_ptr(&config.peers[1])?, IdentityPtr(0));
+
+        let unknown = RosenpassPeer {
+            identity: Some("tenant-b".to_owned()),
+            ..Default::default()
+        };
+        assert!(config.identity_ptr(&unknown).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_rotation_config() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str(
+            r#"
+            public_key = "/pk"
+            secret_key = "/sk"
+            listen = []
+            key_rotation_overlap_secs = 3600
+
+            [next_keypair]
+            public_key = "/next/pk"
+            secret_key = "/next/sk"
+
+            [[identities]]
+            name = "tenant-a"
+            public_key = "/tenant-a/pk"
+            secret_key = "/tenant-a/sk"
+            next_keypair = { public_key = "/tenant-a/next/pk", secret_key = "/tenant-a/next/sk" }
+
+            [[peers]]
+            public_key = "/peer-a/pk"
+            next_public_key = "/peer-a/next/pk"
+        "#,
+        )?;
+        assert_eq!(
+            config.next_keypair,
+            Some(Keypair::new("/next/pk", "/next/sk"))
+        );
+        assert_eq!(config.key_rotation_overlap_secs, Some(3600));
+        assert_eq!(
+            config.identities[0].next_keypair,
+            Some(Keypair::new("/tenant-a/next/pk", "/tenant-a/next/sk"))
+        );
+        assert_eq!(
+            config.peers[0].next_public_key,
+            Some(PathBuf::from("/peer-a/next/pk"))
+        );
+
+        // Without a rotation in progress, nothing is stored
+        let plain = Rosenpass::from_sk_pk("/sk", "/pk");
+        let table = toml_ser(&plain)?;
+        assert!(!table.contains_key("next_keypair"));
+        assert!(!table.contains_key("key_rotation_overlap_secs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_endpoint_refresh_config() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str(
+            r#"
+            listen = []
+            endpoint_refresh_secs = 60
+        "#,
+        )?;
+        assert_eq!(config.endpoint_refresh_secs, Some(60));
+
+        // The default is not stored
+        let plain = Rosenpass::from_sk_pk("/sk", "/pk");
+        assert!(!toml_ser(&plain)?.contains_key("endpoint_refresh_secs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_under_load_config() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str(
+            r#"
+            listen = []
+            [under_load]
+            enter_busy_percent = 60
+            leave_busy_percent = 40
+            source_rate = 5
+        "#,
+        )?;
+        assert_eq!(config.under_load.enter_busy_percent, 60);
+        assert_eq!(config.under_load.leave_busy_percent, 40);
+        assert_eq!(config.under_load.source_rate, 5);
+        // Fields not given keep their defaults
+        assert_eq!(config.under_load.update_interval_ms, 500);
+        assert_eq!(config.under_load.uncookied_rate, 100);
+        // By default, all sources are asked for cookies under load
+        let defaults = crate::load_detection::UnderLoadConfig::default();
+        assert_eq!(defaults.source_rate, 0);
+        assert!(config.under_load.validate().is_ok());
+
+        // The default is not stored
+        let plain = Rosenpass::from_sk_pk("/sk", "/pk");
+        assert!(!toml_ser(&plain)?.contains_key("under_load"));
+
+        let mut flapping = config.under_load.clone();
+        flapping.leave_busy_percent = 70;
+        assert!(flapping.validate().is_err());
+
+        let mut never = config.under_load.clone();
+        never.update_interval_ms = 0;
+        assert!(never.validate().is_err());
+
+        assert!(toml::from_str::<Rosenpass>("listen = []\n[under_load]\nratio = 1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limit_config() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str(
+            r#"
+            listen = []
+            [rate_limit]
+            init_hello_rate = 5
+            ipv6_prefix_len = 56
+        "#,
+        )?;
+        assert_eq!(config.rate_limit.init_hello_rate, 5);
+        assert_eq!(config.rate_limit.ipv6_prefix_len, 56);
+        // Fields not given keep their defaults
+        assert_eq!(config.rate_limit.init_hello_burst, 20);
+        assert!(config.rate_limit.validate().is_ok());
+        // The limit is enabled by default
+        assert!(crate::rate_limit::RateLimitConfig::default().is_enabled());
+
+        // The default is not stored
+        let plain = Rosenpass::from_sk_pk("/sk", "/pk");
+        assert!(!toml_ser(&plain)?.contains_key("rate_limit"));
+
+        let mut too_long = config.rate_limit.clone();
+        too_long.ipv4_prefix_len = 33;
+        assert!(too_long.validate().is_err());
+
+        let mut no_burst = config.rate_limit.clone();
+        no_burst.init_hello_burst = 0;
+        assert!(no_burst.validate().is_err());
+        // Irrelevant once the limit is disabled
+        no_burst.init_hello_rate = 0;
+        assert!(no_burst.validate().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kem_workers_config() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str(
+            r#"
+            listen = []
+            [kem_workers]
+            threads = 2
+        "#,
+        )?;
+        assert_eq!(config.kem_workers.threads(), 2);
+        // Fields not given keep their defaults
+        assert_eq!(config.kem_workers.queue_len, 64);
+        assert!(config.kem_workers.validate().is_ok());
+
+        // The default is not stored, and starts at least one but no more than four threads
+        let plain = Rosenpass::from_sk_pk("/sk", "/pk");
+        assert!(!toml_ser(&plain)?.contains_key("kem_workers"));
+        assert!((1..=4).contains(&plain.kem_workers.threads()));
+
+        let mut no_queue = config.kem_workers.clone();
+        no_queue.queue_len = 0;
+        assert!(no_queue.validate().is_err());
+        // Irrelevant once the workers are disabled
+        no_queue.threads = Some(0);
+        assert!(no_queue.validate().is_ok());
+
+        assert!(toml::from_str::<Rosenpass>("listen = []\n[kem_workers]\npool = 1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade_config() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str("listen = []\nupgrade_on_sigusr2 = true")?;
+        assert!(config.upgrade_on_sigusr2);
+
+        // Upgrades are disabled by default, which is not stored
+        let plain = Rosenpass::from_sk_pk("/sk", "/pk");
+        assert!(!plain.upgrade_on_sigusr2);
+        assert!(!toml_ser(&plain)?.contains_key("upgrade_on_sigusr2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ephemeral_kem() -> anyhow::Result<()> {
+        let config: Rosenpass = toml::from_str(
+            r#"
+            listen = []
+
+            [[peers]]
+            public_key = "/peer-a/pk"
+
+            [[peers]]
+            public_key = "/peer-b/pk"
+            ephemeral_kem = "MlKem768"
+
+            [[peers]]
+            public_key = "/peer-c/pk"
+            ephemeral_kem = "MlKem1024"
+        "#,
+        )?;
+        let kems: Vec<_> = config.peers.iter().map(|p| p.ephemeral_kem).collect();
+        assert_eq!(
+            kems,
+            vec![
+                None,
+                Some(EphemeralKemChoice::MlKem768),
+                Some(EphemeralKemChoice::MlKem1024)
+            ]
+        );
+
+        let unknown = toml::from_str::<RosenpassPeer>(
+            r#"
+            public_key = "/peer-a/pk"
+            ephemeral_kem = "MlKem512"
+        "#,
+        );
+        assert!(unknown.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cipher_suite() -> anyhow::Result<()> {
+        let peer: RosenpassPeer = toml::from_str(
+            r#"
+            public_key = "/peer-a/pk"
+            protocol_version = "V03"
+            ephemeral_kem = "MlKem1024"
+            aead = "Aes256Gcm"
+            hybrid_x25519 = true
+        "#,
+        )?;
+        assert_eq!(
+            peer.cipher_suite(),
+            crate::protocol::CipherSuite {
+                protocol_version: crate::protocol::ProtocolVersion::V03,
+                ephemeral_kem: crate::protocol::EphemeralKemChoice::MlKem1024,
+                aead: crate::protocol::AeadChoice::Aes256Gcm,
+                hybrid_x25519: true,
+            }
+        );
+
+        // Unset fields select the defaults
+        let peer = RosenpassPeer::default();
+        assert_eq!(
+            peer.cipher_suite(),
+            crate::protocol::CipherSuite::new(crate::protocol::ProtocolVersion::V02)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pin_endpoint() -> anyhow::Result<()> {
+        let peer: RosenpassPeer = toml::from_str(
+            r#"
+            public_key = "/peer-a/pk"
+            endpoint = "127.0.0.1:9998"
+            pin_endpoint = true
+        "#,
+        )?;
+        assert!(peer.pin_endpoint);
+
+        let mut ap = AppPeer::default();
+        peer.apply_to_app_peer(&mut ap);
+        assert!(ap.pin_endpoint);
+
+        // Peers may roam by default
+        assert!(!RosenpassPeer::default().pin_endpoint);
+
+        Ok(())
+    }
+
     #[test]
     fn test_protocol_version() {
         let mut rosenpass = Rosenpass::empty();
@@ -0,0 +1,593 @@
+//! Re-reading the configuration file while the Rosenpass server is running
+//!
+//! When started from a configuration file, the [AppServer] re-reads
+//! [config::Rosenpass::config_file_path] upon receiving SIGHUP (see
+//! [AppServer::enable_config_reload]). The file is compared against the configuration
+//! currently in use and the difference is applied in place (see [AppServer::reload_config]):
+//!
+//! - Peers are identified by the contents of their public key file; peers whose configuration
+//!   did not change keep their sessions. During a key rotation, the public key file may contain
+//!   either the active or the alternate key of the running peer (see
+//!   [crate::protocol::key_rotation]).
+//! - Changes to a peer's `endpoint`, `pin_endpoint`, `key_out` (including its owner and group),
+//!   `key_out_exec`, `next_public_key`, or WireGuard settings are applied to the running peer; its
+//!   session is preserved. Pinning the endpoint discards the address the peer roamed to.
+//!   Removing `next_public_key` only discards a next key that was not promoted yet. New endpoints
+//!   given as hostnames are resolved in the background (see [crate::endpoint_resolver]), so a
+//!   hostname that can not be resolved does not cause the reload to be refused.
+//! - Changes to a peer's pre-shared key, cipher suite (`protocol_version`, `ephemeral_kem`,
+//!   `aead` or `hybrid_x25519`), OSK domain separator, or identity require a new handshake; the
+//!   peer is removed and added again.
+//! - Listen addresses added to the file are bound; listen addresses removed from the file
+//!   are closed. Sockets opened because no listen address was configured at all are left alone.
+//! - The verbosity, `key_rotation_overlap_secs`, `endpoint_refresh_secs`, the `under_load`
+//!   thresholds, and the `rate_limit` settings are updated. Changing the rate limits resets the
+//!   state of the rate limiter.
+//!
+//! Changing the server keypair, its `next_keypair`, or the additional identities requires a
+//! restart, as does changing the API, metrics, or `kem_workers` configuration, the state file, or
+//! `upgrade_on_sigusr2`.
+//!
+//! Promoting keys through the API (`rosenpass promote-key`) does not modify the configuration
+//! file; update the file once the rotation is complete.
+//! Invalid configuration files are refused as a whole without touching the running state.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::c_int;
+use std::fmt::Display;
+use std::io::{ErrorKind, Read};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use log::info;
+use mio::Interest;
+use signal_hook::{consts::SIGHUP, SigId};
+
+use rosenpass_util::file::LoadValue;
+
+use crate::app_server::{
+    AppPeerPtr, AppServer, AppServerIoSource, BrokerPeer, BrokerStorePtr, Endpoint, SocketPtr,
+};
+use crate::config::{self, RosenpassPeer, Verbosity};
+use crate::endpoint_resolver::DEFAULT_REFRESH_INTERVAL;
+use crate::protocol::basic_types::{SPk, SymKey};
+use crate::protocol::constants::KEY_ROTATION_OVERLAP;
+use crate::protocol::osk_domain_separator::OskDomainSeparator;
+use crate::protocol::IdentityPtr;
+
+/// Watches for SIGHUP, or another signal given to [Self::for_signal]
+///
+/// The signal handler sets a flag that can be polled using [Self::take_requested] and
+/// writes to a socket pair registered with [mio], so a blocking poll is woken up.
+///
+/// The signal handlers are removed when this struct is dropped.
+#[derive(Debug)]
+pub struct ReloadSignal {
+    /// Set by the signal handler
+    requested: Arc<AtomicBool>,
+    /// Read end of the self-pipe the signal handler writes to
+    pipe: mio::net::UnixStream,
+    /// Signal handler registrations; see [signal_hook::low_level::unregister]
+    sig_ids: Vec<SigId>,
+}
+
+impl ReloadSignal {
+    /// Register the SIGHUP handlers and add the notification pipe to the given [mio::Registry]
+    pub fn new(registry: &mio::Registry, token: mio::Token) -> anyhow::Result<Self> {
+        Self::for_signal(SIGHUP, registry, token)
+    }
+
+    /// Like [Self::new], but watching for the given signal instead of SIGHUP
+    pub fn for_signal(
+        signal: c_int,
+        registry: &mio::Registry,
+        token: mio::Token,
+    ) -> anyhow::Result<Self> {
+        let (read, write) = std::os::unix::net::UnixStream::pair()?;
+        read.set_nonblocking(true)?;
+        write.set_nonblocking(true)?;
+
+        let mut pipe = mio::net::UnixStream::from_std(read);
+        registry.register(&mut pipe, token, Interest::READABLE)?;
+
+        let requested = Arc::new(AtomicBool::new(false));
+        let sig_ids = vec![
+            signal_hook::flag::register(signal, Arc::clone(&requested))?,
+            signal_hook::low_level::pipe::register(signal, write)?,
+        ];
+
+        Ok(Self {
+            requested,
+            pipe,
+            sig_ids,
+        })
+    }
+
+    /// Check whether the signal was received without resetting the flag
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Check whether the signal was received since the last call, resetting the flag
+    pub fn take_requested(&self) -> bool {
+        self.requested.swap(false, Ordering::Relaxed)
+    }
+
+    /// Consume all notifications written to the self-pipe
+    pub fn drain(&mut self) -> std::io::Result<()> {
+        let mut buf = [0u8; 16];
+        loop {
+            match self.pipe.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(_) => continue,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for ReloadSignal {
+    fn drop(&mut self) {
+        for id in self.sig_ids.drain(..) {
+            signal_hook::low_level::unregister(id);
+        }
+    }
+}
+
+/// State kept by [AppServer] to support [AppServer::reload_config]
+#[derive(Debug)]
+pub struct ConfigReload {
+    /// Notification about SIGHUP
+    pub signal: ReloadSignal,
+    /// The configuration currently in use
+    config: config::Rosenpass,
+    /// The peers created from [config::Rosenpass::peers]; same order
+    peers: Vec<AppPeerPtr>,
+    /// The sockets created from [config::Rosenpass::listen]
+    listen: Vec<(SocketAddr, SocketPtr)>,
+    /// The WireGuard PSK broker used for peers with WireGuard configuration
+    broker: BrokerStorePtr,
+}
+
+/// What [AppServer::reload_config] changed; used for logging
+#[derive(Debug, Default)]
+pub struct ConfigReloadSummary {
+    /// Public key paths of the peers that were added
+    pub peers_added: Vec<PathBuf>,
+    /// Public key paths of the peers that were removed
+    pub peers_removed: Vec<PathBuf>,
+    /// Public key paths of the peers that were updated while keeping their session
+    pub peers_updated: Vec<PathBuf>,
+    /// Public key paths of the peers that had to be removed and added again
+    pub peers_recreated: Vec<PathBuf>,
+    /// Listen addresses that were bound
+    pub listen_added: Vec<SocketAddr>,
+    /// Listen addresses that were closed
+    pub listen_removed: Vec<SocketAddr>,
+    /// Previous and new verbosity, if changed
+    pub verbosity: Option<(Verbosity, Verbosity)>,
+}
+
+impl ConfigReloadSummary {
+    /// Whether the reload changed nothing at all
+    pub fn is_empty(&self) -> bool {
+        self.peers_added.is_empty()
+            && self.peers_removed.is_empty()
+            && self.peers_updated.is_empty()
+            && self.peers_recreated.is_empty()
+            && self.listen_added.is_empty()
+            && self.listen_removed.is_empty()
+            && self.verbosity.is_none()
+    }
+}
+
+impl Display for ConfigReloadSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+
+        let mut parts = Vec::new();
+        for (what, peers) in [
+            ("added peers", &self.peers_added),
+            ("removed peers", &self.peers_removed),
+            ("updated peers", &self.peers_updated),
+            ("re-created peers", &self.peers_recreated),
+        ] {
+            if !peers.is_empty() {
+                parts.push(format!("{what} {peers:?}"));
+            }
+        }
+        if !self.listen_added.is_empty() {
+            parts.push(format!("listening on {:?}", self.listen_added));
+        }
+        if !self.listen_removed.is_empty() {
+            parts.push(format!("stopped listening on {:?}", self.listen_removed));
+        }
+        if let Some((old, new)) = self.verbosity {
+            parts.push(format!("verbosity {old:?} -> {new:?}"));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// What [AppServer::reload_config] does with a peer from the new configuration file
+enum PeerChange {
+    /// Configuration unchanged
+    Keep(AppPeerPtr),
+    /// Only IO related configuration changed; update in place, keeping the session
+    Update {
+        cfg_no: usize,
+        peer: AppPeerPtr,
+        broker_peer: Option<BrokerPeer>,
+        /// The new endpoint, if it changed; resolved in the background
+        initial_endpoint: Option<Option<Endpoint>>,
+        endpoint_changed: bool,
+        /// The new next public key, if it changed
+        next_pk: Option<Option<SPk>>,
+    },
+    /// Cryptographic configuration changed; remove the peer and add it again
+    Recreate(usize, PreparedPeer),
+    /// New peer
+    Add(PreparedPeer),
+}
+
+/// Everything needed to add a peer that can fail, loaded before the running state is touched
+struct PreparedPeer {
+    identity: IdentityPtr,
+    pk: SPk,
+    next_pk: Option<SPk>,
+    psk: Option<SymKey>,
+    broker_peer: Option<BrokerPeer>,
+    osk_domain_separator: OskDomainSeparator,
+}
+
+impl PreparedPeer {
+    fn new(
+        identity: IdentityPtr,
+        pk: SPk,
+        cfg: &RosenpassPeer,
+        broker: &BrokerStorePtr,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            identity,
+            pk,
+            next_pk: cfg.next_public_key.as_ref().map(SPk::load).transpose()?,
+            psk: cfg.load_psk()?,
+            broker_peer: cfg.broker_peer(broker)?,
+            osk_domain_separator: cfg.osk_domain_separator.clone().try_into()?,
+        })
+    }
+
+    fn add_to_app_server(
+        self,
+        srv: &mut AppServer,
+        cfg: &RosenpassPeer,
+    ) -> anyhow::Result<AppPeerPtr> {
+        let peer = srv.add_peer_for_identity(
+            self.identity,
+            self.psk,
+            self.pk,
+            cfg.key_out.clone(),
+            self.broker_peer,
+            cfg.endpoint.clone(),
+            cfg.protocol_version,
+            self.osk_domain_separator,
+        )?;
+        cfg.apply_to_app_peer(peer.get_app_mut(srv));
+        srv.set_peer_cipher_suite(peer, cfg.cipher_suite())?;
+        if self.next_pk.is_some() {
+            (srv.crypto_server_mut()?).set_next_peer_key(peer.lower(), self.next_pk)?;
+        }
+        Ok(peer)
+    }
+}
+
+impl AppServer {
+    /// Re-read the configuration file upon SIGHUP; see [crate::config_reload]
+    ///
+    /// Must be called right after the peers from `config` were added to a server constructed
+    /// with `config.listen` through [Self::new]; `peers` are the pointers returned when adding
+    /// the peers in [config::Rosenpass::peers], in the same order.
+    pub fn enable_config_reload(
+        &mut self,
+        config: config::Rosenpass,
+        peers: Vec<AppPeerPtr>,
+        broker: BrokerStorePtr,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            self.config_reload.is_none(),
+            "Configuration reload is already enabled"
+        );
+        ensure!(
+            peers.len() == config.peers.len(),
+            "Number of peers does not match the configuration"
+        );
+
+        let token = self.mio_token_dispenser.dispense();
+        let signal = ReloadSignal::new(self.mio_poll.registry(), token)?;
+        self.register_io_source(token, AppServerIoSource::ReloadSignal);
+
+        let listen = (config.listen.iter().copied())
+            .enumerate()
+            .map(|(sock_no, addr)| (addr, SocketPtr(sock_no)))
+            .collect();
+
+        self.config_reload = Some(ConfigReload {
+            signal,
+            config,
+            peers,
+            listen,
+            broker,
+        });
+        Ok(())
+    }
+
+    /// Re-read the configuration file and apply the changes; see [crate::config_reload]
+    ///
+    /// Returns an error without changing anything if the configuration file is invalid or
+    /// contains changes that can not be applied at runtime. Errors while applying the changes
+    /// (e.g. failing to erase the key of a removed peer) abort the reload midway; the changes
+    /// applied up to that point are kept, and the next reload applies the remaining ones.
+    pub fn reload_config(&mut self) -> anyhow::Result<ConfigReloadSummary> {
+        let mut state = self
+            .config_reload
+            .take()
+            .context("Configuration reload is not enabled")?;
+        let path = state.config.config_file_path.clone();
+
+        let res = self.reload_config_with(&mut state);
+        self.config_reload = Some(state);
+        // Endpoints may have changed even if the reload failed midway
+        self.endpoint_resolver.peers_changed();
+
+        let summary = res?;
+        info!("Reloaded configuration from {path:?}: {summary}");
+        Ok(summary)
+    }
+
+    /// Internal helper for [Self::reload_config]
+    fn reload_config_with(
+        &mut self,
+        state: &mut ConfigReload,
+    ) -> anyhow::Result<ConfigReloadSummary> {
+        let path = &state.config.config_file_path;
+        let new = config::Rosenpass::load(path)
+            .with_context(|| format!("Could not load configuration file {path:?}"))?;
+        new.validate()?;
+        ensure!(
+            new.keypair == state.config.keypair,
+            "Changing the server keypair requires a restart"
+        );
+        ensure!(
+            new.next_keypair == state.config.next_keypair,
+            "Changing the next server keypair requires a restart"
+        );
+        ensure!(
+            new.identities == state.config.identities,
+            "Changing the server identities requires a restart"
+        );
+
+        // Match peers by public key; everything that can fail happens before we touch the
+        // running state
+        let pks = (new.peers.iter())
+            .map(|cfg| SPk::load(&cfg.public_key))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let identities = (new.peers.iter())
+            .map(|cfg| new.identity_ptr(cfg))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let (matches, removed) = {
+            let cs = self
+                .crypto_server()
+                .context("Can not reload configuration before the server keypair is supplied")?;
+
+            // Peers from the current configuration that are still registered, by both of their
+            // keys during a key rotation
+            let mut current = (state.peers.iter().copied())
+                .enumerate()
+                .filter(|(_, peer)| self.contains_peer(*peer))
+                .flat_map(|(cfg_no, peer)| {
+                    let peer = peer.lower().get(cs);
+                    let alternate = peer.alternate_spkt.as_ref().map(|alt| &alt.spkt);
+                    std::iter::once(&peer.spkt)
+                        .chain(alternate)
+                        .map(move |pk| (pk, cfg_no))
+                })
+                .collect::<HashMap<_, _>>();
+
+            let mut seen = HashSet::new();
+            let mut matches = Vec::with_capacity(pks.len());
+            for (no, (cfg, pk)) in new.peers.iter().zip(pks.iter()).enumerate() {
+                ensure!(
+                    seen.insert(pk),
+                    "Peer {no} ({:?}) is listed twice",
+                    cfg.public_key
+                );
+
+                let cfg_no = current.remove(&pk);
+                match cfg_no {
+                    Some(cfg_no) => current.retain(|_, no| *no != cfg_no),
+                    None => ensure!(
+                        !cs.peer_ptrs().any(|p| p.get(cs).has_public_key(pk)),
+                        "Peer {no} ({:?}) is already registered through other means",
+                        cfg.public_key
+                    ),
+                }
+                matches.push(cfg_no);
+            }
+
+            let removed = current.into_values().collect::<HashSet<_>>();
+            (matches, removed.into_iter().collect::<Vec<_>>())
+        };
+
+        let mut changes = Vec::with_capacity(matches.len());
+        for (((cfg, pk), identity), cfg_no) in
+            (new.peers.iter().zip(pks).zip(identities)).zip(matches)
+        {
+            let Some(cfg_no) = cfg_no else {
+                let prepared = PreparedPeer::new(identity, pk, cfg, &state.broker)?;
+                changes.push(PeerChange::Add(prepared));
+                continue;
+            };
+
+            let peer = state.peers[cfg_no];
+            let old = &state.config.peers[cfg_no];
+            let change = if old == cfg {
+                PeerChange::Keep(peer)
+            } else if old.pre_shared_key != cfg.pre_shared_key
+                || old.cipher_suite() != cfg.cipher_suite()
+                || old.osk_domain_separator != cfg.osk_domain_separator
+                || old.identity != cfg.identity
+            {
+                let prepared = PreparedPeer::new(identity, pk, cfg, &state.broker)?;
+                PeerChange::Recreate(cfg_no, prepared)
+            } else {
+                PeerChange::Update {
+                    cfg_no,
+                    peer,
+                    broker_peer: cfg.broker_peer(&state.broker)?,
+                    initial_endpoint: (old.endpoint != cfg.endpoint).then(|| {
+                        (cfg.endpoint.clone()).map(Endpoint::discovery_from_unresolved_hostname)
+                    }),
+                    endpoint_changed: old.endpoint != cfg.endpoint
+                        || (cfg.pin_endpoint && !old.pin_endpoint),
+                    next_pk: match old.next_public_key == cfg.next_public_key {
+                        true => None,
+                        false => Some(cfg.next_public_key.as_ref().map(SPk::load).transpose()?),
+                    },
+                }
+            };
+            changes.push(change);
+        }
+
+        // Bind the new listen sockets
+        let listen_added = (new.listen.iter().copied())
+            .filter(|addr| !state.listen.iter().any(|(a, _)| a == addr))
+            .map(|addr| -> anyhow::Result<_> {
+                let sock = mio::net::UdpSocket::bind(addr)
+                    .with_context(|| format!("Could not bind to {addr}"))?;
+                Ok((addr, sock))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let listen_removed_count = (state.listen.iter())
+            .filter(|(addr, _)| !new.listen.contains(addr))
+            .count();
+        let live_sockets = self.sockets.iter().flatten().count();
+        ensure!(
+            live_sockets + listen_added.len() > listen_removed_count,
+            "No sockets to listen on!"
+        );
+
+        // Apply the changes. Each change that was applied is recorded in `state` right away, so
+        // if a later one fails, the next reload starts from what is actually running. Removed
+        // peers may stay in `state.peers`; they are skipped when matching peers.
+        let mut summary = ConfigReloadSummary::default();
+
+        for (addr, sock) in listen_added {
+            let sock = self.register_listen_socket(sock)?;
+            state.listen.push((addr, sock));
+            summary.listen_added.push(addr);
+        }
+        while let Some(idx) = (state.listen.iter()).position(|(addr, _)| !new.listen.contains(addr))
+        {
+            let (addr, sock) = state.listen[idx];
+            self.unregister_listen_socket(sock)?;
+            state.listen.remove(idx);
+            summary.listen_removed.push(addr);
+        }
+
+        for cfg_no in removed {
+            self.remove_peer(state.peers[cfg_no])?;
+            (summary.peers_removed).push(state.config.peers[cfg_no].public_key.clone());
+        }
+
+        let mut peers = Vec::with_capacity(changes.len());
+        for (cfg, change) in new.peers.iter().zip(changes) {
+            let peer = match change {
+                PeerChange::Keep(peer) => peer,
+                PeerChange::Update {
+                    cfg_no,
+                    peer,
+                    broker_peer,
+                    initial_endpoint,
+                    endpoint_changed,
+                    next_pk,
+                } => {
+                    if let Some(next_pk) = next_pk {
+                        (self.crypto_server_mut()?).set_next_peer_key(peer.lower(), next_pk)?;
+                    }
+                    let ap = peer.get_app_mut(self);
+                    ap.outfile.clone_from(&cfg.key_out);
+                    cfg.apply_to_app_peer(ap);
+                    ap.broker_peer = broker_peer;
+                    if let Some(initial_endpoint) = initial_endpoint {
+                        ap.initial_endpoint = initial_endpoint;
+                    }
+                    if endpoint_changed {
+                        ap.current_endpoint = None;
+                    }
+                    state.config.peers[cfg_no] = cfg.clone();
+                    summary.peers_updated.push(cfg.public_key.clone());
+                    peer
+                }
+                PeerChange::Recreate(cfg_no, prepared) => {
+                    self.remove_peer(state.peers[cfg_no])?;
+                    let peer = prepared.add_to_app_server(self, cfg)?;
+                    state.config.peers[cfg_no] = cfg.clone();
+                    state.peers[cfg_no] = peer;
+                    summary.peers_recreated.push(cfg.public_key.clone());
+                    peer
+                }
+                PeerChange::Add(prepared) => {
+                    let peer = prepared.add_to_app_server(self, cfg)?;
+                    state.config.peers.push(cfg.clone());
+                    state.peers.push(peer);
+                    summary.peers_added.push(cfg.public_key.clone());
+                    peer
+                }
+            };
+            peers.push(peer);
+        }
+
+        self.key_rotation_overlap =
+            (new.key_rotation_overlap_secs).map_or(KEY_ROTATION_OVERLAP, |secs| secs as f64);
+        self.endpoint_resolver.refresh_interval =
+            (new.endpoint_refresh_secs).map_or(DEFAULT_REFRESH_INTERVAL, Duration::from_secs);
+        self.load_detection.config = new.under_load.clone();
+        self.rate_limiter.set_config(new.rate_limit.clone());
+
+        if new.verbosity != self.verbosity {
+            summary.verbosity = Some((self.verbosity, new.verbosity));
+            self.verbosity = new.verbosity;
+        }
+
+        // The API configuration can not be changed at runtime; it also contains settings
+        // supplied on the command line
+        #[cfg(feature = "experiment_api")]
+        let new = config::Rosenpass {
+            api: std::mem::take(&mut state.config.api),
+            ..new
+        };
+        // Neither can the metrics listeners, the KEM workers, the state file, or upgrades
+        let new = config::Rosenpass {
+            metrics: std::mem::take(&mut state.config.metrics),
+            kem_workers: std::mem::take(&mut state.config.kem_workers),
+            state_file: state.config.state_file.take(),
+            upgrade_on_sigusr2: state.config.upgrade_on_sigusr2,
+            ..new
+        };
+        state.config = new;
+        state.peers = peers;
+
+        Ok(summary)
+    }
+}
@@ -0,0 +1,356 @@
+//! Resolving peer endpoints given as hostnames again from time to time
+//!
+//! The `endpoint` of a peer may be a hostname, e.g. one managed through a dynamic DNS service.
+//! Adding a peer does not wait for the hostname to be resolved (see
+//! [Endpoint::discovery_from_unresolved_hostname]); instead, [AppServer::poll] resolves it right
+//! away and then again every [EndpointResolver::refresh_interval], so the peer stays reachable
+//! after its address changed. The peer can not be contacted until the first lookup succeeded.
+//! Failed lookups are retried after [RETRY_DELAY], doubling the delay after each further failure
+//! up to the refresh interval; see [lookup_delay]. With a refresh interval of zero, hostnames are
+//! only resolved until the first lookup succeeded, retrying up to every
+//! [DEFAULT_REFRESH_INTERVAL].
+//!
+//! The resolver of the standard library blocks, so each lookup runs in a short-lived background
+//! thread; [AppServer::poll] merely collects the results and never waits for a lookup. The thread
+//! wakes up the event loop through [AppServer::waker] once the lookup is done.
+//!
+//! The peers with hostname endpoints are only looked for again after peers were added or removed
+//! or the configuration was reloaded (see [AppServer::add_peer], [AppServer::remove_peer] and
+//! [AppServer::reload_config]).
+//!
+//! When the addresses changed, [AppPeer::initial_endpoint] is replaced and an ongoing host-path
+//! discovery (see [HostPathDiscoveryEndpoint]) is restarted with the new addresses. An address
+//! the peer was reached at (see [AppPeer::current_endpoint]) is kept; if the peer can no longer be
+//! reached there, host-path discovery includes the new addresses once the key expires.
+//!
+//! [AppPeer::initial_endpoint]: crate::app_server::AppPeer::initial_endpoint
+//! [AppPeer::current_endpoint]: crate::app_server::AppPeer::current_endpoint
+
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{error, warn};
+use mio::Waker;
+
+use crate::app_server::{AppPeer, AppPeerPtr, AppServer, Endpoint, HostPathDiscoveryEndpoint};
+use crate::protocol::timing::Timing;
+
+/// Default value of [EndpointResolver::refresh_interval]
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Delay before retrying a failed lookup; doubled after each further failure
+pub const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Result of a lookup running in the background
+type LookupResult = std::io::Result<Vec<SocketAddr>>;
+
+/// A finished lookup: the index of the peer, the hostname that was resolved, and the result
+type Finished = (usize, String, LookupResult);
+
+/// Lookup state of a single peer
+#[derive(Debug)]
+struct PeerLookup {
+    /// The hostname to resolve
+    hostname: String,
+    /// Point in time at which the next lookup is started
+    due: Instant,
+    /// Number of lookups that failed in a row
+    failures: u32,
+    /// Whether a lookup is running in the background
+    running: bool,
+}
+
+/// Lookup state of all peers with hostname endpoints
+///
+/// Stored in [AppServer::endpoint_resolver].
+#[derive(Debug)]
+pub struct EndpointResolver {
+    /// Interval at which hostnames are resolved again; zero disables resolving them again once
+    /// they were resolved
+    pub refresh_interval: Duration,
+    /// Lookup state by index of the peer
+    peers: HashMap<usize, PeerLookup>,
+    /// Whether peers were added, removed or reconfigured since [Self::peers] was last updated
+    peers_changed: bool,
+    /// Point in time at which the next lookup is due, if any
+    next_due: Option<Instant>,
+    /// Handed to the lookup threads to send their results
+    finished_tx: Sender<Finished>,
+    /// Results of the lookups that finished
+    finished: Receiver<Finished>,
+}
+
+impl Default for EndpointResolver {
+    fn default() -> Self {
+        let (finished_tx, finished) = mpsc::channel();
+        Self {
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            peers: HashMap::new(),
+            peers_changed: true,
+            next_due: None,
+            finished_tx,
+            finished,
+        }
+    }
+}
+
+impl EndpointResolver {
+    /// Whether hostnames are resolved again at all
+    pub fn is_enabled(&self) -> bool {
+        !self.refresh_interval.is_zero()
+    }
+
+    /// Whether any lookups are running in the background
+    pub fn is_running(&self) -> bool {
+        self.peers.values().any(|lookup| lookup.running)
+    }
+
+    /// Look for the peers with hostname endpoints again during the next poll
+    ///
+    /// Called whenever peers were added, removed or reconfigured.
+    pub fn peers_changed(&mut self) {
+        self.peers_changed = true;
+    }
+
+    /// Make sure [Self::next_due] is no later than `due`
+    fn schedule(&mut self, due: Instant) {
+        self.next_due = Some(self.next_due.map_or(due, |next| next.min(due)));
+    }
+
+    /// Upper bound for the delay between lookups of a single hostname; see [lookup_delay]
+    fn max_delay(&self) -> Duration {
+        match self.is_enabled() {
+            true => self.refresh_interval,
+            false => DEFAULT_REFRESH_INTERVAL,
+        }
+    }
+
+    /// Recompute [Self::next_due] from the lookups that are not running
+    fn update_next_due(&mut self) {
+        self.next_due = (self.peers.values())
+            .filter(|lookup| !lookup.running)
+            .map(|lookup| lookup.due)
+            .min();
+    }
+}
+
+/// Delay before the next lookup, given the `refresh_interval` and the number of lookups that
+/// failed in a row
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use rosenpass::endpoint_resolver::{lookup_delay, RETRY_DELAY};
+///
+/// let refresh_interval = Duration::from_secs(60);
+/// assert_eq!(lookup_delay(refresh_interval, 0), refresh_interval);
+/// assert_eq!(lookup_delay(refresh_interval, 1), RETRY_DELAY);
+/// assert_eq!(lookup_delay(refresh_interval, 2), RETRY_DELAY * 2);
+/// assert_eq!(lookup_delay(refresh_interval, 3), RETRY_DELAY * 4);
+/// assert_eq!(lookup_delay(refresh_interval, 100), refresh_interval);
+/// ```
+pub fn lookup_delay(refresh_interval: Duration, failures: u32) -> Duration {
+    if failures == 0 {
+        return refresh_interval;
+    }
+    let backoff = RETRY_DELAY.saturating_mul(1 << (failures - 1).min(16));
+    backoff.min(refresh_interval)
+}
+
+/// The hostname the endpoint of the peer is resolved from, if any, and whether it was not
+/// resolved yet
+fn hostname_of(peer: Option<&AppPeer>) -> Option<(&str, bool)> {
+    match peer?.initial_endpoint.as_ref()? {
+        Endpoint::Discovery(host) => Some((host.hostname()?, host.addresses().is_empty())),
+        Endpoint::SocketBoundAddress(_) => None,
+    }
+}
+
+/// Resolve the `hostname` of peer `no` in a background thread, sending the result to `tx` and
+/// waking up the event loop
+fn spawn_lookup(
+    no: usize,
+    hostname: &str,
+    tx: Sender<Finished>,
+    waker: Arc<Waker>,
+) -> std::io::Result<()> {
+    let hostname = hostname.to_owned();
+    std::thread::Builder::new()
+        .name("rosenpass-resolve".to_owned())
+        .spawn(move || {
+            let res = hostname.to_socket_addrs().map(Iterator::collect);
+            // The receiver is gone if the server was dropped in the meantime
+            if tx.send((no, hostname, res)).is_err() {
+                return;
+            }
+            if let Err(e) = waker.wake() {
+                error!("Could not wake up the event loop: {e:?}");
+            }
+        })?;
+    Ok(())
+}
+
+impl AppServer {
+    /// Start lookups that are due and apply the results of the finished ones
+    ///
+    /// Returns the time to wait for IO, which is `timeout` capped at the time until the next
+    /// lookup is due.
+    ///
+    /// Used internally in [Self::poll]
+    pub(crate) fn poll_endpoint_resolver(&mut self, timeout: Timing) -> Timing {
+        if std::mem::take(&mut self.endpoint_resolver.peers_changed) {
+            self.track_hostname_endpoints();
+        }
+
+        while let Ok((no, hostname, res)) = self.endpoint_resolver.finished.try_recv() {
+            let Some(lookup) = self.endpoint_resolver.peers.get_mut(&no) else {
+                continue;
+            };
+            // The endpoint of the peer changed while the lookup was running
+            if !lookup.running || lookup.hostname != hostname {
+                continue;
+            }
+            lookup.running = false;
+            self.apply_lookup(AppPeerPtr(no), res);
+        }
+
+        let now = Instant::now();
+        if (self.endpoint_resolver.next_due).is_some_and(|due| due <= now) {
+            self.start_due_lookups(now);
+        }
+        match self.endpoint_resolver.next_due {
+            Some(due) => timeout.min(due.saturating_duration_since(now).as_secs_f64()),
+            None => timeout,
+        }
+    }
+
+    /// Start the lookups that are due at `now`
+    fn start_due_lookups(&mut self, now: Instant) {
+        let waker = self.waker();
+        let resolver = &mut self.endpoint_resolver;
+
+        let mut failed = Vec::new();
+        for (&no, lookup) in resolver.peers.iter_mut() {
+            if lookup.running || now < lookup.due {
+                continue;
+            }
+            let res = match &waker {
+                Ok(waker) => {
+                    let tx = resolver.finished_tx.clone();
+                    spawn_lookup(no, &lookup.hostname, tx, waker.clone())
+                }
+                Err(e) => Err(std::io::Error::new(e.kind(), e.to_string())),
+            };
+            match res {
+                Ok(()) => lookup.running = true,
+                Err(e) => failed.push((AppPeerPtr(no), e)),
+            }
+        }
+
+        for (peer, e) in failed {
+            self.apply_lookup(peer, Err(e));
+        }
+        self.endpoint_resolver.update_next_due();
+    }
+
+    /// Keep [EndpointResolver::peers] in line with the hostname endpoints of the peers, which may
+    /// change through adding, removing and reconfiguring peers
+    ///
+    /// Hostnames that were not resolved yet are resolved right away.
+    fn track_hostname_endpoints(&mut self) {
+        let resolver = &mut self.endpoint_resolver;
+        let refresh = resolver.is_enabled();
+        let peer = |no: usize| self.peers.get(no).and_then(Option::as_ref);
+        // Without refreshing, only hostnames that were not resolved yet need a lookup
+        let hostname =
+            |no: usize| hostname_of(peer(no)).filter(|&(_, unresolved)| refresh || unresolved);
+
+        resolver.peers.retain(|&no, lookup| {
+            hostname(no).map(|(host, _)| host) == Some(lookup.hostname.as_str())
+        });
+
+        for no in 0..self.peers.len() {
+            if resolver.peers.contains_key(&no) {
+                continue;
+            }
+            if let Some((host, unresolved)) = hostname(no) {
+                let now = Instant::now();
+                let lookup = PeerLookup {
+                    hostname: host.to_owned(),
+                    due: match unresolved {
+                        true => now,
+                        false => now + resolver.refresh_interval,
+                    },
+                    failures: 0,
+                    running: false,
+                };
+                resolver.peers.insert(no, lookup);
+            }
+        }
+        resolver.update_next_due();
+    }
+
+    /// Schedule the next lookup for the peer and replace its endpoint if the addresses changed
+    fn apply_lookup(&mut self, peer: AppPeerPtr, res: LookupResult) {
+        let (refresh, max_delay) = (
+            self.endpoint_resolver.is_enabled(),
+            self.endpoint_resolver.max_delay(),
+        );
+        let Some(lookup) = self.endpoint_resolver.peers.get_mut(&peer.0) else {
+            return;
+        };
+
+        let res = res.and_then(|addrs| match addrs.is_empty() {
+            true => Err(std::io::Error::other("No addresses found")),
+            false => Ok(addrs),
+        });
+        lookup.failures = match res {
+            Ok(_) => 0,
+            Err(_) => lookup.failures.saturating_add(1),
+        };
+        lookup.due = Instant::now() + lookup_delay(max_delay, lookup.failures);
+
+        let (due, hostname) = (lookup.due, lookup.hostname.clone());
+        let addrs = match res {
+            Ok(addrs) => addrs,
+            Err(e) => {
+                self.endpoint_resolver.schedule(due);
+                warn!(
+                    "Could not resolve endpoint {hostname} of peer {}: {e}",
+                    peer.0
+                );
+                return;
+            }
+        };
+        // Without refreshing, the hostname is not resolved again
+        if refresh {
+            self.endpoint_resolver.schedule(due);
+        } else {
+            self.endpoint_resolver.peers.remove(&peer.0);
+        }
+
+        // The order of the addresses may change with every lookup
+        let sorted = |addrs: &[SocketAddr]| {
+            let mut addrs = addrs.to_vec();
+            addrs.sort_unstable();
+            addrs
+        };
+        let known = (peer.get_app(self).initial_endpoint.as_ref()).map(|ep| sorted(ep.addresses()));
+        if known.as_ref() == Some(&sorted(&addrs)) {
+            return;
+        }
+
+        self.change_endpoint(peer, |ap| {
+            let host = HostPathDiscoveryEndpoint::resolved(hostname, addrs);
+            ap.initial_endpoint = Some(Endpoint::Discovery(host));
+            // Restart an ongoing host-path discovery with the new addresses
+            if matches!(ap.current_endpoint, Some(Endpoint::Discovery(_))) {
+                ap.current_endpoint = None;
+            }
+        });
+    }
+}
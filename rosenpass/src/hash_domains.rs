@@ -189,6 +189,30 @@ hash_domain_ns!(
     ///
     /// See the [module](self) documentation on how to use the hash domains in general.
     protocol, biscuit_ad, "biscuit additional data");
+hash_domain_ns!(
+    /// Hash domain based on [protocol] for deriving the key encrypting the persistent state of
+    /// the [crate::protocol::CryptoServer] from its static secret key.
+    ///
+    /// # Examples
+    ///
+    /// See the source of [crate::protocol::CryptoServer::seal_state] and
+    /// [crate::protocol::CryptoServer::restore_state]
+    /// to figure out how this is concretely used.
+    ///
+    /// See the [module](self) documentation on how to use the hash domains in general.
+    protocol, state_file_key, "state file key");
+hash_domain_ns!(
+    /// Hash domain based on [protocol] for deriving the key encrypting the snapshots of the live
+    /// state of the [crate::protocol::CryptoServer] from its static secret key.
+    ///
+    /// # Examples
+    ///
+    /// See the source of [crate::protocol::CryptoServer::snapshot] and
+    /// [crate::protocol::CryptoServer::restore_snapshot]
+    /// to figure out how this is concretely used.
+    ///
+    /// See the [module](self) documentation on how to use the hash domains in general.
+    protocol, snapshot_key, "snapshot key");
 hash_domain_ns!(
     /// This hash domain begins our actual handshake procedure, initializing the
     /// chaining key [crate::protocol::HandshakeState::ck]. 
@@ -288,6 +312,21 @@ hash_domain!(
     ///
     /// See the [module](self) documentation on how to use the hash domains in general.
     _ckextract, res_enc, "responder handshake encryption");
+hash_domain!(
+    /// Chaining key domain separator mixed in before the values of the X25519 key exchange in
+    /// hybrid handshakes.
+    ///
+    /// See [_ckextract].
+    ///
+    /// # Examples
+    ///
+    /// This domain separator finds use in
+    /// [crate::protocol::HandshakeState::encaps_and_mix_x25519] and
+    /// [crate::protocol::HandshakeState::decaps_and_mix_x25519]. See
+    /// [crate::protocol::CipherSuite::hybrid_x25519] for details.
+    ///
+    /// See the [module](self) documentation on how to use the hash domains in general.
+    _ckextract, hybrid_x25519, "hybrid x25519");
 
 hash_domain_ns!(
     /// Chaining key domain separator for any usage specific purposes.
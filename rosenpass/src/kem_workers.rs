@@ -0,0 +1,325 @@
+//! Decapsulating the static KEM ciphertexts of handshake messages on worker threads
+//!
+//! Processing an [InitHello](crate::msgs::InitHello) or a [RespHello](crate::msgs::RespHello)
+//! requires decapsulating a Classic McEliece ciphertext. On the single-threaded event loop of the
+//! [AppServer], many concurrent handshakes would be processed one after another, delaying the
+//! retransmissions and key renewals of all other peers.
+//!
+//! Once [AppServer::enable_kem_workers] was called, received InitHello and RespHello messages
+//! are instead handed to a pool of worker threads, which compute their decapsulations (see
+//! [crate::protocol::decapsulation]). The message itself is processed on the event loop once its
+//! decapsulation is done: [AppServer::poll] returns it as [AppPollResult::Decapsulated]. This
+//! way, the [CryptoServer](crate::protocol::CryptoServer) is still only ever modified by the event
+//! loop, and processing a message yields the same result as without the workers. The workers wake
+//! up the event loop through [AppServer::waker]; messages are handed back in the order they were received
+//! in.
+//!
+//! At most [KemWorkersConfig::queue_len] messages wait for or are being decapsulated by the
+//! workers at a time. Further messages are dropped and counted in
+//! [crate::metrics::Metrics::kem_queue_overflows]; their senders retransmit them.
+//!
+//! Messages that have to carry a cookie (see [crate::load_detection]) are processed on the event
+//! loop right away, since most of them are answered with a cookie reply without decapsulating
+//! anything. The time the workers spend decapsulating counts towards the busy signal of the
+//! under load detection.
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Context};
+use log::{debug, error};
+use mio::Waker;
+use serde::{Deserialize, Serialize};
+
+use crate::app_server::{AppPollResult, AppServer, Endpoint};
+use crate::protocol::decapsulation::{Decapsulation, DecapsulationJob};
+
+/// Maximum number of worker threads started by default
+pub const DEFAULT_MAX_THREADS: usize = 4;
+
+/// Stack size of the worker threads; the Classic McEliece implementation needs quite a bit
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Configuration of the KEM workers; see [crate::kem_workers]
+///
+/// ```toml
+/// [kem_workers]
+/// threads = 4
+/// queue_len = 64
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct KemWorkersConfig {
+    /// Number of worker threads; defaults to the number of CPUs, but at most
+    /// [DEFAULT_MAX_THREADS]
+    ///
+    /// Zero disables the workers; all messages are then processed on the event loop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
+    /// Maximum number of messages waiting for or being decapsulated by the workers
+    pub queue_len: usize,
+}
+
+impl Default for KemWorkersConfig {
+    fn default() -> Self {
+        Self {
+            threads: None,
+            queue_len: 64,
+        }
+    }
+}
+
+impl KemWorkersConfig {
+    /// Whether this is the default configuration
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check that the queue can hold messages at all
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.threads() == 0 || self.queue_len > 0,
+            "kem_workers.queue_len must not be zero"
+        );
+        Ok(())
+    }
+
+    /// The number of worker threads to start
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(|| {
+            let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+            cpus.min(DEFAULT_MAX_THREADS)
+        })
+    }
+}
+
+/// A job handed to the workers, along with its position in the queue
+type Job = (u64, DecapsulationJob);
+
+/// A finished job along with the time it took; `None` if the decapsulation failed
+type Finished = (u64, Option<Decapsulation>, Duration);
+
+/// A received message waiting for its decapsulation
+#[derive(Debug)]
+struct Pending {
+    /// The message
+    msg: Vec<u8>,
+    /// Where the message was received from
+    endpoint: Endpoint,
+    /// The decapsulation, once the workers are done with the message
+    done: Option<Option<Decapsulation>>,
+}
+
+/// A received message whose static KEM ciphertext was decapsulated by the workers
+///
+/// Returned by [AppServer::poll] as [AppPollResult::Decapsulated].
+#[derive(Debug)]
+pub struct Decapsulated {
+    /// The message
+    pub msg: Vec<u8>,
+    /// Where the message was received from
+    pub endpoint: Endpoint,
+    /// The decapsulation; `None` if it failed, so the message is processed as usual
+    pub decapsulation: Option<Decapsulation>,
+}
+
+/// The pool of worker threads, stored in [AppServer::kem_workers]
+#[derive(Debug)]
+pub struct KemWorkers {
+    /// Maximum length of [Self::pending]
+    queue_len: usize,
+    /// Queue of jobs shared by the workers
+    jobs: Sender<Job>,
+    /// Jobs finished by the workers
+    finished: Receiver<Finished>,
+    /// Messages waiting for their decapsulation, by their position in the queue
+    pending: BTreeMap<u64, Pending>,
+    /// Position of the next message put into the queue
+    next_seq: u64,
+    /// Time the workers spent on jobs finished since the last call to [Self::take_busy]
+    busy: Duration,
+    /// The worker threads; they stop once [Self::jobs] is dropped
+    _threads: Vec<JoinHandle<()>>,
+}
+
+impl KemWorkers {
+    /// Start the worker threads; `waker` is woken whenever a job is finished
+    pub fn new(config: &KemWorkersConfig, waker: Arc<Waker>) -> anyhow::Result<Self> {
+        let (jobs, job_rx) = mpsc::channel::<Job>();
+        let (finished_tx, finished) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let threads = (0..config.threads())
+            .map(|no| {
+                let (job_rx, finished_tx, waker) =
+                    (job_rx.clone(), finished_tx.clone(), waker.clone());
+                std::thread::Builder::new()
+                    .name(format!("rosenpass-kem-{no}"))
+                    .stack_size(STACK_SIZE)
+                    .spawn(move || work(&job_rx, &finished_tx, &waker))
+                    .context("Could not start KEM worker thread")
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            queue_len: config.queue_len,
+            jobs,
+            finished,
+            pending: BTreeMap::new(),
+            next_seq: 0,
+            busy: Duration::ZERO,
+            _threads: threads,
+        })
+    }
+
+    /// Hand a message to the workers
+    ///
+    /// Returns false if the queue is full; the message is dropped then.
+    pub fn submit(&mut self, job: DecapsulationJob, msg: &[u8], endpoint: Endpoint) -> bool {
+        if self.pending.len() >= self.queue_len {
+            return false;
+        }
+        let seq = self.next_seq;
+        if self.jobs.send((seq, job)).is_err() {
+            error!("All KEM worker threads are gone; dropping message from {endpoint}");
+            return false;
+        }
+        self.next_seq += 1;
+        let pending = Pending {
+            msg: msg.to_vec(),
+            endpoint,
+            done: None,
+        };
+        self.pending.insert(seq, pending);
+        true
+    }
+
+    /// The next message whose decapsulation is done, in the order the messages were submitted in
+    pub fn next_decapsulated(&mut self) -> Option<Decapsulated> {
+        loop {
+            match self.finished.try_recv() {
+                Ok((seq, decapsulation, took)) => {
+                    self.busy += took;
+                    if let Some(pending) = self.pending.get_mut(&seq) {
+                        pending.done = Some(decapsulation);
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                // The workers are gone; process the remaining messages without them
+                Err(TryRecvError::Disconnected) => {
+                    for pending in self.pending.values_mut() {
+                        pending.done.get_or_insert(None);
+                    }
+                    break;
+                }
+            }
+        }
+
+        let mut first = self.pending.first_entry()?;
+        let decapsulation = first.get_mut().done.take()?;
+        let Pending { msg, endpoint, .. } = first.remove();
+        Some(Decapsulated {
+            msg,
+            endpoint,
+            decapsulation,
+        })
+    }
+
+    /// Time the workers spent on jobs since the last call, divided by the number of workers
+    pub fn take_busy(&mut self) -> Duration {
+        let threads = self._threads.len().max(1) as u32;
+        std::mem::take(&mut self.busy) / threads
+    }
+}
+
+/// Main function of a worker thread
+fn work(jobs: &Mutex<Receiver<Job>>, finished: &Sender<Finished>, waker: &Waker) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(jobs) => jobs.recv(),
+            Err(_) => return,
+        };
+        // The sender is gone; the pool was dropped
+        let Ok((seq, job)) = job else {
+            return;
+        };
+
+        let started = Instant::now();
+        let decapsulation = job
+            .run()
+            .map_err(|e| debug!("Could not decapsulate message ahead of time: {e:?}"))
+            .ok();
+        if finished
+            .send((seq, decapsulation, started.elapsed()))
+            .is_err()
+        {
+            return;
+        }
+        if let Err(e) = waker.wake() {
+            error!("Could not wake up the event loop: {e:?}");
+        }
+    }
+}
+
+impl AppServer {
+    /// Decapsulate handshake messages on worker threads; see [crate::kem_workers]
+    ///
+    /// Does nothing if `config` disables the workers.
+    pub fn enable_kem_workers(&mut self, config: &KemWorkersConfig) -> anyhow::Result<()> {
+        ensure!(
+            self.kem_workers.is_none(),
+            "KEM workers are already enabled"
+        );
+        if config.threads() == 0 {
+            return Ok(());
+        }
+
+        let waker = self.waker()?;
+        self.kem_workers = Some(KemWorkers::new(config, waker)?);
+        Ok(())
+    }
+
+    /// Hand the message to the KEM workers if it needs a decapsulation
+    ///
+    /// Returns the endpoint back if the message should be processed right away instead. Must only
+    /// be called for messages that do not need a cookie.
+    ///
+    /// Used internally in [Self::event_loop_without_error_handling]
+    pub(crate) fn submit_decapsulation(
+        &mut self,
+        msg: &[u8],
+        endpoint: Endpoint,
+    ) -> Option<Endpoint> {
+        if self.kem_workers.is_none() {
+            return Some(endpoint);
+        }
+        let job = match self.crypto_server().map(|srv| srv.decapsulation_job(msg)) {
+            Ok(Ok(Some(job))) => job,
+            // Let the event loop report errors
+            _ => return Some(endpoint),
+        };
+
+        let Some(workers) = self.kem_workers.as_mut() else {
+            return Some(endpoint);
+        };
+        if !workers.submit(job, msg, endpoint) {
+            debug!("KEM worker queue is full; dropping message");
+            self.metrics.record_kem_queue_overflow();
+        }
+        None
+    }
+
+    /// The next message decapsulated by the KEM workers, if any
+    ///
+    /// Used internally in [Self::poll]
+    pub(crate) fn poll_kem_workers(&mut self) -> Option<AppPollResult> {
+        let workers = self.kem_workers.as_mut()?;
+        let next = workers.next_decapsulated();
+        let busy = workers.take_busy();
+        self.load_detection.record_busy(busy);
+        next.map(AppPollResult::Decapsulated)
+    }
+}
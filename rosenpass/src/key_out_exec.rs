@@ -0,0 +1,321 @@
+//! Passing output keys to external commands
+//!
+//! Peers configured with a [KeyOutExec] (the `key_out_exec` option of a peer in the configuration
+//! file) run a command whenever [AppServer::output_key] produces a key for them. The command is
+//! invoked as
+//!
+//! ```text
+//! <COMMAND...> <PEER_ID> <REASON>
+//! ```
+//!
+//! where `PEER_ID` is the base64 encoded peer id and `REASON` is either `exchanged` or `stale`
+//! (see [crate::app_server::KeyOutputReason]). The key itself is never passed through the command
+//! line or the environment, since both are visible to other processes. Instead, the base64 encoded
+//! key followed by a newline is provided as configured through [KeyOutExec::key_delivery]:
+//!
+//! - [KeyDelivery::Stdin] writes it to the standard input of the command
+//! - [KeyDelivery::Memfd] stores it in a sealed, anonymous memory file that the command inherits
+//!   as file descriptor 3 (Linux only)
+//!
+//! The commands run in the background while the key exchange continues. Commands that take
+//! longer than [KeyOutExec::timeout_secs] are killed. If a new key is produced for a peer while
+//! the command handling the previous key is still running, the previous command is killed as well,
+//! so the newest key always wins. Failures are logged, but do not interrupt the key exchange.
+//!
+//! The standard output of the commands is redirected to standard error, because the standard
+//! output of Rosenpass is reserved for key output events.
+
+use std::io::{stderr, Write};
+use std::os::fd::AsFd;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Context};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+
+use rosenpass_util::b64::b64_encode;
+use rosenpass_util::mem::DiscardResultExt;
+
+use crate::app_server::{AppPeerPtr, AppServer, MAX_B64_KEY_SIZE};
+use crate::protocol::basic_types::SymKey;
+use crate::protocol::timing::Timing;
+
+/// Interval at which running commands are checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Configuration of the command run for each key output for a peer; see [crate::key_out_exec]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyOutExec {
+    /// The program to run, followed by its arguments
+    ///
+    /// The peer id and the [crate::app_server::KeyOutputReason] are appended to these arguments.
+    pub command: Vec<String>,
+    /// How the key is passed to the command
+    #[serde(default)]
+    pub key_delivery: KeyDelivery,
+    /// Number of seconds after which the command is killed
+    #[serde(default = "KeyOutExec::default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl KeyOutExec {
+    /// The default for [Self::timeout_secs]
+    pub fn default_timeout_secs() -> u64 {
+        10
+    }
+
+    /// Check that the configuration can be used to run a command
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosenpass::key_out_exec::{KeyDelivery, KeyOutExec};
+    ///
+    /// let mut exec = KeyOutExec {
+    ///     command: vec!["true".to_string()],
+    ///     key_delivery: KeyDelivery::Stdin,
+    ///     timeout_secs: KeyOutExec::default_timeout_secs(),
+    /// };
+    /// assert!(exec.validate().is_ok());
+    ///
+    /// exec.command.clear();
+    /// assert!(exec.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            !self.command.is_empty(),
+            "key_out_exec command must not be empty"
+        );
+        ensure!(
+            cfg!(target_os = "linux") || self.key_delivery != KeyDelivery::Memfd,
+            "key_out_exec key delivery through a memfd is only supported on Linux"
+        );
+        Ok(())
+    }
+
+    /// Start the command for the given key without waiting for it to finish
+    ///
+    /// `peer_id` is the base64 encoded peer id and `why` the textual representation of the
+    /// [crate::app_server::KeyOutputReason], as used in the key output events.
+    pub fn spawn(&self, peer_id: &str, why: &str, key: &SymKey) -> anyhow::Result<Child> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .context("key_out_exec command is empty")?;
+
+        // This might leave some fragments of the secret in memory, just like
+        // storing the key in the outfile does; see AppServer::output_key
+        let mut buf = [0u8; MAX_B64_KEY_SIZE + 1];
+        let len = b64_encode(key.secret(), &mut buf)?.len();
+        buf[len] = b'\n';
+        let encoded = &buf[..len + 1];
+
+        let mut cmd = Command::new(program);
+        cmd.args(args)
+            .args([peer_id, why])
+            .stdout(Stdio::from(stderr().as_fd().try_clone_to_owned()?));
+
+        let res = match self.key_delivery {
+            KeyDelivery::Stdin => spawn_with_stdin(cmd, encoded),
+            KeyDelivery::Memfd => spawn_with_memfd(cmd, encoded),
+        };
+        buf.fill(0);
+        res.with_context(|| format!("Could not run key_out_exec command {program:?}"))
+    }
+}
+
+/// How [KeyOutExec] passes the key to the command
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyDelivery {
+    /// The key is written to the standard input of the command, which is closed afterwards
+    #[default]
+    Stdin,
+    /// The key is provided in a sealed memfd inherited as file descriptor 3; standard input is
+    /// empty
+    Memfd,
+}
+
+/// Spawn the command, writing the key to its standard input
+fn spawn_with_stdin(mut cmd: Command, key: &[u8]) -> anyhow::Result<Child> {
+    let mut child = cmd.stdin(Stdio::piped()).spawn()?;
+
+    // The key is tiny compared to the pipe buffer, so this does not block even if the
+    // command does not read it
+    let mut stdin = child.stdin.take().context("Missing standard input")?;
+    if let Err(e) = stdin.write_all(key) {
+        child.kill().discard_result();
+        child.wait().discard_result();
+        return Err(e).context("Could not write key to the standard input of the command");
+    }
+
+    Ok(child)
+}
+
+/// Spawn the command, providing the key in a memfd mapped to file descriptor 3
+#[cfg(target_os = "linux")]
+fn spawn_with_memfd(mut cmd: Command, key: &[u8]) -> anyhow::Result<Child> {
+    use std::io::{Seek, SeekFrom};
+    use std::os::fd::AsRawFd;
+
+    use command_fds::{CommandFdExt, FdMapping};
+    use rustix::fs::{fcntl_add_seals, memfd_create, MemfdFlags, SealFlags};
+
+    let fd = memfd_create(
+        "rosenpass-key-out",
+        MemfdFlags::CLOEXEC | MemfdFlags::ALLOW_SEALING,
+    )?;
+    let mut file = std::fs::File::from(fd);
+    file.write_all(key)?;
+    file.seek(SeekFrom::Start(0))?;
+    fcntl_add_seals(
+        &file,
+        SealFlags::SEAL | SealFlags::SHRINK | SealFlags::GROW | SealFlags::WRITE,
+    )?;
+
+    cmd.stdin(Stdio::null()).fd_mappings(vec![FdMapping {
+        parent_fd: file.as_raw_fd(),
+        child_fd: 3,
+    }])?;
+    Ok(cmd.spawn()?)
+}
+
+/// Spawn the command, providing the key in a memfd mapped to file descriptor 3
+#[cfg(not(target_os = "linux"))]
+fn spawn_with_memfd(_cmd: Command, _key: &[u8]) -> anyhow::Result<Child> {
+    anyhow::bail!("Key delivery through a memfd is only supported on Linux")
+}
+
+/// A [KeyOutExec] command that has not finished yet
+#[derive(Debug)]
+struct RunningCommand {
+    /// The peer the key was output for
+    peer: AppPeerPtr,
+    /// Base64 encoded id of [Self::peer], for logging
+    peer_id: String,
+    /// The running command
+    child: Child,
+    /// Point in time after which the command is killed
+    deadline: Instant,
+}
+
+/// The [KeyOutExec] commands started by [AppServer::output_key] that have not finished yet
+///
+/// Stored in [AppServer::key_out_commands].
+#[derive(Debug, Default)]
+pub struct KeyOutCommands {
+    running: Vec<RunningCommand>,
+}
+
+impl KeyOutCommands {
+    /// Whether no commands are running
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    /// Kill all running commands and wait for them to exit
+    pub fn kill_all(&mut self) {
+        for mut cmd in self.running.drain(..) {
+            warn!(
+                "key_out_exec command for peer {} is still running; killing it",
+                cmd.peer_id
+            );
+            cmd.child.kill().discard_result();
+            cmd.child.wait().discard_result();
+        }
+    }
+}
+
+impl AppServer {
+    /// Run the [KeyOutExec] command configured for the peer, if any
+    ///
+    /// Called by [Self::output_key]; `peer_id` and `why` are formatted as in the key output
+    /// events. Errors are logged instead of being returned, since a failing command should not
+    /// interrupt the key exchange.
+    pub(crate) fn run_key_out_exec(
+        &mut self,
+        peer: AppPeerPtr,
+        peer_id: &str,
+        why: &str,
+        key: &SymKey,
+    ) {
+        let Some(exec) = peer.get_app(self).key_out_exec.clone() else {
+            return;
+        };
+
+        // Only the command for the newest key may keep running
+        self.key_out_commands.running.retain_mut(|cmd| {
+            if cmd.peer.0 != peer.0 {
+                return true;
+            }
+            warn!(
+                "key_out_exec command for peer {} is still running; killing it to handle the new key",
+                cmd.peer_id
+            );
+            cmd.child.kill().discard_result();
+            cmd.child.wait().discard_result();
+            false
+        });
+
+        match exec.spawn(peer_id, why, key) {
+            Ok(child) => self.key_out_commands.running.push(RunningCommand {
+                peer,
+                peer_id: peer_id.to_owned(),
+                child,
+                deadline: Instant::now() + Duration::from_secs(exec.timeout_secs),
+            }),
+            Err(e) => error!("Failed to handle {why} key for peer {peer_id}: {e:?}"),
+        }
+    }
+
+    /// Reap finished [KeyOutExec] commands and kill those that exceeded their timeout
+    ///
+    /// Returns the time to wait for IO, which is `timeout` capped at a short poll interval while
+    /// commands are running.
+    ///
+    /// Used internally in [Self::poll]
+    pub(crate) fn poll_key_out_commands(&mut self, timeout: Timing) -> Timing {
+        if self.key_out_commands.is_empty() {
+            return timeout;
+        }
+
+        let now = Instant::now();
+        self.key_out_commands
+            .running
+            .retain_mut(|cmd| match cmd.child.try_wait() {
+                Ok(Some(status)) if status.success() => false,
+                Ok(Some(status)) => {
+                    error!(
+                        "key_out_exec command for peer {} failed ({status})",
+                        cmd.peer_id
+                    );
+                    false
+                }
+                Ok(None) if now >= cmd.deadline => {
+                    error!(
+                        "key_out_exec command for peer {} timed out; killing it",
+                        cmd.peer_id
+                    );
+                    cmd.child.kill().discard_result();
+                    cmd.child.wait().discard_result();
+                    false
+                }
+                Ok(None) => true,
+                Err(e) => {
+                    error!(
+                        "Could not get status of key_out_exec command for peer {}: {e:?}",
+                        cmd.peer_id
+                    );
+                    false
+                }
+            });
+
+        if self.key_out_commands.is_empty() {
+            timeout
+        } else {
+            timeout.min(POLL_INTERVAL.as_secs_f64())
+        }
+    }
+}
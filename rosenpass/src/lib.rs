@@ -5,10 +5,23 @@
 //!   main function quickly hands over to [crate::cli::CliArgs::run] which contains quite a bit
 //!   of our startup logic
 //! - [crate::config] has the code to parse and generate configuration files
+//! - [crate::config_reload] re-reads the configuration file upon SIGHUP and applies the changes
+//!   to the running [crate::app_server::AppServer]
+//! - [crate::endpoint_resolver] resolves peer endpoints given as hostnames again from time to time
 //! - [crate::hash_domains] lists the different hash function domains used in the Rosenpass
 //!   protocol
+//! - [crate::kem_workers] decapsulates handshake messages on worker threads
+//! - [crate::key_out_exec] runs external commands receiving the keys output for a peer
+//! - [crate::load_detection] decides whether the server is under load and which sources have to
+//!   present cookies
+//! - [crate::metrics] counts protocol events and exports them in the Prometheus text format
 //! - [crate::msgs] provides declarations of the Rosenpass protocol network messages and facilities
 //!   to parse those messages through the [::zerocopy] crate
+//! - [crate::state_file] keeps the biscuit keys and cookie secrets across restarts
+//! - [crate::upgrade] replaces the running process upon SIGUSR2 if enabled, handing over the
+//!   sessions
+//! - [crate::rate_limit] limits the rate at which a single source can make the server process
+//!   InitHello messages carrying a valid cookie while under load
 //! - [crate::protocol] this is where the bulk of our code lives; this module contains the actual
 //!   cryptographic protocol logic
 //! - crate::api implements the Rosenpass unix socket API, if feature "experiment_api" is active
@@ -18,9 +31,18 @@ pub mod api;
 pub mod app_server;
 pub mod cli;
 pub mod config;
+pub mod config_reload;
+pub mod endpoint_resolver;
 pub mod hash_domains;
+pub mod kem_workers;
+pub mod key_out_exec;
+pub mod load_detection;
+pub mod metrics;
 pub mod msgs;
 pub mod protocol;
+pub mod rate_limit;
+pub mod state_file;
+pub mod upgrade;
 
 /// Error types used in diverse places across Rosenpass
 #[derive(thiserror::Error, Debug)]
@@ -0,0 +1,335 @@
+//! Detecting whether the server is under load, i.e. whether a denial of service attack may be
+//! happening
+//!
+//! The [AppServer] decides whether to switch to [DoSOperation::UnderLoad] at the end of each
+//! period of [UnderLoadConfig::update_interval_ms], based on two signals:
+//!
+//! - The share of polls for network messages that found messages waiting already, compared to
+//!   the polls that had to wait for messages (see [AppServer::try_recv]). Under load, messages
+//!   arrive faster than they are processed.
+//! - The share of time spent processing network messages. This catches floods of messages that
+//!   are expensive to process, such as [crate::msgs::InitHello].
+//!
+//! The server enters under load operation as soon as either signal exceeds its `enter`
+//! threshold. It only returns to normal operation once both signals stayed below their lower
+//! `leave` thresholds for [UnderLoadConfig::calm_periods] periods in a row, so the server does not
+//! flap between both modes.
+//!
+//! While under load, the server asks all sources for cookies (see
+//! [crate::protocol::CryptoServer::handle_msg_under_load]) by default. Setting
+//! [UnderLoadConfig::source_rate] exempts sources that are not flooding, i.e. sources that sent
+//! at most that many messages per second in the current and the previous period; their messages
+//! are processed as usual. Since attackers may spoof many low-rate sources, at most
+//! [UnderLoadConfig::uncookied_rate] messages per second are processed without a cookie in total;
+//! once that many were, all sources are asked for cookies until the period ends.
+//!
+//! Sources are identified by their IP address. Only [MAX_TRACKED_SOURCES] sources are tracked per
+//! period; once that many sources were seen, all sources not tracked yet are asked for cookies as
+//! well, and during the following period, all sources are.
+//!
+//! The time spent processing messages includes the time the [crate::kem_workers] spent
+//! decapsulating messages, divided by the number of worker threads, so a growing worker backlog
+//! makes the server enter under load operation as well.
+
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use anyhow::ensure;
+use serde::{Deserialize, Serialize};
+
+use crate::app_server::{AppServer, AppServerEvent, AppServerTest, DoSOperation, Endpoint};
+
+/// Maximum number of sources whose message rate is tracked in a single period
+pub const MAX_TRACKED_SOURCES: usize = 4096;
+
+/// Configuration of the under load detection; see [crate::load_detection]
+///
+/// ```toml
+/// [under_load]
+/// update_interval_ms = 500
+/// enter_poll_percent = 50
+/// leave_poll_percent = 30
+/// enter_busy_percent = 80
+/// leave_busy_percent = 50
+/// calm_periods = 2
+/// source_rate = 0
+/// uncookied_rate = 100
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct UnderLoadConfig {
+    /// Length of the periods at the end of which the under load status is updated, in
+    /// milliseconds
+    pub update_interval_ms: u64,
+    /// Percentage of polls finding messages waiting already above which the server enters under
+    /// load operation
+    pub enter_poll_percent: u8,
+    /// Percentage of polls finding messages waiting already the server must stay at or below to
+    /// return to normal operation
+    pub leave_poll_percent: u8,
+    /// Percentage of time spent processing messages above which the server enters under load
+    /// operation
+    pub enter_busy_percent: u8,
+    /// Percentage of time spent processing messages the server must stay at or below to return
+    /// to normal operation
+    pub leave_busy_percent: u8,
+    /// Number of periods in a row in which both signals must stay at or below their `leave`
+    /// thresholds before the server returns to normal operation
+    pub calm_periods: u32,
+    /// Messages per second a single source may send before it has to present a cookie while the
+    /// server is under load; zero (the default) asks all sources for cookies
+    pub source_rate: u32,
+    /// Messages per second, summed over all sources, that are processed without a cookie while
+    /// the server is under load; only used if [Self::source_rate] is not zero
+    pub uncookied_rate: u32,
+}
+
+impl Default for UnderLoadConfig {
+    fn default() -> Self {
+        Self {
+            update_interval_ms: 500,
+            enter_poll_percent: 50,
+            leave_poll_percent: 30,
+            enter_busy_percent: 80,
+            leave_busy_percent: 50,
+            calm_periods: 2,
+            source_rate: 0,
+            uncookied_rate: 100,
+        }
+    }
+}
+
+impl UnderLoadConfig {
+    /// Whether this is the default configuration
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check that the thresholds make sense
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.update_interval_ms > 0,
+            "under_load.update_interval_ms must not be zero"
+        );
+        ensure!(
+            self.enter_poll_percent <= 100 && self.enter_busy_percent <= 100,
+            "under_load percentages must not exceed 100"
+        );
+        ensure!(
+            self.leave_poll_percent <= self.enter_poll_percent,
+            "under_load.leave_poll_percent must not exceed under_load.enter_poll_percent"
+        );
+        ensure!(
+            self.leave_busy_percent <= self.enter_busy_percent,
+            "under_load.leave_busy_percent must not exceed under_load.enter_busy_percent"
+        );
+        Ok(())
+    }
+
+    /// Length of the periods at the end of which the under load status is updated
+    pub fn update_interval(&self) -> Duration {
+        Duration::from_millis(self.update_interval_ms)
+    }
+
+    /// Number of messages a single source may send during a period before it is considered to
+    /// be flooding; `None` if all sources are asked for cookies
+    fn source_limit(&self) -> Option<u64> {
+        if self.source_rate == 0 {
+            return None;
+        }
+        Some(self.per_period(self.source_rate))
+    }
+
+    /// Number of messages processed without a cookie during a period while under load
+    fn uncookied_limit(&self) -> u64 {
+        match self.uncookied_rate {
+            0 => 0,
+            rate => self.per_period(rate),
+        }
+    }
+
+    /// Convert a rate per second into a number of messages per period, at least one
+    fn per_period(&self, rate: u32) -> u64 {
+        let limit = rate as u64 * self.update_interval_ms / 1000;
+        limit.max(1)
+    }
+}
+
+/// State of the under load detection, stored in [AppServer::load_detection]
+#[derive(Debug, Default)]
+pub struct LoadDetection {
+    /// The configuration in use
+    pub config: UnderLoadConfig,
+    /// Time spent processing messages during the current period
+    busy: Duration,
+    /// Number of periods in a row in which both signals stayed at or below their `leave`
+    /// thresholds
+    calm: u32,
+    /// Messages received per source during the current period
+    sources: HashMap<IpAddr, u64>,
+    /// Whether more than [MAX_TRACKED_SOURCES] sources sent messages during the current period
+    sources_overflow: bool,
+    /// Sources that exceeded [UnderLoadConfig::source_rate] during the previous period
+    flooding: HashSet<IpAddr>,
+    /// Whether more than [MAX_TRACKED_SOURCES] sources sent messages during the previous period
+    flooding_overflow: bool,
+    /// Messages processed without a cookie while under load during the current period
+    uncookied: u64,
+}
+
+impl LoadDetection {
+    /// Account for a message received from `endpoint`
+    pub fn record_message(&mut self, endpoint: &Endpoint) {
+        let Some(source) = source_of(endpoint) else {
+            return;
+        };
+        let tracked = self.sources.len() < MAX_TRACKED_SOURCES;
+        match self.sources.get_mut(&source) {
+            Some(count) => *count += 1,
+            None if tracked => {
+                self.sources.insert(source, 1);
+            }
+            None => self.sources_overflow = true,
+        }
+    }
+
+    /// Account for time spent processing a message
+    pub fn record_busy(&mut self, duration: Duration) {
+        self.busy += duration;
+    }
+
+    /// Whether messages from `endpoint` have to carry a cookie while under load
+    pub fn is_flooding(&self, endpoint: &Endpoint) -> bool {
+        let (Some(limit), Some(source)) = (self.config.source_limit(), source_of(endpoint)) else {
+            return true;
+        };
+        // Too many sources to tell them apart; the addresses are likely spoofed
+        if self.flooding_overflow {
+            return true;
+        }
+        match self.sources.get(&source) {
+            Some(&count) => count > limit || self.flooding.contains(&source),
+            None => self.sources_overflow || self.flooding.contains(&source),
+        }
+    }
+
+    /// Whether a message from `endpoint` may be processed without a cookie while under load
+    ///
+    /// Counts the message against [UnderLoadConfig::uncookied_rate] if so.
+    pub fn admit_uncookied(&mut self, endpoint: &Endpoint) -> bool {
+        if self.is_flooding(endpoint) || self.uncookied >= self.config.uncookied_limit() {
+            return false;
+        }
+        self.uncookied += 1;
+        true
+    }
+
+    /// Start a new period
+    fn end_period(&mut self) {
+        let limit = self.config.source_limit().unwrap_or(0);
+        self.flooding = self
+            .sources
+            .drain()
+            .filter_map(|(source, count)| (count > limit).then_some(source))
+            .collect();
+        self.flooding_overflow = std::mem::take(&mut self.sources_overflow);
+        self.busy = Duration::ZERO;
+        self.uncookied = 0;
+    }
+}
+
+/// The address identifying the sender of a message
+fn source_of(endpoint: &Endpoint) -> Option<IpAddr> {
+    match endpoint {
+        Endpoint::SocketBoundAddress(host) => Some(host.addr().ip()),
+        Endpoint::Discovery(_) => None,
+    }
+}
+
+impl AppServer {
+    /// Update [Self::under_load] at the end of each period; see [crate::load_detection]
+    ///
+    /// Used internally in [Self::try_recv]
+    pub(crate) fn update_under_load(&mut self) {
+        let was_under_load = self.under_load;
+
+        if let Some(AppServerTest {
+            enable_dos_permanently: true,
+            ..
+        }) = self.test_helpers
+        {
+            self.under_load = DoSOperation::UnderLoad;
+        } else {
+            let elapsed = self.last_update_time.elapsed();
+            if elapsed <= self.load_detection.config.update_interval() {
+                return;
+            }
+            self.last_update_time = Instant::now();
+
+            let total_polls = self.blocking_polls_count + self.non_blocking_polls_count;
+            let poll_ratio = if total_polls > 0 {
+                self.non_blocking_polls_count as f64 / total_polls as f64
+            } else if self.unpolled_count > 0 {
+                //There are no polls, so we are under load
+                1.0
+            } else {
+                0.0
+            };
+            let busy_ratio = self.load_detection.busy.as_secs_f64() / elapsed.as_secs_f64();
+
+            let detection = &mut self.load_detection;
+            let cfg = &detection.config;
+            let exceeds = |ratio: f64, percent: u8| ratio * 100.0 > percent as f64;
+            let enter = exceeds(poll_ratio, cfg.enter_poll_percent)
+                || exceeds(busy_ratio, cfg.enter_busy_percent);
+            let calm = !exceeds(poll_ratio, cfg.leave_poll_percent)
+                && !exceeds(busy_ratio, cfg.leave_busy_percent);
+
+            detection.calm = match calm {
+                true => detection.calm.saturating_add(1),
+                false => 0,
+            };
+            self.under_load = match self.under_load {
+                _ if enter => DoSOperation::UnderLoad,
+                DoSOperation::UnderLoad if detection.calm < cfg.calm_periods => {
+                    DoSOperation::UnderLoad
+                }
+                _ => DoSOperation::Normal,
+            };
+
+            detection.end_period();
+            self.blocking_polls_count = 0;
+            self.non_blocking_polls_count = 0;
+            self.unpolled_count = 0;
+        }
+
+        match (was_under_load, self.under_load) {
+            (DoSOperation::Normal, DoSOperation::UnderLoad) => {
+                self.emit_event(AppServerEvent::UnderLoadEntered)
+            }
+            (DoSOperation::UnderLoad, DoSOperation::Normal) => {
+                self.emit_event(AppServerEvent::UnderLoadLeft)
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a message from `endpoint` has to carry a cookie; see [crate::load_detection]
+    ///
+    /// Called once per received message, since messages processed without a cookie while under
+    /// load are counted.
+    pub(crate) fn requires_cookie(&mut self, endpoint: &Endpoint) -> bool {
+        match (self.under_load, &self.test_helpers) {
+            (DoSOperation::Normal, _) => false,
+            (
+                DoSOperation::UnderLoad,
+                Some(AppServerTest {
+                    enable_dos_permanently: true,
+                    ..
+                }),
+            ) => true,
+            (DoSOperation::UnderLoad, _) => !self.load_detection.admit_uncookied(endpoint),
+        }
+    }
+}
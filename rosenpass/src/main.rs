@@ -4,7 +4,9 @@ use clap::CommandFactory;
 use clap::Parser;
 use clap_mangen::roff::{roman, Roff};
 use log::error;
+use rosenpass::app_server::LogFormat;
 use rosenpass::cli::CliArgs;
+use std::io::Write;
 use std::process::exit;
 
 /// Printing custom man sections when generating the man page
@@ -66,6 +68,17 @@ pub fn main() {
             log::debug!("setting log level to {:?} (set via CLI parameter)", level);
             log_builder.filter_level(level); // set log level filter from CLI args if available
         }
+        if args.get_log_format() == LogFormat::Json {
+            log_builder.format(|buf, record| {
+                let line = serde_json::json!({
+                    "timestamp": buf.timestamp_millis().to_string(),
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "message": record.args().to_string(),
+                });
+                writeln!(buf, "{line}")
+            });
+        }
         log_builder.init();
 
         // // check the effectiveness of the log level filter with the following lines:
@@ -0,0 +1,618 @@
+//! Counters describing the operation of the Rosenpass server
+//!
+//! The [Metrics] registry lives in [AppServer::metrics]. It is updated while handling network
+//! messages and from [AppServer::emit_event]. The counters can be read
+//!
+//! - in the [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/)
+//!   over HTTP, from the TCP and unix socket listeners configured in [MetricsConfig]; e.g.
+//!   `curl http://127.0.0.1:9477/metrics`
+//! - through the `GetStats` API message, if the `experiment_api` feature is enabled
+//!
+//! The HTTP listeners are registered in the [mio] loop of the [AppServer]; requests are answered
+//! without blocking the key exchange. The exporter does not implement any access control, so
+//! the TCP listeners should generally be bound to a loopback address.
+
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use mio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
+use serde::{Deserialize, Serialize};
+
+use rosenpass_util::io::IoResultKindHintExt;
+use rosenpass_util::mio::interest::RW as MIO_RW;
+
+use crate::app_server::{AppServer, AppServerEvent, AppServerIoSource, KeyOutputReason};
+use crate::msgs::MsgType;
+use crate::protocol::DoSOperation;
+use crate::RosenpassError;
+
+/// Maximum size of an HTTP request accepted by the metrics exporter
+const MAX_REQUEST_LEN: usize = 8192;
+
+/// Counters describing the operation of the Rosenpass server
+///
+/// All counters start at zero when the server starts.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metrics {
+    /// [MsgType::InitHello] messages received
+    pub received_init_hello: u64,
+    /// [MsgType::RespHello] messages received
+    pub received_resp_hello: u64,
+    /// [MsgType::InitConf] messages received
+    pub received_init_conf: u64,
+    /// [MsgType::EmptyData] messages received
+    pub received_empty_data: u64,
+    /// [MsgType::CookieReply] messages received
+    pub received_cookie_reply: u64,
+    /// Messages received with a message type not listed in [MsgType]
+    pub received_unknown: u64,
+    /// Received messages that could not be decoded, i.e. messages with an unknown type or of the
+    /// wrong size
+    pub decode_errors: u64,
+    /// Received messages that could not be processed, including those counted in
+    /// [Self::decode_errors]; see [AppServerEvent::HandshakeFailed]
+    pub handshake_failures: u64,
+    /// Handshakes started as initiator
+    pub handshakes_started: u64,
+    /// Handshake messages retransmitted
+    pub retransmissions: u64,
+    /// Cookie reply messages sent while under load
+    pub cookie_replies_sent: u64,
+    /// Keys exchanged, i.e. successful handshakes
+    pub keys_exchanged: u64,
+    /// Keys erased because no new key was exchanged in time
+    pub keys_stale: u64,
+    /// How often the server started operating in [DoSOperation::UnderLoad] mode
+    pub under_load_entered: u64,
+    /// How often the server returned to [DoSOperation::Normal] mode
+    pub under_load_left: u64,
+    /// Errors returned by WireGuard PSK brokers while setting a pre-shared key
+    pub broker_errors: u64,
+    /// [MsgType::InitHello] messages dropped by the per-source rate limit; see
+    /// [crate::rate_limit]
+    pub rate_limited: u64,
+    /// Handshake messages dropped because the queue of the KEM workers was full; see
+    /// [crate::kem_workers]
+    pub kem_queue_overflows: u64,
+}
+
+impl Metrics {
+    /// Count a network message received
+    pub fn record_received(&mut self, msg: &[u8]) {
+        let typ = msg.first().map(|t| MsgType::try_from(*t));
+        let counter = match typ {
+            Some(Ok(MsgType::InitHello)) => &mut self.received_init_hello,
+            Some(Ok(MsgType::RespHello)) => &mut self.received_resp_hello,
+            Some(Ok(MsgType::InitConf)) => &mut self.received_init_conf,
+            Some(Ok(MsgType::EmptyData)) => &mut self.received_empty_data,
+            Some(Ok(MsgType::CookieReply)) => &mut self.received_cookie_reply,
+            Some(Err(_)) | None => &mut self.received_unknown,
+        };
+        *counter += 1;
+    }
+
+    /// Count an error returned by [crate::protocol::CryptoServer::handle_msg]
+    ///
+    /// The failure itself is counted through [Self::record_event]; this only determines whether
+    /// the message could not be decoded.
+    pub fn record_message_error(&mut self, err: &anyhow::Error) {
+        if err.chain().any(|e| e.is::<RosenpassError>()) {
+            self.decode_errors += 1;
+        }
+    }
+
+    /// Count an event reported by [AppServer::emit_event]
+    pub fn record_event(&mut self, ev: &AppServerEvent<'_>) {
+        use AppServerEvent as E;
+        let counter = match ev {
+            E::KeyOutput(_, KeyOutputReason::Exchanged) => &mut self.keys_exchanged,
+            E::KeyOutput(_, KeyOutputReason::Stale) => &mut self.keys_stale,
+            E::HandshakeStarted(_) => &mut self.handshakes_started,
+            E::HandshakeRetransmitted(_) => &mut self.retransmissions,
+            E::HandshakeFailed(_) => &mut self.handshake_failures,
+            E::CookieReplySent(_) => &mut self.cookie_replies_sent,
+            E::UnderLoadEntered => &mut self.under_load_entered,
+            E::UnderLoadLeft => &mut self.under_load_left,
+            E::PeerEndpointChanged(_) => return,
+        };
+        *counter += 1;
+    }
+
+    /// Count an error from a WireGuard PSK broker
+    pub fn record_broker_error(&mut self) {
+        self.broker_errors += 1;
+    }
+
+    /// Count a message dropped by the [crate::rate_limit::RateLimiter]
+    pub fn record_rate_limited(&mut self) {
+        self.rate_limited += 1;
+    }
+
+    /// Count a message dropped by the [crate::kem_workers::KemWorkers]
+    pub fn record_kem_queue_overflow(&mut self) {
+        self.kem_queue_overflows += 1;
+    }
+}
+
+/// Where to serve metrics in the Prometheus text format
+///
+/// Both kinds of listeners speak HTTP; metrics are served under `/metrics`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// TCP addresses to listen on, e.g. `127.0.0.1:9477`
+    #[serde(default)]
+    pub listen: Vec<SocketAddr>,
+
+    /// Where in the file-system to create unix sockets to listen on
+    #[serde(default)]
+    pub listen_path: Vec<PathBuf>,
+}
+
+impl MetricsConfig {
+    /// Whether no listeners are configured
+    pub fn is_empty(&self) -> bool {
+        self.listen.is_empty() && self.listen_path.is_empty()
+    }
+
+    /// Bind the configured listeners and add them to the [AppServer] using
+    /// [AppServer::add_metrics_listener]
+    pub fn apply_to_app_server(&self, srv: &mut AppServer) -> anyhow::Result<()> {
+        for addr in self.listen.iter() {
+            let listener = TcpListener::bind(*addr)
+                .with_context(|| format!("Could not listen for metrics requests on {addr}"))?;
+            srv.add_metrics_listener(MetricsListener::Tcp(listener))?;
+        }
+
+        for path in self.listen_path.iter() {
+            let listener = UnixListener::bind(path)
+                .with_context(|| format!("Could not listen for metrics requests on {path:?}"))?;
+            srv.add_metrics_listener(MetricsListener::Unix(listener))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A listener for HTTP requests to the metrics exporter
+#[derive(Debug)]
+pub enum MetricsListener {
+    /// Listening on a TCP socket
+    Tcp(TcpListener),
+    /// Listening on a unix socket
+    Unix(UnixListener),
+}
+
+impl MetricsListener {
+    /// Accept a new connection
+    fn accept(&self) -> io::Result<MetricsStream> {
+        match self {
+            Self::Tcp(l) => l.accept().map(|(s, _)| MetricsStream::Tcp(s)),
+            Self::Unix(l) => l.accept().map(|(s, _)| MetricsStream::Unix(s)),
+        }
+    }
+
+    /// Access the listener as a [mio::event::Source]
+    fn source(&mut self) -> &mut dyn mio::event::Source {
+        match self {
+            Self::Tcp(l) => l,
+            Self::Unix(l) => l,
+        }
+    }
+}
+
+/// A connection accepted from a [MetricsListener]
+#[derive(Debug)]
+enum MetricsStream {
+    /// Connection accepted from a TCP listener
+    Tcp(TcpStream),
+    /// Connection accepted from a unix socket listener
+    Unix(UnixStream),
+}
+
+impl MetricsStream {
+    /// Access the stream as a [mio::event::Source]
+    fn source(&mut self) -> &mut dyn mio::event::Source {
+        match self {
+            Self::Tcp(s) => s,
+            Self::Unix(s) => s,
+        }
+    }
+}
+
+impl Read for MetricsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(s) => s.read(buf),
+            Self::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for MetricsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(s) => s.write(buf),
+            Self::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(s) => s.flush(),
+            Self::Unix(s) => s.flush(),
+        }
+    }
+}
+
+/// State of a single HTTP connection to the metrics exporter
+///
+/// Each connection serves a single request; the connection is closed once the response is sent.
+#[derive(Debug)]
+struct MetricsConnection {
+    /// The connection to the client
+    stream: MetricsStream,
+    /// Token this connection is registered with in [AppServer::mio_poll]
+    mio_token: mio::Token,
+    /// The request received so far
+    request: Vec<u8>,
+    /// The response, once the request was received completely
+    response: Option<Vec<u8>>,
+    /// How many bytes of [Self::response] were sent already
+    written: usize,
+}
+
+/// Points at a particular source of IO events inside [MetricsServer]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MetricsIoSource {
+    /// Source of IO events is the Nth listener of the [MetricsServer]
+    Listener(usize),
+    /// Source of IO events is the Nth connection of the [MetricsServer]
+    Connection(usize),
+}
+
+/// The HTTP listeners and connections of the metrics exporter; see [AppServer::metrics_server]
+#[derive(Debug, Default)]
+pub struct MetricsServer {
+    /// Listeners added through [AppServer::add_metrics_listener]
+    listeners: Vec<MetricsListener>,
+    /// Open connections; closed connections leave a tombstone (`None`) behind so the indices
+    /// referenced by [MetricsIoSource::Connection] remain valid
+    connections: Vec<Option<MetricsConnection>>,
+}
+
+impl AppServer {
+    /// Serve metrics to HTTP clients connecting to the given listener
+    pub fn add_metrics_listener(&mut self, mut listener: MetricsListener) -> io::Result<()> {
+        let mio_token = self.mio_token_dispenser.dispense();
+        self.mio_poll
+            .registry()
+            .register(listener.source(), mio_token, MIO_RW)?;
+        let io_source = MetricsIoSource::Listener(self.metrics_server.listeners.len());
+        self.metrics_server.listeners.push(listener);
+        self.register_io_source(mio_token, AppServerIoSource::Metrics(io_source));
+        Ok(())
+    }
+
+    /// Number of peers configured, for reporting along with [Self::metrics]
+    pub(crate) fn metrics_peers(&self) -> u64 {
+        self.peers.iter().flatten().count() as u64
+    }
+
+    /// One if the server currently operates under load, zero otherwise; for reporting along with
+    /// [Self::metrics]
+    pub(crate) fn metrics_under_load(&self) -> u64 {
+        match self.under_load {
+            DoSOperation::UnderLoad => 1,
+            DoSOperation::Normal => 0,
+        }
+    }
+
+    /// Render [Self::metrics], along with some information about the current state of the
+    /// server, in the Prometheus text format
+    pub fn render_metrics(&self) -> String {
+        let m = &self.metrics;
+        let peers = self.metrics_peers();
+        let under_load = self.metrics_under_load();
+
+        let mut out = String::new();
+        let mut metric = |name: &str, typ: &str, help: &str, samples: &[(&str, u64)]| {
+            // Writing to a string can not fail
+            let _ = writeln!(out, "# HELP rosenpass_{name} {help}");
+            let _ = writeln!(out, "# TYPE rosenpass_{name} {typ}");
+            for (labels, value) in samples {
+                let _ = writeln!(out, "rosenpass_{name}{labels} {value}");
+            }
+        };
+
+        metric(
+            "messages_received_total",
+            "counter",
+            "Protocol messages received, by message type.",
+            &[
+                (r#"{type="init_hello"}"#, m.received_init_hello),
+                (r#"{type="resp_hello"}"#, m.received_resp_hello),
+                (r#"{type="init_conf"}"#, m.received_init_conf),
+                (r#"{type="empty_data"}"#, m.received_empty_data),
+                (r#"{type="cookie_reply"}"#, m.received_cookie_reply),
+                (r#"{type="unknown"}"#, m.received_unknown),
+            ],
+        );
+        metric(
+            "message_decode_errors_total",
+            "counter",
+            "Received messages that could not be decoded.",
+            &[("", m.decode_errors)],
+        );
+        metric(
+            "handshake_failures_total",
+            "counter",
+            "Received messages that could not be processed, including decode errors.",
+            &[("", m.handshake_failures)],
+        );
+        metric(
+            "handshakes_started_total",
+            "counter",
+            "Handshakes started as initiator.",
+            &[("", m.handshakes_started)],
+        );
+        metric(
+            "retransmissions_total",
+            "counter",
+            "Handshake messages retransmitted.",
+            &[("", m.retransmissions)],
+        );
+        metric(
+            "cookie_replies_sent_total",
+            "counter",
+            "Cookie reply messages sent while under load.",
+            &[("", m.cookie_replies_sent)],
+        );
+        metric(
+            "keys_exchanged_total",
+            "counter",
+            "Keys exchanged, i.e. successful handshakes.",
+            &[("", m.keys_exchanged)],
+        );
+        metric(
+            "keys_stale_total",
+            "counter",
+            "Keys erased because no new key was exchanged in time.",
+            &[("", m.keys_stale)],
+        );
+        metric(
+            "under_load_transitions_total",
+            "counter",
+            "Transitions between normal operation and operation under load.",
+            &[
+                (r#"{direction="entered"}"#, m.under_load_entered),
+                (r#"{direction="left"}"#, m.under_load_left),
+            ],
+        );
+        metric(
+            "under_load",
+            "gauge",
+            "Whether the server currently operates under load.",
+            &[("", under_load)],
+        );
+        metric(
+            "broker_errors_total",
+            "counter",
+            "Errors returned by WireGuard PSK brokers while setting a pre-shared key.",
+            &[("", m.broker_errors)],
+        );
+        metric(
+            "messages_rate_limited_total",
+            "counter",
+            "InitHello messages dropped by the per-source rate limit.",
+            &[("", m.rate_limited)],
+        );
+        metric(
+            "messages_kem_queue_overflows_total",
+            "counter",
+            "Handshake messages dropped because the queue of the KEM workers was full.",
+            &[("", m.kem_queue_overflows)],
+        );
+        metric(
+            "peers",
+            "gauge",
+            "Number of peers configured.",
+            &[("", peers)],
+        );
+
+        out
+    }
+
+    /// Accept new connections on all metrics listeners and poll all metrics connections
+    ///
+    /// Used by [Self::try_recv] when polling all IO sources.
+    pub(crate) fn poll_metrics(&mut self) -> anyhow::Result<()> {
+        for idx in 0..self.metrics_server.listeners.len() {
+            self.poll_metrics_source(MetricsIoSource::Listener(idx))?;
+        }
+        for idx in 0..self.metrics_server.connections.len() {
+            self.poll_metrics_source(MetricsIoSource::Connection(idx))?;
+        }
+        Ok(())
+    }
+
+    /// Poll a particular IO source of the metrics exporter
+    pub(crate) fn poll_metrics_source(&mut self, io_source: MetricsIoSource) -> anyhow::Result<()> {
+        match io_source {
+            MetricsIoSource::Listener(idx) => self.accept_metrics_connections(idx),
+            MetricsIoSource::Connection(idx) => self.poll_metrics_connection(idx),
+        }
+    }
+
+    /// Accept all pending connections on a metrics listener
+    fn accept_metrics_connections(&mut self, idx: usize) -> anyhow::Result<()> {
+        loop {
+            let mut stream = match self.metrics_server.listeners[idx]
+                .accept()
+                .io_err_kind_hint()
+            {
+                Ok(stream) => stream,
+                Err((_, ErrorKind::WouldBlock)) => return Ok(()),
+                Err((_, ErrorKind::Interrupted)) => continue,
+                Err((e, _)) => {
+                    // Errors like running out of file descriptors should not bring down the
+                    // key exchange
+                    log::warn!("Could not accept metrics connection: {e:?}");
+                    return Ok(());
+                }
+            };
+
+            let mio_token = self.mio_token_dispenser.dispense();
+            self.mio_poll
+                .registry()
+                .register(stream.source(), mio_token, MIO_RW)?;
+            let conn = MetricsConnection {
+                stream,
+                mio_token,
+                request: Vec::new(),
+                response: None,
+                written: 0,
+            };
+
+            // Reuse the slot of a closed connection if possible
+            let conns = &mut self.metrics_server.connections;
+            let conn_idx = conns
+                .iter()
+                .position(Option::is_none)
+                .unwrap_or(conns.len());
+            match conns.get_mut(conn_idx) {
+                Some(slot) => *slot = Some(conn),
+                None => conns.push(Some(conn)),
+            }
+            let io_source = MetricsIoSource::Connection(conn_idx);
+            self.register_io_source(mio_token, AppServerIoSource::Metrics(io_source));
+
+            // The request might have arrived already
+            self.poll_metrics_connection(conn_idx)?;
+        }
+    }
+
+    /// Read the request from a metrics connection and send the response once the request was
+    /// received completely; closes the connection afterwards or if an error occurs
+    fn poll_metrics_connection(&mut self, idx: usize) -> anyhow::Result<()> {
+        let Some(mut conn) = self.metrics_server.connections[idx].take() else {
+            return Ok(()); // Stale event for a closed connection
+        };
+
+        let done = match conn.poll(|| self.render_metrics()) {
+            Ok(done) => done,
+            Err(e) => {
+                log::debug!("Error on metrics connection: {e:?}");
+                true
+            }
+        };
+
+        if done {
+            self.mio_poll.registry().deregister(conn.stream.source())?;
+            self.unregister_io_source(conn.mio_token);
+        } else {
+            self.metrics_server.connections[idx] = Some(conn);
+        }
+
+        Ok(())
+    }
+}
+
+impl MetricsConnection {
+    /// Make as much progress on the connection as possible without blocking
+    ///
+    /// Returns true once the connection should be closed.
+    fn poll<F: FnOnce() -> String>(&mut self, render: F) -> io::Result<bool> {
+        if self.response.is_none() {
+            match self.read_request()? {
+                RequestStatus::Incomplete => return Ok(false),
+                RequestStatus::Closed => return Ok(true),
+                RequestStatus::Complete => {
+                    self.response = Some(http_response(&self.request, render));
+                }
+            }
+        }
+
+        let response = self.response.as_ref().unwrap();
+        while self.written < response.len() {
+            match self
+                .stream
+                .write(&response[self.written..])
+                .io_err_kind_hint()
+            {
+                Ok(0) => return Ok(true),
+                Ok(n) => self.written += n,
+                Err((_, ErrorKind::WouldBlock)) => return Ok(false),
+                Err((_, ErrorKind::Interrupted)) => continue,
+                Err((e, _)) => return Err(e),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Read from the connection until the request header is complete or the read would block
+    fn read_request(&mut self) -> io::Result<RequestStatus> {
+        let mut buf = [0u8; 1024];
+        loop {
+            if request_complete(&self.request) || self.request.len() >= MAX_REQUEST_LEN {
+                return Ok(RequestStatus::Complete);
+            }
+
+            match self.stream.read(&mut buf).io_err_kind_hint() {
+                Ok(0) => return Ok(RequestStatus::Closed),
+                Ok(n) => self.request.extend_from_slice(&buf[..n]),
+                Err((_, ErrorKind::WouldBlock)) => return Ok(RequestStatus::Incomplete),
+                Err((_, ErrorKind::Interrupted)) => continue,
+                Err((e, _)) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Result of [MetricsConnection::read_request]
+enum RequestStatus {
+    /// The request header was received completely
+    Complete,
+    /// More data is needed
+    Incomplete,
+    /// The client closed the connection
+    Closed,
+}
+
+/// Check whether the HTTP request header was received completely
+fn request_complete(req: &[u8]) -> bool {
+    req.windows(4).any(|w| w == b"\r\n\r\n") || req.windows(2).any(|w| w == b"\n\n")
+}
+
+/// Produce the HTTP response for the given request
+fn http_response<F: FnOnce() -> String>(req: &[u8], render: F) -> Vec<u8> {
+    let request_line = req.split(|c| *c == b'\n').next().unwrap_or_default();
+    let mut parts = request_line
+        .split(|c| c.is_ascii_whitespace())
+        .filter(|p| !p.is_empty());
+    let (method, path) = (parts.next(), parts.next());
+
+    let (status, body) = match (method, path) {
+        _ if req.len() >= MAX_REQUEST_LEN && !request_complete(req) => {
+            ("431 Request Header Fields Too Large", String::new())
+        }
+        (Some(b"GET"), Some(b"/metrics" | b"/")) => ("200 OK", render()),
+        (Some(b"GET"), _) => ("404 Not Found", String::new()),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+
+    let mut res = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n",
+        body.len()
+    );
+    res.push_str(&body);
+    res.into_bytes()
+}
@@ -125,13 +125,20 @@ pub struct Envelope<M: AsBytes + FromBytes> {
 /// // Check that write above on byte representation was effective
 /// assert_eq!(ih.payload.sidi, [1,2,3,4]);
 /// ```
+///
+/// The size of the message depends on the ephemeral KEM used; `EPK_LEN` is the length of its
+/// public key. See [crate::protocol::EphemeralKemChoice]. `XPK_LEN` is the length of the X25519
+/// public key in hybrid handshakes and zero otherwise; see
+/// [crate::protocol::CipherSuite::hybrid_x25519].
 #[repr(packed)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
-pub struct InitHello {
+pub struct InitHello<const EPK_LEN: usize = { EphemeralKem::PK_LEN }, const XPK_LEN: usize = 0> {
     /// Randomly generated connection id
     pub sidi: [u8; 4],
-    /// Kyber 512 Ephemeral Public Key
-    pub epki: [u8; EphemeralKem::PK_LEN],
+    /// Ephemeral Public Key (Kyber 512 by default)
+    pub epki: [u8; EPK_LEN],
+    /// Ephemeral X25519 Public Key (only in hybrid handshakes)
+    pub xpki: [u8; XPK_LEN],
     /// Classic McEliece Ciphertext
     pub sctr: [u8; StaticKem::CT_LEN],
     /// Encryped: 16 byte hash of McEliece initiator static key
@@ -174,15 +181,23 @@ pub struct InitHello {
 /// // Check that write above on byte representation was effective
 /// assert_eq!(ih.payload.sidi, [1,2,3,4]);
 /// ```
+///
+/// The size of the message depends on the ephemeral KEM used; `ECT_LEN` is the length of its
+/// ciphertext. See [crate::protocol::EphemeralKemChoice]. `XCT_LEN` is the length of the X25519
+/// ciphertext in hybrid handshakes and zero otherwise; see
+/// [crate::protocol::CipherSuite::hybrid_x25519].
 #[repr(packed)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
-pub struct RespHello {
+pub struct RespHello<const ECT_LEN: usize = { EphemeralKem::CT_LEN }, const XCT_LEN: usize = 0> {
     /// Randomly generated connection id
     pub sidr: [u8; 4],
     /// Copied from InitHello
     pub sidi: [u8; 4],
-    /// Kyber 512 Ephemeral Ciphertext
-    pub ecti: [u8; EphemeralKem::CT_LEN],
+    /// Ephemeral Ciphertext (Kyber 512 by default)
+    pub ecti: [u8; ECT_LEN],
+    /// Ephemeral X25519 Ciphertext, i.e. the responder's ephemeral X25519 public key (only in
+    /// hybrid handshakes)
+    pub xcti: [u8; XCT_LEN],
     /// Classic McEliece Ciphertext
     pub scti: [u8; StaticKem::CT_LEN],
     /// Empty encrypted message (just an auth tag)
@@ -351,7 +366,8 @@ pub struct CookieReplyInner {
 /// Specialized message for use in the cookie mechanism.
 ///
 /// This just brings [CookieReplyInner] up to the size
-/// of [InitHello] to avoid amplification Denial of Service attacks.
+/// of [InitHello] to avoid amplification Denial of Service attacks. The size
+/// used is that of the smallest [InitHello], i.e. the one using Kyber 512.
 ///
 /// See the [whitepaper](https://rosenpass.eu/whitepaper.pdf) ([/papers/whitepaper.md] in this repository) for details.
 ///
@@ -449,6 +465,19 @@ mod test_constants {
         assert_eq!(BISCUIT_PT_LEN, 2 * KEY_LEN + 12);
     }
 
+    #[test]
+    fn max_message_len() {
+        use crate::msgs::{Envelope, InitHello, RespHello, MAX_MESSAGE_LEN};
+        use rosenpass_cipher_traits::primitives::Kem as _;
+        use rosenpass_ciphers::{MlKem1024, X25519};
+        use std::mem::size_of;
+
+        type LargestInitHello = InitHello<{ MlKem1024::PK_LEN }, { X25519::PK_LEN }>;
+        type LargestRespHello = RespHello<{ MlKem1024::CT_LEN }, { X25519::CT_LEN }>;
+        assert!(size_of::<Envelope<LargestInitHello>>() <= MAX_MESSAGE_LEN);
+        assert!(size_of::<Envelope<LargestRespHello>>() <= MAX_MESSAGE_LEN);
+    }
+
     #[test]
     fn biscuit_ct_len() {
         assert_eq!(
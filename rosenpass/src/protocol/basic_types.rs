@@ -1,7 +1,7 @@
 //! Key types and other fundamental types used in the Rosenpass protocol
 
 use rosenpass_cipher_traits::primitives::{Aead, Kem};
-use rosenpass_ciphers::{EphemeralKem, StaticKem, XAead, KEY_LEN};
+use rosenpass_ciphers::{MlKem1024, StaticKem, XAead, KEY_LEN, X25519};
 use rosenpass_secret_memory::{Public, PublicBox, Secret};
 
 use crate::msgs::{BISCUIT_ID_LEN, MAX_MESSAGE_LEN, SESSION_ID_LEN};
@@ -13,8 +13,16 @@ pub type SPk = PublicBox<{ StaticKem::PK_LEN }>;
 /// Static secret key
 pub type SSk = Secret<{ StaticKem::SK_LEN }>;
 /// Ephemeral public key
-pub type EPk = Public<{ EphemeralKem::PK_LEN }>;
-pub type ESk = Secret<{ EphemeralKem::SK_LEN }>;
+///
+/// Large enough for the public key of any [super::EphemeralKemChoice]; smaller keys only use
+/// the beginning of the buffer.
+pub type EPk = Public<{ MlKem1024::PK_LEN }>;
+/// Ephemeral secret key; like [EPk], large enough for any [super::EphemeralKemChoice]
+pub type ESk = Secret<{ MlKem1024::SK_LEN }>;
+/// Ephemeral X25519 public key; used in hybrid handshakes
+pub type XPk = Public<{ X25519::PK_LEN }>;
+/// Ephemeral X25519 secret key; used in hybrid handshakes
+pub type XSk = Secret<{ X25519::SK_LEN }>;
 
 /// Symmetric key
 pub type SymKey = Secret<KEY_LEN>;
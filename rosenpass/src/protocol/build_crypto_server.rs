@@ -7,7 +7,7 @@ use crate::config::ProtocolVersion;
 
 use super::basic_types::{SPk, SSk, SymKey};
 use super::osk_domain_separator::OskDomainSeparator;
-use super::{CryptoServer, PeerPtr};
+use super::{AeadChoice, CipherSuite, CryptoServer, EphemeralKemChoice, PeerPtr};
 
 #[derive(Debug, Clone)]
 /// A pair of matching public/secret keys used to launch the crypto server.
@@ -154,23 +154,23 @@ pub struct MissingKeypair;
 /// use rosenpass::config::ProtocolVersion;
 ///
 /// use rosenpass::protocol::basic_types::{SPk, SymKey};
-/// use rosenpass::protocol::{BuildCryptoServer, Keypair, PeerParams};
+/// use rosenpass::protocol::{AeadChoice, BuildCryptoServer, EphemeralKemChoice, Keypair, PeerParams};
 /// use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
 ///
 /// // We have to define the security policy before using Secrets.
 /// secret_policy_use_only_malloc_secrets();
 ///
 /// let keypair = Keypair::random();
-/// let peer1 = PeerParams { psk: Some(SymKey::random()), pk: SPk::random(), protocol_version: ProtocolVersion::V02, osk_domain_separator: OskDomainSeparator::default() };
-/// let peer2 = PeerParams { psk: None, pk: SPk::random(), protocol_version: ProtocolVersion::V02, osk_domain_separator: OskDomainSeparator::default() };
+/// let peer1 = PeerParams { psk: Some(SymKey::random()), pk: SPk::random(), protocol_version: ProtocolVersion::V02, osk_domain_separator: OskDomainSeparator::default(), ephemeral_kem: EphemeralKemChoice::default(), aead: AeadChoice::default(), hybrid_x25519: false };
+/// let peer2 = PeerParams { psk: None, pk: SPk::random(), protocol_version: ProtocolVersion::V02, osk_domain_separator: OskDomainSeparator::default(), ephemeral_kem: EphemeralKemChoice::default(), aead: AeadChoice::default(), hybrid_x25519: false };
 ///
 /// let mut builder = BuildCryptoServer::new(Some(keypair.clone()), vec![peer1]);
 /// builder.add_peer(peer2.psk.clone(), peer2.pk, ProtocolVersion::V02, OskDomainSeparator::default());
 ///
 /// let server = builder.build().expect("build failed");
 /// assert_eq!(server.peers.len(), 2);
-/// assert_eq!(server.sskm.secret(), keypair.sk.secret());
-/// assert_eq!(server.spkm, keypair.pk);
+/// assert_eq!(server.identities[0].sskm.secret(), keypair.sk.secret());
+/// assert_eq!(server.identities[0].spkm, keypair.pk);
 /// ```
 pub struct BuildCryptoServer {
     /// The key pair (secret/public key) identifying the crypto server instance.
@@ -201,10 +201,24 @@ impl Build<CryptoServer> for BuildCryptoServer {
                 pk,
                 protocol_version,
                 osk_domain_separator,
+                ephemeral_kem,
+                aead,
+                hybrid_x25519,
             } = params;
 
-            let PeerPtr(idx2) =
-                srv.add_peer(psk, pk, protocol_version.into(), osk_domain_separator)?;
+            let suite = CipherSuite {
+                protocol_version: protocol_version.into(),
+                ephemeral_kem,
+                aead,
+                hybrid_x25519,
+            };
+            let PeerPtr(idx2) = srv.add_peer(
+                psk,
+                pk,
+                suite.protocol_version.clone(),
+                osk_domain_separator,
+            )?;
+            srv.set_peer_cipher_suite(PeerPtr(idx2), suite)?;
 
             assert!(idx == idx2, "Peer id changed during CryptoServer construction from {idx} to {idx2}. This is a developer error.")
         }
@@ -229,6 +243,12 @@ pub struct PeerParams {
     /// The used protocol version.
     pub protocol_version: ProtocolVersion,
     pub osk_domain_separator: OskDomainSeparator,
+    /// The KEM used for the ephemeral key exchange; see [CipherSuite::ephemeral_kem].
+    pub ephemeral_kem: EphemeralKemChoice,
+    /// The AEAD used for encrypted fields; see [CipherSuite::aead].
+    pub aead: AeadChoice,
+    /// Whether to use the hybrid X25519 exchange; see [CipherSuite::hybrid_x25519].
+    pub hybrid_x25519: bool,
 }
 
 impl BuildCryptoServer {
@@ -290,8 +310,8 @@ impl BuildCryptoServer {
     ///
     /// // New server instances can now make use of the assigned key pair
     /// let server = builder.build().expect("build failed");
-    /// assert_eq!(server.sskm.secret(), keypair.sk.secret());
-    /// assert_eq!(server.spkm, keypair.pk);
+    /// assert_eq!(server.identities[0].sskm.secret(), keypair.sk.secret());
+    /// assert_eq!(server.identities[0].spkm, keypair.pk);
     /// ```
     ///
     /// ## Basic error handling: Re-assigning key pairs
@@ -353,7 +373,7 @@ impl BuildCryptoServer {
     /// // New server instances will then start with the peer being registered already
     /// let server = builder.build().expect("build failed");
     /// assert_eq!(server.peers.len(), 1);
-    /// let peer = &server.peers[0];
+    /// let peer = server.peers[0].as_ref().expect("peer is missing");
     /// let peer_psk = Some(peer.psk.clone()).expect("PSK is None");
     /// assert_eq!(peer.spkt, public_key);
     /// assert_eq!(peer_psk.secret(), pre_shared_key.secret());
@@ -371,6 +391,9 @@ impl BuildCryptoServer {
             pk,
             protocol_version,
             osk_domain_separator,
+            ephemeral_kem: EphemeralKemChoice::default(),
+            aead: AeadChoice::default(),
+            hybrid_x25519: false,
         });
         self
     }
@@ -44,6 +44,10 @@ pub const PEER_COOKIE_VALUE_EPOCH: Timing = 120.0;
 /// The biscuit mechanism is used to make sure the responder is stateless in our protocol.
 pub const BISCUIT_EPOCH: Timing = 300.0;
 
+/// Seconds during which the previous static key remains valid after promoting the next one
+/// (see [crate::protocol::key_rotation])
+pub const KEY_ROTATION_OVERLAP: Timing = 3600.0 * 24.0;
+
 /// The initiator opportunistically retransmits their messages; it applies an increasing delay
 /// between each retreansmission. This is the factor by which the delay grows after each
 /// retransmission.
@@ -0,0 +1,221 @@
+//! Computing static KEM decapsulations ahead of time, outside of the [CryptoServer]
+//!
+//! Decapsulating the static KEM ciphertext of an [InitHello] (step IHR5 in
+//! [CryptoServer::handle_init_hello]) or a [RespHello] (step RHI5 in
+//! [CryptoServer::handle_resp_hello]) is the most expensive part of processing these messages.
+//! A decapsulation only depends on the static secret key and the ciphertext and draws no
+//! randomness, so it can be computed on another thread without touching the [CryptoServer]:
+//!
+//! 1. [CryptoServer::decapsulation_job] determines the keypair a message has to be
+//!    decapsulated with and puts a shared reference to its secret key, along with the
+//!    ciphertext, into a [DecapsulationJob].
+//! 2. [DecapsulationJob::run] computes the [Decapsulation]; this can happen on any thread.
+//! 3. [CryptoServer::with_decapsulation] processes the message as usual, e.g. using
+//!    [CryptoServer::handle_msg], but uses the [Decapsulation] instead of decapsulating again.
+//!
+//! The message is fully validated in the last step. A [Decapsulation] is only used if both the
+//! keypair and the ciphertext it was computed from match the ones the message is processed
+//! with; otherwise, e.g. because the key was rotated in the meantime, the ciphertext is simply
+//! decapsulated again. The result of processing a message therefore does not depend on whether
+//! or where a decapsulation was computed ahead of time.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::ops::DerefMut;
+//! use rosenpass::protocol::basic_types::{MsgBuf, SPk, SSk, SymKey};
+//! use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
+//! use rosenpass::protocol::{CryptoServer, PeerPtr, ProtocolVersion};
+//! use rosenpass_ciphers::StaticKem;
+//! use rosenpass_cipher_traits::primitives::Kem;
+//!
+//! rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+//!
+//! let keypair = || -> anyhow::Result<(SSk, SPk)> {
+//!     let (mut sk, mut pk) = (SSk::zero(), SPk::zero());
+//!     StaticKem.keygen(sk.secret_mut(), pk.deref_mut())?;
+//!     Ok((sk, pk))
+//! };
+//! let ((sk_a, pk_a), (sk_b, pk_b)) = (keypair()?, keypair()?);
+//! let mut a = CryptoServer::new(sk_a, pk_a.clone());
+//! let mut b = CryptoServer::new(sk_b, pk_b.clone());
+//! let psk = SymKey::random();
+//! let (version, domain) = (ProtocolVersion::V03, OskDomainSeparator::default);
+//! a.add_peer(Some(psk.clone()), pk_b, version.clone(), domain())?;
+//! b.add_peer(Some(psk), pk_a, version, domain())?;
+//!
+//! let (mut a_buf, mut b_buf) = (MsgBuf::zero(), MsgBuf::zero());
+//! let mut len = a.initiate_handshake(PeerPtr(0), a_buf.as_mut_slice())?;
+//!
+//! // Decapsulate InitHello and RespHello on another thread, then process them as usual
+//! for _ in 0..2 {
+//!     let job = b.decapsulation_job(&a_buf[..len])?.expect("Message needs a decapsulation");
+//!     let worker = std::thread::Builder::new().stack_size(8 * 1024 * 1024);
+//!     let decapsulation = worker.spawn(move || job.run())?.join().unwrap()?;
+//!     let res = b.with_decapsulation(Some(decapsulation), |srv| {
+//!         srv.handle_msg(&a_buf[..len], &mut b_buf[..])
+//!     })?;
+//!     len = res.resp.unwrap();
+//!     std::mem::swap(&mut a, &mut b);
+//!     std::mem::swap(&mut a_buf, &mut b_buf);
+//! }
+//!
+//! // InitConf does not need a decapsulation
+//! assert!(b.decapsulation_job(&a_buf[..len])?.is_none());
+//! b.handle_msg(&a_buf[..len], &mut b_buf[..])?;
+//!
+//! assert_eq!(a.osk(PeerPtr(0))?.secret(), b.osk(PeerPtr(0))?.secret());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use zerocopy::Ref;
+
+use rosenpass_cipher_traits::primitives::Kem;
+use rosenpass_ciphers::{EphemeralKem, MlKem1024, MlKem768, StaticKem, X25519};
+use rosenpass_secret_memory::Secret;
+
+use crate::msgs::{Envelope, InitHello, MsgType, RespHello};
+use crate::RosenpassError;
+
+use super::basic_types::{SSk, SessionId};
+use super::protocol::with_ephemeral_kem;
+use super::{CryptoServer, EphemeralKemChoice, HandshakeState, IdentityKeyPtr};
+
+/// Shared key resulting from a static KEM decapsulation
+type StaticShk = Secret<{ StaticKem::SHK_LEN }>;
+
+/// Static KEM ciphertext
+type StaticCt = [u8; StaticKem::CT_LEN];
+
+/// A static KEM decapsulation to be computed, possibly on another thread; see
+/// [crate::protocol::decapsulation]
+#[derive(Debug)]
+pub struct DecapsulationJob {
+    /// The keypair to decapsulate with
+    key: IdentityKeyPtr,
+    /// The secret key of [Self::key], shared with the [CryptoServer]
+    sk: Arc<SSk>,
+    /// The ciphertext to decapsulate
+    ct: StaticCt,
+}
+
+impl DecapsulationJob {
+    /// Compute the decapsulation
+    pub fn run(self) -> Result<Decapsulation> {
+        let mut shk = StaticShk::zero();
+        StaticKem.decaps(shk.secret_mut(), self.sk.secret(), &self.ct)?;
+        Ok(Decapsulation {
+            key: self.key,
+            sk: self.sk,
+            ct: self.ct,
+            shk,
+        })
+    }
+}
+
+/// A static KEM decapsulation computed by [DecapsulationJob::run]; see
+/// [crate::protocol::decapsulation]
+#[derive(Debug)]
+pub struct Decapsulation {
+    /// The keypair the ciphertext was decapsulated with
+    key: IdentityKeyPtr,
+    /// The secret key of [Self::key]; identifies the keypair in case the slot was reused by a
+    /// key rotation in the meantime
+    sk: Arc<SSk>,
+    /// The decapsulated ciphertext
+    ct: StaticCt,
+    /// The resulting shared key
+    shk: StaticShk,
+}
+
+impl Decapsulation {
+    /// The shared key, if this is the decapsulation of `ct` with the secret key `sk` of `key`
+    fn shk_for(&self, key: IdentityKeyPtr, sk: &Arc<SSk>, ct: &StaticCt) -> Option<&StaticShk> {
+        let matches = self.key == key && Arc::ptr_eq(&self.sk, sk) && self.ct == *ct;
+        matches.then_some(&self.shk)
+    }
+}
+
+impl CryptoServer {
+    /// The static KEM decapsulation processing `rx_buf` requires, if any; see
+    /// [crate::protocol::decapsulation]
+    ///
+    /// Returns `None` for messages other than [InitHello] and [RespHello] and for messages
+    /// that can not be processed anyway, e.g. a RespHello for an unknown session. Errors are only
+    /// returned for malformed messages.
+    pub fn decapsulation_job(&self, rx_buf: &[u8]) -> Result<Option<DecapsulationJob>> {
+        let job = |key: IdentityKeyPtr, ct: &StaticCt| DecapsulationJob {
+            key,
+            sk: Arc::clone(key.sskm(self)),
+            ct: *ct,
+        };
+
+        match rx_buf.first().map(|&typ| MsgType::try_from(typ)) {
+            Some(Ok(MsgType::InitHello)) => {
+                let (ekem, hybrid) = EphemeralKemChoice::from_init_hello_len(rx_buf.len())?;
+                with_ephemeral_kem!(ekem, hybrid, EKem, X25519_LEN => {
+                    let msg_in: Ref<&[u8], Envelope<InitHello<{ EKem::PK_LEN }, X25519_LEN>>> =
+                        Ref::new(rx_buf).ok_or(RosenpassError::BufferSizeMismatch)?;
+                    let Ok(key) = self.lookup_identity(&msg_in) else {
+                        return Ok(None);
+                    };
+                    Ok(Some(job(key, &msg_in.payload.sctr)))
+                })
+            }
+            Some(Ok(MsgType::RespHello)) => {
+                let (ekem, hybrid) = EphemeralKemChoice::from_resp_hello_len(rx_buf.len())?;
+                with_ephemeral_kem!(ekem, hybrid, EKem, X25519_LEN => {
+                    let msg_in: Ref<&[u8], Envelope<RespHello<{ EKem::CT_LEN }, X25519_LEN>>> =
+                        Ref::new(rx_buf).ok_or(RosenpassError::BufferSizeMismatch)?;
+                    let rh = &msg_in.payload;
+                    let Some(hs) = self.lookup_handshake(SessionId::from_slice(&rh.sidi)) else {
+                        return Ok(None);
+                    };
+                    let key = IdentityKeyPtr::active(hs.peer().get(self).identity);
+                    Ok(Some(job(key, &rh.scti)))
+                })
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Run `f`, e.g. [Self::handle_msg], using the given `decapsulation` instead of
+    /// decapsulating the static KEM ciphertext of the message again; see
+    /// [crate::protocol::decapsulation]
+    pub fn with_decapsulation<R>(
+        &mut self,
+        decapsulation: Option<Decapsulation>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let prev = std::mem::replace(&mut self.decapsulation, decapsulation);
+        let res = f(self);
+        self.decapsulation = prev;
+        res
+    }
+
+    /// The shared key for decapsulating `ct` with the keypair `key` from [Self::decapsulation],
+    /// if available
+    pub(super) fn decapsulated(&self, key: IdentityKeyPtr, ct: &StaticCt) -> Option<&StaticShk> {
+        (self.decapsulation.as_ref())?.shk_for(key, key.sskm(self), ct)
+    }
+}
+
+impl HandshakeState {
+    /// Like [Self::decaps_and_mix] with [StaticKem], using the shared key `decapsulated` if it
+    /// was already computed ahead of time; see [CryptoServer::decapsulated]
+    pub fn decaps_and_mix_static(
+        &mut self,
+        decapsulated: Option<&StaticShk>,
+        sk: &SSk,
+        pk: &[u8; StaticKem::PK_LEN],
+        ct: &StaticCt,
+    ) -> Result<&mut Self> {
+        match decapsulated {
+            Some(shk) => self.mix(pk)?.mix(shk.secret())?.mix(ct),
+            None => self.decaps_and_mix(&StaticKem, sk.secret(), pk, ct),
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//! Rotating static keys without interrupting handshakes
+//!
+//! Rosenpass peers know each other by their static public keys, so replacing a key usually
+//! requires updating every peer at the same moment. To avoid this, each [Identity] of the
+//! [CryptoServer] and each [Peer] can hold a second key next to the active one:
+//!
+//! 1. The new key is registered as the *next* key on both sides, using
+//!    [CryptoServer::set_next_identity_key] on the server owning the key and
+//!    [CryptoServer::set_next_peer_key] on its peers. Incoming handshakes are accepted under
+//!    both keys from now on, while outgoing handshakes still use the active key.
+//! 2. Every side promotes the next key at its own pace using
+//!    [CryptoServer::promote_identity_key] and [CryptoServer::promote_peer_key]. The previously
+//!    active key becomes the *previous* key and keeps being accepted for an overlap period
+//!    ([KEY_ROTATION_OVERLAP] by default).
+//! 3. Once the overlap period is over, [CryptoServer::poll] forgets the previous key.
+//!
+//! Which of the keys a message is addressed to is determined using the [Envelope::mac]; see
+//! [CryptoServer::lookup_identity]. Peers are registered in [CryptoServer::index] under the peer
+//! IDs of both of their keys.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::ops::DerefMut;
+//! use rosenpass::protocol::basic_types::{SSk, SPk};
+//! use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
+//! use rosenpass::protocol::{CryptoServer, IdentityPtr, ProtocolVersion};
+//! use rosenpass_ciphers::StaticKem;
+//! use rosenpass_cipher_traits::primitives::Kem;
+//!
+//! rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+//!
+//! let keypair = || -> anyhow::Result<(SSk, SPk)> {
+//!     let (mut sk, mut pk) = (SSk::zero(), SPk::zero());
+//!     StaticKem.keygen(sk.secret_mut(), pk.deref_mut())?;
+//!     Ok((sk, pk))
+//! };
+//!
+//! let (sskm, spkm) = keypair()?;
+//! let mut srv = CryptoServer::new(sskm, spkm.clone());
+//! let (_, spkt) = keypair()?;
+//! let peer = srv.add_peer(None, spkt, ProtocolVersion::V03, OskDomainSeparator::default())?;
+//!
+//! // Roll our own key
+//! let (sskm2, spkm2) = keypair()?;
+//! srv.set_next_identity_key(IdentityPtr(0), Some((sskm2, spkm2.clone())))?;
+//! srv.promote_identity_key(IdentityPtr(0), 3600.0)?;
+//! assert_eq!(srv.identities[0].spkm, spkm2);
+//! assert_eq!(srv.identities[0].alternate.as_ref().unwrap().spkm, spkm);
+//!
+//! // Roll the key of the peer
+//! let (_, spkt2) = keypair()?;
+//! srv.set_next_peer_key(peer, Some(spkt2.clone()))?;
+//! let next_pidt = peer.get(&srv).alternate_spkt.as_ref().unwrap().pidt;
+//! assert_eq!(srv.find_peer(next_pidt), Some(peer));
+//! srv.promote_peer_key(peer, 3600.0)?;
+//! assert_eq!(peer.get(&srv).spkt, spkt2);
+//! assert_eq!(peer.get(&srv).pidt()?, next_pidt);
+//!
+//! // Nothing left to promote
+//! assert!(srv.promote_peer_key(peer, 3600.0).is_err());
+//!
+//! Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! [KEY_ROTATION_OVERLAP]: super::constants::KEY_ROTATION_OVERLAP
+//! [Envelope::mac]: super::Envelope::mac
+
+use std::mem;
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Result};
+
+use super::basic_types::{SPk, SSk};
+use super::index::PeerIndexKey;
+use super::timing::{has_happened, Timing};
+use super::{
+    AlternateKeypair, AlternatePublicKey, CryptoServer, Identity, IdentityPtr, Peer, PeerPtr,
+};
+
+impl CryptoServer {
+    /// Set or clear the next keypair of `identity`
+    ///
+    /// Incoming handshakes addressed to the next keypair are accepted right away; it is used for
+    /// outgoing handshakes after [Self::promote_identity_key].
+    ///
+    /// Setting a next keypair replaces the previous keypair of an earlier rotation, ending its
+    /// overlap period. Clearing the next keypair leaves the previous keypair alone.
+    pub fn set_next_identity_key(
+        &mut self,
+        identity: IdentityPtr,
+        next: Option<(SSk, SPk)>,
+    ) -> Result<()> {
+        ensure!(
+            identity.0 < self.identities.len(),
+            "Cannot set next key for identity {:?}; no such identity registered.",
+            identity
+        );
+
+        let Some((sskm, spkm)) = next else {
+            let id = identity.get_mut(self);
+            if id.has_next_key() {
+                id.alternate = None;
+            }
+            return Ok(());
+        };
+
+        let conflict = self.identity_ptrs().any(|other| {
+            let other_id = other.get(self);
+            match other == identity {
+                true => other_id.spkm == spkm,
+                false => other_id.has_public_key(&spkm),
+            }
+        });
+        ensure!(
+            !conflict,
+            "Cannot set next key for identity {:?}; the key is already in use.",
+            identity
+        );
+
+        identity.get_mut(self).alternate = Some(AlternateKeypair {
+            sskm: Arc::new(sskm),
+            spkm,
+            expires_at: None,
+        });
+        Ok(())
+    }
+
+    /// Set or clear the next public key of `peer`
+    ///
+    /// The peer ID of the next public key is added to [Self::index], so incoming handshakes from
+    /// the peer are accepted under either key; the next public key is used for outgoing
+    /// handshakes after [Self::promote_peer_key].
+    ///
+    /// Setting a next public key replaces the previous public key of an earlier rotation, ending
+    /// its overlap period. Clearing the next public key leaves the previous public key alone.
+    pub fn set_next_peer_key(&mut self, peer: PeerPtr, next: Option<SPk>) -> Result<()> {
+        ensure!(
+            self.contains_peer(peer),
+            "Cannot set next key for peer {:?}; no such peer registered.",
+            peer
+        );
+
+        let Some(spkt) = next else {
+            if peer.get(self).has_next_key() {
+                peer.drop_alternate_key(self);
+            }
+            return Ok(());
+        };
+
+        let keyed_hash = peer.get(self).cipher_suite.keyed_hash();
+        let pidt = Peer::pidt_of(&spkt, keyed_hash)?;
+        let current = peer.get(self);
+        ensure!(
+            current.spkt != spkt,
+            "Cannot set next key for peer {:?}; the key is already active.",
+            peer
+        );
+        if let Some(no) = self.index.get(&PeerIndexKey::Peer(pidt)) {
+            let is_alternate = current.alternate_spkt.as_ref().map(|alt| alt.pidt) == Some(pidt);
+            ensure!(
+                *no == peer.0 && is_alternate,
+                "Cannot set next key for peer {:?}; peer with this id already registered.",
+                peer
+            );
+        }
+
+        peer.drop_alternate_key(self);
+        self.index.insert(PeerIndexKey::Peer(pidt), peer.0);
+        peer.get_mut(self).alternate_spkt = Some(AlternatePublicKey {
+            spkt,
+            pidt,
+            expires_at: None,
+        });
+        Ok(())
+    }
+
+    /// Make the next keypair of `identity` the active one
+    ///
+    /// The previously active keypair remains valid for incoming handshakes during `overlap`
+    /// seconds. Ongoing handshakes of peers bound to the identity are aborted and new ones are
+    /// initiated right away using the new keypair, so problems with the new keypair become
+    /// visible immediately. Established sessions are not affected.
+    ///
+    /// Raises an error if there is no next keypair; see [Self::set_next_identity_key].
+    pub fn promote_identity_key(&mut self, identity: IdentityPtr, overlap: Timing) -> Result<()> {
+        ensure!(
+            identity.0 < self.identities.len(),
+            "Cannot promote key of identity {:?}; no such identity registered.",
+            identity
+        );
+        let expires_at = self.timebase.now() + overlap;
+        let id = identity.get_mut(self);
+        let next = match id.alternate.take() {
+            Some(next) if next.expires_at.is_none() => next,
+            previous => {
+                id.alternate = previous;
+                bail!("Cannot promote key of identity {identity:?}; no next key set.");
+            }
+        };
+
+        id.alternate = Some(AlternateKeypair {
+            sskm: mem::replace(&mut id.sskm, next.sskm),
+            spkm: mem::replace(&mut id.spkm, next.spkm),
+            expires_at: Some(expires_at),
+        });
+
+        let peers = self
+            .peer_ptrs()
+            .filter(|p| p.get(self).identity == identity);
+        for peer in peers.collect::<Vec<_>>() {
+            peer.restart_handshake(self);
+        }
+        Ok(())
+    }
+
+    /// Make the next public key of `peer` the active one
+    ///
+    /// The previously active public key remains valid for incoming handshakes during `overlap`
+    /// seconds. An ongoing handshake with the peer is aborted and a new one is initiated right
+    /// away using the new public key. The established session is not affected.
+    ///
+    /// Raises an error if there is no next public key; see [Self::set_next_peer_key].
+    pub fn promote_peer_key(&mut self, peer: PeerPtr, overlap: Timing) -> Result<()> {
+        ensure!(
+            self.contains_peer(peer),
+            "Cannot promote key of peer {:?}; no such peer registered.",
+            peer
+        );
+        let expires_at = self.timebase.now() + overlap;
+        let previous_pidt = peer.get(self).pidt()?;
+        let p = peer.get_mut(self);
+        let next = match p.alternate_spkt.take() {
+            Some(next) if next.expires_at.is_none() => next,
+            previous => {
+                p.alternate_spkt = previous;
+                bail!("Cannot promote key of peer {peer:?}; no next key set.");
+            }
+        };
+
+        p.alternate_spkt = Some(AlternatePublicKey {
+            spkt: mem::replace(&mut p.spkt, next.spkt),
+            pidt: previous_pidt,
+            expires_at: Some(expires_at),
+        });
+
+        peer.restart_handshake(self);
+        Ok(())
+    }
+}
+
+impl Identity {
+    /// Whether a next keypair was set that can be promoted
+    pub fn has_next_key(&self) -> bool {
+        (self.alternate.as_ref()).is_some_and(|alt| alt.expires_at.is_none())
+    }
+}
+
+impl Peer {
+    /// Whether a next public key was set that can be promoted
+    pub fn has_next_key(&self) -> bool {
+        (self.alternate_spkt.as_ref()).is_some_and(|alt| alt.expires_at.is_none())
+    }
+}
+
+impl IdentityPtr {
+    /// Time left until the previous keypair of this identity is forgotten
+    pub(crate) fn alternate_key_life_left(&self, srv: &CryptoServer) -> Option<Timing> {
+        let alt = self.get(srv).alternate.as_ref()?;
+        alt.expires_at.map(|t| t - srv.timebase.now())
+    }
+
+    /// Forget the previous keypair of this identity once the overlap period is over
+    pub(crate) fn drop_expired_alternate_key(&self, srv: &mut CryptoServer) {
+        if self
+            .alternate_key_life_left(srv)
+            .is_some_and(|t| has_happened(t, 0.0))
+        {
+            self.get_mut(srv).alternate = None;
+        }
+    }
+}
+
+impl PeerPtr {
+    /// Time left until the previous public key of this peer is forgotten
+    pub(crate) fn alternate_key_life_left(&self, srv: &CryptoServer) -> Option<Timing> {
+        let alt = self.get(srv).alternate_spkt.as_ref()?;
+        alt.expires_at.map(|t| t - srv.timebase.now())
+    }
+
+    /// Forget the previous public key of this peer once the overlap period is over
+    pub(crate) fn drop_expired_alternate_key(&self, srv: &mut CryptoServer) {
+        if self
+            .alternate_key_life_left(srv)
+            .is_some_and(|t| has_happened(t, 0.0))
+        {
+            self.drop_alternate_key(srv);
+        }
+    }
+
+    /// Remove the second public key of this peer along with its entry in [CryptoServer::index]
+    fn drop_alternate_key(&self, srv: &mut CryptoServer) {
+        if let Some(alt) = self.get_mut(srv).alternate_spkt.take() {
+            srv.index.remove(&PeerIndexKey::Peer(alt.pidt));
+        }
+    }
+
+    /// Abort the ongoing handshake with the peer and initiate a new one immediately
+    fn restart_handshake(&self, srv: &mut CryptoServer) {
+        self.hs().take(srv);
+        self.get_mut(srv).initiation_forced = true;
+    }
+}
@@ -84,8 +84,12 @@ pub use build_crypto_server::*;
 pub mod basic_types;
 pub mod constants;
 pub mod cookies;
+pub mod decapsulation;
 pub mod index;
+pub mod key_rotation;
 pub mod osk_domain_separator;
+pub mod persistent_state;
+pub mod snapshot;
 pub mod testutils;
 pub mod timing;
 pub mod zerocopy;
@@ -0,0 +1,385 @@
+//! Persisting biscuit keys and cookie secrets across restarts
+//!
+//! The [CryptoServer] generates its [CryptoServer::biscuit_keys] and
+//! [CryptoServer::cookie_secrets] randomly upon startup. When a responder restarts, all biscuits
+//! it issued before become undecryptable, so every initiator with a handshake in flight has to
+//! start over after running into its retransmission timeout.
+//!
+//! [CryptoServer::seal_state] serializes these keys along with their age and the
+//! [CryptoServer::biscuit_ctr] and encrypts them with a key derived from the static secret key of
+//! the server (see [hash_domains::state_file_key]). [CryptoServer::restore_state] loads such a
+//! state into a freshly created server, so planned restarts are transparent to peers.
+//!
+//! Since the restored biscuit keys still decrypt old biscuits, the state also contains
+//! [Peer::biscuit_used](super::Peer::biscuit_used) of every peer, identified by its peer id; this
+//! keeps captured [InitConf](crate::msgs::InitConf) messages from being replayed after a restart.
+//! Restoring the state should therefore happen after adding the peers.
+//!
+//! The biscuit counter stored is advanced by [BISCUIT_CTR_RESERVE], so the state needs not be
+//! written for every biscuit issued; [CryptoServer::state_changed_since] tells when it needs to
+//! be written again, i.e. after keys were rotated, a peer accepted a new biscuit, or peers were
+//! added or removed.
+//!
+//! Keys are stored together with their time of creation as a UNIX timestamp, so the time spent
+//! while Rosenpass was not running counts towards their lifetime.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::ops::DerefMut;
+//! use rosenpass::protocol::basic_types::{SSk, SPk};
+//! use rosenpass::protocol::CryptoServer;
+//! use rosenpass_ciphers::StaticKem;
+//! use rosenpass_cipher_traits::primitives::Kem;
+//!
+//! rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+//!
+//! let (mut sk, mut pk) = (SSk::zero(), SPk::zero());
+//! StaticKem.keygen(sk.secret_mut(), pk.deref_mut())?;
+//!
+//! let mut srv = CryptoServer::new(sk.clone(), pk.clone());
+//! let bk = srv.active_biscuit_key();
+//! let (sealed, saved) = srv.seal_state()?;
+//! assert!(!srv.state_changed_since(&saved));
+//!
+//! let mut restarted = CryptoServer::new(sk, pk);
+//! restarted.restore_state(&sealed)?;
+//! assert_eq!(
+//!     restarted.biscuit_keys[bk.0].value.secret(),
+//!     srv.biscuit_keys[bk.0].value.secret()
+//! );
+//!
+//! // A server with a different key can not read the state
+//! let (mut sk, mut pk) = (SSk::zero(), SPk::zero());
+//! StaticKem.keygen(sk.secret_mut(), pk.deref_mut())?;
+//! assert!(CryptoServer::new(sk, pk).restore_state(&sealed).is_err());
+//!
+//! Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{ensure, Context, Result};
+use zerocopy::{AsBytes, FromBytes, FromZeroes, Ref};
+use zeroize::Zeroizing;
+
+use rosenpass_cipher_traits::primitives::AeadWithNonceInCiphertext;
+use rosenpass_ciphers::{KeyedHash, XAead, KEY_LEN};
+use rosenpass_constant_time as constant_time;
+use rosenpass_secret_memory::{Public, Secret};
+
+use crate::hash_domains;
+use crate::msgs::BISCUIT_ID_LEN;
+
+use super::basic_types::{SymKey, XAEADNonce};
+use super::constants::{BISCUIT_EPOCH, COOKIE_SECRET_EPOCH, COOKIE_SECRET_LEN};
+use super::cookies::CookieStore;
+use super::timing::{Timing, BCE};
+use super::CryptoServer;
+
+/// Number of biscuits that may be issued before the state needs to be stored again
+pub const BISCUIT_CTR_RESERVE: u128 = 1 << 16;
+
+/// Additional data for the encryption of the state; identifies the format
+const STATE_AD: &[u8] = b"rosenpass state file v2";
+
+/// The plaintext of the state stored by [CryptoServer::seal_state]
+///
+/// Times of creation are stored as UNIX timestamps, with zero denoting unused keys.
+///
+/// Also part of the snapshots produced by [CryptoServer::snapshot].
+#[repr(packed)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+pub(super) struct StatePlaintext {
+    /// Value for [CryptoServer::biscuit_ctr] after restoring
+    biscuit_ctr: [u8; BISCUIT_ID_LEN],
+    /// The values of [CryptoServer::biscuit_keys]
+    biscuit_keys: [[u8; KEY_LEN]; 2],
+    /// Times of creation of [CryptoServer::biscuit_keys]
+    biscuit_keys_created: [f64; 2],
+    /// The values of [CryptoServer::cookie_secrets]
+    cookie_secrets: [[u8; COOKIE_SECRET_LEN]; 2],
+    /// Times of creation of [CryptoServer::cookie_secrets]
+    cookie_secrets_created: [f64; 2],
+}
+
+/// The part of the plaintext of the state stored by [CryptoServer::seal_state] not specific to
+/// any peer
+#[repr(packed)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct StateHeader {
+    /// Biscuit keys, cookie secrets, and biscuit counter
+    keys: StatePlaintext,
+    /// Number of [BiscuitUsedRecord]s following the header
+    peer_count: u32,
+}
+
+/// The replay protection of a single peer in the state
+#[repr(packed)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct BiscuitUsedRecord {
+    /// The peer id ([super::Peer::pidt]) used to find the peer when restoring
+    peer_id: [u8; KEY_LEN],
+    /// [super::Peer::biscuit_used]
+    biscuit_used: [u8; BISCUIT_ID_LEN],
+}
+
+/// Length of [StateHeader]
+const HEADER_LEN: usize = std::mem::size_of::<StateHeader>();
+
+/// Length of [BiscuitUsedRecord]
+const RECORD_LEN: usize = std::mem::size_of::<BiscuitUsedRecord>();
+
+/// Length the encryption adds to the plaintext of the state
+const SEAL_OVERHEAD: usize = XAead::NONCE_LEN + XAead::TAG_LEN;
+
+/// Describes the state last stored with [CryptoServer::seal_state] or loaded with
+/// [CryptoServer::restore_state]
+///
+/// Used with [CryptoServer::state_changed_since] to decide when the state needs to be stored
+/// again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedState {
+    /// Times of creation of the biscuit keys and cookie secrets
+    created_at: [Timing; 4],
+    /// The biscuit counter stored
+    biscuit_ctr: u128,
+    /// [super::Peer::biscuit_used] of every entry of [CryptoServer::peers]
+    biscuit_used: Vec<Option<[u8; BISCUIT_ID_LEN]>>,
+}
+
+/// Current time as UNIX timestamp
+pub(super) fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// Decode a little endian biscuit counter
+pub(super) fn biscuit_ctr_from_bytes(ctr: &[u8]) -> u128 {
+    let mut buf = [0u8; 16];
+    buf[..BISCUIT_ID_LEN].copy_from_slice(ctr);
+    u128::from_le_bytes(buf)
+}
+
+/// Convert the time of creation of a key to a UNIX timestamp
+pub(super) fn created_to_unix(created_at: Timing, now: Timing, unix_now: f64) -> f64 {
+    if created_at <= BCE {
+        0.0
+    } else {
+        unix_now - (now - created_at)
+    }
+}
+
+/// Convert a UNIX timestamp stored by [created_to_unix] back to a point in time relative to
+/// `now`
+///
+/// Times in the future according to the system clock are treated as `now`.
+pub(super) fn created_from_unix(created: f64, now: Timing, unix_now: f64) -> Timing {
+    now - (unix_now - created).max(0.0)
+}
+
+/// Restore a key stored with its time of creation as a UNIX timestamp
+///
+/// Keys that are unused or expired after `lifetime` are erased.
+fn restore_store<const N: usize>(
+    store: &mut CookieStore<N>,
+    value: &[u8],
+    created: f64,
+    lifetime: Timing,
+    now: Timing,
+    unix_now: f64,
+) {
+    let created_at = created_from_unix(created, now, unix_now);
+    if created <= 0.0 || !created.is_finite() || now - created_at >= lifetime {
+        store.erase();
+        return;
+    }
+    store.value = Secret::from_slice(value);
+    store.created_at = created_at;
+}
+
+impl CryptoServer {
+    /// The key used to encrypt the state; derived from the secret key of the first identity ([Self::identities])
+    fn state_key(&self) -> Result<SymKey> {
+        Ok(hash_domains::state_file_key(KeyedHash::keyed_shake256())?
+            .turn_secret()
+            .mix(self.identities[0].sskm.secret())?
+            .into_secret())
+    }
+
+    /// Times of creation of the biscuit keys and cookie secrets
+    fn key_creation_times(&self) -> [Timing; 4] {
+        let [bk0, bk1] = &self.biscuit_keys;
+        let [cs0, cs1] = &self.cookie_secrets;
+        [
+            bk0.created_at,
+            bk1.created_at,
+            cs0.created_at,
+            cs1.created_at,
+        ]
+    }
+
+    /// [super::Peer::biscuit_used] of every entry of [Self::peers]
+    fn biscuits_used(&self) -> impl Iterator<Item = Option<[u8; BISCUIT_ID_LEN]>> + '_ {
+        (self.peers.iter()).map(|peer| peer.as_ref().map(|peer| *peer.biscuit_used))
+    }
+
+    /// The [SavedState] describing the current state with the given biscuit counter
+    fn saved_state(&self, biscuit_ctr: u128) -> SavedState {
+        SavedState {
+            created_at: self.key_creation_times(),
+            biscuit_ctr,
+            biscuit_used: self.biscuits_used().collect(),
+        }
+    }
+
+    /// Encrypt the biscuit keys, cookie secrets, biscuit counter, and the biscuit numbers used
+    /// by the peers for storage; see [crate::protocol::persistent_state]
+    ///
+    /// Returns the encrypted state and a [SavedState] for use with [Self::state_changed_since].
+    pub fn seal_state(&self) -> Result<(Vec<u8>, SavedState)> {
+        let biscuit_ctr = biscuit_ctr_from_bytes(&*self.biscuit_ctr) + BISCUIT_CTR_RESERVE;
+        let peers = self.peers.iter().flatten().collect::<Vec<_>>();
+
+        let mut pt = Zeroizing::new(vec![0u8; HEADER_LEN + peers.len() * RECORD_LEN]);
+        let (header, records) = pt.split_at_mut(HEADER_LEN);
+
+        let mut header: Ref<&mut [u8], StateHeader> = Ref::new(header).unwrap();
+        self.store_keys(&mut header.keys, biscuit_ctr);
+        header.peer_count = u32::try_from(peers.len()).context("Too many peers")?;
+
+        for (peer, record) in peers.iter().zip(records.chunks_exact_mut(RECORD_LEN)) {
+            let mut record: Ref<&mut [u8], BiscuitUsedRecord> = Ref::new(record).unwrap();
+            record.peer_id.copy_from_slice(&*peer.pidt()?);
+            record.biscuit_used.copy_from_slice(&*peer.biscuit_used);
+        }
+
+        let mut sealed = vec![0u8; pt.len() + SEAL_OVERHEAD];
+        let n = XAEADNonce::random();
+        XAead.encrypt_with_nonce_in_ctxt(
+            &mut sealed,
+            self.state_key()?.secret(),
+            &*n,
+            STATE_AD,
+            &pt,
+        )?;
+
+        Ok((sealed, self.saved_state(biscuit_ctr)))
+    }
+
+    /// Fill `state` with the biscuit keys and cookie secrets, storing `biscuit_ctr` as the
+    /// biscuit counter
+    pub(super) fn store_keys(&self, state: &mut StatePlaintext, biscuit_ctr: u128) {
+        let now = self.timebase.now();
+        let unix_now = unix_now();
+
+        state
+            .biscuit_ctr
+            .copy_from_slice(&biscuit_ctr.to_le_bytes()[..BISCUIT_ID_LEN]);
+        for (i, key) in self.biscuit_keys.iter().enumerate() {
+            state.biscuit_keys[i].copy_from_slice(key.value.secret());
+        }
+        for (i, secret) in self.cookie_secrets.iter().enumerate() {
+            state.cookie_secrets[i].copy_from_slice(secret.value.secret());
+        }
+        // The fields are unaligned, so they have to be assigned as a whole
+        state.biscuit_keys_created = self
+            .biscuit_keys
+            .each_ref()
+            .map(|key| created_to_unix(key.created_at, now, unix_now));
+        state.cookie_secrets_created = self
+            .cookie_secrets
+            .each_ref()
+            .map(|secret| created_to_unix(secret.created_at, now, unix_now));
+    }
+
+    /// Load a state produced by [Self::seal_state]; see [crate::protocol::persistent_state]
+    ///
+    /// This should be called right after creating the server and adding the peers, before any
+    /// biscuits are issued. Keys that expired in the meantime are not restored; peers in the
+    /// state which are missing in this server are skipped. Fails if the state was not produced
+    /// by a server with the same static secret key.
+    ///
+    /// Returns a [SavedState] for use with [Self::state_changed_since].
+    pub fn restore_state(&mut self, sealed: &[u8]) -> Result<SavedState> {
+        ensure!(
+            sealed.len() >= HEADER_LEN + SEAL_OVERHEAD,
+            "State is truncated"
+        );
+
+        let mut pt = Zeroizing::new(vec![0u8; sealed.len() - SEAL_OVERHEAD]);
+        XAead
+            .decrypt_with_nonce_in_ctxt(&mut pt, self.state_key()?.secret(), STATE_AD, sealed)
+            .context("Could not decrypt state; was it stored using another secret key?")?;
+        let (header, records) = pt.split_at(HEADER_LEN);
+
+        let header: Ref<&[u8], StateHeader> = Ref::new(header).unwrap();
+        let peer_count = header.peer_count as usize;
+        ensure!(
+            records.len() == peer_count * RECORD_LEN,
+            "State has wrong length for {peer_count} peers"
+        );
+        let biscuit_ctr = self.load_keys(&header.keys);
+
+        for record in records.chunks_exact(RECORD_LEN) {
+            let record: Ref<&[u8], BiscuitUsedRecord> = Ref::new(record).unwrap();
+            let Some(peer) = self.find_peer(Public::from_slice(&record.peer_id)) else {
+                continue;
+            };
+            // Never move the replay protection backwards
+            let biscuit_used = &mut peer.get_mut(self).biscuit_used;
+            if constant_time::compare(&record.biscuit_used, &**biscuit_used) > 0 {
+                *biscuit_used = Public::from_slice(&record.biscuit_used);
+            }
+        }
+
+        Ok(self.saved_state(biscuit_ctr))
+    }
+
+    /// Load the biscuit keys and cookie secrets from `state`, erasing expired keys
+    ///
+    /// The biscuit counter is set to the one stored unless that would move it backwards.
+    /// Returns the new value of the biscuit counter.
+    pub(super) fn load_keys(&mut self, state: &StatePlaintext) -> u128 {
+        let now = self.timebase.now();
+        let unix_now = unix_now();
+        // Copy the unaligned fields out of the struct before using them
+        let (biscuit_keys_created, cookie_secrets_created) =
+            (state.biscuit_keys_created, state.cookie_secrets_created);
+        for (i, key) in self.biscuit_keys.iter_mut().enumerate() {
+            let (value, created) = (&state.biscuit_keys[i], biscuit_keys_created[i]);
+            restore_store(key, value, created, 2.0 * BISCUIT_EPOCH, now, unix_now);
+        }
+        for (i, secret) in self.cookie_secrets.iter_mut().enumerate() {
+            let (value, created) = (&state.cookie_secrets[i], cookie_secrets_created[i]);
+            restore_store(
+                secret,
+                value,
+                created,
+                2.0 * COOKIE_SECRET_EPOCH,
+                now,
+                unix_now,
+            );
+        }
+
+        // Never move the counter backwards
+        let biscuit_ctr = biscuit_ctr_from_bytes(&state.biscuit_ctr)
+            .max(biscuit_ctr_from_bytes(&*self.biscuit_ctr));
+        self.biscuit_ctr
+            .copy_from_slice(&biscuit_ctr.to_le_bytes()[..BISCUIT_ID_LEN]);
+
+        biscuit_ctr
+    }
+
+    /// Whether the state changed since it was stored, i.e. whether keys were rotated, the
+    /// biscuits counter reached the value stored, or the biscuit numbers used by the peers
+    /// changed
+    pub fn state_changed_since(&self, saved: &SavedState) -> bool {
+        let ctr = biscuit_ctr_from_bytes(&*self.biscuit_ctr);
+        ctr >= saved.biscuit_ctr
+            || self.key_creation_times() != saved.created_at
+            || !self.biscuits_used().eq(saved.biscuit_used.iter().copied())
+    }
+}
@@ -12,18 +12,25 @@ use std::{
     fmt::{Debug, Display},
     mem::size_of,
     ops::Deref,
+    sync::Arc,
 };
 
-use anyhow::{bail, ensure, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use memoffset::span_of;
 use zerocopy::{AsBytes, FromBytes, Ref};
 
+use rosenpass_cipher_traits::algorithms::{aead_chacha20poly1305, aead_xchacha20poly1305};
 use rosenpass_cipher_traits::primitives::{
-    Aead as _, AeadWithNonceInCiphertext, Kem, KeyedHashInstance,
+    Aead as _, AeadError, AeadWithNonceInCiphertext, Kem, KeyedHashInstance,
 };
 use rosenpass_ciphers::hash_domain::{SecretHashDomain, SecretHashDomainNamespace};
-use rosenpass_ciphers::{Aead, EphemeralKem, KeyedHash, StaticKem, XAead};
+use rosenpass_ciphers::{
+    Aead, Aes256Gcm, EphemeralKem, KeyedHash, MlKem1024, MlKem768, StaticKem, XAead, XAes256Gcm,
+    X25519,
+};
 use rosenpass_constant_time as constant_time;
+#[cfg(feature = "internal_deterministic")]
+use rosenpass_secret_memory::rand::{with_rng, InjectedRng};
 use rosenpass_secret_memory::{Public, Secret};
 use rosenpass_to::{ops::copy_slice, To};
 use rosenpass_util::{
@@ -37,7 +44,7 @@ use crate::{hash_domains, msgs::*, RosenpassError};
 
 use super::basic_types::{
     BiscuitId, EPk, ESk, MsgBuf, PeerId, PeerNo, PublicSymKey, SPk, SSk, SessionId, SymKey,
-    XAEADNonce,
+    XAEADNonce, XPk, XSk,
 };
 use super::constants::{
     BISCUIT_EPOCH, COOKIE_SECRET_EPOCH, COOKIE_SECRET_LEN, COOKIE_VALUE_LEN,
@@ -46,6 +53,7 @@ use super::constants::{
     RETRANSMIT_DELAY_GROWTH, RETRANSMIT_DELAY_JITTER,
 };
 use super::cookies::{BiscuitKey, CookieSecret, CookieStore};
+use super::decapsulation::Decapsulation;
 use super::index::{PeerIndex, PeerIndexKey};
 use super::osk_domain_separator::OskDomainSeparator;
 use super::timing::{has_happened, Timing, BCE, UNENDING};
@@ -82,10 +90,12 @@ pub struct CryptoServer {
     /// this field.
     pub timebase: Timebase,
 
-    /// Static Secret Key Mine (our secret key)
-    pub sskm: SSk,
-    /// Static Public Key Mine (our public key)
-    pub spkm: SPk,
+    /// The static keypairs this server answers to
+    ///
+    /// The first identity is the keypair passed to [Self::new]; further identities are added
+    /// through [Self::add_identity]. Each [Peer] is bound to one identity ([Peer::identity]),
+    /// so a single server can serve multiple isolated tenants.
+    pub identities: Vec<Identity>,
     /// Counter used to fill the [Biscuit::biscuit_no] field for biscuits issued.
     ///
     /// Every [Biscuit] issued contains a biscuit number; this is the counter used to generate
@@ -103,7 +113,10 @@ pub struct CryptoServer {
     pub biscuit_keys: [BiscuitKey; 2],
 
     /// List of peers and their session and handshake states
-    pub peers: Vec<Peer>,
+    ///
+    /// Peers removed through [Self::remove_peer] leave a tombstone (`None`) in their slot
+    /// so that the [PeerPtr] values referring to all other peers remain valid.
+    pub peers: Vec<Option<Peer>>,
     /// Index into the list of peers. See [PeerIndexKey] for details.
     pub index: PeerIndex,
     /// Hash key for known responder confirmation responses.
@@ -136,10 +149,93 @@ pub struct CryptoServer {
     ///
     /// See [CryptoServer::handle_msg_under_load], and [CryptoServer::active_or_retired_cookie_secrets].
     pub cookie_secrets: [CookieSecret; 2],
+
+    /// Replacement for the default source of randomness
+    ///
+    /// If set, all randomness used by [Self::initiate_handshake], [Self::handle_msg],
+    /// [Self::handle_msg_under_load], [Self::retransmit_handshake], and [Self::poll] is drawn
+    /// from this generator. Together with a manual clock (see
+    /// [rosenpass_util::time::with_clock]), this makes the server fully deterministic, which is
+    /// used to generate test vectors.
+    ///
+    /// Only available with the `internal_deterministic` feature; see [Self::new_deterministic].
+    #[cfg(feature = "internal_deterministic")]
+    pub rng: Option<InjectedRng>,
+
+    /// Static KEM decapsulation computed ahead of time for the message being processed
+    ///
+    /// See [Self::with_decapsulation] and [crate::protocol::decapsulation].
+    pub decapsulation: Option<Decapsulation>,
+}
+
+/// A static keypair of a [CryptoServer]
+///
+/// Identities live in [CryptoServer::identities] and are referred to through [IdentityPtr].
+#[derive(Debug)]
+pub struct Identity {
+    /// Static Secret Key Mine (our secret key)
+    ///
+    /// Shared with [crate::protocol::decapsulation::DecapsulationJob]s, so the key is never copied.
+    pub sskm: Arc<SSk>,
+    /// Static Public Key Mine (our public key)
+    pub spkm: SPk,
+    /// Second keypair accepted for incoming messages during a static key rotation
+    ///
+    /// See [crate::protocol::key_rotation].
+    pub alternate: Option<AlternateKeypair>,
+}
+
+/// Second keypair of an [Identity] during a static key rotation
+///
+/// Before the rotation, this is the next keypair; after [CryptoServer::promote_identity_key],
+/// it is the previous keypair, accepted until the end of the overlap period.
+/// See [crate::protocol::key_rotation].
+#[derive(Debug)]
+pub struct AlternateKeypair {
+    /// Static Secret Key Mine
+    pub sskm: Arc<SSk>,
+    /// Static Public Key Mine
+    pub spkm: SPk,
+    /// When the keypair stops being accepted; `None` for a next keypair
+    pub expires_at: Option<Timing>,
+}
+
+/// Selects one of the keypairs of an [Identity]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KeySlot {
+    /// [Identity::sskm] and [Identity::spkm]; used for all outgoing messages
+    Active,
+    /// [Identity::alternate]
+    Alternate,
+}
+
+impl Identity {
+    /// Construct an identity from its active keypair
+    pub fn new(sskm: SSk, spkm: SPk) -> Self {
+        Self {
+            sskm: Arc::new(sskm),
+            spkm,
+            alternate: None,
+        }
+    }
+
+    /// Check whether `pk` is one of the public keys of this identity
+    pub fn has_public_key(&self, pk: &SPk) -> bool {
+        &self.spkm == pk || self.alternate.as_ref().is_some_and(|alt| &alt.spkm == pk)
+    }
+
+    /// Calculate the peer ID of this identity, i.e. the peer ID other servers know us by
+    #[rustfmt::skip]
+    pub fn pidm(&self, keyed_hash: KeyedHash) -> Result<PeerId> {
+        Ok(Public::new(
+            hash_domains::peerid(keyed_hash)?
+                .mix(self.spkm.deref())?
+                .into_value()))
+    }
 }
 
 /// Specifies the protocol version used by a peer.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ProtocolVersion {
     V02,
     V03,
@@ -153,6 +249,14 @@ impl ProtocolVersion {
             ProtocolVersion::V03 => KeyedHash::keyed_shake256(),
         }
     }
+
+    /// Returns the protocol version using the given [KeyedHash]
+    pub fn from_keyed_hash(keyed_hash: &KeyedHash) -> Self {
+        match keyed_hash {
+            KeyedHash::IncorrectHmacBlake2b(_) => ProtocolVersion::V02,
+            KeyedHash::KeyedShake256(_) => ProtocolVersion::V03,
+        }
+    }
 }
 
 impl From<crate::config::ProtocolVersion> for ProtocolVersion {
@@ -164,6 +268,365 @@ impl From<crate::config::ProtocolVersion> for ProtocolVersion {
     }
 }
 
+/// The KEM used for the ephemeral key exchange with a peer, i.e. for [InitHello::epki] and
+/// [RespHello::ecti]
+///
+/// Both peers must be configured to use the same ephemeral KEM. The sizes of [InitHello] and
+/// [RespHello] follow the chosen KEM; since the supported KEMs differ in the lengths of their
+/// public keys and ciphertexts, the KEM a message was produced with is recognized by its size.
+///
+/// See [CipherSuite::ephemeral_kem].
+///
+/// # Examples
+///
+/// ```
+/// use rosenpass::protocol::EphemeralKemChoice;
+///
+/// for kem in EphemeralKemChoice::ALL {
+///     for hybrid in [false, true] {
+///         let ih_len = kem.init_hello_len(hybrid);
+///         let rh_len = kem.resp_hello_len(hybrid);
+///         assert_eq!(EphemeralKemChoice::from_init_hello_len(ih_len)?, (kem, hybrid));
+///         assert_eq!(EphemeralKemChoice::from_resp_hello_len(rh_len)?, (kem, hybrid));
+///     }
+/// }
+/// assert!(EphemeralKemChoice::from_init_hello_len(42).is_err());
+///
+/// Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EphemeralKemChoice {
+    /// Kyber-512; see [EphemeralKem]
+    #[default]
+    Kyber512,
+    /// ML-KEM-768 (NIST security category 3); see [MlKem768]
+    MlKem768,
+    /// ML-KEM-1024 (NIST security category 5); see [MlKem1024]
+    MlKem1024,
+}
+
+/// Evaluates `$body` with the type alias `$kem` referring to the [Kem] implementation selected
+/// by the [EphemeralKemChoice] `$choice`
+///
+/// The second form additionally defines the constant `$x25519_len` as the length of the
+/// [InitHello::xpki] and [RespHello::xcti] fields, depending on whether the hybrid X25519
+/// exchange is used according to `$hybrid`; see [CipherSuite::hybrid_x25519].
+macro_rules! with_ephemeral_kem {
+    ($choice:expr, $hybrid:expr, $kem:ident, $x25519_len:ident => $body:expr) => {
+        if $hybrid {
+            const $x25519_len: usize = X25519::PK_LEN;
+            with_ephemeral_kem!($choice, $kem => $body)
+        } else {
+            const $x25519_len: usize = 0;
+            with_ephemeral_kem!($choice, $kem => $body)
+        }
+    };
+    ($choice:expr, $kem:ident => $body:expr) => {
+        match $choice {
+            EphemeralKemChoice::Kyber512 => {
+                type $kem = EphemeralKem;
+                $body
+            }
+            EphemeralKemChoice::MlKem768 => {
+                type $kem = MlKem768;
+                $body
+            }
+            EphemeralKemChoice::MlKem1024 => {
+                type $kem = MlKem1024;
+                $body
+            }
+        }
+    };
+}
+pub(super) use with_ephemeral_kem;
+
+impl EphemeralKemChoice {
+    /// All supported ephemeral KEMs
+    pub const ALL: [Self; 3] = [Self::Kyber512, Self::MlKem768, Self::MlKem1024];
+
+    /// Length of the ephemeral public key, i.e. of [InitHello::epki]
+    pub fn pk_len(&self) -> usize {
+        with_ephemeral_kem!(self, EKem => EKem::PK_LEN)
+    }
+
+    /// Length of the ephemeral ciphertext, i.e. of [RespHello::ecti]
+    pub fn ct_len(&self) -> usize {
+        with_ephemeral_kem!(self, EKem => EKem::CT_LEN)
+    }
+
+    /// Size of an [Envelope]<[InitHello]> using this KEM, with or without the hybrid X25519
+    /// exchange
+    pub fn init_hello_len(&self, hybrid_x25519: bool) -> usize {
+        with_ephemeral_kem!(self, hybrid_x25519, EKem, X25519_LEN => {
+            size_of::<Envelope<InitHello<{ EKem::PK_LEN }, X25519_LEN>>>()
+        })
+    }
+
+    /// Size of an [Envelope]<[RespHello]> using this KEM, with or without the hybrid X25519
+    /// exchange
+    pub fn resp_hello_len(&self, hybrid_x25519: bool) -> usize {
+        with_ephemeral_kem!(self, hybrid_x25519, EKem, X25519_LEN => {
+            size_of::<Envelope<RespHello<{ EKem::CT_LEN }, X25519_LEN>>>()
+        })
+    }
+
+    /// Recognize the KEM producing public keys of the given length
+    pub fn from_pk_len(len: usize) -> Result<Self, RosenpassError> {
+        (Self::ALL.into_iter())
+            .find(|kem| kem.pk_len() == len)
+            .ok_or(RosenpassError::BufferSizeMismatch)
+    }
+
+    /// Recognize the KEM producing ciphertexts of the given length
+    pub fn from_ct_len(len: usize) -> Result<Self, RosenpassError> {
+        (Self::ALL.into_iter())
+            .find(|kem| kem.ct_len() == len)
+            .ok_or(RosenpassError::BufferSizeMismatch)
+    }
+
+    /// Recognize the KEM used by an [Envelope]<[InitHello]> and whether the hybrid X25519
+    /// exchange is used from the size of the message
+    pub fn from_init_hello_len(len: usize) -> Result<(Self, bool), RosenpassError> {
+        (Self::ALL.into_iter())
+            .flat_map(|kem| [(kem, false), (kem, true)])
+            .find(|(kem, hybrid)| kem.init_hello_len(*hybrid) == len)
+            .ok_or(RosenpassError::BufferSizeMismatch)
+    }
+
+    /// Recognize the KEM used by an [Envelope]<[RespHello]> and whether the hybrid X25519
+    /// exchange is used from the size of the message
+    pub fn from_resp_hello_len(len: usize) -> Result<(Self, bool), RosenpassError> {
+        (Self::ALL.into_iter())
+            .flat_map(|kem| [(kem, false), (kem, true)])
+            .find(|(kem, hybrid)| kem.resp_hello_len(*hybrid) == len)
+            .ok_or(RosenpassError::BufferSizeMismatch)
+    }
+}
+
+impl From<crate::config::EphemeralKemChoice> for EphemeralKemChoice {
+    fn from(v: crate::config::EphemeralKemChoice) -> Self {
+        match v {
+            crate::config::EphemeralKemChoice::Kyber512 => EphemeralKemChoice::Kyber512,
+            crate::config::EphemeralKemChoice::MlKem768 => EphemeralKemChoice::MlKem768,
+            crate::config::EphemeralKemChoice::MlKem1024 => EphemeralKemChoice::MlKem1024,
+        }
+    }
+}
+
+/// The AEAD used for the encrypted fields of handshake messages and for biscuits
+///
+/// Implements [Aead](rosenpass_cipher_traits::primitives::Aead) for the encrypted fields of
+/// handshake messages; biscuits use the extended-nonce variant returned by [Self::xaead].
+///
+/// Cookie replies are always encrypted with [XAead]: the responder sends them before it knows
+/// which peer an [InitHello] comes from.
+///
+/// The responder recognizes the AEAD used by an [InitHello] by trying each of them when
+/// decrypting [InitHello::pidic]; see [HandshakeState::decrypt_and_mix_any_aead].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AeadChoice {
+    /// ChaCha20-Poly1305 for handshake messages and XChaCha20-Poly1305 for biscuits; see
+    /// [Aead] and [XAead]
+    #[default]
+    ChaCha20Poly1305,
+    /// AES-256-GCM for handshake messages and XAES-256-GCM for biscuits; see [Aes256Gcm] and
+    /// [XAes256Gcm]
+    Aes256Gcm,
+}
+
+impl AeadChoice {
+    /// All supported AEADs
+    pub const ALL: [Self; 2] = [Self::ChaCha20Poly1305, Self::Aes256Gcm];
+
+    /// The extended-nonce variant of this AEAD
+    pub fn xaead(&self) -> XAeadChoice {
+        XAeadChoice(*self)
+    }
+}
+
+impl From<crate::config::AeadChoice> for AeadChoice {
+    fn from(v: crate::config::AeadChoice) -> Self {
+        match v {
+            crate::config::AeadChoice::ChaCha20Poly1305 => AeadChoice::ChaCha20Poly1305,
+            crate::config::AeadChoice::Aes256Gcm => AeadChoice::Aes256Gcm,
+        }
+    }
+}
+
+impl
+    rosenpass_cipher_traits::primitives::Aead<
+        { aead_chacha20poly1305::KEY_LEN },
+        { aead_chacha20poly1305::NONCE_LEN },
+        { aead_chacha20poly1305::TAG_LEN },
+    > for AeadChoice
+{
+    fn encrypt(
+        &self,
+        ciphertext: &mut [u8],
+        key: &[u8; aead_chacha20poly1305::KEY_LEN],
+        nonce: &[u8; aead_chacha20poly1305::NONCE_LEN],
+        ad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<(), AeadError> {
+        match self {
+            Self::ChaCha20Poly1305 => Aead.encrypt(ciphertext, key, nonce, ad, plaintext),
+            Self::Aes256Gcm => Aes256Gcm.encrypt(ciphertext, key, nonce, ad, plaintext),
+        }
+    }
+
+    fn decrypt(
+        &self,
+        plaintext: &mut [u8],
+        key: &[u8; aead_chacha20poly1305::KEY_LEN],
+        nonce: &[u8; aead_chacha20poly1305::NONCE_LEN],
+        ad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<(), AeadError> {
+        match self {
+            Self::ChaCha20Poly1305 => Aead.decrypt(plaintext, key, nonce, ad, ciphertext),
+            Self::Aes256Gcm => Aes256Gcm.decrypt(plaintext, key, nonce, ad, ciphertext),
+        }
+    }
+}
+
+/// The extended-nonce variant of an [AeadChoice]; see [AeadChoice::xaead]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct XAeadChoice(pub AeadChoice);
+
+impl
+    rosenpass_cipher_traits::primitives::Aead<
+        { aead_xchacha20poly1305::KEY_LEN },
+        { aead_xchacha20poly1305::NONCE_LEN },
+        { aead_xchacha20poly1305::TAG_LEN },
+    > for XAeadChoice
+{
+    fn encrypt(
+        &self,
+        ciphertext: &mut [u8],
+        key: &[u8; aead_xchacha20poly1305::KEY_LEN],
+        nonce: &[u8; aead_xchacha20poly1305::NONCE_LEN],
+        ad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<(), AeadError> {
+        match self.0 {
+            AeadChoice::ChaCha20Poly1305 => XAead.encrypt(ciphertext, key, nonce, ad, plaintext),
+            AeadChoice::Aes256Gcm => XAes256Gcm.encrypt(ciphertext, key, nonce, ad, plaintext),
+        }
+    }
+
+    fn decrypt(
+        &self,
+        plaintext: &mut [u8],
+        key: &[u8; aead_xchacha20poly1305::KEY_LEN],
+        nonce: &[u8; aead_xchacha20poly1305::NONCE_LEN],
+        ad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<(), AeadError> {
+        match self.0 {
+            AeadChoice::ChaCha20Poly1305 => XAead.decrypt(plaintext, key, nonce, ad, ciphertext),
+            AeadChoice::Aes256Gcm => XAes256Gcm.decrypt(plaintext, key, nonce, ad, ciphertext),
+        }
+    }
+}
+
+/// The cryptographic primitives used for the key exchange with a peer
+///
+/// Both peers must be configured to use the same cipher suite, but a server can use different
+/// suites for different peers, so a deployment can migrate to a new suite one peer at a time.
+/// The static KEM is not part of the suite; it is fixed by the format of the public keys.
+///
+/// The suite used by an incoming message is recognized on reception: the ephemeral KEM and the
+/// use of the hybrid X25519 exchange by the size of the message (see [EphemeralKemChoice]), the
+/// keyed hash by trying both (see [ProtocolVersion]) and the AEAD by trying each (see
+/// [AeadChoice]). Messages using another suite than the one configured for the peer are
+/// rejected with a [CipherSuiteMismatch] error.
+///
+/// See [CryptoServer::set_peer_cipher_suite].
+///
+/// # Examples
+///
+/// ```
+/// use rosenpass::protocol::{AeadChoice, CipherSuite, EphemeralKemChoice, ProtocolVersion};
+///
+/// let suite = CipherSuite::new(ProtocolVersion::V03);
+/// assert_eq!(suite.ephemeral_kem, EphemeralKemChoice::Kyber512);
+/// assert_eq!(suite.aead, AeadChoice::ChaCha20Poly1305);
+/// assert!(!suite.hybrid_x25519);
+///
+/// let suite = CipherSuite {
+///     ephemeral_kem: EphemeralKemChoice::MlKem768,
+///     ..suite
+/// };
+/// assert_eq!(suite.to_string(), "V03/MlKem768/ChaCha20Poly1305");
+///
+/// let suite = CipherSuite {
+///     hybrid_x25519: true,
+///     ..suite
+/// };
+/// assert_eq!(suite.to_string(), "V03/MlKem768+X25519/ChaCha20Poly1305");
+///
+/// let suite = CipherSuite {
+///     aead: AeadChoice::Aes256Gcm,
+///     ..suite
+/// };
+/// assert_eq!(suite.to_string(), "V03/MlKem768+X25519/Aes256Gcm");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CipherSuite {
+    /// Selects the keyed hash; see [ProtocolVersion::keyed_hash]
+    pub protocol_version: ProtocolVersion,
+    /// The KEM used for the ephemeral key exchange
+    pub ephemeral_kem: EphemeralKemChoice,
+    /// The AEAD used for encrypted fields
+    pub aead: AeadChoice,
+    /// Whether to complement the post-quantum ephemeral KEM with an X25519 key exchange
+    ///
+    /// This provides hybrid security for the exchanged keys, which is useful when Rosenpass is
+    /// used without WireGuard, e.g. when keys are written to files. The X25519 key exchange
+    /// adds [InitHello::xpki] and [RespHello::xcti] to the messages and is mixed into the
+    /// chaining key under the [hash_domains::hybrid_x25519] domain separator.
+    pub hybrid_x25519: bool,
+}
+
+impl CipherSuite {
+    /// The default cipher suite for the given protocol version
+    pub fn new(protocol_version: ProtocolVersion) -> Self {
+        Self {
+            protocol_version,
+            ephemeral_kem: EphemeralKemChoice::default(),
+            aead: AeadChoice::default(),
+            hybrid_x25519: false,
+        }
+    }
+
+    /// Returns the [KeyedHash] used by this suite
+    pub fn keyed_hash(&self) -> KeyedHash {
+        self.protocol_version.keyed_hash()
+    }
+}
+
+impl std::fmt::Display for CipherSuite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hybrid = if self.hybrid_x25519 { "+X25519" } else { "" };
+        write!(
+            f,
+            "{:?}/{:?}{hybrid}/{:?}",
+            self.protocol_version, self.ephemeral_kem, self.aead
+        )
+    }
+}
+
+/// Raised by [CryptoServer::handle_msg] when a message from a peer uses another [CipherSuite]
+/// than the one configured for the peer
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Cipher suite mismatch: peer is configured to use {configured}, but the message uses {received}")]
+pub struct CipherSuiteMismatch {
+    /// The suite configured for the peer
+    pub configured: CipherSuite,
+    /// The suite used by the message
+    pub received: CipherSuite,
+}
+
 /// A peer that the server can execute a key exchange with.
 ///
 /// Peers generally live in [CryptoServer::peers]. [PeerNo] captures an array
@@ -246,6 +709,12 @@ pub struct Peer {
     /// [CryptoServer::initiate_handshake] (and by proxy [CryptoServer::handle_initiation]),
     /// on its own accord. Instead, it will issue a
     pub initiation_requested: bool,
+    /// Set by [CryptoServer::force_initiation] to make [CryptoServer::poll] issue a
+    /// [PollResult::SendInitiation] event for this peer as soon as possible, even if the
+    /// current session and handshake are still fresh.
+    ///
+    /// Cleared once the event was issued.
+    pub initiation_forced: bool,
     /// Stores a known response for a [Envelope]<[InitConf]> message, i.e. a
     /// [Envelope]<[EmptyData]>.
     ///
@@ -256,10 +725,36 @@ pub struct Peer {
     /// This allows us to perform retransmission for the purpose of dealing with packet loss
     /// on the network without having to account for it in the cryptographic code itself.
     pub known_init_conf_response: Option<KnownInitConfResponse>,
-    /// The protocol version used by with this peer.
-    pub protocol_version: ProtocolVersion,
+    /// The primitives used for the key exchange with this peer
+    ///
+    /// See [CryptoServer::set_peer_cipher_suite].
+    pub cipher_suite: CipherSuite,
     /// Domain separator for generated OSKs
     pub osk_domain_separator: OskDomainSeparator,
+    /// The identity of the local server this peer talks to
+    ///
+    /// Messages from this peer are only accepted if they are addressed to this identity.
+    /// See [CryptoServer::add_peer_for_identity].
+    pub identity: IdentityPtr,
+    /// Second public key of the peer during a static key rotation
+    ///
+    /// See [crate::protocol::key_rotation].
+    pub alternate_spkt: Option<AlternatePublicKey>,
+}
+
+/// Second public key of a [Peer] during a static key rotation
+///
+/// Before the rotation, this is the next public key; after [CryptoServer::promote_peer_key],
+/// it is the previous public key, accepted until the end of the overlap period.
+/// See [crate::protocol::key_rotation].
+#[derive(Debug)]
+pub struct AlternatePublicKey {
+    /// Static Public Key Theirs
+    pub spkt: SPk,
+    /// The peer ID belonging to [Self::spkt]; also registered in [CryptoServer::index]
+    pub pidt: PeerId,
+    /// When the public key stops being accepted; `None` for a next public key
+    pub expires_at: Option<Timing>,
 }
 
 impl Peer {
@@ -283,10 +778,13 @@ impl Peer {
             biscuit_used: BiscuitId::zero(),
             session: None,
             initiation_requested: false,
+            initiation_forced: false,
             handshake: None,
             known_init_conf_response: None,
-            protocol_version,
+            cipher_suite: CipherSuite::new(protocol_version),
             osk_domain_separator: OskDomainSeparator::default(),
+            identity: IdentityPtr(0),
+            alternate_spkt: None,
         }
     }
 }
@@ -314,6 +812,8 @@ pub struct HandshakeState {
     pub sidr: SessionId,
     /// Chaining Key; i.e. the core cryptographic state
     pub ck: SecretHashDomainNamespace, // TODO: We should probably add an abstr
+    /// The AEAD used by [Self::encrypt_and_mix] and [Self::decrypt_and_mix]
+    pub aead: AeadChoice,
 }
 
 /// Indicates which role a party takes (or took) during the handshake
@@ -371,6 +871,10 @@ pub struct InitiatorHandshake {
     pub eski: ESk,
     /// Ephemeral Public Key Initiator; public key of the ephemeral keypair
     pub epki: EPk,
+    /// Secret key of the ephemeral X25519 keypair; only used in hybrid handshakes
+    pub eski_x25519: XSk,
+    /// Public key of the ephemeral X25519 keypair; only used in hybrid handshakes
+    pub epki_x25519: XPk,
 
     /// Unused; TODO: Remove
     pub tx_at: Timing,
@@ -696,6 +1200,19 @@ pub struct PeerCookieValuePtr(usize);
 /// somehow focus on the known response value but require access to the [CryptoServer].
 pub struct KnownInitConfResponsePtr(PeerNo);
 
+/// Valid index to [CryptoServer::identities]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct IdentityPtr(pub usize);
+
+/// Valid reference to one of the keypairs of an [Identity]; see [KeySlot]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct IdentityKeyPtr {
+    /// The identity the keypair belongs to
+    pub identity: IdentityPtr,
+    /// Which of the keypairs of the identity is referenced
+    pub slot: KeySlot,
+}
+
 /// Valid index to [CryptoServer::biscuit_keys]
 ///
 /// Provides appropriate utility functions, especially those that
@@ -721,7 +1238,9 @@ impl PeerPtr {
     ///
     /// See [Self]
     pub fn get<'a>(&self, srv: &'a CryptoServer) -> &'a Peer {
-        &srv.peers[self.0]
+        srv.peers[self.0]
+            .as_ref()
+            .expect("Peer referenced by PeerPtr was removed")
     }
 
     /// Mutable access to a peer.
@@ -734,7 +1253,9 @@ impl PeerPtr {
     ///
     /// See [Self]
     pub fn get_mut<'a>(&self, srv: &'a mut CryptoServer) -> &'a mut Peer {
-        &mut srv.peers[self.0]
+        srv.peers[self.0]
+            .as_mut()
+            .expect("Peer referenced by PeerPtr was removed")
     }
 
     /// Produce pointer to associated session
@@ -785,7 +1306,7 @@ impl IniHsPtr {
     ///
     /// See [PeerPtr]
     pub fn get<'a>(&self, srv: &'a CryptoServer) -> &'a Option<InitiatorHandshake> {
-        &srv.peers[self.0].handshake
+        &self.peer().get(srv).handshake
     }
 
     /// Mutable access to the handshake value
@@ -798,7 +1319,7 @@ impl IniHsPtr {
     ///
     /// See [PeerPtr]
     pub fn get_mut<'a>(&self, srv: &'a mut CryptoServer) -> &'a mut Option<InitiatorHandshake> {
-        &mut srv.peers[self.0].handshake
+        &mut self.peer().get_mut(srv).handshake
     }
 
     /// Access the associated peer
@@ -829,7 +1350,9 @@ impl IniHsPtr {
     ) -> Result<&'a mut InitiatorHandshake> {
         srv.register_session(hs.core.sidi, self.peer())?;
         self.take(srv);
-        self.peer().get_mut(srv).initiation_requested = false;
+        let peer = self.peer().get_mut(srv);
+        peer.initiation_requested = false;
+        peer.initiation_forced = false;
         Ok(self.peer().get_mut(srv).handshake.insert(hs))
     }
 
@@ -860,7 +1383,7 @@ impl SessionPtr {
     ///
     /// See [PeerPtr]
     pub fn get<'a>(&self, srv: &'a CryptoServer) -> &'a Option<Session> {
-        &srv.peers[self.0].session
+        &self.peer().get(srv).session
     }
 
     /// Mutable access to the session value
@@ -873,7 +1396,7 @@ impl SessionPtr {
     ///
     /// See [PeerPtr]
     pub fn get_mut<'a>(&self, srv: &'a mut CryptoServer) -> &'a mut Option<Session> {
-        &mut srv.peers[self.0].session
+        &mut self.peer().get_mut(srv).session
     }
 
     /// Access the associated peer
@@ -915,6 +1438,74 @@ impl SessionPtr {
     }
 }
 
+impl IdentityPtr {
+    /// Access the referenced identity
+    pub fn get<'a>(&self, srv: &'a CryptoServer) -> &'a Identity {
+        &srv.identities[self.0]
+    }
+
+    /// Mutable access to the referenced identity
+    pub fn get_mut<'a>(&self, srv: &'a mut CryptoServer) -> &'a mut Identity {
+        &mut srv.identities[self.0]
+    }
+
+    /// The keypairs of this identity that are accepted for incoming messages, starting with
+    /// the active keypair
+    pub fn key_ptrs(&self, srv: &CryptoServer) -> impl Iterator<Item = IdentityKeyPtr> {
+        let identity = *self;
+        let now = srv.timebase.now();
+        let alternate = (self.get(srv).alternate.as_ref())
+            .is_some_and(|alt| !alt.expires_at.is_some_and(|t| has_happened(t, now)));
+        [
+            Some(KeySlot::Active),
+            alternate.then_some(KeySlot::Alternate),
+        ]
+        .into_iter()
+        .flatten()
+        .map(move |slot| IdentityKeyPtr { identity, slot })
+    }
+}
+
+impl IdentityKeyPtr {
+    /// Reference the active keypair of `identity`
+    pub fn active(identity: IdentityPtr) -> Self {
+        Self {
+            identity,
+            slot: KeySlot::Active,
+        }
+    }
+
+    /// Static Secret Key Mine of the referenced keypair
+    ///
+    /// # Panic & Safety
+    ///
+    /// The function panics if the referenced alternate keypair was removed.
+    pub fn sskm<'a>(&self, srv: &'a CryptoServer) -> &'a Arc<SSk> {
+        let identity = self.identity.get(srv);
+        match self.slot {
+            KeySlot::Active => &identity.sskm,
+            KeySlot::Alternate => &Self::alternate(identity).sskm,
+        }
+    }
+
+    /// Static Public Key Mine of the referenced keypair
+    ///
+    /// # Panic & Safety
+    ///
+    /// The function panics if the referenced alternate keypair was removed.
+    pub fn spkm<'a>(&self, srv: &'a CryptoServer) -> &'a SPk {
+        let identity = self.identity.get(srv);
+        match self.slot {
+            KeySlot::Active => &identity.spkm,
+            KeySlot::Alternate => &Self::alternate(identity).spkm,
+        }
+    }
+
+    fn alternate(identity: &Identity) -> &AlternateKeypair {
+        (identity.alternate.as_ref()).expect("Keypair referenced by IdentityKeyPtr was removed")
+    }
+}
+
 impl BiscuitKeyPtr {
     /// Access the referenced biscuit key
     pub fn get<'a>(&self, srv: &'a CryptoServer) -> &'a BiscuitKey {
@@ -950,7 +1541,8 @@ impl PeerCookieValuePtr {
     ///
     /// See [PeerPtr]
     pub fn get<'a>(&self, srv: &'a CryptoServer) -> Option<&'a CookieStore<COOKIE_SECRET_LEN>> {
-        srv.peers[self.0]
+        PeerPtr(self.0)
+            .get(srv)
             .handshake
             .as_ref()
             .map(|v| &v.cookie_value)
@@ -1172,15 +1764,14 @@ impl CryptoServer {
     /// StaticKem.keygen(sskm.secret_mut(), spkm.deref_mut())?;
     ///
     /// let srv = CryptoServer::new(sskm, spkm.clone());
-    /// assert_eq!(srv.spkm, spkm);
+    /// assert_eq!(srv.identities[0].spkm, spkm);
     ///
     /// Ok::<(), anyhow::Error>(())
     /// ```
     pub fn new(sk: SSk, pk: SPk) -> CryptoServer {
         let tb = Timebase::default();
         CryptoServer {
-            sskm: sk,
-            spkm: pk,
+            identities: vec![Identity::new(sk, pk)],
 
             // Defaults
             timebase: tb,
@@ -1191,44 +1782,387 @@ impl CryptoServer {
             known_response_hasher: KnownResponseHasher::new(),
             peer_poll_off: 0,
             cookie_secrets: [CookieStore::new(), CookieStore::new()],
+            #[cfg(feature = "internal_deterministic")]
+            rng: None,
+            decapsulation: None,
         }
     }
 
+    /// Construct a deterministic CryptoServer
+    ///
+    /// All randomness is drawn from `rng`; see [Self::rng]. Use
+    /// [rosenpass_util::time::with_clock] to make the timing information deterministic as well.
+    /// The handshakes performed by this server are only as secure as `rng`, so this is only
+    /// useful for generating test vectors.
+    ///
+    /// Only available with the `internal_deterministic` feature.
+    #[cfg(feature = "internal_deterministic")]
+    pub fn new_deterministic(sk: SSk, pk: SPk, rng: InjectedRng) -> Self {
+        let mut rng = Some(rng);
+        let mut srv = with_rng(&mut rng, || Self::new(sk, pk));
+        srv.rng = rng;
+        srv
+    }
+
+    /// Run `f` with [Self::rng] as the source of randomness, if set
+    #[cfg(feature = "internal_deterministic")]
+    fn with_injected_rng<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let mut rng = self.rng.take();
+        let res = with_rng(&mut rng, || f(self));
+        self.rng = rng;
+        res
+    }
+
     /// Iterate over the available biscuit keys by their pointers [BiscuitKeyPtr]
     pub fn biscuit_key_ptrs(&self) -> impl Iterator<Item = BiscuitKeyPtr> {
         (0..self.biscuit_keys.len()).map(BiscuitKeyPtr)
     }
 
-    /// Iterate over the available cookie secrets by their pointers [ServerCookieSecretPtr]
-    pub fn cookie_secret_ptrs(&self) -> impl Iterator<Item = ServerCookieSecretPtr> {
-        (0..self.cookie_secrets.len()).map(ServerCookieSecretPtr)
-    }
+    /// Iterate over the available cookie secrets by their pointers [ServerCookieSecretPtr]
+    pub fn cookie_secret_ptrs(&self) -> impl Iterator<Item = ServerCookieSecretPtr> {
+        (0..self.cookie_secrets.len()).map(ServerCookieSecretPtr)
+    }
+
+    /// Add another static keypair to the server, returning the new identity
+    ///
+    /// Peers are bound to the identity using [Self::add_peer_for_identity]. Incoming
+    /// handshakes are associated with an identity through their [Envelope::mac], which is
+    /// keyed with the public key of the recipient (see [Self::lookup_identity]).
+    ///
+    /// ```
+    /// use std::ops::DerefMut;
+    /// use rosenpass::protocol::basic_types::{SSk, SPk};
+    /// use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
+    /// use rosenpass::protocol::{CryptoServer, IdentityPtr, ProtocolVersion};
+    /// use rosenpass_ciphers::StaticKem;
+    /// use rosenpass_cipher_traits::primitives::Kem;
+    ///
+    /// rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    ///
+    /// let keypair = || -> anyhow::Result<(SSk, SPk)> {
+    ///     let (mut sk, mut pk) = (SSk::zero(), SPk::zero());
+    ///     StaticKem.keygen(sk.secret_mut(), pk.deref_mut())?;
+    ///     Ok((sk, pk))
+    /// };
+    ///
+    /// let (sskm, spkm) = keypair()?;
+    /// let mut srv = CryptoServer::new(sskm, spkm);
+    ///
+    /// let (sskm2, spkm2) = keypair()?;
+    /// let tenant = srv.add_identity(sskm2, spkm2.clone())?;
+    /// assert_eq!(tenant, IdentityPtr(1));
+    /// assert_eq!(tenant.get(&srv).spkm, spkm2);
+    ///
+    /// let (_, spkt) = keypair()?;
+    /// let peer = srv.add_peer_for_identity(tenant, None, spkt, ProtocolVersion::V03, OskDomainSeparator::default())?;
+    /// assert_eq!(peer.get(&srv).identity, tenant);
+    ///
+    /// Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn add_identity(&mut self, sk: SSk, pk: SPk) -> Result<IdentityPtr> {
+        ensure!(
+            !self.identities.iter().any(|id| id.has_public_key(&pk)),
+            "Cannot add identity; an identity with this public key is already registered."
+        );
+        self.identities.push(Identity::new(sk, pk));
+        Ok(IdentityPtr(self.identities.len() - 1))
+    }
+
+    /// Iterate over the identities of this server by their pointers [IdentityPtr]
+    pub fn identity_ptrs(&self) -> impl Iterator<Item = IdentityPtr> {
+        (0..self.identities.len()).map(IdentityPtr)
+    }
+
+    /// Look up an identity by the peer ID our peers know it by; see [Identity::pidm]
+    ///
+    /// Both hash functions are tried, since the peer ID depends on the protocol version.
+    pub fn find_identity(&self, id: PeerId) -> Option<IdentityPtr> {
+        self.identity_ptrs().find(|identity| {
+            [
+                KeyedHash::keyed_shake256(),
+                KeyedHash::incorrect_hmac_blake2b(),
+            ]
+            .into_iter()
+            .any(|keyed_hash| identity.get(self).pidm(keyed_hash).ok() == Some(id))
+        })
+    }
+
+    /// Determine the identity and keypair an incoming message is addressed to
+    ///
+    /// The [Envelope::mac] is keyed with the public key of the recipient, so checking it for
+    /// every keypair tells us which one the sender meant without performing any expensive
+    /// cryptographic operations. Both hash functions are tried, since the protocol version of
+    /// the sender is not known yet.
+    ///
+    /// Besides the keypairs of multiple identities, this distinguishes the keypairs used
+    /// during a static key rotation (see [crate::protocol::key_rotation]).
+    pub fn lookup_identity<M: AsBytes + FromBytes>(
+        &self,
+        msg: &Envelope<M>,
+    ) -> Result<IdentityKeyPtr> {
+        // Keep the behavior of servers with a single keypair; the seal is checked later anyway
+        if self.identities.len() == 1 && self.identities[0].alternate.is_none() {
+            return Ok(IdentityKeyPtr::active(IdentityPtr(0)));
+        }
+
+        for identity in self.identity_ptrs() {
+            for key in identity.key_ptrs(self) {
+                for keyed_hash in [
+                    KeyedHash::keyed_shake256(),
+                    KeyedHash::incorrect_hmac_blake2b(),
+                ] {
+                    if msg.check_seal(self, key, keyed_hash)? {
+                        return Ok(key);
+                    }
+                }
+            }
+        }
+        bail!("Message is not addressed to any of our identities")
+    }
+
+    /// Iterate over all peers, starting with the `n`th peer, wrapping at the
+    /// end of the peers vec so that also all peers from index 0 to `n - 1` are
+    /// yielded
+    ///
+    /// Peers that have been removed using [Self::remove_peer] are skipped.
+    pub fn peer_ptrs_off(&self, n: usize) -> impl Iterator<Item = PeerPtr> + '_ {
+        let l = self.peers.len();
+        (0..l)
+            .map(move |i| (i + n) % l)
+            .filter(|&i| self.peers[i].is_some())
+            .map(PeerPtr)
+    }
+
+    /// Iterate over all peers that have not been removed
+    pub fn peer_ptrs(&self) -> impl Iterator<Item = PeerPtr> + '_ {
+        self.peer_ptrs_off(0)
+    }
+
+    /// Check whether the given peer pointer refers to a peer that exists and
+    /// has not been removed using [Self::remove_peer]
+    pub fn contains_peer(&self, peer: PeerPtr) -> bool {
+        matches!(self.peers.get(peer.0), Some(Some(_)))
+    }
+
+    /// Add a peer with an optional pre shared key (`psk`), its public key (`pk`) and the peer's
+    /// protocol version (`protocol_version`).
+    ///
+    /// ```
+    /// use std::ops::DerefMut;
+    /// use rosenpass::protocol::basic_types::{SSk, SPk, SymKey};
+    /// use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
+    /// use rosenpass::protocol::{CryptoServer, ProtocolVersion};
+    /// use rosenpass_ciphers::StaticKem;
+    /// use rosenpass_cipher_traits::primitives::Kem;
+    ///
+    /// rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    ///
+    /// let (mut sskm, mut spkm) = (SSk::zero(), SPk::zero());
+    /// StaticKem.keygen(sskm.secret_mut(), spkm.deref_mut())?;
+    /// let mut srv = CryptoServer::new(sskm, spkm);
+    ///
+    /// let (mut sskt, mut spkt) = (SSk::zero(), SPk::zero());
+    /// StaticKem.keygen(sskt.secret_mut(), spkt.deref_mut())?;
+    ///
+    /// let psk = SymKey::random();
+    /// // We use the latest protocol version for the example.
+    /// let peer = srv.add_peer(Some(psk), spkt.clone(), ProtocolVersion::V03, OskDomainSeparator::for_wireguard_psk())?;
+    ///
+    /// assert_eq!(peer.get(&srv).spkt, spkt);
+    ///
+    /// Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn add_peer(
+        &mut self,
+        psk: Option<SymKey>,
+        pk: SPk,
+        protocol_version: ProtocolVersion,
+        osk_domain_separator: OskDomainSeparator,
+    ) -> Result<PeerPtr> {
+        self.add_peer_for_identity(
+            IdentityPtr(0),
+            psk,
+            pk,
+            protocol_version,
+            osk_domain_separator,
+        )
+    }
+
+    /// Add a peer talking to the given `identity` of this server; see [Self::add_identity]
+    ///
+    /// Peers are still identified by their public key alone, so the same peer can not be
+    /// added for two different identities.
+    ///
+    /// See [Self::add_peer] for a description of the remaining parameters.
+    pub fn add_peer_for_identity(
+        &mut self,
+        identity: IdentityPtr,
+        psk: Option<SymKey>,
+        pk: SPk,
+        protocol_version: ProtocolVersion,
+        osk_domain_separator: OskDomainSeparator,
+    ) -> Result<PeerPtr> {
+        ensure!(
+            identity.0 < self.identities.len(),
+            "Cannot add peer for identity {:?}; no such identity registered.",
+            identity
+        );
+        let peer = Peer {
+            psk: psk.unwrap_or_else(SymKey::zero),
+            spkt: pk,
+            biscuit_used: BiscuitId::zero(),
+            session: None,
+            handshake: None,
+            known_init_conf_response: None,
+            initiation_requested: false,
+            initiation_forced: false,
+            cipher_suite: CipherSuite::new(protocol_version),
+            osk_domain_separator,
+            identity,
+            alternate_spkt: None,
+        };
+        let peerid = peer.pidt()?;
+        let peerno = self.peers.len();
+        match self.index.entry(PeerIndexKey::Peer(peerid)) {
+            Occupied(_) => bail!(
+                "Cannot insert peer with id {:?}; peer with this id already registered.",
+                peerid
+            ),
+            Vacant(e) => e.insert(peerno),
+        };
+        self.peers.push(Some(peer));
+        Ok(PeerPtr(peerno))
+    }
+
+    /// Choose the [CipherSuite] used for the key exchange with `peer`
+    ///
+    /// The peer must be configured to use the same suite; handshake messages using another
+    /// suite are rejected with a [CipherSuiteMismatch] error. An ongoing handshake with the
+    /// peer is aborted, since its messages were produced with the previous suite.
+    ///
+    /// The protocol version is chosen when adding the peer (see [Self::add_peer]) and can not
+    /// be changed here, since the peer ID depends on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rosenpass::protocol::{testutils::ServerForTesting, CipherSuite, EphemeralKemChoice, ProtocolVersion};
+    ///
+    /// let (peer, _, mut srv) = ServerForTesting::new(ProtocolVersion::V03)?.tuple();
+    /// assert_eq!(peer.get(&srv).cipher_suite, CipherSuite::new(ProtocolVersion::V03));
+    ///
+    /// let suite = CipherSuite {
+    ///     ephemeral_kem: EphemeralKemChoice::MlKem1024,
+    ///     ..CipherSuite::new(ProtocolVersion::V03)
+    /// };
+    /// srv.set_peer_cipher_suite(peer, suite.clone())?;
+    /// assert_eq!(peer.get(&srv).cipher_suite, suite);
+    ///
+    /// assert!(srv.set_peer_cipher_suite(peer, CipherSuite::new(ProtocolVersion::V02)).is_err());
+    ///
+    /// Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_peer_cipher_suite(&mut self, peer: PeerPtr, suite: CipherSuite) -> Result<()> {
+        ensure!(
+            self.contains_peer(peer),
+            "Cannot set cipher suite for peer {:?}; no such peer registered.",
+            peer
+        );
+        let current = &peer.get(self).cipher_suite;
+        ensure!(
+            current.protocol_version == suite.protocol_version,
+            "Cannot change the protocol version of peer {:?} from {:?} to {:?}; \
+            the peer has to be re-added instead.",
+            peer,
+            current.protocol_version,
+            suite.protocol_version
+        );
+        if *current != suite {
+            peer.hs().take(self);
+            peer.get_mut(self).cipher_suite = suite;
+        }
+        Ok(())
+    }
+
+    /// Remove a peer from the server.
+    ///
+    /// This erases the peer's [Session], its [InitiatorHandshake] (including the
+    /// [InitiatorHandshake::cookie_value]), the [KnownInitConfResponse] cache and all
+    /// entries in [Self::index] that refer to the peer. The slot in [Self::peers]
+    /// is left as a tombstone, so all other [PeerPtr] values remain valid; slots are
+    /// never reused.
+    ///
+    /// Any key previously exchanged with this peer is not deleted by this function;
+    /// the caller is responsible for revoking it.
+    ///
+    /// ```
+    /// use std::ops::DerefMut;
+    /// use rosenpass::protocol::basic_types::{SSk, SPk};
+    /// use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
+    /// use rosenpass::protocol::{CryptoServer, ProtocolVersion};
+    /// use rosenpass_ciphers::StaticKem;
+    /// use rosenpass_cipher_traits::primitives::Kem;
+    ///
+    /// rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    ///
+    /// let (mut sskm, mut spkm) = (SSk::zero(), SPk::zero());
+    /// StaticKem.keygen(sskm.secret_mut(), spkm.deref_mut())?;
+    /// let mut srv = CryptoServer::new(sskm, spkm);
+    ///
+    /// let add_peer = |srv: &mut CryptoServer| {
+    ///     let (mut sskt, mut spkt) = (SSk::zero(), SPk::zero());
+    ///     StaticKem.keygen(sskt.secret_mut(), spkt.deref_mut())?;
+    ///     srv.add_peer(None, spkt, ProtocolVersion::V03, OskDomainSeparator::default())
+    /// };
+    ///
+    /// let a = add_peer(&mut srv)?;
+    /// let b = add_peer(&mut srv)?;
+    /// let pidt_a = a.get(&srv).pidt()?;
+    ///
+    /// srv.remove_peer(a)?;
+    /// assert!(!srv.contains_peer(a));
+    /// assert!(srv.contains_peer(b));
+    /// assert_eq!(srv.find_peer(pidt_a), None);
+    /// assert_eq!(srv.peer_ptrs().collect::<Vec<_>>(), vec![b]);
+    ///
+    /// // Removing a peer twice is an error
+    /// assert!(srv.remove_peer(a).is_err());
+    ///
+    /// Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn remove_peer(&mut self, peer: PeerPtr) -> Result<()> {
+        ensure!(
+            self.contains_peer(peer),
+            "Cannot remove peer {:?}; no such peer registered.",
+            peer
+        );
+
+        // Use the accessors so the session index is updated as usual
+        peer.hs().take(self);
+        peer.session().take(self);
+        peer.known_init_conf_response().remove(self);
 
-    /// Calculate the peer ID of this CryptoServer
-    #[rustfmt::skip]
-    pub fn pidm(&self, keyed_hash: KeyedHash) -> Result<PeerId> {
-        Ok(Public::new(
-            hash_domains::peerid(keyed_hash)?
-                .mix(self.spkm.deref())?
-                .into_value()))
-    }
+        let peerid = peer.get(self).pidt()?;
+        self.index.remove(&PeerIndexKey::Peer(peerid));
+        // Defensive: Make sure no index entry can resolve to the tombstone
+        self.index.retain(|_, no| *no != peer.0);
 
-    /// Iterate over all peers, starting with the `n`th peer, wrapping at the
-    /// end of the peers vec so that also all peers from index 0 to `n - 1` are
-    /// yielded
-    pub fn peer_ptrs_off(&self, n: usize) -> impl Iterator<Item = PeerPtr> {
-        let l = self.peers.len();
-        (0..l).map(move |i| PeerPtr((i + n) % l))
+        self.peers[peer.0] = None;
+        Ok(())
     }
 
-    /// Add a peer with an optional pre shared key (`psk`), its public key (`pk`) and the peer's
-    /// protocol version (`protocol_version`).
+    /// Request an immediate handshake with the given peer, e.g. to force a rekey
+    ///
+    /// The next call to [Self::poll] will return [PollResult::SendInitiation] for this peer,
+    /// even if the current session or the ongoing handshake would not call for a new
+    /// handshake yet. Acting on that event through [Self::initiate_handshake] displaces any
+    /// ongoing handshake.
     ///
     /// ```
     /// use std::ops::DerefMut;
-    /// use rosenpass::protocol::basic_types::{SSk, SPk, SymKey};
+    /// use rosenpass::protocol::basic_types::{SSk, SPk};
     /// use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
-    /// use rosenpass::protocol::{CryptoServer, ProtocolVersion};
+    /// use rosenpass::protocol::{CryptoServer, PollResult, ProtocolVersion};
     /// use rosenpass_ciphers::StaticKem;
     /// use rosenpass_cipher_traits::primitives::Kem;
     ///
@@ -1240,44 +2174,27 @@ impl CryptoServer {
     ///
     /// let (mut sskt, mut spkt) = (SSk::zero(), SPk::zero());
     /// StaticKem.keygen(sskt.secret_mut(), spkt.deref_mut())?;
+    /// let peer = srv.add_peer(None, spkt, ProtocolVersion::V03, OskDomainSeparator::default())?;
     ///
-    /// let psk = SymKey::random();
-    /// // We use the latest protocol version for the example.
-    /// let peer = srv.add_peer(Some(psk), spkt.clone(), ProtocolVersion::V03, OskDomainSeparator::for_wireguard_psk())?;
+    /// // The initiation is requested once; here, the application ignores the request
+    /// assert!(matches!(srv.poll()?, PollResult::SendInitiation(p) if p == peer));
+    /// assert!(matches!(srv.poll()?, PollResult::Sleep(_)));
     ///
-    /// assert_eq!(peer.get(&srv).spkt, spkt);
+    /// // Forcing the initiation makes poll request it again
+    /// srv.force_initiation(peer)?;
+    /// assert!(matches!(srv.poll()?, PollResult::SendInitiation(p) if p == peer));
+    /// assert!(matches!(srv.poll()?, PollResult::Sleep(_)));
     ///
     /// Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn add_peer(
-        &mut self,
-        psk: Option<SymKey>,
-        pk: SPk,
-        protocol_version: ProtocolVersion,
-        osk_domain_separator: OskDomainSeparator,
-    ) -> Result<PeerPtr> {
-        let peer = Peer {
-            psk: psk.unwrap_or_else(SymKey::zero),
-            spkt: pk,
-            biscuit_used: BiscuitId::zero(),
-            session: None,
-            handshake: None,
-            known_init_conf_response: None,
-            initiation_requested: false,
-            protocol_version,
-            osk_domain_separator,
-        };
-        let peerid = peer.pidt()?;
-        let peerno = self.peers.len();
-        match self.index.entry(PeerIndexKey::Peer(peerid)) {
-            Occupied(_) => bail!(
-                "Cannot insert peer with id {:?}; peer with this id already registered.",
-                peerid
-            ),
-            Vacant(e) => e.insert(peerno),
-        };
-        self.peers.push(peer);
-        Ok(PeerPtr(peerno))
+    pub fn force_initiation(&mut self, peer: PeerPtr) -> Result<()> {
+        ensure!(
+            self.contains_peer(peer),
+            "Cannot force initiation with peer {:?}; no such peer registered.",
+            peer
+        );
+        peer.get_mut(self).initiation_forced = true;
+        Ok(())
     }
 
     /// Register a new session
@@ -1469,8 +2386,11 @@ impl Peer {
             handshake: None,
             known_init_conf_response: None,
             initiation_requested: false,
-            protocol_version,
+            initiation_forced: false,
+            cipher_suite: CipherSuite::new(protocol_version),
             osk_domain_separator,
+            identity: IdentityPtr(0),
+            alternate_spkt: None,
         }
     }
 
@@ -1480,13 +2400,32 @@ impl Peer {
     /// # Examples
     ///
     /// See example in [Self].
-    #[rustfmt::skip]
     pub fn pidt(&self) -> Result<PeerId> {
+        Self::pidt_of(&self.spkt, self.cipher_suite.keyed_hash())
+    }
+
+    /// Calculate the peer ID for the public key `spkt`; see [Self::pidt]
+    #[rustfmt::skip]
+    pub fn pidt_of(spkt: &SPk, keyed_hash: KeyedHash) -> Result<PeerId> {
         Ok(Public::new(
-            hash_domains::peerid(self.protocol_version.keyed_hash())?
-                .mix(self.spkt.deref())?
+            hash_domains::peerid(keyed_hash)?
+                .mix(spkt.deref())?
                 .into_value()))
     }
+
+    /// Check whether `pk` is one of the public keys of this peer
+    pub fn has_public_key(&self, pk: &SPk) -> bool {
+        &self.spkt == pk || (self.alternate_spkt.as_ref()).is_some_and(|alt| &alt.spkt == pk)
+    }
+
+    /// The public key of the peer belonging to the given peer ID; this is
+    /// [Self::alternate_spkt] if the peer ID was derived from it and [Self::spkt] otherwise
+    pub fn spkt_for(&self, pidt: PeerId) -> &SPk {
+        match &self.alternate_spkt {
+            Some(alt) if alt.pidt == pidt => &alt.spkt,
+            _ => &self.spkt,
+        }
+    }
 }
 
 impl Session {
@@ -1631,9 +2570,12 @@ impl Mortal for SessionPtr {
 
 impl Mortal for BiscuitKeyPtr {
     /// At [BiscuitKey::created_at]
+    ///
+    /// Keys restored through [CryptoServer::restore_state] may have been created before the
+    /// [CryptoServer::timebase], so only [BCE] denotes an unused key.
     fn created_at(&self, srv: &CryptoServer) -> Option<Timing> {
         let t = self.get(srv).created_at;
-        if t < 0.0 {
+        if t <= BCE {
             None
         } else {
             Some(t)
@@ -1653,9 +2595,12 @@ impl Mortal for BiscuitKeyPtr {
 
 impl Mortal for ServerCookieSecretPtr {
     /// At [CookieSecret::created_at]
+    ///
+    /// Keys restored through [CryptoServer::restore_state] may have been created before the
+    /// [CryptoServer::timebase], so only [BCE] denotes an unused key.
     fn created_at(&self, srv: &CryptoServer) -> Option<Timing> {
         let t = self.get(srv).created_at;
-        if t < 0.0 {
+        if t <= BCE {
             None
         } else {
             Some(t)
@@ -1699,9 +2644,12 @@ impl Mortal for PeerCookieValuePtr {
 
 impl Mortal for KnownInitConfResponsePtr {
     /// At [KnownInitConfResponse::received_at]
+    ///
+    /// Responses restored through [CryptoServer::restore_snapshot] may have been received before
+    /// the [CryptoServer::timebase], so only [BCE] denotes an unused response.
     fn created_at(&self, srv: &CryptoServer) -> Option<Timing> {
         let t = self.get(srv)?.received_at;
-        if t < 0.0 {
+        if t <= BCE {
             None
         } else {
             Some(t)
@@ -1865,17 +2813,27 @@ impl CryptoServer {
     ///
     /// See [Self::poll] on how to use this function with poll.
     pub fn initiate_handshake(&mut self, peer: PeerPtr, tx_buf: &mut [u8]) -> Result<usize> {
+        #[cfg(feature = "internal_deterministic")]
+        if self.rng.is_some() {
+            return self.with_injected_rng(|srv| srv.initiate_handshake(peer, tx_buf));
+        }
+
         // NOTE retransmission? yes if initiator, no if responder
         // TODO remove unnecessary copying between global tx_buf and per-peer buf
         // TODO move retransmission storage to io server
         //
         // Envelope::<InitHello>::default(); // TODO
-        let mut msg = truncating_cast_into::<Envelope<InitHello>>(tx_buf)?;
-        self.handle_initiation(peer, &mut msg.payload)?;
-        let len = self.seal_and_commit_msg(peer, MsgType::InitHello, &mut msg)?;
-        peer.hs()
-            .store_msg_for_retransmission(self, msg.as_bytes())?;
-        Ok(len)
+        let suite = &peer.get(self).cipher_suite;
+        with_ephemeral_kem!(suite.ephemeral_kem, suite.hybrid_x25519, EKem, X25519_LEN => {
+            let mut msg = truncating_cast_into::<
+                Envelope<InitHello<{ EKem::PK_LEN }, X25519_LEN>>,
+            >(tx_buf)?;
+            self.handle_initiation(&EKem::default(), peer, &mut msg.payload)?;
+            let len = self.seal_and_commit_msg(peer, MsgType::InitHello, &mut msg)?;
+            peer.hs()
+                .store_msg_for_retransmission(self, msg.as_bytes())?;
+            Ok(len)
+        })
     }
 }
 
@@ -1887,6 +2845,13 @@ pub struct HandleMsgResult {
     ///
     /// The key can then be accessed through the session; see [PeerPtr::session].
     pub exchanged_with: Option<PeerPtr>,
+    /// If the message was authenticated as coming from a peer, then this field indicates which
+    /// peer.
+    ///
+    /// Only set for messages that can not be replayed, so the address the message was received
+    /// from can be used to reach the peer from now on. Messages that are merely retransmitted,
+    /// such as [MsgType::InitConf] messages answered from the cache, are not included.
+    pub authenticated_by: Option<PeerPtr>,
     /// If processing the message yielded a response, then this field indicates its size.
     ///
     /// The message data will be in a buffer given to function as a mutable parameter.
@@ -1955,10 +2920,13 @@ impl CryptoServer {
         tx_buf: &mut [u8],
         host_identification: &H,
     ) -> Result<HandleMsgResult> {
-        let mut active_cookie_value: Option<[u8; COOKIE_SIZE]> = None;
-        let mut rx_cookie = [0u8; COOKIE_SIZE];
-        let mut rx_mac = [0u8; MAC_SIZE];
-        let mut rx_sid = [0u8; 4];
+        #[cfg(feature = "internal_deterministic")]
+        if self.rng.is_some() {
+            return self.with_injected_rng(|srv| {
+                srv.handle_msg_under_load(rx_buf, tx_buf, host_identification)
+            });
+        }
+
         let msg_type: Result<MsgType, _> = rx_buf[0].try_into();
         match msg_type {
             Ok(MsgType::InitConf) => {
@@ -1981,56 +2949,155 @@ impl CryptoServer {
             }
         }
 
-        for cookie_secret in self.active_or_retired_cookie_secrets() {
-            if let Some(cookie_secret) = cookie_secret {
-                let cookie_secret = cookie_secret.get(self).value.secret();
-                let mut cookie_value = [0u8; 16];
-                cookie_value.copy_from_slice(
-                    &hash_domains::cookie_value(KeyedHash::keyed_shake256())?
-                        .mix(cookie_secret)?
-                        .mix(host_identification.encode())?
-                        .into_value()[..16],
-                );
+        // The ephemeral KEM used is recognized by the size of the message
+        let (ekem, hybrid) = EphemeralKemChoice::from_init_hello_len(rx_buf.len())?;
+        with_ephemeral_kem!(ekem, hybrid, EKem, X25519_LEN => {
+            self.handle_init_hello_under_load::<{ EKem::PK_LEN }, X25519_LEN, H>(
+                rx_buf,
+                tx_buf,
+                host_identification,
+            )
+        })
+    }
 
-                // Most recently filled value is active cookie value
-                if active_cookie_value.is_none() {
-                    active_cookie_value = Some(cookie_value);
-                }
+    /// Whether `rx_buf` is an [InitHello] carrying a valid cookie for `host_identification`
+    ///
+    /// Such a message is processed by [Self::handle_msg_under_load] rather than answered with a
+    /// [CookieReply], and the cookie proves that the sender can receive messages at the address
+    /// identified by `host_identification`. Malformed messages never carry a valid cookie.
+    #[cfg(feature = "experiment_cookie_dos_mitigation")]
+    pub fn has_valid_cookie<H: HostIdentification>(
+        &mut self,
+        rx_buf: &[u8],
+        host_identification: &H,
+    ) -> bool {
+        #[cfg(feature = "internal_deterministic")]
+        if self.rng.is_some() {
+            return self.with_injected_rng(|srv| srv.has_valid_cookie(rx_buf, host_identification));
+        }
 
-                let mut expected = [0u8; COOKIE_SIZE];
+        if rx_buf.first() != Some(&(MsgType::InitHello as u8)) {
+            return false;
+        }
+        let Ok((ekem, hybrid)) = EphemeralKemChoice::from_init_hello_len(rx_buf.len()) else {
+            return false;
+        };
+        with_ephemeral_kem!(ekem, hybrid, EKem, X25519_LEN => {
+            self.init_hello_has_valid_cookie::<{ EKem::PK_LEN }, X25519_LEN, H>(
+                rx_buf,
+                host_identification,
+            )
+        })
+        .unwrap_or(false)
+    }
 
-                let msg_in = Ref::<&[u8], Envelope<InitHello>>::new(rx_buf)
-                    .ok_or(RosenpassError::BufferSizeMismatch)?;
-                expected.copy_from_slice(
-                    &hash_domains::cookie(KeyedHash::keyed_shake256())?
-                        .mix(&cookie_value)?
-                        .mix(&msg_in.as_bytes()[span_of!(Envelope<InitHello>, msg_type..cookie)])?
-                        .into_value()[..16],
-                );
+    /// Whether `rx_buf` is an [InitHello] carrying a valid cookie for `host_identification`
+    ///
+    /// Without cookie-based DoS mitigation, no message carries a valid cookie.
+    #[cfg(not(feature = "experiment_cookie_dos_mitigation"))]
+    #[inline]
+    pub fn has_valid_cookie<H: HostIdentification>(
+        &mut self,
+        _rx_buf: &[u8],
+        _host_identification: &H,
+    ) -> bool {
+        false
+    }
 
-                rx_cookie.copy_from_slice(&msg_in.cookie);
-                rx_mac.copy_from_slice(&msg_in.mac);
-                rx_sid.copy_from_slice(&msg_in.payload.sidi);
+    /// The cookie values of `host_identification` under the active and the retired cookie
+    /// secrets, starting with the active one; see [Self::active_or_retired_cookie_secrets]
+    #[cfg(feature = "experiment_cookie_dos_mitigation")]
+    fn cookie_values<H: HostIdentification>(
+        &mut self,
+        host_identification: &H,
+    ) -> Result<Vec<[u8; COOKIE_SIZE]>> {
+        let mut values = Vec::new();
+        for cookie_secret in self
+            .active_or_retired_cookie_secrets()
+            .into_iter()
+            .flatten()
+        {
+            let cookie_secret = cookie_secret.get(self).value.secret();
+            let mut cookie_value = [0u8; COOKIE_SIZE];
+            cookie_value.copy_from_slice(
+                &hash_domains::cookie_value(KeyedHash::keyed_shake256())?
+                    .mix(cookie_secret)?
+                    .mix(host_identification.encode())?
+                    .into_value()[..COOKIE_SIZE],
+            );
+            values.push(cookie_value);
+        }
+        Ok(values)
+    }
 
-                //If valid cookie is found, process message
-                if constant_time::memcmp(&rx_cookie, &expected) {
-                    log::debug!(
-                        "Rx {:?} from {} under load, valid cookie",
-                        msg_type,
-                        host_identification
-                    );
-                    let result = self.handle_msg(rx_buf, tx_buf)?;
-                    return Ok(result);
-                }
-            } else {
-                break;
+    /// Cookie validation for [Self::has_valid_cookie], once the message is known to be an
+    /// [InitHello] with an ephemeral public key of length `EKEM_PK_LEN` and an X25519 public
+    /// key of length `X25519_LEN`
+    #[cfg(feature = "experiment_cookie_dos_mitigation")]
+    fn init_hello_has_valid_cookie<
+        const EKEM_PK_LEN: usize,
+        const X25519_LEN: usize,
+        H: HostIdentification,
+    >(
+        &mut self,
+        rx_buf: &[u8],
+        host_identification: &H,
+    ) -> Result<bool> {
+        let msg_in = Ref::<&[u8], Envelope<InitHello<EKEM_PK_LEN, X25519_LEN>>>::new(rx_buf)
+            .ok_or(RosenpassError::BufferSizeMismatch)?;
+        for cookie_value in self.cookie_values(host_identification)? {
+            let mut expected = [0u8; COOKIE_SIZE];
+            expected.copy_from_slice(
+                &hash_domains::cookie(KeyedHash::keyed_shake256())?
+                    .mix(&cookie_value)?
+                    .mix(
+                        &msg_in.as_bytes()[span_of!(
+                            Envelope<InitHello<EKEM_PK_LEN, X25519_LEN>>,
+                            msg_type..cookie
+                        )],
+                    )?
+                    .into_value()[..COOKIE_SIZE],
+            );
+            if constant_time::memcmp(&msg_in.cookie, &expected) {
+                return Ok(true);
             }
         }
+        Ok(false)
+    }
+
+    /// Cookie validation for [Self::handle_msg_under_load], once the message is known to be an
+    /// [InitHello] with an ephemeral public key of length `EKEM_PK_LEN` and an X25519 public
+    /// key of length `X25519_LEN`
+    #[cfg(feature = "experiment_cookie_dos_mitigation")]
+    fn handle_init_hello_under_load<
+        const EKEM_PK_LEN: usize,
+        const X25519_LEN: usize,
+        H: HostIdentification,
+    >(
+        &mut self,
+        rx_buf: &[u8],
+        tx_buf: &mut [u8],
+        host_identification: &H,
+    ) -> Result<HandleMsgResult> {
+        let msg_type = MsgType::InitHello;
+
+        // If a valid cookie is found, process the message
+        if self.init_hello_has_valid_cookie::<EKEM_PK_LEN, X25519_LEN, H>(
+            rx_buf,
+            host_identification,
+        )? {
+            log::debug!(
+                "Rx {:?} from {} under load, valid cookie",
+                msg_type,
+                host_identification
+            );
+            return self.handle_msg(rx_buf, tx_buf);
+        }
 
         // Otherwise send cookie reply
-        if active_cookie_value.is_none() {
+        let Some(cookie_value) = self.cookie_values(host_identification)?.into_iter().next() else {
             bail!("No active cookie value found");
-        }
+        };
 
         log::debug!(
             "Rx {:?} from {} under load, tx cookie reply message",
@@ -2038,9 +3105,12 @@ impl CryptoServer {
             host_identification
         );
 
-        let cookie_value = active_cookie_value.unwrap();
+        let msg_in = Ref::<&[u8], Envelope<InitHello<EKEM_PK_LEN, X25519_LEN>>>::new(rx_buf)
+            .ok_or(RosenpassError::BufferSizeMismatch)?;
+        let (rx_mac, rx_sid) = (msg_in.mac, msg_in.payload.sidi);
+        let key = self.lookup_identity(&msg_in)?;
         let cookie_key = hash_domains::cookie_key(KeyedHash::keyed_shake256())?
-            .mix(self.spkm.deref())?
+            .mix(key.spkm(self).deref())?
             .into_value();
 
         let mut msg_out = truncating_cast_into::<CookieReply>(tx_buf)?;
@@ -2071,6 +3141,7 @@ impl CryptoServer {
 
         Ok(HandleMsgResult {
             exchanged_with: None,
+            authenticated_by: None,
             resp: Some(size_of::<CookieReply>()),
         })
     }
@@ -2111,10 +3182,17 @@ impl CryptoServer {
     ///
     /// See [Self::poll] on how to use this function with poll.
     pub fn handle_msg(&mut self, rx_buf: &[u8], tx_buf: &mut [u8]) -> Result<HandleMsgResult> {
+        #[cfg(feature = "internal_deterministic")]
+        if self.rng.is_some() {
+            return self.with_injected_rng(|srv| srv.handle_msg(rx_buf, tx_buf));
+        }
+
         let seal_broken = "Message seal broken!";
         // length of the response. We assume no response, so None for now
         let mut len = 0;
         let mut exchanged = false;
+        // whether the message proves that the sender is the peer; see HandleMsgResult
+        let mut authenticated = false;
 
         ensure!(!rx_buf.is_empty(), "received empty message, ignoring it");
 
@@ -2122,59 +3200,81 @@ impl CryptoServer {
 
         log::debug!("Rx {:?}, processing", msg_type);
 
-        let mut msg_out = truncating_cast_into::<Envelope<RespHello>>(tx_buf)?;
-
         let peer = match msg_type {
+            // The ephemeral KEM used is recognized by the size of the message
             Ok(MsgType::InitHello) => {
-                let msg_in: Ref<&[u8], Envelope<InitHello>> =
-                    Ref::new(rx_buf).ok_or(RosenpassError::BufferSizeMismatch)?;
-
-                // At this point, we do not know the hash functon used by the peer, thus we try both,
-                // with a preference for SHAKE256.
-                let peer_shake256 = self.handle_init_hello(
-                    &msg_in.payload,
-                    &mut msg_out.payload,
-                    KeyedHash::keyed_shake256(),
-                );
-                let (peer, peer_hash_choice) = match peer_shake256 {
-                    Ok(peer) => (peer, KeyedHash::keyed_shake256()),
-                    Err(_) => {
-                        let peer_blake2b = self.handle_init_hello(
-                            &msg_in.payload,
-                            &mut msg_out.payload,
-                            KeyedHash::incorrect_hmac_blake2b(),
-                        );
-                        match peer_blake2b {
-                            Ok(peer) => (peer, KeyedHash::incorrect_hmac_blake2b()),
-                            Err(_) => bail!("No valid hash function found for InitHello"),
+                let (ekem, hybrid) = EphemeralKemChoice::from_init_hello_len(rx_buf.len())?;
+                with_ephemeral_kem!(ekem, hybrid, EKem, X25519_LEN => {
+                    let msg_in: Ref<&[u8], Envelope<InitHello<{ EKem::PK_LEN }, X25519_LEN>>> =
+                        Ref::new(rx_buf).ok_or(RosenpassError::BufferSizeMismatch)?;
+                    let mut msg_out = truncating_cast_into::<
+                        Envelope<RespHello<{ EKem::CT_LEN }, X25519_LEN>>,
+                    >(tx_buf)?;
+                    let key = self.lookup_identity(&msg_in)?;
+
+                    // At this point, we do not know the hash functon used by the peer, thus we try both,
+                    // with a preference for SHAKE256.
+                    let peer_shake256 = self.handle_init_hello(
+                        &EKem::default(),
+                        &msg_in.payload,
+                        &mut msg_out.payload,
+                        key,
+                        KeyedHash::keyed_shake256(),
+                    );
+                    // The cipher suite used by the peer, including the hash function, is checked
+                    // against the local configuration in handle_init_hello.
+                    let (peer, peer_hash_choice) = match peer_shake256 {
+                        Ok(peer) => (peer, KeyedHash::keyed_shake256()),
+                        Err(err_shake256) => {
+                            let peer_blake2b = self.handle_init_hello(
+                                &EKem::default(),
+                                &msg_in.payload,
+                                &mut msg_out.payload,
+                                key,
+                                KeyedHash::incorrect_hmac_blake2b(),
+                            );
+                            match peer_blake2b {
+                                Ok(peer) => (peer, KeyedHash::incorrect_hmac_blake2b()),
+                                // Prefer reporting a cipher suite mismatch over the generic error
+                                Err(err_blake2b) => {
+                                    return Err([err_shake256, err_blake2b]
+                                        .into_iter()
+                                        .find(|e| e.is::<CipherSuiteMismatch>())
+                                        .unwrap_or_else(|| {
+                                            anyhow!("No valid hash function found for InitHello")
+                                        }))
+                                }
+                            }
                         }
-                    }
-                };
-                // Now, we make sure that the hash function used by the peer is the same as the one
-                // that is specified in the local configuration.
-                self.verify_hash_choice_match(peer, peer_hash_choice.clone())?;
+                    };
 
-                ensure!(msg_in.check_seal(self, peer_hash_choice)?, seal_broken);
+                    ensure!(msg_in.check_seal(self, key, peer_hash_choice)?, seal_broken);
 
-                len = self.seal_and_commit_msg(peer, MsgType::RespHello, &mut msg_out)?;
-                peer
+                    len = self.seal_and_commit_msg(peer, MsgType::RespHello, &mut msg_out)?;
+                    peer
+                })
             }
             Ok(MsgType::RespHello) => {
-                let msg_in: Ref<&[u8], Envelope<RespHello>> =
-                    Ref::new(rx_buf).ok_or(RosenpassError::BufferSizeMismatch)?;
-
-                let mut msg_out = truncating_cast_into::<Envelope<InitConf>>(tx_buf)?;
-                let peer = self.handle_resp_hello(&msg_in.payload, &mut msg_out.payload)?;
-                ensure!(
-                    msg_in.check_seal(self, peer.get(self).protocol_version.keyed_hash())?,
-                    seal_broken
-                );
-
-                len = self.seal_and_commit_msg(peer, MsgType::InitConf, &mut msg_out)?;
-                peer.hs()
-                    .store_msg_for_retransmission(self, &msg_out.as_bytes()[..len])?;
-                exchanged = true;
-                peer
+                let (ekem, hybrid) = EphemeralKemChoice::from_resp_hello_len(rx_buf.len())?;
+                with_ephemeral_kem!(ekem, hybrid, EKem, X25519_LEN => {
+                    let msg_in: Ref<&[u8], Envelope<RespHello<{ EKem::CT_LEN }, X25519_LEN>>> =
+                        Ref::new(rx_buf).ok_or(RosenpassError::BufferSizeMismatch)?;
+
+                    let mut msg_out = truncating_cast_into::<Envelope<InitConf>>(tx_buf)?;
+                    let peer = self.handle_resp_hello(
+                        &EKem::default(),
+                        &msg_in.payload,
+                        &mut msg_out.payload,
+                    )?;
+                    ensure!(msg_in.check_seal_for_peer(self, peer)?, seal_broken);
+
+                    len = self.seal_and_commit_msg(peer, MsgType::InitConf, &mut msg_out)?;
+                    peer.hs()
+                        .store_msg_for_retransmission(self, &msg_out.as_bytes()[..len])?;
+                    exchanged = true;
+                    authenticated = true;
+                    peer
+                })
             }
             Ok(MsgType::InitConf) => {
                 let msg_in: Ref<&[u8], Envelope<InitConf>> =
@@ -2187,11 +3287,7 @@ impl CryptoServer {
                     // Cached response; copy out of cache
                     Some(cached) => {
                         let peer = cached.peer();
-                        ensure!(
-                            msg_in
-                                .check_seal(self, peer.get(self).protocol_version.keyed_hash())?,
-                            seal_broken
-                        );
+                        ensure!(msg_in.check_seal_for_peer(self, peer)?, seal_broken);
                         let cached = cached
                             .get(self)
                             .map(|v| v.response.borrow())
@@ -2203,11 +3299,14 @@ impl CryptoServer {
 
                     // No cached response, actually call cryptographic handler
                     None => {
+                        let key = self.lookup_identity(&msg_in)?;
+
                         // At this point, we do not know the hash functon used by the peer, thus we try both,
                         // with a preference for SHAKE256.
                         let peer_shake256 = self.handle_init_conf(
                             &msg_in.payload,
                             &mut msg_out.payload,
+                            key.identity,
                             KeyedHash::keyed_shake256(),
                         );
                         let (peer, peer_hash_choice) = match peer_shake256 {
@@ -2216,6 +3315,7 @@ impl CryptoServer {
                                 let peer_blake2b = self.handle_init_conf(
                                     &msg_in.payload,
                                     &mut msg_out.payload,
+                                    key.identity,
                                     KeyedHash::incorrect_hmac_blake2b(),
                                 );
                                 match peer_blake2b {
@@ -2226,8 +3326,16 @@ impl CryptoServer {
                         };
                         // Now, we make sure that the hash function used by the peer is the same as the one
                         // that is specified in the local configuration.
-                        self.verify_hash_choice_match(peer, peer_hash_choice.clone())?;
-                        ensure!(msg_in.check_seal(self, peer_hash_choice)?, seal_broken);
+                        self.verify_cipher_suite(
+                            peer,
+                            CipherSuite {
+                                protocol_version: ProtocolVersion::from_keyed_hash(
+                                    &peer_hash_choice,
+                                ),
+                                ..peer.get(self).cipher_suite
+                            },
+                        )?;
+                        ensure!(msg_in.check_seal(self, key, peer_hash_choice)?, seal_broken);
 
                         KnownInitConfResponsePtr::insert_for_request_msg(
                             self,
@@ -2237,6 +3345,7 @@ impl CryptoServer {
                         );
 
                         exchanged = true;
+                        authenticated = true;
                         peer
                     }
                 };
@@ -2248,7 +3357,9 @@ impl CryptoServer {
                 let msg_in: Ref<&[u8], Envelope<EmptyData>> =
                     Ref::new(rx_buf).ok_or(RosenpassError::BufferSizeMismatch)?;
 
-                self.handle_resp_conf(&msg_in, seal_broken.to_string())?
+                let peer = self.handle_resp_conf(&msg_in, seal_broken.to_string())?;
+                authenticated = true;
+                peer
             }
             Ok(MsgType::CookieReply) => {
                 let msg_in: Ref<&[u8], CookieReply> =
@@ -2264,23 +3375,25 @@ impl CryptoServer {
 
         Ok(HandleMsgResult {
             exchanged_with: exchanged.then_some(peer),
+            authenticated_by: authenticated.then_some(peer),
             resp: if len == 0 { None } else { Some(len) },
         })
     }
 
-    /// Given a peer and a [KeyedHash] `peer_hash_choice`, this function checks whether the
-    /// `peer_hash_choice` matches the hash function that is expected for the peer.
-    fn verify_hash_choice_match(&self, peer: PeerPtr, peer_hash_choice: KeyedHash) -> Result<()> {
-        match peer.get(self).protocol_version.keyed_hash() {
-            KeyedHash::KeyedShake256(_) => match peer_hash_choice {
-                KeyedHash::KeyedShake256(_) => Ok(()),
-                KeyedHash::IncorrectHmacBlake2b(_) => bail!("Hash function mismatch"),
-            },
-            KeyedHash::IncorrectHmacBlake2b(_) => match peer_hash_choice {
-                KeyedHash::KeyedShake256(_) => bail!("Hash function mismatch"),
-                KeyedHash::IncorrectHmacBlake2b(_) => Ok(()),
-            },
-        }
+    /// Given a peer and the [CipherSuite] `received` used by a message from the peer, this
+    /// function checks whether `received` matches the suite configured for the peer.
+    ///
+    /// Raises a [CipherSuiteMismatch] error otherwise.
+    fn verify_cipher_suite(&self, peer: PeerPtr, received: CipherSuite) -> Result<()> {
+        let configured = peer.get(self).cipher_suite.clone();
+        ensure!(
+            configured == received,
+            CipherSuiteMismatch {
+                configured,
+                received
+            }
+        );
+        Ok(())
     }
 
     /// This is used to finalize a message in a transmission buffer
@@ -2828,10 +3941,21 @@ impl CryptoServer {
     #[doc = include_str!("../../tests/poll_example.rs")]
     #[doc = "```"]
     pub fn poll(&mut self) -> Result<PollResult> {
+        #[cfg(feature = "internal_deterministic")]
+        if self.rng.is_some() {
+            return self.with_injected_rng(|srv| srv.poll());
+        }
+
+        // Like [Self::peer_ptrs_off], but without borrowing self; [PeerPtr::poll] skips removed
+        // peers
+        let (l, n) = (self.peers.len(), self.peer_poll_off);
+        let peers = (0..l).map(move |i| PeerPtr((i + n) % l));
+
         let r = begin_poll() // Poll each biscuit and peer until an event is found
             .poll_children(self, self.biscuit_key_ptrs())?
             .poll_children(self, self.cookie_secret_ptrs())?
-            .poll_children(self, self.peer_ptrs_off(self.peer_poll_off))?;
+            .poll_children(self, self.identity_ptrs())?
+            .poll_children(self, peers)?;
         self.peer_poll_off = match r.peer() {
             Some(p) => p.0 + 1, // Event found while polling peer p; will poll peer p+1 next
             None => 0, // No peer ev found. Resetting to 0 out of an irrational fear of non-zero numbers
@@ -2856,11 +3980,30 @@ impl Pollable for ServerCookieSecretPtr {
     }
 }
 
+impl Pollable for IdentityPtr {
+    fn poll(&self, srv: &mut CryptoServer) -> Result<PollResult> {
+        begin_poll()
+            .sched(
+                self.alternate_key_life_left(srv),
+                void_poll(|| self.drop_expired_alternate_key(srv)), // End of key rotation overlap
+            )
+            .ok()
+    }
+}
+
 impl Pollable for PeerPtr {
     fn poll(&self, srv: &mut CryptoServer) -> Result<PollResult> {
+        // Peers removed through [CryptoServer::remove_peer] have nothing to do
+        if !srv.contains_peer(*self) {
+            return Ok(begin_poll());
+        }
         let (ses, hs) = (self.session(), self.hs());
         begin_poll()
             .sched(hs.life_left(srv), void_poll(|| hs.take(srv))) // Silently erase old handshakes
+            .sched(
+                self.alternate_key_life_left(srv),
+                void_poll(|| self.drop_expired_alternate_key(srv)), // End of key rotation overlap
+            )
             .sched(ses.life_left(srv), || {
                 // Erase old sessions
                 ses.take(srv);
@@ -2871,12 +4014,18 @@ impl Pollable for PeerPtr {
             // ignore the request hence there is a need to do record keeping on that)
             // AND after the existing session becomes stale or if there is session at all
             // AND after the current handshake becomes stale or there is no handshake at all
+            // OR right away if the initiation was forced through [CryptoServer::force_initiation]
             .sched(
-                Wait::immediate_unless(self.get(srv).initiation_requested)
-                    .and(Wait::or_immediate(ses.youth_left(srv)))
-                    .and(Wait::or_immediate(hs.youth_left(srv))),
+                match self.get(srv).initiation_forced {
+                    true => Wait::immediate(),
+                    false => Wait::immediate_unless(self.get(srv).initiation_requested)
+                        .and(Wait::or_immediate(ses.youth_left(srv)))
+                        .and(Wait::or_immediate(hs.youth_left(srv))),
+                },
                 || {
-                    self.get_mut(srv).initiation_requested = true;
+                    let peer = self.get_mut(srv);
+                    peer.initiation_requested = true;
+                    peer.initiation_forced = false;
                     PollResult::SendInitiation(*self)
                 },
             )
@@ -2918,6 +4067,11 @@ impl CryptoServer {
     /// For a full example of how to use the crypto server, including how to process retransmission
     /// handling, see the example in [Self::poll].
     pub fn retransmit_handshake(&mut self, peer: PeerPtr, tx_buf: &mut [u8]) -> Result<usize> {
+        #[cfg(feature = "internal_deterministic")]
+        if self.rng.is_some() {
+            return self.with_injected_rng(|srv| srv.retransmit_handshake(peer, tx_buf));
+        }
+
         peer.hs().apply_retransmission(self, tx_buf)
     }
 }
@@ -2964,14 +4118,21 @@ impl IniHsPtr {
         }
 
         // Add cookie to retransmitted message
-        let mut envelope = truncating_cast_into::<Envelope<InitHello>>(tx_buf)?;
-        envelope.seal_cookie(self.peer(), srv)?;
+        let suite = &self.peer().get(srv).cipher_suite;
+        with_ephemeral_kem!(suite.ephemeral_kem, suite.hybrid_x25519, EKem, X25519_LEN => {
+            let mut envelope = truncating_cast_into::<
+                Envelope<InitHello<{ EKem::PK_LEN }, X25519_LEN>>,
+            >(tx_buf)?;
+            envelope.seal_cookie(self.peer(), srv)?;
+        });
 
         Ok(ih_tx_len)
     }
 
     /// Internal business logic; used to register the fact that a retransmission has happened.
     pub fn register_retransmission(&self, srv: &mut CryptoServer) -> Result<()> {
+        use rand::Rng;
+
         let tb = srv.timebase.clone();
         let ih = self
             .get_mut(srv)
@@ -2986,7 +4147,7 @@ impl IniHsPtr {
                         .min(ih.tx_count as f64),
                 )
                 * RETRANSMIT_DELAY_JITTER
-                * (rand::random::<f64>() + 1.0);
+                * (rosenpass_secret_memory::rand::rng().gen::<f64>() + 1.0);
         ih.tx_count += 1;
         Ok(())
     }
@@ -3021,7 +4182,7 @@ where
 {
     /// Internal business logic: Calculate the message authentication code (`mac`) and also append cookie value
     pub fn seal(&mut self, peer: PeerPtr, srv: &CryptoServer) -> Result<()> {
-        let mac = hash_domains::mac(peer.get(srv).protocol_version.keyed_hash())?
+        let mac = hash_domains::mac(peer.get(srv).cipher_suite.keyed_hash())?
             .mix(peer.get(srv).spkt.deref())?
             .mix(&self.as_bytes()[span_of!(Self, msg_type..mac)])?;
         self.mac.copy_from_slice(mac.into_value()[..16].as_ref());
@@ -3049,26 +4210,55 @@ where
     M: AsBytes + FromBytes,
 {
     /// Internal business logic: Check the message authentication code produced by [Self::seal]
-    pub fn check_seal(&self, srv: &CryptoServer, shake_or_blake: KeyedHash) -> Result<bool> {
+    ///
+    /// The message authentication code is keyed with the public key of the recipient, i.e. the
+    /// public key referenced by `key`.
+    pub fn check_seal(
+        &self,
+        srv: &CryptoServer,
+        key: IdentityKeyPtr,
+        shake_or_blake: KeyedHash,
+    ) -> Result<bool> {
         let expected = hash_domains::mac(shake_or_blake)?
-            .mix(srv.spkm.deref())?
+            .mix(key.spkm(srv).deref())?
             .mix(&self.as_bytes()[span_of!(Self, msg_type..mac)])?;
         Ok(constant_time::memcmp(
             &self.mac,
             &expected.into_value()[..16],
         ))
     }
+
+    /// Check the seal of a message from a known peer, using the identity and hash function
+    /// configured for the peer
+    ///
+    /// During a static key rotation, the peer may still address us by our previous key or
+    /// already by our next key, so all keypairs of the identity are accepted.
+    pub fn check_seal_for_peer(&self, srv: &CryptoServer, peer: PeerPtr) -> Result<bool> {
+        let peer = peer.get(srv);
+        for key in peer.identity.key_ptrs(srv) {
+            if self.check_seal(srv, key, peer.cipher_suite.keyed_hash())? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
 }
 
 impl InitiatorHandshake {
     /// Zero initialization of an InitiatorHandshake, with up to date timestamp
-    pub fn zero_with_timestamp(srv: &CryptoServer, keyed_hash: KeyedHash) -> Self {
+    pub fn zero_with_timestamp(
+        srv: &CryptoServer,
+        keyed_hash: KeyedHash,
+        aead: AeadChoice,
+    ) -> Self {
         InitiatorHandshake {
             created_at: srv.timebase.now(),
             next: HandshakeStateMachine::RespHello,
-            core: HandshakeState::zero(keyed_hash),
+            core: HandshakeState::zero(keyed_hash, aead),
             eski: ESk::zero(),
             epki: EPk::zero(),
+            eski_x25519: XSk::zero(),
+            epki_x25519: XPk::zero(),
             tx_at: 0.0,
             tx_retry_at: 0.0,
             tx_count: 0,
@@ -3081,11 +4271,12 @@ impl InitiatorHandshake {
 
 impl HandshakeState {
     /// Zero initialization of an HandshakeState
-    pub fn zero(keyed_hash: KeyedHash) -> Self {
+    pub fn zero(keyed_hash: KeyedHash, aead: AeadChoice) -> Self {
         Self {
             sidi: SessionId::zero(),
             sidr: SessionId::zero(),
             ck: SecretHashDomain::zero(keyed_hash).dup(),
+            aead,
         }
     }
 
@@ -3123,7 +4314,7 @@ impl HandshakeState {
             .mix(&hash_domains::hs_enc(self.ck.keyed_hash().clone())?)?
             .into_secret();
         ensure!(Aead::NONCE_LEN == 12);
-        Aead.encrypt(ct, k.secret(), &[0u8; 12], &[], pt)?;
+        self.aead.encrypt(ct, k.secret(), &[0u8; 12], &[], pt)?;
         self.mix(ct)
     }
 
@@ -3137,10 +4328,28 @@ impl HandshakeState {
             .mix(&hash_domains::hs_enc(self.ck.keyed_hash().clone())?)?
             .into_secret();
         ensure!(Aead::NONCE_LEN == 12);
-        Aead.decrypt(pt, k.secret(), &[0u8; 12], &[], ct)?;
+        self.aead.decrypt(pt, k.secret(), &[0u8; 12], &[], ct)?;
         self.mix(ct)
     }
 
+    /// Variant of [Self::decrypt_and_mix] that tries every [AeadChoice] and sets [Self::aead] to
+    /// the first one that decrypts `ct` successfully
+    ///
+    /// Used by the responder to recognize the AEAD of an [InitHello] before knowing which peer
+    /// it comes from.
+    pub fn decrypt_and_mix_any_aead(&mut self, pt: &mut [u8], ct: &[u8]) -> Result<&mut Self> {
+        let mut result = Err(anyhow!("No AEAD available"));
+        for aead in AeadChoice::ALL {
+            self.aead = aead;
+            result = self.decrypt_and_mix(pt, ct).map(|_| ());
+            if result.is_ok() {
+                break;
+            }
+        }
+        result?;
+        Ok(self)
+    }
+
     /// Encapsulate a secret with a KEM and mix the resulting secret into the chaining key.
     ///
     /// The ciphertext must be transmitted to the other party.
@@ -3186,6 +4395,24 @@ impl HandshakeState {
         self.mix(pk)?.mix(shk.secret())?.mix(ct)
     }
 
+    /// Hybrid X25519 exchange on the responder side; see [CipherSuite::hybrid_x25519]
+    ///
+    /// Like [Self::encaps_and_mix] with [X25519], but mixes the [hash_domains::hybrid_x25519]
+    /// domain separator into the chaining key first. Takes slices since the message fields
+    /// are only sized for X25519 in hybrid handshakes.
+    pub fn encaps_and_mix_x25519(&mut self, ct: &mut [u8], pk: &[u8]) -> Result<&mut Self> {
+        let sep = hash_domains::hybrid_x25519(self.ck.keyed_hash().clone())?;
+        self.mix(&sep)?
+            .encaps_and_mix(&X25519, ct.try_into()?, pk.try_into()?)
+    }
+
+    /// Decapsulation counterpart to [Self::encaps_and_mix_x25519] on the initiator side
+    pub fn decaps_and_mix_x25519(&mut self, sk: &[u8], pk: &[u8], ct: &[u8]) -> Result<&mut Self> {
+        let sep = hash_domains::hybrid_x25519(self.ck.keyed_hash().clone())?;
+        self.mix(&sep)?
+            .decaps_and_mix(&X25519, sk.try_into()?, pk.try_into()?, ct.try_into()?)
+    }
+
     /// Store the chaining key inside a cookie value called a "biscuit".
     ///
     /// This biscuit can be transmitted to the other party and must be returned
@@ -3213,8 +4440,8 @@ impl HandshakeState {
             .copy_from_slice(self.ck.clone().danger_into_secret().secret());
 
         // calculate ad contents
-        let ad = hash_domains::biscuit_ad(peer.get(srv).protocol_version.keyed_hash())?
-            .mix(srv.spkm.deref())?
+        let ad = hash_domains::biscuit_ad(peer.get(srv).cipher_suite.keyed_hash())?
+            .mix(peer.get(srv).identity.get(srv).spkm.deref())?
             .mix(self.sidi.as_slice())?
             .mix(self.sidr.as_slice())?
             .into_value();
@@ -3231,39 +4458,61 @@ impl HandshakeState {
 
         let k = bk.get(srv).value.secret();
         let pt = biscuit.as_bytes();
-        XAead.encrypt_with_nonce_in_ctxt(biscuit_ct, k, &*n, &ad, pt)?;
+        self.aead
+            .xaead()
+            .encrypt_with_nonce_in_ctxt(biscuit_ct, k, &*n, &ad, pt)?;
 
         self.mix(biscuit_ct)
     }
 
     /// This is the counterpart to [Self::store_biscuit] that restores a stored biscuit
+    ///
+    /// `identity` is the identity the biscuit was issued by; the biscuit is only accepted
+    /// if it belongs to a peer bound to that identity. Biscuits issued before a static key
+    /// rotation of the identity remain valid.
+    ///
+    /// The biscuit is encrypted with the extended-nonce variant of the AEAD used in the
+    /// handshake (see [Self::store_biscuit]), so each [AeadChoice] is tried; the one that
+    /// succeeds becomes the [Self::aead] of the restored handshake state.
     pub fn load_biscuit(
         srv: &CryptoServer,
         biscuit_ct: &[u8],
         sidi: SessionId,
         sidr: SessionId,
+        identity: IdentityPtr,
         shake_or_blake: KeyedHash,
     ) -> Result<(PeerPtr, BiscuitId, HandshakeState)> {
         // The first bit of the biscuit indicates which biscuit key was used
         let bk = BiscuitKeyPtr(((biscuit_ct[0] & 0b1000_0000) >> 7) as usize);
 
-        // Calculate additional data fields
-        let ad = hash_domains::biscuit_ad(shake_or_blake)?
-            .mix(srv.spkm.deref())?
-            .mix(sidi.as_slice())?
-            .mix(sidr.as_slice())?
-            .into_value();
-
-        // Allocate and decrypt the biscuit data
+        // Allocate and decrypt the biscuit data; the additional data contains the public key
+        // that was active when the biscuit was issued
         let mut biscuit = Secret::<BISCUIT_PT_LEN>::zero(); // pt buf
         let mut biscuit: Ref<&mut [u8], Biscuit> =
             Ref::new(biscuit.secret_mut().as_mut_slice()).unwrap();
-        XAead.decrypt_with_nonce_in_ctxt(
-            biscuit.as_bytes_mut(),
-            bk.get(srv).value.secret(),
-            &ad,
-            biscuit_ct,
-        )?;
+        let mut decrypted = Err(AeadError::DecryptError);
+        let mut aead = AeadChoice::default();
+        'keys: for key in identity.key_ptrs(srv) {
+            // Calculate additional data fields
+            let ad = hash_domains::biscuit_ad(shake_or_blake.clone())?
+                .mix(key.spkm(srv).deref())?
+                .mix(sidi.as_slice())?
+                .mix(sidr.as_slice())?
+                .into_value();
+            for choice in AeadChoice::ALL {
+                aead = choice;
+                decrypted = aead.xaead().decrypt_with_nonce_in_ctxt(
+                    biscuit.as_bytes_mut(),
+                    bk.get(srv).value.secret(),
+                    &ad,
+                    biscuit_ct,
+                );
+                if decrypted.is_ok() {
+                    break 'keys;
+                }
+            }
+        }
+        decrypted?;
 
         // Reconstruct the biscuit fields
         let no = BiscuitId::from_slice(&biscuit.biscuit_no);
@@ -3272,14 +4521,23 @@ impl HandshakeState {
         let peer = srv
             .find_peer(pid) // TODO: FindPeer should return a Result<()>
             .with_context(|| format!("Could not decode biscuit for peer {pid:?}: No such peer."))?;
+        ensure!(
+            peer.get(srv).identity == identity,
+            "Could not decode biscuit for peer {pid:?}: Peer belongs to another identity."
+        );
 
         let ck = SecretHashDomain::danger_from_secret(
             Secret::from_slice(&biscuit.ck),
-            peer.get(srv).protocol_version.keyed_hash(),
+            peer.get(srv).cipher_suite.keyed_hash(),
         )
         .dup();
         // Reconstruct the handshake state
-        let mut hs = Self { sidi, sidr, ck };
+        let mut hs = Self {
+            sidi,
+            sidr,
+            ck,
+            aead,
+        };
         hs.mix(biscuit_ct)?;
 
         Ok((peer, no, hs))
@@ -3296,7 +4554,7 @@ impl HandshakeState {
         role: HandshakeRole,
         either_shake_or_blake: KeyedHash,
     ) -> Result<Session> {
-        let HandshakeState { ck, sidi, sidr } = self;
+        let HandshakeState { ck, sidi, sidr, .. } = self;
         let tki = ck
             .mix(&hash_domains::ini_enc(either_shake_or_blake.clone())?)?
             .into_secret();
@@ -3349,7 +4607,7 @@ impl CryptoServer {
         peer: PeerPtr,
         domain_separator: &OskDomainSeparator,
     ) -> Result<SymKey> {
-        let hash_choice = peer.get(self).protocol_version.keyed_hash();
+        let hash_choice = peer.get(self).cipher_suite.keyed_hash();
         let compressed_domain_separator = domain_separator.compress_with(hash_choice)?;
         self.osk_with_compressed_domain_separator(peer, &compressed_domain_separator)
     }
@@ -3373,7 +4631,7 @@ impl CryptoServer {
     /// See the documentation and examples in [super::osk_domain_separator] for more information
     /// about using custom domain separators.
     pub fn osk(&self, peer: PeerPtr) -> Result<SymKey> {
-        let hash_choice = peer.get(self).protocol_version.keyed_hash();
+        let hash_choice = peer.get(self).cipher_suite.keyed_hash();
         let compressed_domain_separator = peer
             .get(self)
             .osk_domain_separator
@@ -3399,14 +4657,28 @@ macro_rules! protocol_section {
 impl CryptoServer {
     /// Core cryptographic protocol implementation: Kicks of the handshake
     /// on the initiator side, producing the InitHello message.
-    pub fn handle_initiation(&mut self, peer: PeerPtr, ih: &mut InitHello) -> Result<PeerPtr> {
+    ///
+    /// `ekem` is the ephemeral KEM configured for the peer; see [CipherSuite::ephemeral_kem].
+    /// `X25519_LEN` is zero unless the hybrid X25519 exchange is used; see
+    /// [CipherSuite::hybrid_x25519].
+    pub fn handle_initiation<
+        const EKEM_SK_LEN: usize,
+        const EKEM_PK_LEN: usize,
+        const EKEM_CT_LEN: usize,
+        const EKEM_SHK_LEN: usize,
+        const X25519_LEN: usize,
+        EKemImpl: Kem<EKEM_SK_LEN, EKEM_PK_LEN, EKEM_CT_LEN, EKEM_SHK_LEN>,
+    >(
+        &mut self,
+        ekem: &EKemImpl,
+        peer: PeerPtr,
+        ih: &mut InitHello<EKEM_PK_LEN, X25519_LEN>,
+    ) -> Result<PeerPtr> {
         #[cfg(feature = "trace_bench")]
         let _span_guard = rosenpass_util::trace_bench::trace().emit_span("handle_initiation");
 
-        let mut hs = InitiatorHandshake::zero_with_timestamp(
-            self,
-            peer.get(self).protocol_version.keyed_hash(),
-        );
+        let suite = &peer.get(self).cipher_suite;
+        let mut hs = InitiatorHandshake::zero_with_timestamp(self, suite.keyed_hash(), suite.aead);
 
         // IHI1
         protocol_section!("IHI1", {
@@ -3421,8 +4693,15 @@ impl CryptoServer {
 
         // IHI3
         protocol_section!("IHI3", {
-            EphemeralKem.keygen(hs.eski.secret_mut(), &mut *hs.epki)?;
-            ih.epki.copy_from_slice(&hs.epki.value);
+            ekem.keygen(
+                (&mut hs.eski.secret_mut()[..EKEM_SK_LEN]).try_into()?,
+                (&mut hs.epki[..EKEM_PK_LEN]).try_into()?,
+            )?;
+            ih.epki.copy_from_slice(&hs.epki[..EKEM_PK_LEN]);
+            if X25519_LEN > 0 {
+                X25519.keygen(hs.eski_x25519.secret_mut(), &mut *hs.epki_x25519)?;
+                ih.xpki.copy_from_slice(hs.epki_x25519.as_slice());
+            }
         });
 
         // IHI4
@@ -3440,7 +4719,8 @@ impl CryptoServer {
         protocol_section!("IHI6", {
             hs.core.encrypt_and_mix(
                 ih.pidic.as_mut_slice(),
-                self.pidm(peer.get(self).protocol_version.keyed_hash())?
+                (peer.get(self).identity.get(self))
+                    .pidm(peer.get(self).cipher_suite.keyed_hash())?
                     .as_ref(),
             )?;
         });
@@ -3448,7 +4728,7 @@ impl CryptoServer {
         // IHI7
         protocol_section!("IHI7", {
             hs.core
-                .mix(self.spkm.deref())?
+                .mix(peer.get(self).identity.get(self).spkm.deref())?
                 .mix(peer.get(self).psk.secret())?;
         });
 
@@ -3465,22 +4745,36 @@ impl CryptoServer {
 
     /// Core cryptographic protocol implementation: Parses an [InitHello] message and produces a
     /// [RespHello] message on the responder side.
-    pub fn handle_init_hello(
+    ///
+    /// `key` is the keypair the message is addressed to; see [Self::lookup_identity]. `ekem` is
+    /// the ephemeral KEM used by the message; see [EphemeralKemChoice::from_init_hello_len].
+    /// `X25519_LEN` is zero unless the message uses the hybrid X25519 exchange.
+    pub fn handle_init_hello<
+        const EKEM_SK_LEN: usize,
+        const EKEM_PK_LEN: usize,
+        const EKEM_CT_LEN: usize,
+        const EKEM_SHK_LEN: usize,
+        const X25519_LEN: usize,
+        EKemImpl: Kem<EKEM_SK_LEN, EKEM_PK_LEN, EKEM_CT_LEN, EKEM_SHK_LEN>,
+    >(
         &mut self,
-        ih: &InitHello,
-        rh: &mut RespHello,
+        ekem: &EKemImpl,
+        ih: &InitHello<EKEM_PK_LEN, X25519_LEN>,
+        rh: &mut RespHello<EKEM_CT_LEN, X25519_LEN>,
+        key: IdentityKeyPtr,
         keyed_hash: KeyedHash,
     ) -> Result<PeerPtr> {
         #[cfg(feature = "trace_bench")]
         let _span_guard = rosenpass_util::trace_bench::trace().emit_span("handle_init_hello");
 
-        let mut core = HandshakeState::zero(keyed_hash);
+        // The AEAD is recognized in IHR6
+        let mut core = HandshakeState::zero(keyed_hash, AeadChoice::default());
 
         core.sidi = SessionId::from_slice(&ih.sidi);
 
         // IHR1
         protocol_section!("IHR1", {
-            core.init(self.spkm.deref())?;
+            core.init(key.spkm(self).deref())?;
         });
 
         // IHR4
@@ -3490,20 +4784,37 @@ impl CryptoServer {
 
         // IHR5
         protocol_section!("IHR5", {
-            core.decaps_and_mix(&StaticKem, self.sskm.secret(), self.spkm.deref(), &ih.sctr)?;
+            let (sskm, spkm) = (key.sskm(self), key.spkm(self));
+            let decapsulated = self.decapsulated(key, &ih.sctr);
+            core.decaps_and_mix_static(decapsulated, sskm, spkm.deref(), &ih.sctr)?;
         });
 
         // IHR6
-        let peer = protocol_section!("IHR6", {
+        let (peer, peerid) = protocol_section!("IHR6", {
             let mut peerid = PeerId::zero();
-            core.decrypt_and_mix(&mut *peerid, &ih.pidic)?;
-            self.find_peer(peerid)
-                .with_context(|| format!("No such peer {peerid:?}."))?
+            core.decrypt_and_mix_any_aead(&mut *peerid, &ih.pidic)?;
+            let peer = self
+                .find_peer(peerid)
+                .with_context(|| format!("No such peer {peerid:?}."))?;
+            ensure!(
+                peer.get(self).identity == key.identity,
+                "Peer {peerid:?} belongs to another identity."
+            );
+            self.verify_cipher_suite(
+                peer,
+                CipherSuite {
+                    protocol_version: ProtocolVersion::from_keyed_hash(core.ck.keyed_hash()),
+                    ephemeral_kem: EphemeralKemChoice::from_pk_len(EKEM_PK_LEN)?,
+                    aead: core.aead,
+                    hybrid_x25519: X25519_LEN > 0,
+                },
+            )?;
+            (peer, peerid)
         });
 
         // IHR7
         protocol_section!("IHR7", {
-            core.mix(peer.get(self).spkt.deref())?
+            core.mix(peer.get(self).spkt_for(peerid).deref())?
                 .mix(peer.get(self).psk.secret())?;
         });
 
@@ -3526,12 +4837,20 @@ impl CryptoServer {
 
         // RHR4
         protocol_section!("RHR4", {
-            core.encaps_and_mix(&EphemeralKem, &mut rh.ecti, &ih.epki)?;
+            core.encaps_and_mix(ekem, &mut rh.ecti, &ih.epki)?;
         });
 
+        // Hybrid X25519 exchange; not part of the whitepaper
+        if X25519_LEN > 0 {
+            protocol_section!("RHR4-X25519", {
+                core.encaps_and_mix_x25519(&mut rh.xcti, &ih.xpki)?;
+            });
+        }
+
         // RHR5
         protocol_section!("RHR5", {
-            core.encaps_and_mix(&StaticKem, &mut rh.scti, peer.get(self).spkt.deref())?;
+            let spkt = peer.get(self).spkt_for(peerid);
+            core.encaps_and_mix(&StaticKem, &mut rh.scti, spkt.deref())?;
         });
 
         // RHR6
@@ -3549,7 +4868,22 @@ impl CryptoServer {
 
     /// Core cryptographic protocol implementation: Parses an [RespHello] message and produces an
     /// [InitConf] message on the initiator side.
-    pub fn handle_resp_hello(&mut self, rh: &RespHello, ic: &mut InitConf) -> Result<PeerPtr> {
+    ///
+    /// `ekem` is the ephemeral KEM used by the message; see
+    /// [EphemeralKemChoice::from_resp_hello_len].
+    pub fn handle_resp_hello<
+        const EKEM_SK_LEN: usize,
+        const EKEM_PK_LEN: usize,
+        const EKEM_CT_LEN: usize,
+        const EKEM_SHK_LEN: usize,
+        const X25519_LEN: usize,
+        EKemImpl: Kem<EKEM_SK_LEN, EKEM_PK_LEN, EKEM_CT_LEN, EKEM_SHK_LEN>,
+    >(
+        &mut self,
+        ekem: &EKemImpl,
+        rh: &RespHello<EKEM_CT_LEN, X25519_LEN>,
+        ic: &mut InitConf,
+    ) -> Result<PeerPtr> {
         #[cfg(feature = "trace_bench")]
         let _span_guard = rosenpass_util::trace_bench::trace().emit_span("handle_resp_hello");
 
@@ -3588,6 +4922,14 @@ impl CryptoServer {
             exp,
             got
         );
+        self.verify_cipher_suite(
+            peer,
+            CipherSuite {
+                ephemeral_kem: EphemeralKemChoice::from_ct_len(EKEM_CT_LEN)?,
+                hybrid_x25519: X25519_LEN > 0,
+                ..peer.get(self).cipher_suite.clone()
+            },
+        )?;
 
         let mut core = hs!().core.clone();
         core.sidr.copy_from_slice(&rh.sidr);
@@ -3603,16 +4945,30 @@ impl CryptoServer {
         // RHI4
         protocol_section!("RHI4", {
             core.decaps_and_mix(
-                &EphemeralKem,
-                hs!().eski.secret(),
-                hs!().epki.deref(),
+                ekem,
+                hs!().eski.secret()[..EKEM_SK_LEN].try_into()?,
+                hs!().epki[..EKEM_PK_LEN].try_into()?,
                 &rh.ecti,
             )?;
         });
 
+        // Hybrid X25519 exchange; not part of the whitepaper
+        if X25519_LEN > 0 {
+            protocol_section!("RHI4-X25519", {
+                core.decaps_and_mix_x25519(
+                    hs!().eski_x25519.secret(),
+                    hs!().epki_x25519.as_slice(),
+                    &rh.xcti,
+                )?;
+            });
+        }
+
         // RHI5
         protocol_section!("RHI5", {
-            core.decaps_and_mix(&StaticKem, self.sskm.secret(), self.spkm.deref(), &rh.scti)?;
+            let key = IdentityKeyPtr::active(peer.get(self).identity);
+            let (sskm, spkm) = (key.sskm(self), key.spkm(self));
+            let decapsulated = self.decapsulated(key, &rh.scti);
+            core.decaps_and_mix_static(decapsulated, sskm, spkm.deref(), &rh.scti)?;
         });
 
         // RHI6
@@ -3653,7 +5009,7 @@ impl CryptoServer {
                 core.enter_live(
                     self,
                     HandshakeRole::Initiator,
-                    peer.get(self).protocol_version.keyed_hash(),
+                    peer.get(self).cipher_suite.keyed_hash(),
                 )?,
             )?;
             hs_mut!().core.erase();
@@ -3668,10 +5024,13 @@ impl CryptoServer {
     ///
     /// This concludes the handshake on the cryptographic level; the [EmptyData] message is just
     /// an acknowledgement message telling the initiator to stop performing retransmissions.
+    ///
+    /// `identity` is the identity the message is addressed to; see [Self::lookup_identity].
     pub fn handle_init_conf(
         &mut self,
         ic: &InitConf,
         rc: &mut EmptyData,
+        identity: IdentityPtr,
         keyed_hash: KeyedHash,
     ) -> Result<PeerPtr> {
         #[cfg(feature = "trace_bench")]
@@ -3685,6 +5044,7 @@ impl CryptoServer {
                 &ic.biscuit,
                 SessionId::from_slice(&ic.sidi),
                 SessionId::from_slice(&ic.sidr),
+                identity,
                 keyed_hash,
             )?
         });
@@ -3725,7 +5085,7 @@ impl CryptoServer {
                 core.enter_live(
                     self,
                     HandshakeRole::Responder,
-                    peer.get(self).protocol_version.keyed_hash(),
+                    peer.get(self).cipher_suite.keyed_hash(),
                 )?,
             )?;
             // TODO: This should be part of the protocol specification.
@@ -3756,6 +5116,7 @@ impl CryptoServer {
         // Send ack – Implementing sending the empty acknowledgement here
         // instead of a generic PeerPtr::send(&Server, Option<&[u8]>) -> Either<EmptyData, Data>
         // because data transmission is a stub currently.
+        let aead = peer.get(self).cipher_suite.aead;
         let ses = peer
             .session()
             .get_mut(self)
@@ -3767,7 +5128,7 @@ impl CryptoServer {
 
         let n = cat!(Aead::NONCE_LEN; &rc.ctr, &[0u8; 4]);
         let k = ses.txkm.secret();
-        Aead.encrypt(&mut rc.auth, k, &n, &[], &[])?; // ct, k, n, ad, pt
+        aead.encrypt(&mut rc.auth, k, &n, &[], &[])?; // ct, k, n, ad, pt
 
         Ok(peer)
     }
@@ -3789,10 +5150,7 @@ impl CryptoServer {
         let hs = self
             .lookup_handshake(sid)
             .with_context(|| format!("Got RespConf packet for non-existent session {sid:?}"))?;
-        ensure!(
-            msg_in.check_seal(self, hs.peer().get(self).protocol_version.keyed_hash())?,
-            seal_broken
-        );
+        ensure!(msg_in.check_seal_for_peer(self, hs.peer())?, seal_broken);
         let ses = hs.peer().session();
 
         let exp = hs.get(self).as_ref().map(|h| h.next);
@@ -3807,6 +5165,7 @@ impl CryptoServer {
 
         // Validate the message
         {
+            let aead = hs.peer().get(self).cipher_suite.aead;
             let s = ses.get_mut(self).as_mut().with_context(|| {
                 format!("Cannot validate EmptyData message. Missing encryption session for {sid:?}")
             })?;
@@ -3815,7 +5174,7 @@ impl CryptoServer {
             let n = u64::from_le_bytes(rc.ctr);
             ensure!(n >= s.txnt, "Stale nonce");
             s.txnt = n;
-            Aead.decrypt(
+            aead.decrypt(
                 // pt, k, n, ad, ct
                 &mut [0u8; 0],
                 s.txkt.secret(),
@@ -3854,13 +5213,19 @@ impl CryptoServer {
                 // TODO: Handle buffer overflow in ih.tx_buf[0] (i.e. the case where the )
                 match ih.tx_buf[0].try_into() {
                     Ok(MsgType::InitHello) => {
-                        match truncating_cast_into_nomut::<Envelope<InitHello>>(&ih.tx_buf.value) {
-                            Ok(t) => {
-                                mac = t.mac;
-                                Ok(())
+                        let suite = &peer.get(self).cipher_suite;
+                        with_ephemeral_kem!(suite.ephemeral_kem, suite.hybrid_x25519, EKem, X25519_LEN => {
+                            match truncating_cast_into_nomut::<
+                                Envelope<InitHello<{ EKem::PK_LEN }, X25519_LEN>>,
+                            >(&ih.tx_buf.value)
+                            {
+                                Ok(t) => {
+                                    mac = t.mac;
+                                    Ok(())
+                                }
+                                Err(e) => Err(e),
                             }
-                            Err(e) => Err(e),
-                        }
+                        })
                     }
                     Ok(MsgType::InitConf) => {
                         match truncating_cast_into_nomut::<Envelope<InitConf>>(&ih.tx_buf.value) {
@@ -0,0 +1,334 @@
+//! Handing the live state of a [CryptoServer] over to another process
+//!
+//! When Rosenpass is restarted, e.g. to upgrade the binary, all sessions are lost; every peer
+//! has to perform a new handshake and WireGuard runs with a stale pre-shared key in the meantime.
+//!
+//! [CryptoServer::snapshot] serializes everything needed to continue where the server left off:
+//!
+//! - the [Session] of every peer, which determines when the next key exchange is due
+//! - [super::Peer::biscuit_used] of every peer, so old [InitConf](crate::msgs::InitConf)
+//!   messages can not be replayed
+//! - [super::Peer::known_init_conf_response] of every peer, so retransmitted
+//!   [InitConf](crate::msgs::InitConf) messages are still answered
+//! - the biscuit keys, cookie secrets and the biscuit counter, so handshakes in flight where we
+//!   are the responder still complete (see [crate::protocol::persistent_state])
+//! - the [CryptoServer::known_response_hasher]
+//!
+//! Handshakes in flight where we are the initiator are not part of the snapshot; they are
+//! restarted.
+//!
+//! The snapshot starts with a version identifier and is encrypted with a key derived from the
+//! static secret key of the server (see [hash_domains::snapshot_key]).
+//! [CryptoServer::restore_snapshot] loads a snapshot into a freshly created server. Peers are
+//! matched by their peer id; peers in the snapshot which are missing in the new server are
+//! skipped, and peers added to the new server start without a session.
+//!
+//! Times are stored as UNIX timestamps, so the time passed between taking and restoring the
+//! snapshot counts towards the lifetime of sessions and keys.
+//!
+//! See [crate::upgrade] for how Rosenpass passes a snapshot to a new process.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::ops::DerefMut;
+//! use rosenpass::protocol::basic_types::{MsgBuf, SSk, SPk, SymKey};
+//! use rosenpass::protocol::osk_domain_separator::OskDomainSeparator;
+//! use rosenpass::protocol::{CryptoServer, PeerPtr, ProtocolVersion};
+//! use rosenpass_ciphers::StaticKem;
+//! use rosenpass_cipher_traits::primitives::Kem;
+//!
+//! rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+//!
+//! let (mut ska, mut pka) = (SSk::zero(), SPk::zero());
+//! StaticKem.keygen(ska.secret_mut(), pka.deref_mut())?;
+//! let (mut skb, mut pkb) = (SSk::zero(), SPk::zero());
+//! StaticKem.keygen(skb.secret_mut(), pkb.deref_mut())?;
+//!
+//! let psk = SymKey::random();
+//! let new_b = || -> anyhow::Result<CryptoServer> {
+//!     let mut b = CryptoServer::new(skb.clone(), pkb.clone());
+//!     b.add_peer(Some(psk.clone()), pka.clone(), ProtocolVersion::V03, OskDomainSeparator::default())?;
+//!     Ok(b)
+//! };
+//! let mut a = CryptoServer::new(ska, pka.clone());
+//! a.add_peer(Some(psk.clone()), pkb.clone(), ProtocolVersion::V03, OskDomainSeparator::default())?;
+//! let mut b = new_b()?;
+//!
+//! // Perform a handshake
+//! let (mut a_buf, mut b_buf) = (MsgBuf::zero(), MsgBuf::zero());
+//! let len = a.initiate_handshake(PeerPtr(0), &mut *a_buf)?;
+//! let len = b.handle_msg(&a_buf[..len], &mut *b_buf)?.resp.unwrap();
+//! let len = a.handle_msg(&b_buf[..len], &mut *a_buf)?.resp.unwrap();
+//! b.handle_msg(&a_buf[..len], &mut *b_buf)?;
+//!
+//! // Hand the session over to a new server
+//! let snapshot = b.snapshot()?;
+//! let mut b = new_b()?;
+//! assert_eq!(b.restore_snapshot(&snapshot)?, 1);
+//! assert_eq!(a.osk(PeerPtr(0))?.secret(), b.osk(PeerPtr(0))?.secret());
+//!
+//! // A server with a different key can not read the snapshot
+//! assert!(a.restore_snapshot(&snapshot).is_err());
+//!
+//! Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{bail, ensure, Context, Result};
+use zerocopy::{AsBytes, FromBytes, FromZeroes, Ref};
+use zeroize::Zeroizing;
+
+use rosenpass_cipher_traits::primitives::AeadWithNonceInCiphertext;
+use rosenpass_ciphers::hash_domain::SecretHashDomain;
+use rosenpass_ciphers::{KeyedHash, XAead, KEY_LEN};
+use rosenpass_secret_memory::{Public, Secret};
+
+use crate::hash_domains;
+use crate::msgs::{EmptyData, Envelope, BISCUIT_ID_LEN, SESSION_ID_LEN};
+
+use super::basic_types::{SymKey, XAEADNonce};
+use super::persistent_state::{
+    biscuit_ctr_from_bytes, created_from_unix, created_to_unix, unix_now, StatePlaintext,
+};
+use super::{CryptoServer, HandshakeRole, KnownInitConfResponse, Session};
+
+/// Identifies the format of the snapshot; precedes the encrypted snapshot and is used as
+/// additional data for the encryption
+const SNAPSHOT_VERSION: &[u8] = b"rosenpass snapshot v1\n";
+
+/// The part of the plaintext of the snapshot not specific to any peer
+#[repr(packed)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SnapshotHeader {
+    /// Biscuit keys, cookie secrets, and biscuit counter
+    keys: StatePlaintext,
+    /// The key of [CryptoServer::known_response_hasher]
+    known_response_hasher: [u8; KEY_LEN],
+    /// Number of [PeerRecord]s following the header
+    peer_count: u32,
+}
+
+/// The state of a [Session] in the snapshot
+#[repr(packed)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SessionRecord {
+    /// [Session::created_at] as UNIX timestamp
+    created_at: f64,
+    /// [Session::sidm]
+    sidm: [u8; SESSION_ID_LEN],
+    /// [Session::sidt]
+    sidt: [u8; SESSION_ID_LEN],
+    /// [Session::handshake_role]; zero for the initiator, one for the responder
+    handshake_role: u8,
+    /// [Session::ck]
+    ck: [u8; KEY_LEN],
+    /// [Session::txkm]
+    txkm: [u8; KEY_LEN],
+    /// [Session::txkt]
+    txkt: [u8; KEY_LEN],
+    /// [Session::txnm]
+    txnm: u64,
+    /// [Session::txnt]
+    txnt: u64,
+}
+
+/// A [KnownInitConfResponse] in the snapshot
+#[repr(packed)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct KnownResponseRecord {
+    /// [KnownInitConfResponse::received_at] as UNIX timestamp
+    received_at: f64,
+    /// [KnownInitConfResponse::request_mac]
+    request_mac: [u8; 16],
+    /// [KnownInitConfResponse::response]
+    response: Envelope<EmptyData>,
+}
+
+/// The state of a single peer in the snapshot
+#[repr(packed)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct PeerRecord {
+    /// The peer id ([super::Peer::pidt]) used to find the peer when restoring
+    peer_id: [u8; KEY_LEN],
+    /// [super::Peer::biscuit_used]
+    biscuit_used: [u8; BISCUIT_ID_LEN],
+    /// Whether [Self::session] is set
+    has_session: u8,
+    /// [super::Peer::session]
+    session: SessionRecord,
+    /// Whether [Self::known_response] is set
+    has_known_response: u8,
+    /// [super::Peer::known_init_conf_response]
+    known_response: KnownResponseRecord,
+}
+
+/// Length of [SnapshotHeader]
+const HEADER_LEN: usize = std::mem::size_of::<SnapshotHeader>();
+
+/// Length of [PeerRecord]
+const PEER_RECORD_LEN: usize = std::mem::size_of::<PeerRecord>();
+
+/// Length the encryption adds to the plaintext of the snapshot
+const SEAL_OVERHEAD: usize = XAead::NONCE_LEN + XAead::TAG_LEN;
+
+impl CryptoServer {
+    /// The key used to encrypt snapshots; derived from the secret key of the first identity ([Self::identities])
+    fn snapshot_key(&self) -> Result<SymKey> {
+        Ok(hash_domains::snapshot_key(KeyedHash::keyed_shake256())?
+            .turn_secret()
+            .mix(self.identities[0].sskm.secret())?
+            .into_secret())
+    }
+
+    /// Serialize and encrypt the live state of the server; see [crate::protocol::snapshot]
+    pub fn snapshot(&self) -> Result<Vec<u8>> {
+        let now = self.timebase.now();
+        let unix_now = unix_now();
+        let peers = self.peers.iter().flatten().collect::<Vec<_>>();
+
+        let mut pt = Zeroizing::new(vec![0u8; HEADER_LEN + peers.len() * PEER_RECORD_LEN]);
+        let (header, records) = pt.split_at_mut(HEADER_LEN);
+
+        let mut header: Ref<&mut [u8], SnapshotHeader> = Ref::new(header).unwrap();
+        self.store_keys(&mut header.keys, biscuit_ctr_from_bytes(&*self.biscuit_ctr));
+        header
+            .known_response_hasher
+            .copy_from_slice(self.known_response_hasher.key.secret());
+        header.peer_count = u32::try_from(peers.len()).context("Too many peers")?;
+
+        for (peer, record) in peers.iter().zip(records.chunks_exact_mut(PEER_RECORD_LEN)) {
+            let mut record: Ref<&mut [u8], PeerRecord> = Ref::new(record).unwrap();
+            record.peer_id.copy_from_slice(&*peer.pidt()?);
+            record.biscuit_used.copy_from_slice(&*peer.biscuit_used);
+
+            // The secrets are written in place to avoid leaving copies on the stack
+            if let Some(ses) = &peer.session {
+                record.has_session = 1;
+                let rec = &mut record.session;
+                rec.created_at = created_to_unix(ses.created_at, now, unix_now);
+                rec.sidm.copy_from_slice(&*ses.sidm);
+                rec.sidt.copy_from_slice(&*ses.sidt);
+                rec.handshake_role = match ses.handshake_role {
+                    HandshakeRole::Initiator => 0,
+                    HandshakeRole::Responder => 1,
+                };
+                rec.ck
+                    .copy_from_slice(ses.ck.clone().danger_into_secret().secret());
+                rec.txkm.copy_from_slice(ses.txkm.secret());
+                rec.txkt.copy_from_slice(ses.txkt.secret());
+                rec.txnm = ses.txnm;
+                rec.txnt = ses.txnt;
+            }
+
+            if let Some(known) = &peer.known_init_conf_response {
+                record.has_known_response = 1;
+                let rec = &mut record.known_response;
+                rec.received_at = created_to_unix(known.received_at, now, unix_now);
+                rec.request_mac.copy_from_slice(&*known.request_mac);
+                rec.response = known.response.clone();
+            }
+        }
+
+        let mut sealed = vec![0u8; SNAPSHOT_VERSION.len() + pt.len() + SEAL_OVERHEAD];
+        let (version, ciphertext) = sealed.split_at_mut(SNAPSHOT_VERSION.len());
+        version.copy_from_slice(SNAPSHOT_VERSION);
+        let n = XAEADNonce::random();
+        XAead.encrypt_with_nonce_in_ctxt(
+            ciphertext,
+            self.snapshot_key()?.secret(),
+            &*n,
+            SNAPSHOT_VERSION,
+            &pt,
+        )?;
+
+        Ok(sealed)
+    }
+
+    /// Load a snapshot produced by [Self::snapshot]; see [crate::protocol::snapshot]
+    ///
+    /// This should be called right after creating the server and adding the peers, before any
+    /// messages are processed. Fails if the snapshot was not produced by a server with the same
+    /// static secret key.
+    ///
+    /// Returns the number of peers whose state was restored.
+    pub fn restore_snapshot(&mut self, sealed: &[u8]) -> Result<usize> {
+        let ciphertext = sealed
+            .strip_prefix(SNAPSHOT_VERSION)
+            .context("Not a snapshot, or a snapshot of an unsupported version")?;
+        ensure!(
+            ciphertext.len() >= HEADER_LEN + SEAL_OVERHEAD,
+            "Snapshot is truncated"
+        );
+
+        let mut pt = Zeroizing::new(vec![0u8; ciphertext.len() - SEAL_OVERHEAD]);
+        XAead
+            .decrypt_with_nonce_in_ctxt(
+                &mut pt,
+                self.snapshot_key()?.secret(),
+                SNAPSHOT_VERSION,
+                ciphertext,
+            )
+            .context("Could not decrypt snapshot; was it taken using another secret key?")?;
+        let (header, records) = pt.split_at(HEADER_LEN);
+
+        let header: Ref<&[u8], SnapshotHeader> = Ref::new(header).unwrap();
+        let peer_count = header.peer_count as usize;
+        ensure!(
+            records.len() == peer_count * PEER_RECORD_LEN,
+            "Snapshot has wrong length for {peer_count} peers"
+        );
+        self.load_keys(&header.keys);
+        self.known_response_hasher.key = SymKey::from_slice(&header.known_response_hasher);
+
+        let now = self.timebase.now();
+        let unix_now = unix_now();
+        let mut restored = 0;
+        for record in records.chunks_exact(PEER_RECORD_LEN) {
+            let record: Ref<&[u8], PeerRecord> = Ref::new(record).unwrap();
+            let Some(peer) = self.find_peer(Public::from_slice(&record.peer_id)) else {
+                continue;
+            };
+            peer.get_mut(self).biscuit_used = Public::from_slice(&record.biscuit_used);
+
+            if record.has_session != 0 {
+                let rec = &record.session;
+                let handshake_role = match rec.handshake_role {
+                    0 => HandshakeRole::Initiator,
+                    1 => HandshakeRole::Responder,
+                    role => bail!("Invalid handshake role {role} in snapshot"),
+                };
+                let keyed_hash = peer.get(self).cipher_suite.keyed_hash();
+                let ses = Session {
+                    created_at: created_from_unix(rec.created_at, now, unix_now),
+                    sidm: Public::new(rec.sidm),
+                    sidt: Public::new(rec.sidt),
+                    handshake_role,
+                    ck: SecretHashDomain::danger_from_secret(
+                        Secret::from_slice(&rec.ck),
+                        keyed_hash,
+                    )
+                    .dup(),
+                    txkm: SymKey::from_slice(&rec.txkm),
+                    txkt: SymKey::from_slice(&rec.txkt),
+                    txnm: rec.txnm,
+                    txnt: rec.txnt,
+                };
+                peer.session().insert(self, ses)?;
+            }
+
+            if record.has_known_response != 0 {
+                let rec = &record.known_response;
+                let known = KnownInitConfResponse {
+                    received_at: created_from_unix(rec.received_at, now, unix_now),
+                    request_mac: Public::new(rec.request_mac),
+                    response: rec.response.clone(),
+                };
+                peer.known_init_conf_response().insert(self, known);
+            }
+
+            restored += 1;
+        }
+
+        Ok(restored)
+    }
+}
@@ -11,12 +11,13 @@ use rosenpass_util::mem::DiscardResultExt;
 
 use crate::msgs::{EmptyData, Envelope, InitConf, InitHello, MsgType, RespHello, MAX_MESSAGE_LEN};
 
-use super::basic_types::{MsgBuf, SPk, SSk, SymKey};
+use super::basic_types::{BiscuitId, MsgBuf, SPk, SSk, SymKey};
 use super::constants::REKEY_AFTER_TIME_RESPONDER;
 use super::osk_domain_separator::OskDomainSeparator;
 use super::zerocopy::{truncating_cast_into, truncating_cast_into_nomut};
 use super::{
-    CryptoServer, HandleMsgResult, HostIdentification, KnownInitConfResponsePtr, PeerPtr,
+    AeadChoice, CipherSuite, CipherSuiteMismatch, CryptoServer, EphemeralKemChoice,
+    HandleMsgResult, HostIdentification, IdentityPtr, KnownInitConfResponsePtr, Peer, PeerPtr,
     PollResult, ProtocolVersion,
 };
 
@@ -207,6 +208,7 @@ fn test_regular_exchange(protocol_version: ProtocolVersion) {
         let HandleMsgResult {
             resp,
             exchanged_with,
+            authenticated_by,
         } = a
             .handle_msg(&b_to_a_buf[..resp_hello_len], &mut *a_to_b_buf)
             .unwrap();
@@ -215,20 +217,37 @@ fn test_regular_exchange(protocol_version: ProtocolVersion) {
         let init_conf_msg_type: MsgType = a_to_b_buf.value[0].try_into().unwrap();
 
         assert_eq!(exchanged_with, Some(PeerPtr(0)));
+        assert_eq!(authenticated_by, Some(PeerPtr(0)));
         assert_eq!(init_conf_msg_type, MsgType::InitConf);
 
         //B handles InitConf, sends EmptyData
         let HandleMsgResult {
-            resp: _,
+            resp,
             exchanged_with,
+            authenticated_by,
         } = b
             .handle_msg(&a_to_b_buf.as_slice()[..init_conf_len], &mut *b_to_a_buf)
             .unwrap();
 
+        let empty_data_len = resp.unwrap();
         let empty_data_msg_type: MsgType = b_to_a_buf.value[0].try_into().unwrap();
 
         assert_eq!(exchanged_with, Some(PeerPtr(0)));
+        assert_eq!(authenticated_by, Some(PeerPtr(0)));
         assert_eq!(empty_data_msg_type, MsgType::EmptyData);
+
+        //A handles EmptyData
+        let HandleMsgResult {
+            resp,
+            exchanged_with,
+            authenticated_by,
+        } = a
+            .handle_msg(&b_to_a_buf.as_slice()[..empty_data_len], &mut *a_to_b_buf)
+            .unwrap();
+
+        assert!(resp.is_none());
+        assert!(exchanged_with.is_none());
+        assert_eq!(authenticated_by, Some(PeerPtr(0)));
     });
 }
 
@@ -279,6 +298,7 @@ fn test_regular_init_conf_retransmit(protocol_version: ProtocolVersion) {
         let HandleMsgResult {
             resp,
             exchanged_with,
+            authenticated_by,
         } = a
             .handle_msg(&b_to_a_buf[..resp_hello_len], &mut *a_to_b_buf)
             .unwrap();
@@ -287,12 +307,14 @@ fn test_regular_init_conf_retransmit(protocol_version: ProtocolVersion) {
         let init_conf_msg_type: MsgType = a_to_b_buf.value[0].try_into().unwrap();
 
         assert_eq!(exchanged_with, Some(PeerPtr(0)));
+        assert_eq!(authenticated_by, Some(PeerPtr(0)));
         assert_eq!(init_conf_msg_type, MsgType::InitConf);
 
         //B handles InitConf, sends EmptyData
         let HandleMsgResult {
             resp: _,
             exchanged_with,
+            authenticated_by,
         } = b
             .handle_msg(&a_to_b_buf.as_slice()[..init_conf_len], &mut *b_to_a_buf)
             .unwrap();
@@ -300,12 +322,15 @@ fn test_regular_init_conf_retransmit(protocol_version: ProtocolVersion) {
         let empty_data_msg_type: MsgType = b_to_a_buf.value[0].try_into().unwrap();
 
         assert_eq!(exchanged_with, Some(PeerPtr(0)));
+        assert_eq!(authenticated_by, Some(PeerPtr(0)));
         assert_eq!(empty_data_msg_type, MsgType::EmptyData);
 
-        //B handles InitConf again, sends EmptyData
+        //B handles InitConf again, sends EmptyData; the retransmission could be a replay, so
+        //its sender must not be taken as the address of the peer
         let HandleMsgResult {
             resp: _,
             exchanged_with,
+            authenticated_by,
         } = b
             .handle_msg(&a_to_b_buf.as_slice()[..init_conf_len], &mut *b_to_a_buf)
             .unwrap();
@@ -313,6 +338,7 @@ fn test_regular_init_conf_retransmit(protocol_version: ProtocolVersion) {
         let empty_data_msg_type: MsgType = b_to_a_buf.value[0].try_into().unwrap();
 
         assert!(exchanged_with.is_none());
+        assert!(authenticated_by.is_none());
         assert_eq!(empty_data_msg_type, MsgType::EmptyData);
     });
 }
@@ -363,6 +389,8 @@ fn cookie_reply_mechanism_responder_under_load(protocol_version: ProtocolVersion
 
         let ip_addr_port_a: VecHostIdentifier = ip_addr_port_a.into();
 
+        assert!(!b.has_valid_cookie(&a_to_b_buf.as_slice()[..init_hello_len], &ip_addr_port_a));
+
         //B handles handshake under load, should send cookie reply message with invalid cookie
         let HandleMsgResult { resp, .. } = b
             .handle_msg_under_load(
@@ -415,6 +443,8 @@ fn cookie_reply_mechanism_responder_under_load(protocol_version: ProtocolVersion
 
         let retx_msg_type: MsgType = a_to_b_buf.value[0].try_into().unwrap();
         assert_eq!(retx_msg_type, MsgType::InitHello);
+        let retx_init_hello = &a_to_b_buf.as_slice()[..retx_init_hello_len];
+        assert!(b.has_valid_cookie(retx_init_hello, &ip_addr_port_a));
 
         //B handles retransmitted message
         let HandleMsgResult { resp, .. } = b
@@ -612,6 +642,7 @@ fn init_conf_retransmission(protocol_version: ProtocolVersion) -> anyhow::Result
         let res = srv.handle_init_conf(
             &ic.payload,
             &mut discard_resp_conf,
+            IdentityPtr(0),
             protocol_version.clone().keyed_hash(),
         );
         assert!(res.is_err());
@@ -697,7 +728,698 @@ fn init_conf_retransmission(protocol_version: ProtocolVersion) -> anyhow::Result
     // since the biscuit is stale
     poll(&mut b)?;
     check_faulty_proc_init_conf(&mut b, &ic1); // ic1 is now effectively broken
-    assert!(b.peers[0].known_init_conf_response.is_none()); // The cache is gone
+    assert!(PeerPtr(0).get(&b).known_init_conf_response.is_none()); // The cache is gone
 
     Ok(())
 }
+
+#[test]
+#[serial]
+fn remove_peer_v02() -> Result<()> {
+    remove_peer(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn remove_peer_v03() -> Result<()> {
+    remove_peer(ProtocolVersion::V03)
+}
+
+fn remove_peer(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        type MsgBufPlus = Public<MAX_MESSAGE_LEN>;
+        let (mut a, mut b) = make_server_pair(protocol_version.clone())?;
+        let a_peer = PeerPtr(0);
+
+        // A second peer on b which must survive the removal of a
+        let (_, pkc) = keygen()?;
+        let c_peer = b.add_peer(
+            None,
+            pkc.clone(),
+            protocol_version,
+            OskDomainSeparator::default(),
+        )?;
+
+        // Full handshake, so b holds a session and a known response for a
+        let (mut a_to_b, mut b_to_a) = (MsgBufPlus::zero(), MsgBufPlus::zero());
+        let len = a.initiate_handshake(PeerPtr(0), &mut *a_to_b)?;
+        let len = b.handle_msg(&a_to_b[..len], &mut *b_to_a)?.resp.unwrap();
+        let init_conf_len = a.handle_msg(&b_to_a[..len], &mut *a_to_b)?.resp.unwrap();
+        let init_conf = a_to_b[..init_conf_len].to_vec();
+        let res = b.handle_msg(&init_conf, &mut *b_to_a)?;
+        assert_eq!(res.exchanged_with, Some(a_peer));
+        assert!(a_peer.session().get(&b).is_some());
+        assert!(a_peer.known_init_conf_response().get(&b).is_some());
+
+        let a_pidt = a_peer.get(&b).pidt()?;
+        b.remove_peer(a_peer)?;
+
+        // All state associated with a is gone
+        assert!(!b.contains_peer(a_peer));
+        assert_eq!(b.find_peer(a_pidt), None);
+        assert!(b.index.values().all(|no| *no != a_peer.0));
+        assert!(b.remove_peer(a_peer).is_err());
+
+        // The other peer is still addressable through the same pointer
+        assert!(b.contains_peer(c_peer));
+        assert_eq!(c_peer.get(&b).spkt, pkc);
+        assert_eq!(b.find_peer(c_peer.get(&b).pidt()?), Some(c_peer));
+        assert_eq!(b.peer_ptrs().collect::<Vec<_>>(), vec![c_peer]);
+
+        // Retransmitted InitConf and fresh InitHello messages from a are rejected
+        assert!(b.handle_msg(&init_conf, &mut *b_to_a).is_err());
+        let len = a.initiate_handshake(PeerPtr(0), &mut *a_to_b)?;
+        assert!(b.handle_msg(&a_to_b[..len], &mut *b_to_a).is_err());
+
+        // Polling only ever yields events for the remaining peer
+        for _ in 0..4 {
+            match b.poll()? {
+                PollResult::Sleep(_) => break,
+                ev => assert_eq!(ev.peer(), Some(c_peer)),
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn restored_state_accepts_biscuits_v02() -> Result<()> {
+    restored_state_accepts_biscuits(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn restored_state_accepts_biscuits_v03() -> Result<()> {
+    restored_state_accepts_biscuits(ProtocolVersion::V03)
+}
+
+fn restored_state_accepts_biscuits(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        type MsgBufPlus = Public<MAX_MESSAGE_LEN>;
+        let psk = SymKey::random();
+        let ((ska, pka), (skb, pkb)) = (keygen()?, keygen()?);
+        let mut a = CryptoServer::new(ska, pka.clone());
+        a.add_peer(
+            Some(psk.clone()),
+            pkb.clone(),
+            protocol_version.clone(),
+            OskDomainSeparator::default(),
+        )?;
+        let responder = || -> Result<CryptoServer> {
+            let mut b = CryptoServer::new(skb.clone(), pkb.clone());
+            b.add_peer(
+                Some(psk.clone()),
+                pka.clone(),
+                protocol_version.clone(),
+                OskDomainSeparator::default(),
+            )?;
+            Ok(b)
+        };
+
+        // b issues a biscuit, then stores its state and restarts
+        let mut b = responder()?;
+        let (mut a_to_b, mut b_to_a) = (MsgBufPlus::zero(), MsgBufPlus::zero());
+        let len = a.initiate_handshake(PeerPtr(0), &mut *a_to_b)?;
+        let len = b.handle_msg(&a_to_b[..len], &mut *b_to_a)?.resp.unwrap();
+        let (sealed, _) = b.seal_state()?;
+        let biscuit_ctr = b.biscuit_ctr.clone();
+        drop(b);
+
+        let init_conf_len = a.handle_msg(&b_to_a[..len], &mut *a_to_b)?.resp.unwrap();
+        let init_conf = a_to_b[..init_conf_len].to_vec();
+
+        // Without the state, the biscuit can not be decrypted
+        let mut b = responder()?;
+        assert!(b.handle_msg(&init_conf, &mut *b_to_a).is_err());
+
+        // With the state, the handshake completes
+        let mut b = responder()?;
+        let saved = b.restore_state(&sealed)?;
+        assert!(!b.state_changed_since(&saved));
+        let ctr = |id: &BiscuitId| {
+            let mut v = [0u8; 16];
+            v[..id.value.len()].copy_from_slice(&id.value);
+            u128::from_le_bytes(v)
+        };
+        assert!(ctr(&b.biscuit_ctr) > ctr(&biscuit_ctr));
+        let res = b.handle_msg(&init_conf, &mut *b_to_a)?;
+        assert_eq!(res.exchanged_with, Some(PeerPtr(0)));
+        assert_eq!(a.osk(PeerPtr(0))?.secret(), b.osk(PeerPtr(0))?.secret());
+
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn restored_state_rejects_replayed_init_conf_v02() -> Result<()> {
+    restored_state_rejects_replayed_init_conf(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn restored_state_rejects_replayed_init_conf_v03() -> Result<()> {
+    restored_state_rejects_replayed_init_conf(ProtocolVersion::V03)
+}
+
+fn restored_state_rejects_replayed_init_conf(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        type MsgBufPlus = Public<MAX_MESSAGE_LEN>;
+        let psk = SymKey::random();
+        let ((ska, pka), (skb, pkb)) = (keygen()?, keygen()?);
+        let mut a = CryptoServer::new(ska, pka.clone());
+        a.add_peer(
+            Some(psk.clone()),
+            pkb.clone(),
+            protocol_version.clone(),
+            OskDomainSeparator::default(),
+        )?;
+        let responder = || -> Result<CryptoServer> {
+            let mut b = CryptoServer::new(skb.clone(), pkb.clone());
+            b.add_peer(
+                Some(psk.clone()),
+                pka.clone(),
+                protocol_version.clone(),
+                OskDomainSeparator::default(),
+            )?;
+            Ok(b)
+        };
+
+        // A complete handshake; the InitConf is captured
+        let mut b = responder()?;
+        let (_, saved) = b.seal_state()?;
+        let (mut a_to_b, mut b_to_a) = (MsgBufPlus::zero(), MsgBufPlus::zero());
+        let len = a.initiate_handshake(PeerPtr(0), &mut *a_to_b)?;
+        let len = b.handle_msg(&a_to_b[..len], &mut *b_to_a)?.resp.unwrap();
+        let len = a.handle_msg(&b_to_a[..len], &mut *a_to_b)?.resp.unwrap();
+        let init_conf = a_to_b[..len].to_vec();
+        b.handle_msg(&init_conf, &mut *b_to_a)?;
+
+        // Accepting the biscuit requires the state to be stored again
+        assert!(b.state_changed_since(&saved));
+        let (sealed, _) = b.seal_state()?;
+        drop(b);
+
+        // Without the biscuit numbers used, the restarted server would accept the replay
+        let mut b = responder()?;
+        let (keys_only, _) = {
+            let mut donor = responder()?;
+            donor.restore_state(&sealed)?;
+            donor.peers[0].as_mut().unwrap().biscuit_used = BiscuitId::zero();
+            donor.seal_state()?
+        };
+        b.restore_state(&keys_only)?;
+        let res = b.handle_msg(&init_conf, &mut *b_to_a)?;
+        assert_eq!(res.exchanged_with, Some(PeerPtr(0)));
+
+        // With the full state, the replayed InitConf is rejected
+        let mut b = responder()?;
+        b.restore_state(&sealed)?;
+        assert!(b.handle_msg(&init_conf, &mut *b_to_a).is_err());
+        assert!(b.osk(PeerPtr(0)).is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn restored_snapshot_continues_session_v02() -> Result<()> {
+    restored_snapshot_continues_session(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn restored_snapshot_continues_session_v03() -> Result<()> {
+    restored_snapshot_continues_session(ProtocolVersion::V03)
+}
+
+fn restored_snapshot_continues_session(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        type MsgBufPlus = Public<MAX_MESSAGE_LEN>;
+        let psk = SymKey::random();
+        let ((ska, pka), (skb, pkb)) = (keygen()?, keygen()?);
+        let mut a = CryptoServer::new(ska, pka.clone());
+        a.add_peer(
+            Some(psk.clone()),
+            pkb.clone(),
+            protocol_version.clone(),
+            OskDomainSeparator::default(),
+        )?;
+        let responder = || -> Result<CryptoServer> {
+            let mut b = CryptoServer::new(skb.clone(), pkb.clone());
+            b.add_peer(
+                Some(psk.clone()),
+                pka.clone(),
+                protocol_version.clone(),
+                OskDomainSeparator::default(),
+            )?;
+            Ok(b)
+        };
+
+        // Complete a handshake with b
+        let mut b = responder()?;
+        let (mut a_to_b, mut b_to_a) = (MsgBufPlus::zero(), MsgBufPlus::zero());
+        let len = a.initiate_handshake(PeerPtr(0), &mut *a_to_b)?;
+        let len = b.handle_msg(&a_to_b[..len], &mut *b_to_a)?.resp.unwrap();
+        let len = a.handle_msg(&b_to_a[..len], &mut *a_to_b)?.resp.unwrap();
+        let init_conf = a_to_b[..len].to_vec();
+        let len = b.handle_msg(&init_conf, &mut *b_to_a)?.resp.unwrap();
+        let empty_data = b_to_a[..len].to_vec();
+
+        // Hand the state over to a new server
+        let snapshot = b.snapshot()?;
+        let mut restored = responder()?;
+        assert_eq!(restored.restore_snapshot(&snapshot)?, 1);
+        assert_eq!(
+            restored.osk(PeerPtr(0))?.secret(),
+            b.osk(PeerPtr(0))?.secret()
+        );
+        assert_eq!(
+            PeerPtr(0).get(&restored).biscuit_used,
+            PeerPtr(0).get(&b).biscuit_used
+        );
+        let sidm = PeerPtr(0).session().get(&b).as_ref().unwrap().sidm;
+        assert!(restored.lookup_session(sidm).is_some());
+
+        // A retransmitted InitConf is answered from the known response cache
+        let res = restored.handle_msg(&init_conf, &mut *b_to_a)?;
+        assert_eq!(res.exchanged_with, None);
+        assert_eq!(&b_to_a[..res.resp.unwrap()], &empty_data[..]);
+
+        // The snapshot is of no use to a server with another key
+        let (skc, pkc) = keygen()?;
+        assert!(CryptoServer::new(skc, pkc)
+            .restore_snapshot(&snapshot)
+            .is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn multiple_identities_v02() -> Result<()> {
+    multiple_identities(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn multiple_identities_v03() -> Result<()> {
+    multiple_identities(ProtocolVersion::V03)
+}
+
+fn multiple_identities(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        type MsgBufPlus = Public<MAX_MESSAGE_LEN>;
+
+        // Full handshake initiated by `ini`, returning the peer of `res` the key was exchanged with
+        let handshake = |ini: &mut CryptoServer, res: &mut CryptoServer| -> Result<PeerPtr> {
+            let (mut ini_to_res, mut res_to_ini) = (MsgBufPlus::zero(), MsgBufPlus::zero());
+            let len = ini.initiate_handshake(PeerPtr(0), &mut *ini_to_res)?;
+            let len = (res.handle_msg(&ini_to_res[..len], &mut *res_to_ini)?.resp)
+                .context("No RespHello")?;
+            let len = (ini.handle_msg(&res_to_ini[..len], &mut *ini_to_res)?.resp)
+                .context("No InitConf")?;
+            let res = res.handle_msg(&ini_to_res[..len], &mut *res_to_ini)?;
+            res.exchanged_with.context("No key exchanged")
+        };
+        let initiator = |pk_res: &SPk| -> Result<CryptoServer> {
+            let (sk, pk) = keygen()?;
+            let mut srv = CryptoServer::new(sk, pk);
+            srv.add_peer(
+                None,
+                pk_res.clone(),
+                protocol_version.clone(),
+                OskDomainSeparator::default(),
+            )?;
+            Ok(srv)
+        };
+
+        // b serves two tenants, each with its own keypair
+        let ((skb0, pkb0), (skb1, pkb1)) = (keygen()?, keygen()?);
+        let mut b = CryptoServer::new(skb0, pkb0.clone());
+        let id1 = b.add_identity(skb1.clone(), pkb1.clone())?;
+        assert_eq!(id1, IdentityPtr(1));
+        assert!(b.add_identity(skb1, pkb1.clone()).is_err());
+
+        let mut a = initiator(&pkb0)?;
+        let mut c = initiator(&pkb1)?;
+        let mut d = initiator(&pkb0)?;
+        let a_peer = b.add_peer(
+            None,
+            a.identities[0].spkm.clone(),
+            protocol_version.clone(),
+            OskDomainSeparator::default(),
+        )?;
+        let c_peer = b.add_peer_for_identity(
+            id1,
+            None,
+            c.identities[0].spkm.clone(),
+            protocol_version.clone(),
+            OskDomainSeparator::default(),
+        )?;
+        // d is a peer of the second tenant but addresses the first one
+        b.add_peer_for_identity(
+            id1,
+            None,
+            d.identities[0].spkm.clone(),
+            protocol_version.clone(),
+            OskDomainSeparator::default(),
+        )?;
+
+        assert_eq!(handshake(&mut a, &mut b)?, a_peer);
+        assert_eq!(a.osk(PeerPtr(0))?.secret(), b.osk(a_peer)?.secret());
+
+        assert_eq!(handshake(&mut c, &mut b)?, c_peer);
+        assert_eq!(c.osk(PeerPtr(0))?.secret(), b.osk(c_peer)?.secret());
+
+        assert!(handshake(&mut d, &mut b).is_err());
+
+        // Messages addressed to neither identity are rejected before any decapsulation
+        let mut e = initiator(&keygen()?.1)?;
+        assert!(handshake(&mut e, &mut b).is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn key_rotation_v02() -> Result<()> {
+    key_rotation(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn key_rotation_v03() -> Result<()> {
+    key_rotation(ProtocolVersion::V03)
+}
+
+fn key_rotation(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        type MsgBufPlus = Public<MAX_MESSAGE_LEN>;
+
+        // Full handshake initiated by `ini` with its first peer
+        let handshake = |ini: &mut CryptoServer, res: &mut CryptoServer| -> Result<()> {
+            let (mut ini_to_res, mut res_to_ini) = (MsgBufPlus::zero(), MsgBufPlus::zero());
+            let len = ini.initiate_handshake(PeerPtr(0), &mut *ini_to_res)?;
+            let len = (res.handle_msg(&ini_to_res[..len], &mut *res_to_ini)?.resp)
+                .context("No RespHello")?;
+            let len = (ini.handle_msg(&res_to_ini[..len], &mut *ini_to_res)?.resp)
+                .context("No InitConf")?;
+            let res_peer = (res.handle_msg(&ini_to_res[..len], &mut *res_to_ini)?)
+                .exchanged_with
+                .context("No key exchanged")?;
+            assert_eq!(ini.osk(PeerPtr(0))?.secret(), res.osk(res_peer)?.secret());
+            Ok(())
+        };
+        let add_peer = |srv: &mut CryptoServer, pk: &SPk| {
+            srv.add_peer(
+                None,
+                pk.clone(),
+                protocol_version.clone(),
+                OskDomainSeparator::default(),
+            )
+        };
+
+        // b is rotating its key; a learns about the new key, c does not
+        let ((ska, pka), (skb, pkb), (skc, pkc)) = (keygen()?, keygen()?, keygen()?);
+        let mut a = CryptoServer::new(ska, pka.clone());
+        let mut b = CryptoServer::new(skb, pkb.clone());
+        let mut c = CryptoServer::new(skc, pkc.clone());
+        let a_peer = add_peer(&mut b, &pka)?;
+        add_peer(&mut b, &pkc)?;
+        let b_peer = add_peer(&mut a, &pkb)?;
+        add_peer(&mut c, &pkb)?;
+
+        let (skb2, pkb2) = keygen()?;
+        b.set_next_identity_key(IdentityPtr(0), Some((skb2, pkb2.clone())))?;
+        a.set_next_peer_key(b_peer, Some(pkb2.clone()))?;
+        handshake(&mut a, &mut b)?;
+        handshake(&mut b, &mut a)?;
+
+        // After promotion, b uses its new key while still accepting the previous one
+        b.promote_identity_key(IdentityPtr(0), 3600.0)?;
+        assert_eq!(b.identities[0].spkm, pkb2);
+        assert!(a_peer.get(&b).initiation_forced);
+        handshake(&mut b, &mut a)?;
+        handshake(&mut a, &mut b)?;
+        handshake(&mut c, &mut b)?;
+
+        a.promote_peer_key(b_peer, 3600.0)?;
+        assert_eq!(b_peer.get(&a).spkt, pkb2);
+        handshake(&mut a, &mut b)?;
+        handshake(&mut b, &mut a)?;
+
+        // Once the overlap is over, the previous key is forgotten
+        let now = b.timebase.now();
+        (b.identities[0].alternate.as_mut().unwrap()).expires_at = Some(now);
+        b.poll()?;
+        assert!(b.identities[0].alternate.is_none());
+        assert!(handshake(&mut c, &mut b).is_err());
+        handshake(&mut a, &mut b)?;
+
+        let previous_pidb = Peer::pidt_of(&pkb, protocol_version.keyed_hash())?;
+        assert_eq!(a.find_peer(previous_pidb), Some(b_peer));
+        let now = a.timebase.now();
+        (b_peer.get_mut(&mut a).alternate_spkt.as_mut().unwrap()).expires_at = Some(now);
+        a.poll()?;
+        assert!(b_peer.get(&a).alternate_spkt.is_none());
+        assert_eq!(a.find_peer(previous_pidb), None);
+
+        // Nothing left to promote
+        assert!(b.promote_identity_key(IdentityPtr(0), 3600.0).is_err());
+        assert!(a.promote_peer_key(b_peer, 3600.0).is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn ephemeral_kem_v02() -> Result<()> {
+    ephemeral_kem(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn ephemeral_kem_v03() -> Result<()> {
+    ephemeral_kem(ProtocolVersion::V03)
+}
+
+fn ephemeral_kem(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        let (mut a_buf, mut b_buf) = (MsgBuf::zero(), MsgBuf::zero());
+
+        let kems = EphemeralKemChoice::ALL.into_iter();
+        for (kem, hybrid_x25519) in kems.flat_map(|kem| [(kem, false), (kem, true)]) {
+            let (mut a, mut b) = make_server_pair(protocol_version.clone())?;
+            let suite = CipherSuite {
+                ephemeral_kem: kem,
+                hybrid_x25519,
+                ..CipherSuite::new(protocol_version.clone())
+            };
+            a.set_peer_cipher_suite(PeerPtr(0), suite.clone())?;
+            b.set_peer_cipher_suite(PeerPtr(0), suite)?;
+
+            // Message sizes follow the chosen KEM and the use of X25519
+            let ih_len = a.initiate_handshake(PeerPtr(0), &mut *a_buf)?;
+            assert_eq!(ih_len, kem.init_hello_len(hybrid_x25519));
+            let rh_len = (b.handle_msg(&a_buf[..ih_len], &mut *b_buf)?.resp)
+                .context("Failed to produce RespHello message")?;
+            assert_eq!(rh_len, kem.resp_hello_len(hybrid_x25519));
+
+            let res = a.handle_msg(&b_buf[..rh_len], &mut *a_buf)?;
+            assert_eq!(res.exchanged_with, Some(PeerPtr(0)));
+            let ic_len = res.resp.context("Failed to produce InitConf message")?;
+            let res = b.handle_msg(&a_buf[..ic_len], &mut *b_buf)?;
+            assert_eq!(res.exchanged_with, Some(PeerPtr(0)));
+
+            assert_eq!(a.osk(PeerPtr(0))?.secret(), b.osk(PeerPtr(0))?.secret());
+        }
+
+        // Peers configured with different ephemeral KEMs reject each other's initiations
+        let (mut a, mut b) = make_server_pair(protocol_version.clone())?;
+        let a_suite = CipherSuite {
+            ephemeral_kem: EphemeralKemChoice::MlKem768,
+            ..CipherSuite::new(protocol_version.clone())
+        };
+        let b_suite = CipherSuite::new(protocol_version.clone());
+        a.set_peer_cipher_suite(PeerPtr(0), a_suite.clone())?;
+
+        let ih_len = a.initiate_handshake(PeerPtr(0), &mut *a_buf)?;
+        let err = (b.handle_msg(&a_buf[..ih_len], &mut *b_buf)).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CipherSuiteMismatch>(),
+            Some(&CipherSuiteMismatch {
+                configured: b_suite,
+                received: a_suite
+            })
+        );
+
+        let ih_len = b.initiate_handshake(PeerPtr(0), &mut *b_buf)?;
+        let err = (a.handle_msg(&b_buf[..ih_len], &mut *a_buf)).unwrap_err();
+        assert!(err.is::<CipherSuiteMismatch>());
+
+        // The same goes for peers disagreeing on the hybrid X25519 exchange
+        let (mut a, mut b) = make_server_pair(protocol_version.clone())?;
+        let a_suite = CipherSuite {
+            hybrid_x25519: true,
+            ..CipherSuite::new(protocol_version.clone())
+        };
+        a.set_peer_cipher_suite(PeerPtr(0), a_suite)?;
+
+        let ih_len = a.initiate_handshake(PeerPtr(0), &mut *a_buf)?;
+        let err = (b.handle_msg(&a_buf[..ih_len], &mut *b_buf)).unwrap_err();
+        assert!(err.is::<CipherSuiteMismatch>());
+
+        let ih_len = b.initiate_handshake(PeerPtr(0), &mut *b_buf)?;
+        let err = (a.handle_msg(&b_buf[..ih_len], &mut *a_buf)).unwrap_err();
+        assert!(err.is::<CipherSuiteMismatch>());
+
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn aead_v02() -> Result<()> {
+    aead(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn aead_v03() -> Result<()> {
+    aead(ProtocolVersion::V03)
+}
+
+fn aead(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        let (mut a_buf, mut b_buf) = (MsgBuf::zero(), MsgBuf::zero());
+
+        for aead in AeadChoice::ALL {
+            let (mut a, mut b) = make_server_pair(protocol_version.clone())?;
+            let suite = CipherSuite {
+                aead,
+                ..CipherSuite::new(protocol_version.clone())
+            };
+            a.set_peer_cipher_suite(PeerPtr(0), suite.clone())?;
+            b.set_peer_cipher_suite(PeerPtr(0), suite)?;
+
+            let ih_len = a.initiate_handshake(PeerPtr(0), &mut *a_buf)?;
+            let rh_len = (b.handle_msg(&a_buf[..ih_len], &mut *b_buf)?.resp)
+                .context("Failed to produce RespHello message")?;
+            let res = a.handle_msg(&b_buf[..rh_len], &mut *a_buf)?;
+            let ic_len = res.resp.context("Failed to produce InitConf message")?;
+            let res = b.handle_msg(&a_buf[..ic_len], &mut *b_buf)?;
+            assert_eq!(res.exchanged_with, Some(PeerPtr(0)));
+            let rc_len = res.resp.context("Failed to produce EmptyData message")?;
+
+            // The acknowledgement uses the AEAD as well
+            let res = a.handle_msg(&b_buf[..rc_len], &mut *a_buf)?;
+            assert_eq!(res.resp, None);
+            assert!(PeerPtr(0).hs().get(&a).is_none());
+
+            assert_eq!(a.osk(PeerPtr(0))?.secret(), b.osk(PeerPtr(0))?.secret());
+        }
+
+        // Peers configured with different AEADs reject each other's initiations
+        let (mut a, mut b) = make_server_pair(protocol_version.clone())?;
+        let a_suite = CipherSuite {
+            aead: AeadChoice::Aes256Gcm,
+            ..CipherSuite::new(protocol_version.clone())
+        };
+        let b_suite = CipherSuite::new(protocol_version.clone());
+        a.set_peer_cipher_suite(PeerPtr(0), a_suite.clone())?;
+
+        let ih_len = a.initiate_handshake(PeerPtr(0), &mut *a_buf)?;
+        let err = (b.handle_msg(&a_buf[..ih_len], &mut *b_buf)).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CipherSuiteMismatch>(),
+            Some(&CipherSuiteMismatch {
+                configured: b_suite.clone(),
+                received: a_suite.clone()
+            })
+        );
+
+        let ih_len = b.initiate_handshake(PeerPtr(0), &mut *b_buf)?;
+        let err = (a.handle_msg(&b_buf[..ih_len], &mut *a_buf)).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CipherSuiteMismatch>(),
+            Some(&CipherSuiteMismatch {
+                configured: a_suite,
+                received: b_suite
+            })
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn decapsulation_ahead_of_time_v02() -> Result<()> {
+    decapsulation_ahead_of_time(ProtocolVersion::V02)
+}
+
+#[test]
+#[serial]
+fn decapsulation_ahead_of_time_v03() -> Result<()> {
+    decapsulation_ahead_of_time(ProtocolVersion::V03)
+}
+
+fn decapsulation_ahead_of_time(protocol_version: ProtocolVersion) -> Result<()> {
+    setup_logging();
+    rosenpass_secret_memory::secret_policy_try_use_memfd_secrets();
+    stacker::grow(8 * 1024 * 1024, || {
+        let (mut a, mut b) = make_server_pair(protocol_version)?;
+        let (mut a_buf, mut b_buf) = (MsgBuf::zero(), MsgBuf::zero());
+
+        // The decapsulation of an earlier InitHello is not used for a later one
+        let ih_len = a.initiate_handshake(PeerPtr(0), &mut *a_buf)?;
+        let stale = (b.decapsulation_job(&a_buf[..ih_len])?)
+            .context("No decapsulation job for InitHello")?
+            .run()?;
+        let ih_len = a.initiate_handshake(PeerPtr(0), &mut *a_buf)?;
+        let res =
+            b.with_decapsulation(Some(stale), |b| b.handle_msg(&a_buf[..ih_len], &mut *b_buf))?;
+        let rh_len = res.resp.context("Failed to produce RespHello message")?;
+        assert!(b.decapsulation.is_none());
+
+        let decapsulation = (a.decapsulation_job(&b_buf[..rh_len])?)
+            .context("No decapsulation job for RespHello")?
+            .run()?;
+        let res = a.with_decapsulation(Some(decapsulation), |a| {
+            a.handle_msg(&b_buf[..rh_len], &mut *a_buf)
+        })?;
+        assert_eq!(res.exchanged_with, Some(PeerPtr(0)));
+        let ic_len = res.resp.context("Failed to produce InitConf message")?;
+
+        // Other messages do not need a decapsulation
+        assert!(b.decapsulation_job(&a_buf[..ic_len])?.is_none());
+        let res = b.handle_msg(&a_buf[..ic_len], &mut *b_buf)?;
+        assert_eq!(res.exchanged_with, Some(PeerPtr(0)));
+        assert_eq!(a.osk(PeerPtr(0))?.secret(), b.osk(PeerPtr(0))?.secret());
+
+        Ok(())
+    })
+}
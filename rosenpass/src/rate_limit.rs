@@ -0,0 +1,262 @@
+//! Limiting the rate at which a single source can make the server process
+//! [crate::msgs::InitHello] messages
+//!
+//! Each InitHello costs the responder a decapsulation of the static KEM, which is by far the most
+//! expensive operation in the protocol. The [RateLimiter] gives each source its own token bucket,
+//! so a single host can not monopolize the responder. An InitHello is dropped without any reply
+//! when the bucket of its source is empty; the buckets refill at
+//! [RateLimitConfig::init_hello_rate] messages per second, up to
+//! [RateLimitConfig::init_hello_burst] messages.
+//!
+//! The limit only applies while the server asks for cookies (see [crate::load_detection]), and
+//! only InitHello messages carrying a valid cookie are charged, right before they get
+//! decapsulated. The cookie proves that the message really came from its source address, so a
+//! spoofing attacker can not drain the bucket of a legitimate peer. Messages without a valid
+//! cookie are answered with a cookie reply, which is cheap, and are not charged.
+//! Without the `experiment_cookie_dos_mitigation` feature there are no cookies, and thus no
+//! proven sources, so the limit never applies.
+//!
+//! Sources are identified by the address part of their [HostIdentification], aggregated to
+//! [RateLimitConfig::ipv4_prefix_len] and [RateLimitConfig::ipv6_prefix_len] bits: a single
+//! host is usually assigned a whole IPv6 /64 network, so limiting individual IPv6 addresses would
+//! be pointless. Ports are ignored for the same reason.
+//!
+//! At most [RateLimitConfig::max_sources] buckets are kept; the bucket of the source that sent
+//! nothing for the longest time is discarded first. A discarded bucket is full when its source
+//! shows up again, so an attacker cycling through more source prefixes than that is not limited
+//! by the [RateLimiter]; cookies still force such an attacker to actually own the addresses.
+//!
+//! [HostIdentification]: crate::protocol::HostIdentification
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv6Addr};
+use std::time::Instant;
+
+use anyhow::ensure;
+use serde::{Deserialize, Serialize};
+
+use crate::app_server::Endpoint;
+use crate::msgs::MsgType;
+
+/// Configuration of the [RateLimiter]; see [crate::rate_limit]
+///
+/// ```toml
+/// [rate_limit]
+/// init_hello_rate = 10
+/// init_hello_burst = 20
+/// ipv4_prefix_len = 32
+/// ipv6_prefix_len = 64
+/// max_sources = 4096
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RateLimitConfig {
+    /// InitHello messages per second processed from a single source while under load; zero
+    /// disables the limit
+    pub init_hello_rate: u32,
+    /// InitHello messages a single source may send at once after it was quiet for a while
+    pub init_hello_burst: u32,
+    /// Length of the prefix identifying an IPv4 source, in bits
+    pub ipv4_prefix_len: u8,
+    /// Length of the prefix identifying an IPv6 source, in bits
+    pub ipv6_prefix_len: u8,
+    /// Maximum number of sources whose buckets are kept
+    pub max_sources: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            init_hello_rate: 10,
+            init_hello_burst: 20,
+            ipv4_prefix_len: 32,
+            ipv6_prefix_len: 64,
+            max_sources: 4096,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Whether this is the default configuration
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether messages are limited at all
+    pub fn is_enabled(&self) -> bool {
+        self.init_hello_rate > 0
+    }
+
+    /// Check that the limits make sense
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.ipv4_prefix_len <= 32,
+            "rate_limit.ipv4_prefix_len must not exceed 32"
+        );
+        ensure!(
+            self.ipv6_prefix_len <= 128,
+            "rate_limit.ipv6_prefix_len must not exceed 128"
+        );
+        if self.is_enabled() {
+            ensure!(
+                self.init_hello_burst > 0,
+                "rate_limit.init_hello_burst must not be zero"
+            );
+            ensure!(
+                self.max_sources > 0,
+                "rate_limit.max_sources must not be zero"
+            );
+        }
+        Ok(())
+    }
+
+    /// The prefix of the address identifying the sender of a message
+    ///
+    /// IPv4 addresses are mapped to IPv6 addresses, so both share a single key space.
+    fn source_of(&self, endpoint: &Endpoint) -> Option<Ipv6Addr> {
+        let Endpoint::SocketBoundAddress(host) = endpoint else {
+            return None;
+        };
+        let (addr, prefix_len) = match host.addr().ip() {
+            IpAddr::V4(ip) => (ip.to_ipv6_mapped(), 96 + self.ipv4_prefix_len as u32),
+            IpAddr::V6(ip) => (ip, self.ipv6_prefix_len as u32),
+        };
+        let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+        Some(Ipv6Addr::from(u128::from(addr) & mask))
+    }
+}
+
+/// Token bucket of a single source
+#[derive(Debug)]
+struct Bucket {
+    /// Messages the source may still send
+    tokens: f64,
+    /// Point in time at which [Self::tokens] was last updated
+    updated: Instant,
+    /// Position of the source in [RateLimiter::recent]
+    stamp: u64,
+}
+
+/// Per-source token buckets limiting InitHello processing, stored in [AppServer::rate_limiter]
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use rosenpass::app_server::{Endpoint, SocketBoundEndpoint, SocketPtr};
+/// use rosenpass::msgs::MsgType;
+/// use rosenpass::rate_limit::{RateLimitConfig, RateLimiter};
+///
+/// let config = RateLimitConfig {
+///     init_hello_rate: 2,
+///     init_hello_burst: 3,
+///     ..RateLimitConfig::default()
+/// };
+/// let mut limiter = RateLimiter::new(config);
+///
+/// let from = |addr: &str| {
+///     let host = SocketBoundEndpoint::new(SocketPtr(0), addr.parse().unwrap());
+///     Endpoint::SocketBoundAddress(host)
+/// };
+/// let hello = [MsgType::InitHello as u8];
+/// let conf = [MsgType::InitConf as u8];
+/// let now = Instant::now();
+///
+/// // The burst is used up…
+/// for _ in 0..3 {
+///     assert!(limiter.admit(&from("[2001:db8::1]:9999"), &hello, now));
+/// }
+/// assert!(!limiter.admit(&from("[2001:db8::1]:9999"), &hello, now));
+/// // …for the entire /64 network
+/// assert!(!limiter.admit(&from("[2001:db8::2]:4242"), &hello, now));
+/// // Other messages and other sources are not affected
+/// assert!(limiter.admit(&from("[2001:db8::1]:9999"), &conf, now));
+/// assert!(limiter.admit(&from("[2001:db8:1::1]:9999"), &hello, now));
+///
+/// // Half a second later, the bucket holds one message again
+/// let later = now + Duration::from_millis(500);
+/// assert!(limiter.admit(&from("[2001:db8::1]:9999"), &hello, later));
+/// assert!(!limiter.admit(&from("[2001:db8::1]:9999"), &hello, later));
+/// ```
+///
+/// [AppServer::rate_limiter]: crate::app_server::AppServer::rate_limiter
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    /// The configuration in use
+    config: RateLimitConfig,
+    /// Buckets by source
+    buckets: HashMap<Ipv6Addr, Bucket>,
+    /// Sources by the order in which they last sent a message, least recent first
+    recent: BTreeMap<u64, Ipv6Addr>,
+    /// Stamp assigned to the source of the next InitHello message
+    next_stamp: u64,
+}
+
+impl RateLimiter {
+    /// Create a limiter without any buckets
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// The configuration in use
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Replace the configuration
+    ///
+    /// All buckets are discarded if the configuration changed.
+    pub fn set_config(&mut self, config: RateLimitConfig) {
+        if config != self.config {
+            *self = Self::new(config);
+        }
+    }
+
+    /// Number of sources whose buckets are kept
+    pub fn sources(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Whether the message `msg` received from `endpoint` at `now` should be processed
+    ///
+    /// Takes a token from the bucket of the source if `msg` is an InitHello message.
+    pub fn admit(&mut self, endpoint: &Endpoint, msg: &[u8], now: Instant) -> bool {
+        if !self.config.is_enabled() || msg.first() != Some(&(MsgType::InitHello as u8)) {
+            return true;
+        }
+        let Some(source) = self.config.source_of(endpoint) else {
+            return true;
+        };
+
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+        let burst = self.config.init_hello_burst as f64;
+        let rate = self.config.init_hello_rate as f64;
+
+        if !self.buckets.contains_key(&source) && self.buckets.len() >= self.config.max_sources {
+            if let Some((_, evicted)) = self.recent.pop_first() {
+                self.buckets.remove(&evicted);
+            }
+        }
+        let bucket = self.buckets.entry(source).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            stamp,
+        });
+        self.recent.remove(&bucket.stamp);
+        let elapsed = now.saturating_duration_since(bucket.updated);
+        bucket.tokens = burst.min(bucket.tokens + elapsed.as_secs_f64() * rate);
+        bucket.updated = now;
+        bucket.stamp = stamp;
+        self.recent.insert(stamp, source);
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
@@ -0,0 +1,90 @@
+//! Storing the biscuit keys and cookie secrets of the [AppServer] in a file
+//!
+//! When a state file is configured ([crate::config::Rosenpass::state_file]), the server loads
+//! the state from the file as soon as the cryptographic server is available and rewrites it
+//! whenever keys are rotated or a peer completes a handshake as responder; see [crate::protocol::persistent_state] for details on what is
+//! stored. This makes planned restarts transparent to peers with a handshake in flight.
+//!
+//! The state is encrypted with a key derived from the static secret key of the server. A state
+//! file that can not be read, e.g. because the secret key changed, is logged and replaced by a
+//! fresh state. The file is always replaced atomically.
+
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+use log::{error, warn};
+
+use rosenpass_util::file::{fwrite_atomic, Visibility};
+
+use crate::app_server::AppServer;
+use crate::protocol::persistent_state::SavedState;
+
+/// The state file of an [AppServer]; see [crate::state_file]
+#[derive(Debug)]
+pub struct StateFile {
+    /// Where the state is stored
+    pub path: PathBuf,
+    /// What was last written to [Self::path]; `None` until the state was loaded
+    saved: Option<SavedState>,
+}
+
+impl StateFile {
+    /// Use the state file at the given path; the file is read by [AppServer::poll]
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, saved: None }
+    }
+}
+
+impl AppServer {
+    /// Store the state of the cryptographic server in the given file; see [crate::state_file]
+    pub fn enable_state_file(&mut self, path: PathBuf) {
+        self.state_file = Some(StateFile::new(path));
+    }
+
+    /// Load the state file once the cryptographic server is available and rewrite it whenever
+    /// the state changed
+    ///
+    /// Errors are logged rather than returned; a broken state file should not stop the key
+    /// exchange.
+    ///
+    /// Used internally in [Self::poll]
+    pub(crate) fn poll_state_file(&mut self) {
+        let Some(state_file) = self.state_file.as_mut() else {
+            return;
+        };
+        let Some(crypto) = self.crypto_site.product_mut() else {
+            return;
+        };
+        let path = &state_file.path;
+
+        match &state_file.saved {
+            Some(saved) if !crypto.state_changed_since(saved) => return,
+            Some(_) => {}
+            None => {
+                let restored = match std::fs::read(path) {
+                    Ok(sealed) => crypto.restore_state(&sealed).map(Some),
+                    Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e.into()),
+                };
+                if let Err(e) = restored {
+                    warn!("Ignoring state file {path:?}: {e:?}");
+                }
+                // The state is always rewritten after loading to reserve new biscuit numbers
+            }
+        }
+
+        let (sealed, saved) = match crypto.seal_state() {
+            Ok(sealed) => sealed,
+            Err(e) => {
+                error!("Could not seal state for {path:?}: {e:?}");
+                return;
+            }
+        };
+        let res = fwrite_atomic(path, Visibility::Secret, |f| Ok(f.write_all(&sealed)?));
+        if let Err(e) = res {
+            // Retried after the next change rather than on every poll
+            error!("Could not write state file {path:?}: {e:?}");
+        }
+        state_file.saved = Some(saved);
+    }
+}
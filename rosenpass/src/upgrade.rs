@@ -0,0 +1,166 @@
+//! Replacing the running Rosenpass process without losing sessions
+//!
+//! Upgrades are disabled by default; they are enabled through `upgrade_on_sigusr2` in the
+//! configuration file or the `--upgrade-on-sigusr2` command line flag.
+//!
+//! Upon receiving SIGUSR2 (see [AppServer::enable_upgrade]), Rosenpass takes a snapshot of the
+//! live state of its [CryptoServer](crate::protocol::CryptoServer) (see
+//! [crate::protocol::snapshot]) and replaces itself by executing the Rosenpass binary again with the same command line. The
+//! snapshot is handed to the new process through an inherited memfd whose file descriptor number
+//! is passed as `--restore-snapshot-fd`; the new process restores the sessions through
+//! [AppServer::restore_snapshot_fd] after adding its peers and continues without new handshakes.
+//!
+//! Since the binary is looked up again, this allows upgrading Rosenpass by replacing the binary on
+//! disk and sending SIGUSR2. The process id stays the same, so service managers are not disturbed.
+//! Running [crate::key_out_exec] commands are killed before the process is replaced, since the
+//! new process could not reap them; the next key exchange with the peer outputs a fresh key.
+//!
+//! Other file descriptors are not inherited by the new process; starting Rosenpass with
+//! `--psk-broker-fd` does not work together with upgrades.
+//!
+//! If the upgrade fails before the process is replaced, the error is logged and the running
+//! process continues. If the new process can not restore the snapshot, e.g. because the secret
+//! key changed, it logs the error and starts without sessions.
+
+use std::convert::Infallible;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+use anyhow::{ensure, Context};
+use log::info;
+use signal_hook::consts::SIGUSR2;
+
+use rosenpass_util::fd::claim_fd;
+
+use crate::app_server::{AppServer, AppServerIoSource};
+use crate::config_reload::ReloadSignal;
+use crate::protocol::timing::UNENDING;
+
+/// The command line option used to pass the snapshot to the new process
+pub const RESTORE_SNAPSHOT_FD_ARG: &str = "--restore-snapshot-fd";
+
+/// State kept by [AppServer] to support [AppServer::upgrade]
+#[derive(Debug)]
+pub struct Upgrade {
+    /// Notification about SIGUSR2
+    pub signal: ReloadSignal,
+    /// The command line of this process, including the program name
+    args: Vec<OsString>,
+}
+
+/// The command line for the new process, passing the snapshot in file descriptor `fd`
+///
+/// A [RESTORE_SNAPSHOT_FD_ARG] from a previous upgrade is removed from `args`.
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::OsString;
+/// use rosenpass::upgrade::successor_args;
+///
+/// let args = ["rosenpass", "--restore-snapshot-fd", "3", "exchange-config", "rp.toml"];
+/// let args = args.map(OsString::from);
+/// assert_eq!(
+///     successor_args(&args, 4),
+///     ["rosenpass", "--restore-snapshot-fd", "4", "exchange-config", "rp.toml"]
+/// );
+/// ```
+pub fn successor_args(args: &[OsString], fd: RawFd) -> Vec<OsString> {
+    let mut rest = args.iter().skip(1);
+    let mut res: Vec<OsString> = args.iter().take(1).cloned().collect();
+    res.push(RESTORE_SNAPSHOT_FD_ARG.into());
+    res.push(fd.to_string().into());
+
+    while let Some(arg) = rest.next() {
+        match arg.to_str() {
+            Some(RESTORE_SNAPSHOT_FD_ARG) => {
+                rest.next();
+            }
+            Some(a) if a.starts_with(&format!("{RESTORE_SNAPSHOT_FD_ARG}=")) => {}
+            _ => res.push(arg.clone()),
+        }
+    }
+    res
+}
+
+impl AppServer {
+    /// Replace the process upon SIGUSR2, preserving the sessions; see [crate::upgrade]
+    pub fn enable_upgrade(&mut self) -> anyhow::Result<()> {
+        ensure!(self.upgrade.is_none(), "Upgrades are already enabled");
+
+        let token = self.mio_token_dispenser.dispense();
+        let signal = ReloadSignal::for_signal(SIGUSR2, self.mio_poll.registry(), token)?;
+        self.register_io_source(token, AppServerIoSource::UpgradeSignal);
+
+        self.upgrade = Some(Upgrade {
+            signal,
+            args: std::env::args_os().collect(),
+        });
+        Ok(())
+    }
+
+    /// Replace the process by a new Rosenpass process, handing over a snapshot of the
+    /// sessions; see [crate::upgrade]
+    ///
+    /// Only returns if the upgrade failed.
+    pub fn upgrade(&mut self) -> anyhow::Result<Infallible> {
+        let args = (self.upgrade.as_ref().map(|upgrade| upgrade.args.clone()))
+            .context("Upgrades are not enabled")?;
+        let (program, _) = args.split_first().context("Program name is unknown")?;
+
+        // The commands could not be reaped by the new process
+        self.poll_key_out_commands(UNENDING);
+        self.key_out_commands.kill_all();
+
+        let snapshot = self.crypto_server()?.snapshot()?;
+        let file = snapshot_memfd(&snapshot)?;
+        let args = successor_args(&args, file.as_raw_fd());
+
+        info!("Replacing the process by {program:?}, passing on the sessions");
+        let err = Command::new(program).args(&args[1..]).exec();
+        Err(err).with_context(|| format!("Could not execute {program:?}"))
+    }
+
+    /// Restore the sessions from a snapshot passed through file descriptor `fd` by the
+    /// previous process; see [crate::upgrade]
+    ///
+    /// Called after the peers were added.
+    pub fn restore_snapshot_fd(&mut self, fd: RawFd) -> anyhow::Result<()> {
+        let mut file = File::from(claim_fd(fd)?);
+        let mut snapshot = Vec::new();
+        file.read_to_end(&mut snapshot)?;
+
+        let restored = self.crypto_server_mut()?.restore_snapshot(&snapshot)?;
+        info!("Restored the sessions of {restored} peers from the previous process");
+        Ok(())
+    }
+}
+
+/// Store the snapshot in a memfd inherited by the new process
+#[cfg(target_os = "linux")]
+fn snapshot_memfd(snapshot: &[u8]) -> anyhow::Result<File> {
+    use std::io::{Seek, SeekFrom, Write};
+
+    use rustix::fs::{fcntl_add_seals, memfd_create, MemfdFlags, SealFlags};
+
+    // No CLOEXEC; the file descriptor has to survive the exec
+    let fd = memfd_create("rosenpass-snapshot", MemfdFlags::ALLOW_SEALING)?;
+    let mut file = File::from(fd);
+    file.write_all(snapshot)?;
+    file.seek(SeekFrom::Start(0))?;
+    fcntl_add_seals(
+        &file,
+        SealFlags::SEAL | SealFlags::SHRINK | SealFlags::GROW | SealFlags::WRITE,
+    )?;
+    Ok(file)
+}
+
+/// Store the snapshot in a memfd inherited by the new process
+#[cfg(not(target_os = "linux"))]
+fn snapshot_memfd(_snapshot: &[u8]) -> anyhow::Result<File> {
+    anyhow::bail!("Upgrading the running process is only supported on Linux")
+}
@@ -0,0 +1,436 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read, Write},
+    net::ToSocketAddrs,
+    os::unix::net::UnixStream,
+    process::Stdio,
+};
+
+use anyhow::Context;
+use rosenpass::api::{
+    self, add_peer_request_protocol_version, add_peer_response_status, event_type,
+    get_peer_status_response_status, get_stats_response_status, list_peers_response_status,
+    peer_status_key_output, peer_status_session_role, remove_peer_response_status,
+    subscribe_events_response_status, trigger_handshake_response_status,
+};
+use rosenpass::app_server::MAX_B64_PEER_ID_SIZE;
+use rosenpass::config::ProtocolVersion;
+use rosenpass::metrics::MetricsConfig;
+use rosenpass_to::{ops::copy_slice_least_src, To};
+use rosenpass_util::{
+    b64::B64Display,
+    length_prefix_encoding::{decoder::LengthPrefixDecoder, encoder::LengthPrefixEncoder},
+    mem::DiscardResultExt,
+    mio::WriteWithFileDescriptors,
+    zerocopy::ZerocopySliceExt,
+};
+use std::os::fd::OwnedFd;
+use tempfile::TempDir;
+use zerocopy::AsBytes;
+
+struct KillChild(std::process::Child);
+
+impl Drop for KillChild {
+    fn drop(&mut self) {
+        use rustix::process::{kill_process, Pid, Signal::Term};
+        let pid = Pid::from_child(&self.0);
+        loop {
+            kill_process(pid, Term).discard_result();
+            if self.0.try_wait().unwrap().is_some() {
+                break;
+            }
+        }
+    }
+}
+
+/// Perform a HTTP GET request on a unix socket, returning the raw response
+fn http_get(path: &std::path::Path, resource: &str) -> anyhow::Result<String> {
+    let mut conn = UnixStream::connect(path)?;
+    write!(conn, "GET {resource} HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+    let mut res = String::new();
+    conn.read_to_string(&mut res)?;
+    Ok(res)
+}
+
+/// Send a request with the given file descriptors and parse the response
+fn request<Res: zerocopy::FromBytes + Copy>(
+    api: &UnixStream,
+    req: &[u8],
+    fds: &[&OwnedFd],
+) -> anyhow::Result<Res> {
+    let mut fds = fds.iter().copied().collect::<VecDeque<_>>();
+    let mut io = WriteWithFileDescriptors::<UnixStream, _, _, _>::new(api, &mut fds);
+    LengthPrefixEncoder::from_message(req).write_all_to_stdio(&mut io)?;
+    assert!(fds.is_empty(), "Failed to write all file descriptors");
+
+    let mut decoder = LengthPrefixDecoder::new([0u8; api::MAX_RESPONSE_LEN]);
+    let res = decoder.read_all_from_stdio(api)?;
+    Ok(*res.zk_parse::<Res>()?)
+}
+
+/// Read events from a subscribed connection until an event of the given type arrives; returns
+/// the types of all events read
+fn wait_for_event(
+    events: &UnixStream,
+    typ: u128,
+) -> anyhow::Result<(Vec<u128>, api::EventPayload)> {
+    let mut seen = Vec::new();
+    loop {
+        let mut decoder = LengthPrefixDecoder::new([0u8; api::MAX_RESPONSE_LEN]);
+        let msg = decoder.read_all_from_stdio(events)?;
+        let ev = msg.zk_parse::<api::Event>()?.payload;
+        let ev_type = ev.event_type;
+        seen.push(ev_type);
+        if ev_type == typ {
+            return Ok((seen, ev));
+        }
+    }
+}
+
+#[test]
+fn api_integration_add_remove_peer_v02() -> anyhow::Result<()> {
+    api_integration_add_remove_peer(ProtocolVersion::V02)
+}
+
+#[test]
+fn api_integration_add_remove_peer_v03() -> anyhow::Result<()> {
+    api_integration_add_remove_peer(ProtocolVersion::V03)
+}
+
+fn api_integration_add_remove_peer(protocol_version: ProtocolVersion) -> anyhow::Result<()> {
+    rosenpass_secret_memory::policy::secret_policy_use_only_malloc_secrets();
+
+    let dir = TempDir::with_prefix("rosenpass-api-integration-test")?;
+
+    macro_rules! tempfile {
+        ($($lst:expr),+) => {{
+            let mut buf =  dir.path().to_path_buf();
+            $(buf.push($lst);)*
+            buf
+        }}
+    }
+
+    let peer_a_endpoint = match protocol_version {
+        ProtocolVersion::V02 => "[::1]:61424",
+        ProtocolVersion::V03 => "[::1]:61425",
+    };
+    let peer_b_osk = tempfile!("b.osk");
+
+    use rosenpass::config;
+
+    // Peer a starts out without any peers; peer b is added through the API
+    let peer_a_keypair = config::Keypair::new(tempfile!("a.pk"), tempfile!("a.sk"));
+    let peer_a = config::Rosenpass {
+        config_file_path: tempfile!("a.config"),
+        keypair: Some(peer_a_keypair.clone()),
+        listen: peer_a_endpoint.to_socket_addrs()?.collect(), // TODO: This could collide by accident
+        verbosity: config::Verbosity::Verbose,
+        api: api::config::ApiConfig {
+            listen_path: vec![tempfile!("a.sock")],
+            listen_fd: vec![],
+            stream_fd: vec![],
+        },
+        metrics: MetricsConfig {
+            listen: vec![],
+            listen_path: vec![tempfile!("a.metrics.sock")],
+        },
+        state_file: None,
+        upgrade_on_sigusr2: false,
+        identities: vec![],
+        next_keypair: None,
+        key_rotation_overlap_secs: None,
+        endpoint_refresh_secs: None,
+        under_load: Default::default(),
+        rate_limit: Default::default(),
+        kem_workers: Default::default(),
+        peers: vec![],
+    };
+
+    let peer_b_keypair = config::Keypair::new(tempfile!("b.pk"), tempfile!("b.sk"));
+    let peer_b = config::Rosenpass {
+        config_file_path: tempfile!("b.config"),
+        keypair: Some(peer_b_keypair.clone()),
+        listen: vec![],
+        verbosity: config::Verbosity::Verbose,
+        api: api::config::ApiConfig {
+            listen_path: vec![],
+            listen_fd: vec![],
+            stream_fd: vec![],
+        },
+        metrics: Default::default(),
+        state_file: None,
+        upgrade_on_sigusr2: false,
+        identities: vec![],
+        next_keypair: None,
+        key_rotation_overlap_secs: None,
+        endpoint_refresh_secs: None,
+        under_load: Default::default(),
+        rate_limit: Default::default(),
+        kem_workers: Default::default(),
+        peers: vec![config::RosenpassPeer {
+            public_key: tempfile!("a.pk"),
+            key_out: Some(peer_b_osk.clone()),
+            endpoint: Some(peer_a_endpoint.to_owned()),
+            pin_endpoint: false,
+            pre_shared_key: None,
+            wg: None,
+            protocol_version,
+            osk_domain_separator: Default::default(),
+            key_out_exec: None,
+            key_out_owner: None,
+            key_out_group: None,
+            identity: None,
+            next_public_key: None,
+            ephemeral_kem: None,
+            aead: None,
+            hybrid_x25519: false,
+        }],
+    };
+
+    // Generate the keys
+    rosenpass::cli::testing::generate_and_save_keypair(
+        peer_a_keypair.secret_key.clone(),
+        peer_a_keypair.public_key.clone(),
+    )?;
+    rosenpass::cli::testing::generate_and_save_keypair(
+        peer_b_keypair.secret_key.clone(),
+        peer_b_keypair.public_key.clone(),
+    )?;
+
+    // Write the configuration files
+    peer_a.commit()?;
+    peer_b.commit()?;
+
+    // Start peer a
+    let _proc_a = KillChild(
+        std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"))
+            .args([
+                "exchange-config",
+                peer_a.config_file_path.to_str().context("")?,
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()?,
+    );
+
+    // Wait for the socket to be created
+    let api_path = peer_a.api.listen_path[0].as_path();
+    let mut attempt = 0;
+    while !api_path.exists() {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        attempt += 1;
+        assert!(
+            attempt < 50,
+            "Api failed to be created even after 10 seconds"
+        );
+    }
+    let api = UnixStream::connect(api_path)?;
+
+    // Subscribe to events on a second connection
+    let events = UnixStream::connect(api_path)?;
+    events.set_read_timeout(Some(std::time::Duration::from_secs(10)))?;
+    let req = api::SubscribeEventsRequest::new();
+    let res: api::SubscribeEventsResponse = request(&events, req.as_bytes(), &[])?;
+    assert_eq!(
+        res,
+        api::SubscribeEventsResponse::new(subscribe_events_response_status::OK)
+    );
+
+    let protocol_version_raw = match protocol_version {
+        ProtocolVersion::V02 => add_peer_request_protocol_version::V02,
+        ProtocolVersion::V03 => add_peer_request_protocol_version::V03,
+    };
+    let mut add_peer_req = api::AddPeerRequest::new();
+    add_peer_req.payload.protocol_version = protocol_version_raw;
+
+    // Missing public key
+    let res: api::AddPeerResponse = request(&api, add_peer_req.as_bytes(), &[])?;
+    assert_eq!(
+        res,
+        api::AddPeerResponse::new(add_peer_response_status::INVALID_REQUEST, [0u8; 32])
+    );
+
+    // Mismatched custom OSK domain separator
+    let mut invalid_req = add_peer_req;
+    copy_slice_least_src(b"example.com").to(&mut invalid_req.payload.osk_organization);
+    let pk_b = OwnedFd::from(std::fs::File::open(&peer_b_keypair.public_key)?);
+    let res: api::AddPeerResponse = request(&api, invalid_req.as_bytes(), &[&pk_b])?;
+    assert_eq!(
+        res,
+        api::AddPeerResponse::new(add_peer_response_status::INVALID_REQUEST, [0u8; 32])
+    );
+
+    // Add peer b
+    let pk_b = OwnedFd::from(std::fs::File::open(&peer_b_keypair.public_key)?);
+    let res: api::AddPeerResponse = request(&api, add_peer_req.as_bytes(), &[&pk_b])?;
+    let status = res.payload.status;
+    assert_eq!(status, add_peer_response_status::OK);
+    let peer_id = res.payload.peer_id;
+    assert_ne!(peer_id, [0u8; 32]);
+
+    // Adding the same peer twice fails
+    let pk_b = OwnedFd::from(std::fs::File::open(&peer_b_keypair.public_key)?);
+    let res: api::AddPeerResponse = request(&api, add_peer_req.as_bytes(), &[&pk_b])?;
+    assert_eq!(
+        res,
+        api::AddPeerResponse::new(add_peer_response_status::PEER_ALREADY_REGISTERED, [0u8; 32])
+    );
+
+    // Start peer b; now that a knows about b, they exchange a key
+    let mut proc_b = KillChild(
+        std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"))
+            .args([
+                "exchange-config",
+                peer_b.config_file_path.to_str().context("")?,
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?,
+    );
+    let mut out_b = BufReader::new(proc_b.0.stdout.take().context("")?).lines();
+    let line = out_b.next().context("")??;
+    assert!(
+        line.ends_with(" exchanged"),
+        "Unexpected rosenpass output: `{line}`"
+    );
+
+    // Peer a learned the endpoint of b before reporting the exchanged key
+    let (seen, ev) = wait_for_event(&events, event_type::KEY_EXCHANGED)?;
+    assert!(
+        seen.contains(&event_type::PEER_ENDPOINT_CHANGED),
+        "Unexpected events: {seen:?}"
+    );
+    let ev_peer_id = ev.peer_id;
+    assert_eq!(ev_peer_id, peer_id);
+
+    // Peer b is listed
+    let req = api::ListPeersRequest::new(0);
+    let res: api::ListPeersResponse = request(&api, req.as_bytes(), &[])?;
+    let (status, total) = (res.payload.status, res.payload.total);
+    assert_eq!(status, list_peers_response_status::OK);
+    assert_eq!(total, 1);
+    assert_eq!(res.payload.peer_ids(), &[peer_id]);
+
+    // Peer b initiated the handshake, so a was the responder
+    let status_req = api::GetPeerStatusRequest::new(peer_id);
+    let res: api::GetPeerStatusResponse = request(&api, status_req.as_bytes(), &[])?;
+    let p = res.payload;
+    let (status, res_peer_id) = (p.status, p.peer_id);
+    assert_eq!(status, get_peer_status_response_status::OK);
+    assert_eq!(res_peer_id, peer_id);
+    let (session_role, last_key_output) = (p.session_role, p.last_key_output);
+    assert_eq!(session_role, peer_status_session_role::RESPONDER);
+    assert_eq!(last_key_output, peer_status_key_output::EXCHANGED);
+    let (now, created, output_at) = (p.now_ms, p.session_created_at_ms, p.last_key_output_at_ms);
+    assert!(created <= now && output_at <= now);
+    let endpoint = p.endpoint;
+    assert_ne!(endpoint, [0u8; 256], "Endpoint of peer b is unknown");
+
+    // Force a rekey through the CLI; this time, a initiates the handshake
+    let b64_peer_id = peer_id.fmt_b64::<MAX_B64_PEER_ID_SIZE>().to_string();
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"))
+        .args([
+            "trigger-handshake",
+            "--api",
+            api_path.to_str().context("")?,
+            &b64_peer_id,
+        ])
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(
+        out.status.success(),
+        "trigger-handshake failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(
+        String::from_utf8(out.stdout)?,
+        format!("Exchanged key with peer {b64_peer_id}\n")
+    );
+
+    let (seen, ev) = wait_for_event(&events, event_type::KEY_EXCHANGED)?;
+    assert!(
+        seen.contains(&event_type::HANDSHAKE_STARTED),
+        "Unexpected events: {seen:?}"
+    );
+    let ev_peer_id = ev.peer_id;
+    assert_eq!(ev_peer_id, peer_id);
+
+    let res: api::GetPeerStatusResponse = request(&api, status_req.as_bytes(), &[])?;
+    let session_role = res.payload.session_role;
+    assert_eq!(session_role, peer_status_session_role::INITIATOR);
+
+    // Both handshakes are reflected in the statistics
+    let res: api::GetStatsResponse = request(&api, api::GetStatsRequest::new().as_bytes(), &[])?;
+    let p = res.payload;
+    let (status, peers, keys_exchanged) = (p.status, p.peers, p.keys_exchanged);
+    assert_eq!(status, get_stats_response_status::OK);
+    assert_eq!(peers, 1);
+    assert!(keys_exchanged >= 2, "Unexpected stats: {p:?}");
+    let (init_hello, resp_hello, init_conf) = (
+        p.received_init_hello,
+        p.received_resp_hello,
+        p.received_init_conf,
+    );
+    assert!(init_hello >= 1 && resp_hello >= 1 && init_conf >= 1);
+    let (started, decode_errors) = (p.handshakes_started, p.decode_errors);
+    assert!(started >= 1);
+    assert_eq!(decode_errors, 0);
+
+    // The same statistics are exported in the Prometheus text format
+    let metrics = http_get(&tempfile!("a.metrics.sock"), "/metrics")?;
+    assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"), "{metrics}");
+    assert!(metrics.contains("\nrosenpass_peers 1\n"), "{metrics}");
+    assert!(
+        metrics.contains("\nrosenpass_messages_received_total{type=\"init_hello\"} "),
+        "{metrics}"
+    );
+    let not_found = http_get(&tempfile!("a.metrics.sock"), "/nothing")?;
+    assert!(not_found.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    // Remove peer b
+    let req = api::RemovePeerRequest::new(peer_id);
+    let res: api::RemovePeerResponse = request(&api, req.as_bytes(), &[])?;
+    assert_eq!(
+        res,
+        api::RemovePeerResponse::new(remove_peer_response_status::OK)
+    );
+
+    // The key exchanged with b is erased
+    let (_, ev) = wait_for_event(&events, event_type::KEY_STALE)?;
+    let ev_peer_id = ev.peer_id;
+    assert_eq!(ev_peer_id, peer_id);
+
+    // Peer b is gone
+    let res: api::RemovePeerResponse = request(&api, req.as_bytes(), &[])?;
+    assert_eq!(
+        res,
+        api::RemovePeerResponse::new(remove_peer_response_status::NO_SUCH_PEER)
+    );
+    let res: api::GetPeerStatusResponse = request(&api, status_req.as_bytes(), &[])?;
+    assert_eq!(
+        res,
+        api::GetPeerStatusResponse::new(get_peer_status_response_status::NO_SUCH_PEER)
+    );
+    let req = api::TriggerHandshakeRequest::new(peer_id);
+    let res: api::TriggerHandshakeResponse = request(&api, req.as_bytes(), &[])?;
+    assert_eq!(
+        res,
+        api::TriggerHandshakeResponse::new(trigger_handshake_response_status::NO_SUCH_PEER)
+    );
+    let req = api::ListPeersRequest::new(0);
+    let res: api::ListPeersResponse = request(&api, req.as_bytes(), &[])?;
+    assert_eq!(
+        res,
+        api::ListPeersResponse::new(list_peers_response_status::OK)
+    );
+
+    // …and can be added again
+    let pk_b = OwnedFd::from(std::fs::File::open(&peer_b_keypair.public_key)?);
+    let res: api::AddPeerResponse = request(&api, add_peer_req.as_bytes(), &[&pk_b])?;
+    assert_eq!(
+        res,
+        api::AddPeerResponse::new(add_peer_response_status::OK, peer_id)
+    );
+
+    Ok(())
+}
@@ -95,10 +95,21 @@ fn api_integration_api_setup(protocol_version: ProtocolVersion) -> anyhow::Resul
             listen_fd: vec![],
             stream_fd: vec![],
         },
+        metrics: Default::default(),
+        state_file: None,
+        upgrade_on_sigusr2: false,
+        identities: vec![],
+        next_keypair: None,
+        key_rotation_overlap_secs: None,
+        endpoint_refresh_secs: None,
+        under_load: Default::default(),
+        rate_limit: Default::default(),
+        kem_workers: Default::default(),
         peers: vec![config::RosenpassPeer {
             public_key: tempfile!("b.pk"),
             key_out: None,
             endpoint: None,
+            pin_endpoint: false,
             pre_shared_key: None,
             wg: Some(config::WireGuard {
                 device: peer_b_wg_device.to_string(),
@@ -107,6 +118,14 @@ fn api_integration_api_setup(protocol_version: ProtocolVersion) -> anyhow::Resul
             }),
             protocol_version: protocol_version.clone(),
             osk_domain_separator: Default::default(),
+            key_out_exec: None,
+            key_out_owner: None,
+            key_out_group: None,
+            identity: None,
+            next_public_key: None,
+            ephemeral_kem: None,
+            aead: None,
+            hybrid_x25519: false,
         }],
     };
 
@@ -121,14 +140,33 @@ fn api_integration_api_setup(protocol_version: ProtocolVersion) -> anyhow::Resul
             listen_fd: vec![],
             stream_fd: vec![],
         },
+        metrics: Default::default(),
+        state_file: None,
+        upgrade_on_sigusr2: false,
+        identities: vec![],
+        next_keypair: None,
+        key_rotation_overlap_secs: None,
+        endpoint_refresh_secs: None,
+        under_load: Default::default(),
+        rate_limit: Default::default(),
+        kem_workers: Default::default(),
         peers: vec![config::RosenpassPeer {
             public_key: tempfile!("a.pk"),
             key_out: Some(peer_b_osk.clone()),
             endpoint: Some(peer_a_endpoint.to_owned()),
+            pin_endpoint: false,
             pre_shared_key: None,
             wg: None,
             protocol_version: protocol_version.clone(),
             osk_domain_separator: Default::default(),
+            key_out_exec: None,
+            key_out_owner: None,
+            key_out_group: None,
+            identity: None,
+            next_public_key: None,
+            ephemeral_kem: None,
+            aead: None,
+            hybrid_x25519: false,
         }],
     };
 
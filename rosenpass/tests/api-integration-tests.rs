@@ -76,14 +76,33 @@ fn api_integration_test(protocol_version: ProtocolVersion) -> anyhow::Result<()>
             listen_fd: vec![],
             stream_fd: vec![],
         },
+        metrics: Default::default(),
+        state_file: None,
+        upgrade_on_sigusr2: false,
+        identities: vec![],
+        next_keypair: None,
+        key_rotation_overlap_secs: None,
+        endpoint_refresh_secs: None,
+        under_load: Default::default(),
+        rate_limit: Default::default(),
+        kem_workers: Default::default(),
         peers: vec![config::RosenpassPeer {
             public_key: tempfile!("b.pk"),
             key_out: Some(peer_a_osk.clone()),
             endpoint: None,
+            pin_endpoint: false,
             pre_shared_key: None,
             wg: None,
             protocol_version: protocol_version.clone(),
             osk_domain_separator: Default::default(),
+            key_out_exec: None,
+            key_out_owner: None,
+            key_out_group: None,
+            identity: None,
+            next_public_key: None,
+            ephemeral_kem: None,
+            aead: None,
+            hybrid_x25519: false,
         }],
     };
 
@@ -98,14 +117,33 @@ fn api_integration_test(protocol_version: ProtocolVersion) -> anyhow::Result<()>
             listen_fd: vec![],
             stream_fd: vec![],
         },
+        metrics: Default::default(),
+        state_file: None,
+        upgrade_on_sigusr2: false,
+        identities: vec![],
+        next_keypair: None,
+        key_rotation_overlap_secs: None,
+        endpoint_refresh_secs: None,
+        under_load: Default::default(),
+        rate_limit: Default::default(),
+        kem_workers: Default::default(),
         peers: vec![config::RosenpassPeer {
             public_key: tempfile!("a.pk"),
             key_out: Some(peer_b_osk.clone()),
             endpoint: Some(peer_a_endpoint.to_owned()),
+            pin_endpoint: false,
             pre_shared_key: None,
             wg: None,
             protocol_version: protocol_version.clone(),
             osk_domain_separator: Default::default(),
+            key_out_exec: None,
+            key_out_owner: None,
+            key_out_group: None,
+            identity: None,
+            next_public_key: None,
+            ephemeral_kem: None,
+            aead: None,
+            hybrid_x25519: false,
         }],
     };
 
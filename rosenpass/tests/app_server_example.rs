@@ -5,7 +5,7 @@ use rosenpass_cipher_traits::primitives::Kem;
 use rosenpass_ciphers::StaticKem;
 use rosenpass_util::{file::LoadValueB64, functional::run, mem::DiscardResultExt, result::OkExt};
 
-use rosenpass::app_server::{AppServer, AppServerTest, MAX_B64_KEY_SIZE};
+use rosenpass::app_server::{AppServer, AppServerTest, SocketPtr, MAX_B64_KEY_SIZE};
 use rosenpass::protocol::basic_types::{SPk, SSk, SymKey};
 use rosenpass::{config::ProtocolVersion, protocol::osk_domain_separator::OskDomainSeparator};
 
@@ -128,10 +128,10 @@ impl TestServer {
     }
 
     fn loopback_port(&self) -> anyhow::Result<u16> {
-        self.app_srv.sockets[0].local_addr()?.port().ok()
+        SocketPtr(0).get(&self.app_srv).local_addr()?.port().ok()
     }
 
     fn public_key(&self) -> anyhow::Result<&SPk> {
-        Ok(&self.app_srv.crypto_server()?.spkm)
+        Ok(&self.app_srv.crypto_server()?.identities[0].spkm)
     }
 }
@@ -0,0 +1,244 @@
+use std::{
+    io::{BufRead, BufReader, Lines},
+    net::UdpSocket,
+    path::Path,
+    process::{ChildStdout, Stdio},
+};
+
+use anyhow::Context;
+use rosenpass_util::mem::DiscardResultExt;
+use tempfile::TempDir;
+
+struct KillChild(std::process::Child);
+
+impl Drop for KillChild {
+    fn drop(&mut self) {
+        use rustix::process::{kill_process, Pid, Signal::Term};
+        let pid = Pid::from_child(&self.0);
+        loop {
+            kill_process(pid, Term).discard_result();
+            if self.0.try_wait().unwrap().is_some() {
+                break;
+            }
+        }
+    }
+}
+
+fn find_udp_socket() -> Option<u16> {
+    (1025..=u16::MAX).find(|&port| UdpSocket::bind(("::1", port)).is_ok())
+}
+
+fn gen_keys(dir: &Path, name: &str) -> anyhow::Result<()> {
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"))
+        .args(["gen-keys", "--secret-key"])
+        .arg(dir.join(format!("{name}.sk")))
+        .arg("--public-key")
+        .arg(dir.join(format!("{name}.pk")))
+        .status()?;
+    anyhow::ensure!(status.success(), "Failed to generate keys for {name}");
+    Ok(())
+}
+
+/// Configuration of a server called `name`, optionally talking to peer `other`
+fn config(dir: &Path, name: &str, port: u16, other: Option<(&str, u16)>) -> String {
+    let d = dir.display();
+    let mut cfg = format!(
+        "\
+        public_key = \"{d}/{name}.pk\"\n\
+        secret_key = \"{d}/{name}.sk\"\n\
+        listen = [\"[::1]:{port}\"]\n\
+        verbosity = \"Verbose\"\n"
+    );
+    if let Some((other, other_port)) = other {
+        cfg += &format!(
+            "\
+            \n[[peers]]\n\
+            public_key = \"{d}/{other}.pk\"\n\
+            endpoint = \"[::1]:{other_port}\"\n\
+            key_out = \"{d}/{name}.osk\"\n"
+        );
+    }
+    cfg
+}
+
+/// Replace the file atomically, so the server never reads a partially written configuration
+fn write_config(path: &Path, content: &str) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+fn next_line(out: &mut Lines<BufReader<ChildStdout>>) -> anyhow::Result<String> {
+    Ok(out.next().context("Rosenpass exited unexpectedly")??)
+}
+
+#[test]
+fn config_reload_on_sighup() -> anyhow::Result<()> {
+    let dir = TempDir::with_prefix("rosenpass-config-reload-test")?;
+    let dir = dir.path();
+
+    gen_keys(dir, "a")?;
+    gen_keys(dir, "b")?;
+
+    let port_a = find_udp_socket().context("No free UDP port")?;
+    let port_b = (port_a + 1..=u16::MAX)
+        .find(|&port| UdpSocket::bind(("::1", port)).is_ok())
+        .context("No free UDP port")?;
+
+    let config_a = dir.join("a.toml");
+    let config_b = dir.join("b.toml");
+    write_config(&config_a, &config(dir, "a", port_a, Some(("b", port_b))))?;
+    // Only a initiates handshakes, so every key exchange is reported exactly once
+    let cfg_b = config(dir, "b", port_b, Some(("a", port_a)))
+        .replace(&format!("endpoint = \"[::1]:{port_a}\"\n"), "");
+    write_config(&config_b, &cfg_b)?;
+
+    let spawn = |config: &Path| -> anyhow::Result<KillChild> {
+        let child = std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"))
+            .arg("exchange-config")
+            .arg(config)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        Ok(KillChild(child))
+    };
+    let sighup = |child: &KillChild| {
+        use rustix::process::{kill_process, Pid, Signal::Hup};
+        kill_process(Pid::from_child(&child.0), Hup)
+    };
+
+    let mut proc_a = spawn(&config_a)?;
+    let _proc_b = spawn(&config_b)?;
+    let mut out_a = BufReader::new(proc_a.0.stdout.take().context("")?).lines();
+
+    // Initial key exchange
+    let line = next_line(&mut out_a)?;
+    assert!(line.ends_with(" exchanged"), "Unexpected output: `{line}`");
+
+    // An invalid configuration file is refused; the process keeps running
+    write_config(&config_a, "this is not a valid configuration file")?;
+    sighup(&proc_a)?;
+
+    // Remove peer b; its key is erased
+    write_config(&config_a, &config(dir, "a", port_a, None))?;
+    sighup(&proc_a)?;
+    let line = next_line(&mut out_a)?;
+    assert!(line.ends_with(" stale"), "Unexpected output: `{line}`");
+    assert!(proc_a.0.try_wait()?.is_none(), "Rosenpass exited");
+
+    // Add peer b again; a initiates a new handshake
+    write_config(&config_a, &config(dir, "a", port_a, Some(("b", port_b))))?;
+    sighup(&proc_a)?;
+    let line = next_line(&mut out_a)?;
+    assert!(line.ends_with(" exchanged"), "Unexpected output: `{line}`");
+
+    Ok(())
+}
+
+#[test]
+fn config_reload_records_changes_applied_before_a_failure() -> anyhow::Result<()> {
+    use rosenpass::app_server::AppServer;
+    use rosenpass::config::Rosenpass;
+    use rosenpass::protocol::basic_types::{SPk, SSk};
+    use rosenpass_util::file::LoadValue;
+    use rosenpass_wireguard_broker::brokers::native_unix::NativeUnixBroker;
+
+    rosenpass_secret_memory::policy::secret_policy_use_only_malloc_secrets();
+
+    let dir = TempDir::with_prefix("rosenpass-config-reload-failure-test")?;
+    let dir = dir.path();
+    for name in ["a", "b", "c", "d"] {
+        gen_keys(dir, name)?;
+    }
+
+    let d = dir.display();
+    let peer = |name: &str| {
+        format!("\n[[peers]]\npublic_key = \"{d}/{name}.pk\"\nkey_out = \"{d}/{name}.osk\"\n")
+    };
+    let port = find_udp_socket().context("No free UDP port")?;
+    let base = config(dir, "a", port, None);
+    let path = dir.join("a.toml");
+    write_config(&path, &(base.clone() + &peer("b") + &peer("c")))?;
+
+    let config = Rosenpass::load(&path)?;
+    let keypair = (SSk::load(dir.join("a.sk"))?, SPk::load(dir.join("a.pk"))?);
+    let mut srv = AppServer::new(Some(keypair), config.listen.clone(), config.verbosity, None)?;
+    let broker = srv.register_broker(Box::new(NativeUnixBroker::new()))?;
+    let peers = (config.peers.iter())
+        .map(|cfg| cfg.add_to_app_server(&mut srv, &broker, config.identity_ptr(cfg)?))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    srv.enable_config_reload(config, peers, broker)?;
+
+    // Adding peer d works; setting the next key of peer b to the key of peer c does not
+    let next_key = format!("next_public_key = \"{d}/c.pk\"\n");
+    let failing = base.clone() + &peer("d") + &peer("b") + &next_key + &peer("c");
+    write_config(&path, &failing)?;
+    assert!(srv.reload_config().is_err());
+    let peer_count = |srv: &AppServer| srv.crypto_server().map(|cs| cs.peer_ptrs().count());
+    assert_eq!(peer_count(&srv)?, 3);
+
+    // Peer d was recorded as added, so it is not added a second time
+    write_config(&path, &(base + &peer("d") + &peer("b") + &peer("c")))?;
+    let summary = srv.reload_config()?;
+    assert!(summary.is_empty(), "Unexpected changes: {summary}");
+    assert_eq!(peer_count(&srv)?, 3);
+
+    Ok(())
+}
+
+#[test]
+fn config_reload_does_not_wait_for_hostnames() -> anyhow::Result<()> {
+    use rosenpass::app_server::{AppPeerPtr, AppServer, Endpoint};
+    use rosenpass::config::Rosenpass;
+    use rosenpass::protocol::basic_types::{SPk, SSk};
+    use rosenpass_util::file::LoadValue;
+    use rosenpass_wireguard_broker::brokers::native_unix::NativeUnixBroker;
+
+    rosenpass_secret_memory::policy::secret_policy_use_only_malloc_secrets();
+
+    let dir = TempDir::with_prefix("rosenpass-config-reload-hostname-test")?;
+    let dir = dir.path();
+    for name in ["a", "b", "c"] {
+        gen_keys(dir, name)?;
+    }
+
+    let d = dir.display();
+    let peer = |name: &str, endpoint: &str| {
+        format!(
+            "\n[[peers]]\npublic_key = \"{d}/{name}.pk\"\nkey_out = \"{d}/{name}.osk\"\n{endpoint}"
+        )
+    };
+    let port = find_udp_socket().context("No free UDP port")?;
+    let base = config(dir, "a", port, None);
+    let path = dir.join("a.toml");
+    write_config(&path, &(base.clone() + &peer("b", "")))?;
+
+    let config = Rosenpass::load(&path)?;
+    let keypair = (SSk::load(dir.join("a.sk"))?, SPk::load(dir.join("a.pk"))?);
+    let mut srv = AppServer::new(Some(keypair), config.listen.clone(), config.verbosity, None)?;
+    let broker = srv.register_broker(Box::new(NativeUnixBroker::new()))?;
+    let peers = (config.peers.iter())
+        .map(|cfg| cfg.add_to_app_server(&mut srv, &broker, config.identity_ptr(cfg)?))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    srv.enable_config_reload(config, peers, broker)?;
+
+    // The hostnames are not resolved during the reload, so `.invalid` names are fine
+    let endpoint = "endpoint = \"peer.invalid:9999\"\n";
+    write_config(&path, &(base + &peer("b", endpoint) + &peer("c", endpoint)))?;
+    let summary = srv.reload_config()?;
+    assert_eq!(summary.peers_updated.len(), 1);
+    assert_eq!(summary.peers_added.len(), 1);
+
+    for peer in srv.crypto_server()?.peer_ptrs().collect::<Vec<_>>() {
+        match AppPeerPtr::lift(peer).get_app(&srv).endpoint() {
+            Some(Endpoint::Discovery(host)) => {
+                assert_eq!(host.hostname(), Some("peer.invalid:9999"));
+                assert!(host.addresses().is_empty());
+            }
+            ep => panic!("Unexpected endpoint {ep:?}"),
+        }
+    }
+
+    Ok(())
+}
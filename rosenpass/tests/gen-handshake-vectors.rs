@@ -0,0 +1,54 @@
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{ensure, Context};
+use serde_json::Value;
+
+/// Run the generator and parse its output
+fn generate() -> anyhow::Result<Value> {
+    let out = Command::new(env!("CARGO_BIN_EXE_rosenpass-gen-handshake-vectors")).output()?;
+    ensure!(
+        out.status.success(),
+        "Generator failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    Ok(serde_json::from_slice(&out.stdout)?)
+}
+
+/// Check the generated handshake vectors against the ones checked in
+///
+/// If `ROSENPASS_BLESS_VECTORS` is set, the vectors are written instead; they must then be
+/// reviewed and checked in.
+#[test]
+fn handshake_vectors() -> anyhow::Result<()> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors/handshake.json");
+
+    let generated = generate()?;
+    assert!(
+        generated == generate()?,
+        "The generator is not deterministic"
+    );
+
+    if std::env::var_os("ROSENPASS_BLESS_VECTORS").is_some() {
+        let mut json = serde_json::to_string_pretty(&generated)?;
+        json.push('\n');
+        std::fs::write(&path, json)?;
+        eprintln!("Wrote new handshake vectors to {}", path.display());
+        return Ok(());
+    }
+
+    let file = std::fs::File::open(&path).with_context(|| {
+        format!("Opening {path:?}; run with ROSENPASS_BLESS_VECTORS=1 to generate the vectors")
+    })?;
+    let checked_in: Value = serde_json::from_reader(file)?;
+
+    // Not using assert_eq!() here; the files are too large to be printed
+    assert!(
+        generated == checked_in,
+        "The generated handshake vectors differ from {}; if this is intended, \
+        rerun with ROSENPASS_BLESS_VECTORS=1 and check in the new vectors",
+        path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    net::UdpSocket,
+    path::Path,
+    process::Stdio,
+};
+
+use anyhow::Context;
+use rosenpass_util::mem::DiscardResultExt;
+use serde_json::Value;
+use tempfile::TempDir;
+
+struct KillChild(std::process::Child);
+
+impl Drop for KillChild {
+    fn drop(&mut self) {
+        use rustix::process::{kill_process, Pid, Signal::Term};
+        let pid = Pid::from_child(&self.0);
+        loop {
+            kill_process(pid, Term).discard_result();
+            if self.0.try_wait().unwrap().is_some() {
+                break;
+            }
+        }
+    }
+}
+
+fn find_udp_socket() -> Option<u16> {
+    (1025..=u16::MAX).find(|&port| UdpSocket::bind(("::1", port)).is_ok())
+}
+
+fn gen_keys(dir: &Path, name: &str) -> anyhow::Result<()> {
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"))
+        .args(["gen-keys", "--secret-key"])
+        .arg(dir.join(format!("{name}.sk")))
+        .arg("--public-key")
+        .arg(dir.join(format!("{name}.pk")))
+        .status()?;
+    anyhow::ensure!(status.success(), "Failed to generate keys for {name}");
+    Ok(())
+}
+
+#[test]
+fn json_log_format() -> anyhow::Result<()> {
+    let dir = TempDir::with_prefix("rosenpass-json-log-format-test")?;
+    let dir = dir.path();
+
+    gen_keys(dir, "a")?;
+    gen_keys(dir, "b")?;
+
+    let port_a = find_udp_socket().context("No free UDP port")?;
+    let listen_a = format!("[::1]:{port_a}");
+
+    let command = |name: &str, other: &str, server_args: &[&str], peer_args: &[&str]| {
+        let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"));
+        cmd.args(["--log-level", "info", "--log-format", "json", "exchange"])
+            .arg("secret-key")
+            .arg(dir.join(format!("{name}.sk")))
+            .arg("public-key")
+            .arg(dir.join(format!("{name}.pk")))
+            .args(server_args)
+            .args(["verbose", "peer", "public-key"])
+            .arg(dir.join(format!("{other}.pk")))
+            .args(peer_args)
+            .arg("outfile")
+            .arg(dir.join(format!("{name}.osk")))
+            .stdin(Stdio::null());
+        cmd
+    };
+
+    let mut proc_a = command("a", "b", &["listen", &listen_a], &[])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map(KillChild)?;
+    let _proc_b = command("b", "a", &[], &["endpoint", &listen_a])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(KillChild)?;
+
+    let mut out_a = BufReader::new(proc_a.0.stdout.take().context("")?).lines();
+    let mut err_a = proc_a.0.stderr.take().context("")?;
+
+    // The key output event is a single JSON object
+    let line = out_a.next().context("Rosenpass exited unexpectedly")??;
+    let event: Value = serde_json::from_str(&line)
+        .with_context(|| format!("Key output event is not valid JSON: `{line}`"))?;
+    assert_eq!(event["event"], "output-key", "{event}");
+    assert_eq!(event["reason"], "exchanged", "{event}");
+    let osk_a = dir.join("a.osk");
+    assert_eq!(event["key_file"], osk_a.to_str().context("")?, "{event}");
+    let peer = event["peer"].as_str().context("Peer id missing")?;
+    assert_eq!(peer.len(), 44, "{event}");
+    let timestamp = event["timestamp"].as_str().context("Timestamp missing")?;
+    assert!(timestamp.ends_with('Z'), "{event}");
+
+    // Each line of the log output is a JSON object as well
+    drop(out_a);
+    drop(proc_a);
+    let mut log = String::new();
+    err_a.read_to_string(&mut log)?;
+    let mut messages = Vec::new();
+    for line in log.lines() {
+        let record: Value = serde_json::from_str(line)
+            .with_context(|| format!("Log line is not valid JSON: `{line}`"))?;
+        for field in ["timestamp", "level", "target", "message"] {
+            assert!(record[field].is_string(), "Field {field} missing: {record}");
+        }
+        messages.push(record["message"].as_str().unwrap().to_owned());
+    }
+    assert!(
+        messages
+            .iter()
+            .any(|m| m.starts_with("Exchanged key with peer ")),
+        "Unexpected log output: {log}"
+    );
+
+    Ok(())
+}
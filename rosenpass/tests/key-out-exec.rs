@@ -0,0 +1,137 @@
+use std::{
+    io::{BufRead, BufReader},
+    net::UdpSocket,
+    path::Path,
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use rosenpass_util::mem::DiscardResultExt;
+use tempfile::TempDir;
+
+struct KillChild(std::process::Child);
+
+impl Drop for KillChild {
+    fn drop(&mut self) {
+        use rustix::process::{kill_process, Pid, Signal::Term};
+        let pid = Pid::from_child(&self.0);
+        loop {
+            kill_process(pid, Term).discard_result();
+            if self.0.try_wait().unwrap().is_some() {
+                break;
+            }
+        }
+    }
+}
+
+fn find_udp_socket(from: u16) -> Option<u16> {
+    (from..=u16::MAX).find(|&port| UdpSocket::bind(("::1", port)).is_ok())
+}
+
+fn gen_keys(dir: &Path, name: &str) -> anyhow::Result<()> {
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"))
+        .args(["gen-keys", "--secret-key"])
+        .arg(dir.join(format!("{name}.sk")))
+        .arg("--public-key")
+        .arg(dir.join(format!("{name}.pk")))
+        .status()?;
+    anyhow::ensure!(status.success(), "Failed to generate keys for {name}");
+    Ok(())
+}
+
+/// Configuration of server `name` talking to `other`; the hook stores the key it receives,
+/// followed by its arguments, in `{name}.hook`
+fn config(dir: &Path, name: &str, port: u16, other: &str, other_port: u16, memfd: bool) -> String {
+    let d = dir.display();
+    let (input, delivery) = match memfd {
+        true => ("<&3", "memfd"),
+        false => ("", "stdin"),
+    };
+    format!(
+        "\
+        public_key = \"{d}/{name}.pk\"\n\
+        secret_key = \"{d}/{name}.sk\"\n\
+        listen = [\"[::1]:{port}\"]\n\
+        verbosity = \"Quiet\"\n\
+        \n[[peers]]\n\
+        public_key = \"{d}/{other}.pk\"\n\
+        endpoint = \"[::1]:{other_port}\"\n\
+        key_out = \"{d}/{name}.osk\"\n\
+        key_out_exec = {{ command = ['sh', '-c', \
+            'cat {input} > \"$0.tmp\" && echo \"$1 $2\" >> \"$0.tmp\" && mv \"$0.tmp\" \"$0\"', \
+            '{d}/{name}.hook'], key_delivery = \"{delivery}\" }}\n"
+    )
+}
+
+/// Wait for the hook of server `name` to finish, returning the received key and arguments
+fn hook_output(dir: &Path, name: &str) -> anyhow::Result<(String, String)> {
+    let path = dir.join(format!("{name}.hook"));
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !path.exists() {
+        anyhow::ensure!(Instant::now() < deadline, "Hook of {name} did not run");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let output = std::fs::read_to_string(path)?;
+    let (key, args) = output
+        .split_once('\n')
+        .with_context(|| format!("Unexpected hook output `{output}`"))?;
+    Ok((key.to_owned(), args.trim_end().to_owned()))
+}
+
+#[test]
+fn key_out_exec() -> anyhow::Result<()> {
+    let dir = TempDir::with_prefix("rosenpass-key-out-exec-test")?;
+    let dir = dir.path();
+
+    gen_keys(dir, "a")?;
+    gen_keys(dir, "b")?;
+
+    let port_a = find_udp_socket(1025).context("No free UDP port")?;
+    let port_b = find_udp_socket(port_a + 1).context("No free UDP port")?;
+
+    let config_a = dir.join("a.toml");
+    let config_b = dir.join("b.toml");
+    std::fs::write(&config_a, config(dir, "a", port_a, "b", port_b, false))?;
+    std::fs::write(&config_b, config(dir, "b", port_b, "a", port_a, true))?;
+
+    let spawn = |config: &Path| -> anyhow::Result<KillChild> {
+        let child = std::process::Command::new(env!("CARGO_BIN_EXE_rosenpass"))
+            .arg("exchange-config")
+            .arg(config)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        Ok(KillChild(child))
+    };
+
+    let mut proc_a = spawn(&config_a)?;
+    let mut proc_b = spawn(&config_b)?;
+
+    // The key output events are not disturbed by the hooks
+    let mut peer_ids = Vec::new();
+    for proc in [&mut proc_a, &mut proc_b] {
+        let out = proc.0.stdout.take().context("")?;
+        let line = BufReader::new(out)
+            .lines()
+            .next()
+            .context("Rosenpass exited unexpectedly")??;
+        let mut fields = line.split(' ');
+        assert_eq!(fields.next(), Some("output-key"), "{line}");
+        assert_eq!(fields.next(), Some("peer"), "{line}");
+        peer_ids.push(fields.next().context("Peer id missing")?.to_owned());
+    }
+
+    // Key delivery through standard input
+    let (key, args) = hook_output(dir, "a")?;
+    assert_eq!(key, std::fs::read_to_string(dir.join("a.osk"))?);
+    assert_eq!(args, format!("{} exchanged", peer_ids[0]));
+
+    // Key delivery through a memfd
+    let (key, args) = hook_output(dir, "b")?;
+    assert_eq!(key, std::fs::read_to_string(dir.join("b.osk"))?);
+    assert_eq!(args, format!("{} exchanged", peer_ids[1]));
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::net::SocketAddr;
+
+use rosenpass::app_server::{Endpoint, SocketBoundEndpoint, SocketPtr};
+use rosenpass::load_detection::{LoadDetection, UnderLoadConfig};
+
+fn endpoint(addr: &str) -> Endpoint {
+    let addr: SocketAddr = addr.parse().unwrap();
+    Endpoint::SocketBoundAddress(SocketBoundEndpoint::new(SocketPtr(0), addr))
+}
+
+#[test]
+fn all_sources_need_cookies_by_default() {
+    let mut detection = LoadDetection::default();
+    let quiet = endpoint("[2001:db8::1]:9999");
+    detection.record_message(&quiet);
+    assert!(!detection.admit_uncookied(&quiet));
+}
+
+#[test]
+fn uncookied_messages_are_capped_over_all_sources() {
+    let mut detection = LoadDetection::default();
+    detection.config = UnderLoadConfig {
+        update_interval_ms: 1000,
+        source_rate: 5,
+        uncookied_rate: 3,
+        ..UnderLoadConfig::default()
+    };
+
+    // Many sources staying below the per-source rate, as with spoofed addresses
+    let admitted = (1..=10)
+        .map(|no| endpoint(&format!("[2001:db8::{no}]:9999")))
+        .filter(|ep| {
+            detection.record_message(ep);
+            detection.admit_uncookied(ep)
+        })
+        .count();
+    assert_eq!(admitted, 3);
+
+    // A source above the per-source rate needs a cookie anyway
+    let mut detection = LoadDetection::default();
+    detection.config = UnderLoadConfig {
+        update_interval_ms: 1000,
+        source_rate: 2,
+        uncookied_rate: 100,
+        ..UnderLoadConfig::default()
+    };
+    let noisy = endpoint("[2001:db8::1]:9999");
+    for _ in 0..3 {
+        detection.record_message(&noisy);
+    }
+    assert!(!detection.admit_uncookied(&noisy));
+}
@@ -213,13 +213,25 @@ fn test_osk_label_mismatch() -> anyhow::Result<()> {
 
     // Create the simulator
     let mut sim = RosenpassSimulator::new(ProtocolVersion::V03, ds_custom1.clone())?;
-    assert_eq!(sim.srv_a.srv.peers[0].osk_domain_separator, ds_custom1);
-    assert_eq!(sim.srv_b.srv.peers[0].osk_domain_separator, ds_custom1);
+    assert_eq!(
+        PeerPtr(0).get(&sim.srv_a.srv).osk_domain_separator,
+        ds_custom1
+    );
+    assert_eq!(
+        PeerPtr(0).get(&sim.srv_b.srv).osk_domain_separator,
+        ds_custom1
+    );
 
     // Deliberately produce a label mismatch
-    sim.srv_b.srv.peers[0].osk_domain_separator = ds_custom2.clone();
-    assert_eq!(sim.srv_a.srv.peers[0].osk_domain_separator, ds_custom1);
-    assert_eq!(sim.srv_b.srv.peers[0].osk_domain_separator, ds_custom2);
+    PeerPtr(0).get_mut(&mut sim.srv_b.srv).osk_domain_separator = ds_custom2.clone();
+    assert_eq!(
+        PeerPtr(0).get(&sim.srv_a.srv).osk_domain_separator,
+        ds_custom1
+    );
+    assert_eq!(
+        PeerPtr(0).get(&sim.srv_b.srv).osk_domain_separator,
+        ds_custom2
+    );
 
     // Perform the key exchanges
     for _ in 0..300 {
@@ -1,9 +1,20 @@
 //! This module provides functionality for generating random numbers using the [rand] crate.
+//!
+//! With the `internal_deterministic` feature, a different generator can be injected for the
+//! current thread for reproducible test vectors; see `with_rng`. This must never be enabled in
+//! production builds.
 
 /// We use the [ThreadRng](rand::rngs::ThreadRng) for randomness in this crate.
+#[cfg(not(feature = "internal_deterministic"))]
 pub type Rng = rand::rngs::ThreadRng;
 
 /// Get the default [Rng].
+#[cfg(not(feature = "internal_deterministic"))]
 pub fn rng() -> Rng {
     rand::thread_rng()
 }
+
+#[cfg(feature = "internal_deterministic")]
+mod injected;
+#[cfg(feature = "internal_deterministic")]
+pub use injected::*;
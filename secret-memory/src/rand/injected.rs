@@ -0,0 +1,192 @@
+//! Injecting a different random number generator for the current thread, which makes
+//! randomness reproducible for test vectors
+//!
+//! Only available with the `internal_deterministic` feature; see [crate::rand].
+
+use std::cell::RefCell;
+use std::fmt;
+
+use rand::rngs::{OsRng, ThreadRng};
+use rand::{CryptoRng, RngCore};
+
+/// A random number generator that can be injected using [with_rng]
+pub struct InjectedRng(Box<dyn RngCore + Send + Sync>);
+
+impl InjectedRng {
+    /// Wrap the given random number generator
+    pub fn new<R: RngCore + Send + Sync + 'static>(rng: R) -> Self {
+        Self(Box::new(rng))
+    }
+}
+
+impl fmt::Debug for InjectedRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("InjectedRng { .. }")
+    }
+}
+
+thread_local! {
+    /// The generator injected for the current thread, if any
+    static INJECTED: RefCell<Option<InjectedRng>> = const { RefCell::new(None) };
+}
+
+/// The random number generator used throughout Rosenpass
+///
+/// This is the [ThreadRng], unless a generator was injected for the current thread using
+/// [with_rng].
+pub struct Rng(ThreadRng);
+
+impl Rng {
+    /// Run `f` with the generator that is currently in effect
+    fn apply<R>(&mut self, f: impl FnOnce(&mut dyn RngCore) -> R) -> R {
+        INJECTED.with(|cell| match cell.borrow_mut().as_mut() {
+            Some(injected) => f(&mut *injected.0),
+            None => f(&mut self.0),
+        })
+    }
+}
+
+impl RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        self.apply(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.apply(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.apply(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.apply(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+impl CryptoRng for Rng {}
+
+/// Get the default [Rng].
+pub fn rng() -> Rng {
+    Rng(rand::thread_rng())
+}
+
+/// Whether a generator was injected for the current thread using [with_rng]
+pub fn is_injected() -> bool {
+    INJECTED.with(|cell| cell.borrow().is_some())
+}
+
+/// Fill `dest` from the injected generator if there is one, or from the operating system
+/// otherwise
+///
+/// This is meant for libraries that bring their own source of randomness: they keep using the
+/// operating system, unless a generator was injected using [with_rng].
+pub fn fill_bytes_os_or_injected(dest: &mut [u8]) {
+    match is_injected() {
+        true => rng().fill_bytes(dest),
+        false => OsRng.fill_bytes(dest),
+    }
+}
+
+/// Run `f` with `rng` injected as the source of randomness for the current thread
+///
+/// The generator is taken out of `rng` for the duration of the call and put back afterwards,
+/// also if `f` panics. If `rng` is `None`, `f` is run with whatever generator is currently in
+/// effect; this allows calls to be nested.
+///
+/// # Examples
+///
+/// ```
+/// use rand::{rngs::StdRng, SeedableRng};
+/// use rosenpass_secret_memory::rand::{is_injected, with_rng, InjectedRng};
+/// use rosenpass_secret_memory::Secret;
+///
+/// rosenpass_secret_memory::secret_policy_use_only_malloc_secrets();
+///
+/// let seeded = || Some(InjectedRng::new(StdRng::seed_from_u64(42)));
+/// let a = with_rng(&mut seeded(), || Secret::<32>::random());
+/// let b = with_rng(&mut seeded(), || Secret::<32>::random());
+/// assert_eq!(a.secret(), b.secret());
+/// assert!(!is_injected());
+/// ```
+pub fn with_rng<R>(rng: &mut Option<InjectedRng>, f: impl FnOnce() -> R) -> R {
+    /// Puts the injected generator back into its slot when dropped
+    struct Restore<'a> {
+        /// Where the generator came from
+        slot: &'a mut Option<InjectedRng>,
+        /// The generator that was in effect before
+        previous: Option<InjectedRng>,
+    }
+
+    impl Drop for Restore<'_> {
+        fn drop(&mut self) {
+            let previous = self.previous.take();
+            *self.slot = INJECTED.with(|cell| cell.replace(previous));
+        }
+    }
+
+    if rng.is_none() {
+        return f();
+    }
+
+    let previous = INJECTED.with(|cell| cell.replace(rng.take()));
+    let _restore = Restore {
+        slot: rng,
+        previous,
+    };
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn sample() -> [u8; 16] {
+        let mut buf = [0u8; 16];
+        rng().fill_bytes(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn injected_rng_is_used_and_restored() {
+        let seeded = || Some(InjectedRng::new(StdRng::seed_from_u64(7)));
+
+        let mut a = seeded();
+        let first = with_rng(&mut a, sample);
+        assert!(a.is_some());
+        assert!(!is_injected());
+
+        // The generator keeps its state between calls
+        let second = with_rng(&mut a, sample);
+        assert_ne!(first, second);
+
+        let mut b = seeded();
+        assert_eq!(with_rng(&mut b, sample), first);
+        assert_eq!(with_rng(&mut b, sample), second);
+    }
+
+    #[test]
+    fn nested_calls_use_outer_rng() {
+        let mut a = Some(InjectedRng::new(StdRng::seed_from_u64(1)));
+        let nested = with_rng(&mut a, || {
+            assert!(is_injected());
+            with_rng(&mut None, sample)
+        });
+
+        let mut b = Some(InjectedRng::new(StdRng::seed_from_u64(1)));
+        assert_eq!(with_rng(&mut b, sample), nested);
+    }
+
+    #[test]
+    fn rng_is_restored_on_panic() {
+        let mut a = Some(InjectedRng::new(StdRng::seed_from_u64(1)));
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_rng(&mut a, || panic!("oops"))
+        }));
+        assert!(res.is_err());
+        assert!(a.is_some());
+        assert!(!is_injected());
+    }
+}
@@ -1,9 +1,9 @@
 //! Helpers for working with files
 
-use anyhow::ensure;
-use std::fs::File;
+use anyhow::{ensure, Context};
+use std::fs::{File, Permissions};
 use std::io::Read;
-use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::{fs::OpenOptions, path::Path};
 
 /// Level of secrecy applied for a file
@@ -14,6 +14,16 @@ pub enum Visibility {
     Secret,
 }
 
+impl Visibility {
+    /// The file mode used for files of this visibility
+    pub fn mode(&self) -> u32 {
+        match self {
+            Visibility::Public => 0o644,
+            Visibility::Secret => 0o600,
+        }
+    }
+}
+
 /// Open a file writeably, truncating the file.
 ///
 /// Sensible default permissions are chosen based on the value of `visibility`
@@ -43,13 +53,81 @@ pub enum Visibility {
 pub fn fopen_w<P: AsRef<Path>>(path: P, visibility: Visibility) -> std::io::Result<File> {
     let mut options = OpenOptions::new();
     options.create(true).write(true).read(false).truncate(true);
-    match visibility {
-        Visibility::Public => options.mode(0o644),
-        Visibility::Secret => options.mode(0o600),
-    };
+    options.mode(visibility.mode());
     options.open(path)
 }
 
+/// Replace the contents of a file atomically
+///
+/// `write` is called with a temporary file created next to `path`; afterwards, the temporary
+/// file is synced to disk and renamed to `path`. Readers therefore observe either the previous
+/// or the new contents of the file, never a partially written one. If `write` fails, the
+/// temporary file is removed and `path` is left untouched.
+///
+/// The permissions are set to [Visibility::mode] explicitly, so unlike with [fopen_w], they do
+/// not depend on the umask. `write` may still change them, e.g. after changing the group of
+/// the file.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+/// use std::os::unix::fs::PermissionsExt;
+/// use tempfile::tempdir;
+/// use rosenpass_util::file::{fwrite_atomic, Visibility};
+///
+/// let dir = tempdir()?;
+/// let path = dir.path().join("secret_key");
+///
+/// fwrite_atomic(&path, Visibility::Secret, |f| Ok(f.write_all(b"Hello World")?))?;
+/// assert_eq!(std::fs::read(&path)?, b"Hello World");
+/// assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+///
+/// // Failed writes leave the file untouched
+/// let res = fwrite_atomic(&path, Visibility::Secret, |_| anyhow::bail!("Failure"));
+/// assert!(res.is_err());
+/// assert_eq!(std::fs::read(&path)?, b"Hello World");
+/// assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+///
+/// Ok::<(), anyhow::Error>(())
+/// ```
+pub fn fwrite_atomic<P, F>(path: P, visibility: Visibility, write: F) -> anyhow::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut File) -> anyhow::Result<()>,
+{
+    let path = path.as_ref();
+    let name = path
+        .file_name()
+        .with_context(|| format!("Could not write {path:?}: not a file name"))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut tmp = tempfile::Builder::new()
+        .prefix(&format!(".{}.", name.to_string_lossy()))
+        .suffix(".tmp")
+        .tempfile_in(dir)
+        .with_context(|| format!("Could not create temporary file for {path:?}"))?;
+    tmp.as_file()
+        .set_permissions(Permissions::from_mode(visibility.mode()))?;
+
+    write(tmp.as_file_mut())?;
+    tmp.as_file()
+        .sync_all()
+        .with_context(|| format!("Could not sync temporary file for {path:?}"))?;
+    tmp.persist(path)
+        .with_context(|| format!("Could not replace {path:?}"))?;
+
+    // Make sure the rename itself survives a crash
+    fopen_r(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("Could not sync directory {dir:?}"))?;
+
+    Ok(())
+}
+
 /// Open a file readably
 ///
 /// # Examples
@@ -398,6 +476,23 @@ mod tests {
         assert_eq!(permissions.mode(), 0o100600);
     }
 
+    #[test]
+    fn test_fwrite_atomic_replaces_permissions() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("test");
+        fopen_w(&path, Visibility::Public)
+            .unwrap()
+            .write_all(b"old")
+            .unwrap();
+
+        fwrite_atomic(&path, Visibility::Secret, |f| Ok(f.write_all(b"new")?)).unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        let permissions = std::fs::metadata(&path).unwrap().permissions();
+        assert_eq!(permissions.mode(), 0o100600);
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+    }
+
     #[test]
     fn test_fopen_r() {
         let tmp_dir = tempdir().unwrap();
@@ -6,6 +6,9 @@ use std::time::Instant;
 /// convenient way to get the seconds elapsed since the creation of the
 /// `Timebase` instance.
 ///
+/// With the `internal_deterministic` feature, the time can instead be taken from a
+/// `ManualClock`; see `with_clock`. This must never be enabled in production builds.
+///
 /// # Examples
 ///
 /// ```
@@ -29,10 +32,19 @@ impl Default for Timebase {
 impl Timebase {
     /// Returns the seconds elapsed since the creation of the `Timebase`
     pub fn now(&self) -> f64 {
+        #[cfg(feature = "internal_deterministic")]
+        if let Some(now) = manual::now() {
+            return now;
+        }
         self.0.elapsed().as_secs_f64()
     }
 }
 
+#[cfg(feature = "internal_deterministic")]
+mod manual;
+#[cfg(feature = "internal_deterministic")]
+pub use manual::{with_clock, ManualClock};
+
 #[cfg(test)]
 mod tests {
     use super::*;
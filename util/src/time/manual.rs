@@ -0,0 +1,96 @@
+//! Driving every [Timebase](super::Timebase) of the current thread by a [ManualClock], which
+//! makes timing information reproducible for test vectors
+//!
+//! Only available with the `internal_deterministic` feature; see [crate::time].
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A clock that only advances when told to
+///
+/// Clones share the same time, so the clock can be advanced while it is in effect.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock(Arc<AtomicU64>);
+
+impl ManualClock {
+    /// Returns the current time of the clock in seconds
+    pub fn now(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::SeqCst))
+    }
+
+    /// Sets the current time of the clock in seconds
+    pub fn set(&self, now: f64) {
+        self.0.store(now.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Advances the clock by `secs` seconds
+    pub fn advance(&self, secs: f64) {
+        self.set(self.now() + secs);
+    }
+}
+
+thread_local! {
+    /// The clock in effect for the current thread, if any
+    static CLOCK: RefCell<Option<ManualClock>> = const { RefCell::new(None) };
+}
+
+/// The time of the clock in effect for the current thread, if any
+pub(super) fn now() -> Option<f64> {
+    CLOCK.with(|cell| cell.borrow().as_ref().map(ManualClock::now))
+}
+
+/// Run `f` with every [Timebase](super::Timebase) following `clock` instead of the system clock
+///
+/// The previous clock is put back afterwards, also if `f` panics.
+///
+/// # Examples
+///
+/// ```
+/// use rosenpass_util::time::{with_clock, ManualClock, Timebase};
+///
+/// let timebase = Timebase::default();
+/// let clock = ManualClock::default();
+/// with_clock(&clock, || {
+///     assert_eq!(timebase.now(), 0.0);
+///     clock.advance(2.5);
+///     assert_eq!(timebase.now(), 2.5);
+/// });
+/// ```
+pub fn with_clock<R>(clock: &ManualClock, f: impl FnOnce() -> R) -> R {
+    /// Puts the previous clock back when dropped
+    struct Restore(Option<ManualClock>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CLOCK.with(|cell| cell.replace(previous));
+        }
+    }
+
+    let _restore = Restore(CLOCK.with(|cell| cell.replace(Some(clock.clone()))));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::Timebase;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timebase_manual() {
+        let timebase = Timebase::default();
+        let clock = ManualClock::default();
+        with_clock(&clock, || {
+            sleep(Duration::from_millis(10));
+            assert_eq!(timebase.now(), 0.0);
+
+            clock.set(10.0);
+            clock.advance(0.5);
+            assert_eq!(timebase.clone().now(), 10.5);
+        });
+        assert!(timebase.now() > 0.0);
+    }
+}